@@ -0,0 +1,533 @@
+use crate::logreader::{safe_text, EventParseError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+use tiny_keccak::{Hasher, Keccak};
+use web3::types::{Bytes, H160, H256, U256};
+
+#[derive(Error, Debug, Clone)]
+pub enum AbiError {
+    #[error("invalid ABI JSON: {0}")]
+    Json(String),
+    #[error("unsupported ABI type {0}")]
+    UnsupportedType(String),
+}
+
+/// Type of the event parameter, as it is declared in ABI
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamType {
+    Address,
+    Bool,
+    Uint(usize),
+    Int(usize),
+    FixedBytes(usize),
+    Bytes,
+    String,
+    Array(Box<ParamType>),
+    FixedArray(Box<ParamType>, usize),
+}
+
+impl ParamType {
+    pub fn parse(s: &str) -> Result<Self, AbiError> {
+        let unsupported = || AbiError::UnsupportedType(s.to_owned());
+        if let Some(inner) = s.strip_suffix("[]") {
+            return Ok(Self::Array(Box::new(Self::parse(inner)?)));
+        }
+        if let Some(pos) = s.rfind('[') {
+            if s.ends_with(']') {
+                let size: usize = s[pos + 1..s.len() - 1].parse().map_err(|_| unsupported())?;
+                return Ok(Self::FixedArray(Box::new(Self::parse(&s[..pos])?), size));
+            }
+        }
+        let bits = |prefix: &str| -> Result<usize, AbiError> {
+            match &s[prefix.len()..] {
+                "" => Ok(256),
+                x => x.parse().map_err(|_| unsupported()),
+            }
+        };
+        match s {
+            "address" => Ok(Self::Address),
+            "bool" => Ok(Self::Bool),
+            "string" => Ok(Self::String),
+            "bytes" => Ok(Self::Bytes),
+            _ if s.starts_with("uint") => Ok(Self::Uint(bits("uint")?)),
+            _ if s.starts_with("int") => Ok(Self::Int(bits("int")?)),
+            _ if s.starts_with("bytes") => Ok(Self::FixedBytes(bits("bytes")?)),
+            _ => Err(unsupported()),
+        }
+    }
+
+    /// type name, as it is used in the event signature
+    pub fn canonical(&self) -> String {
+        match self {
+            Self::Address => "address".to_owned(),
+            Self::Bool => "bool".to_owned(),
+            Self::Uint(bits) => format!("uint{}", bits),
+            Self::Int(bits) => format!("int{}", bits),
+            Self::FixedBytes(size) => format!("bytes{}", size),
+            Self::Bytes => "bytes".to_owned(),
+            Self::String => "string".to_owned(),
+            Self::Array(inner) => format!("{}[]", inner.canonical()),
+            Self::FixedArray(inner, size) => format!("{}[{}]", inner.canonical(), size),
+        }
+    }
+
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            Self::Bytes | Self::String | Self::Array(_) => true,
+            Self::FixedArray(inner, _) => inner.is_dynamic(),
+            _ => false,
+        }
+    }
+
+    // size of the value in the head of the encoded tuple
+    fn head_size(&self) -> usize {
+        match self {
+            Self::FixedArray(inner, size) if !inner.is_dynamic() => inner.head_size() * size,
+            _ => 32,
+        }
+    }
+}
+
+/// Decoded value of the event parameter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", content = "value")]
+pub enum AbiValue {
+    Address(#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))] H160),
+    Bool(bool),
+    Uint(#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))] U256),
+    /// signed integer in two's complement
+    Int(#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))] U256),
+    FixedBytes(#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Bytes"))] Bytes),
+    Bytes(#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Bytes"))] Bytes),
+    String(String),
+    Array(Vec<AbiValue>),
+    /// indexed parameter of dynamic type, only its keccak hash is logged
+    Hash(#[cfg_attr(feature = "schema", schemars(with = "crate::schema::Hash"))] H256),
+}
+
+impl std::fmt::Display for AbiValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Address(x) => write!(f, "{:?}", x),
+            Self::Bool(x) => write!(f, "{}", x),
+            Self::Uint(x) | Self::Int(x) => write!(f, "{}", x),
+            Self::FixedBytes(x) | Self::Bytes(x) => write!(f, "0x{}", hex::encode(&x.0)),
+            Self::String(x) => write!(f, "{}", x),
+            Self::Array(items) => {
+                let list: Vec<String> = items.iter().map(|x| x.to_string()).collect();
+                write!(f, "[{}]", list.join(", "))
+            }
+            Self::Hash(x) => write!(f, "{:?}", x),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventParam {
+    pub name: String,
+    pub kind: ParamType,
+    pub indexed: bool,
+}
+
+/// Event definition from ABI
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventDef {
+    pub name: String,
+    pub inputs: Vec<EventParam>,
+}
+
+impl EventDef {
+    pub fn signature(&self) -> String {
+        let types: Vec<String> = self.inputs.iter().map(|p| p.kind.canonical()).collect();
+        format!("{}({})", self.name, types.join(","))
+    }
+
+    /// the first topic of the log, keccak hash of the signature
+    pub fn topic(&self) -> H256 {
+        let mut hasher = Keccak::v256();
+        let mut out = [0u8; 32];
+        hasher.update(self.signature().as_bytes());
+        hasher.finalize(&mut out);
+        H256::from(out)
+    }
+
+    fn num_indexed(&self) -> usize {
+        self.inputs.iter().filter(|p| p.indexed).count()
+    }
+
+    pub fn decode(&self, log: &web3::types::Log) -> Result<AbiEvent, EventParseError> {
+        if log.topics.len() != self.num_indexed() + 1 {
+            return Err(EventParseError::InvalidTopics(
+                log.topics.len().saturating_sub(1),
+                self.num_indexed(),
+            ));
+        }
+        let data: &[u8] = &log.data.0;
+        let non_indexed: Vec<&ParamType> = self
+            .inputs
+            .iter()
+            .filter(|p| !p.indexed)
+            .map(|p| &p.kind)
+            .collect();
+        let mut values = decode_tuple(data, &non_indexed)?.into_iter();
+        let mut topics = log.topics.iter().skip(1);
+        let mut params = vec![];
+        for p in &self.inputs {
+            let value = if p.indexed {
+                let topic = topics.next().ok_or(EventParseError::NoTopics)?;
+                if p.kind.is_dynamic() {
+                    AbiValue::Hash(*topic)
+                } else {
+                    decode_at(topic.as_bytes(), &p.kind)?
+                }
+            } else {
+                values.next().ok_or(EventParseError::InvalidDataSize(
+                    data.len(),
+                    non_indexed.len(),
+                ))?
+            };
+            params.push(AbiParam {
+                name: p.name.clone(),
+                indexed: p.indexed,
+                value,
+            });
+        }
+        Ok(AbiEvent {
+            name: self.name.clone(),
+            signature: self.signature(),
+            params,
+        })
+    }
+}
+
+fn word(data: &[u8], offset: usize) -> Result<&[u8], EventParseError> {
+    data.get(offset..offset + 32)
+        .ok_or(EventParseError::InvalidDataSize(
+            data.len(),
+            offset / 32 + 1,
+        ))
+}
+
+// reads offset or length, that cannot point outside of the data
+fn size_at(data: &[u8], offset: usize) -> Result<usize, EventParseError> {
+    let val = U256::from_big_endian(word(data, offset)?);
+    if val > U256::from(data.len()) {
+        return Err(EventParseError::InvalidDataSize(
+            data.len(),
+            offset / 32 + 1,
+        ));
+    }
+    Ok(val.as_usize())
+}
+
+fn tail(data: &[u8], offset: usize) -> Result<&[u8], EventParseError> {
+    data.get(offset..)
+        .ok_or(EventParseError::InvalidDataSize(data.len(), offset / 32))
+}
+
+// decodes the value that starts at the beginning of the data
+fn decode_at(data: &[u8], kind: &ParamType) -> Result<AbiValue, EventParseError> {
+    match kind {
+        ParamType::Address => Ok(AbiValue::Address(H160::from_slice(&word(data, 0)?[12..]))),
+        ParamType::Bool => Ok(AbiValue::Bool(word(data, 0)?[31] != 0)),
+        ParamType::Uint(_) => Ok(AbiValue::Uint(U256::from_big_endian(word(data, 0)?))),
+        ParamType::Int(_) => Ok(AbiValue::Int(U256::from_big_endian(word(data, 0)?))),
+        ParamType::FixedBytes(size) => {
+            let w = word(data, 0)?;
+            Ok(AbiValue::FixedBytes(Bytes(w[..(*size).min(32)].to_vec())))
+        }
+        ParamType::Bytes | ParamType::String => {
+            let len = size_at(data, 0)?;
+            let bytes = data
+                .get(32..32 + len)
+                .ok_or(EventParseError::InvalidDataSize(
+                    data.len(),
+                    len.div_ceil(32) + 1,
+                ))?;
+            Ok(match kind {
+                ParamType::String => AbiValue::String(safe_text(bytes)),
+                _ => AbiValue::Bytes(Bytes(bytes.to_vec())),
+            })
+        }
+        ParamType::Array(inner) => {
+            let len = size_at(data, 0)?;
+            let kinds: Vec<&ParamType> = (0..len).map(|_| inner.as_ref()).collect();
+            Ok(AbiValue::Array(decode_tuple(tail(data, 32)?, &kinds)?))
+        }
+        ParamType::FixedArray(inner, size) => {
+            let kinds: Vec<&ParamType> = (0..*size).map(|_| inner.as_ref()).collect();
+            Ok(AbiValue::Array(decode_tuple(data, &kinds)?))
+        }
+    }
+}
+
+// decodes values, encoded with head and tail parts
+fn decode_tuple(data: &[u8], kinds: &[&ParamType]) -> Result<Vec<AbiValue>, EventParseError> {
+    let mut out = vec![];
+    let mut head = 0;
+    for kind in kinds {
+        let value = if kind.is_dynamic() {
+            let offset = size_at(data, head)?;
+            decode_at(tail(data, offset)?, kind)?
+        } else {
+            decode_at(tail(data, head)?, kind)?
+        };
+        out.push(value);
+        head += kind.head_size();
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AbiParam {
+    pub name: String,
+    pub indexed: bool,
+    pub value: AbiValue,
+}
+
+/// Event, decoded with its ABI definition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AbiEvent {
+    pub name: String,
+    pub signature: String,
+    pub params: Vec<AbiParam>,
+}
+
+impl AbiEvent {
+    fn param(&self, index: usize) -> Result<&AbiValue, EventParseError> {
+        match self.params.get(index) {
+            Some(p) => Ok(&p.value),
+            None => Err(EventParseError::InvalidParam(self.name.clone(), index)),
+        }
+    }
+
+    fn invalid(&self, index: usize) -> EventParseError {
+        EventParseError::InvalidParam(self.name.clone(), index)
+    }
+
+    pub fn address(&self, index: usize) -> Result<H160, EventParseError> {
+        match self.param(index)? {
+            AbiValue::Address(x) => Ok(*x),
+            _ => Err(self.invalid(index)),
+        }
+    }
+
+    pub fn value(&self, index: usize) -> Result<U256, EventParseError> {
+        match self.param(index)? {
+            AbiValue::Uint(x) | AbiValue::Int(x) => Ok(*x),
+            _ => Err(self.invalid(index)),
+        }
+    }
+
+    pub fn bool(&self, index: usize) -> Result<bool, EventParseError> {
+        match self.param(index)? {
+            AbiValue::Bool(x) => Ok(*x),
+            _ => Err(self.invalid(index)),
+        }
+    }
+
+    pub fn text(&self, index: usize) -> Result<String, EventParseError> {
+        match self.param(index)? {
+            AbiValue::String(x) => Ok(x.clone()),
+            _ => Err(self.invalid(index)),
+        }
+    }
+
+    pub fn addresses(&self, index: usize) -> Result<Vec<H160>, EventParseError> {
+        match self.param(index)? {
+            AbiValue::Array(items) => items
+                .iter()
+                .map(|x| match x {
+                    AbiValue::Address(a) => Ok(*a),
+                    _ => Err(self.invalid(index)),
+                })
+                .collect(),
+            _ => Err(self.invalid(index)),
+        }
+    }
+}
+
+/// Event definitions, indexed by their topic
+#[derive(Debug, Clone, Default)]
+pub struct Abi {
+    events: BTreeMap<H256, Vec<EventDef>>,
+}
+
+impl Abi {
+    /// reads events from ABI JSON, other entries are ignored
+    pub fn from_json(src: &str) -> Result<Self, AbiError> {
+        let mut abi = Self::default();
+        abi.extend_json(src)?;
+        Ok(abi)
+    }
+
+    pub fn extend_json(&mut self, src: &str) -> Result<(), AbiError> {
+        let entries: Vec<serde_json::Value> =
+            serde_json::from_str(src).map_err(|e| AbiError::Json(e.to_string()))?;
+        for entry in entries {
+            if entry["type"] != "event" || entry["anonymous"] == true {
+                continue;
+            }
+            let name = match entry["name"].as_str() {
+                Some(x) => x.to_owned(),
+                None => return Err(AbiError::Json("event without name".to_owned())),
+            };
+            let mut inputs = vec![];
+            if let Some(list) = entry["inputs"].as_array() {
+                for input in list {
+                    let kind = input["type"].as_str().unwrap_or_default();
+                    inputs.push(EventParam {
+                        name: input["name"].as_str().unwrap_or_default().to_owned(),
+                        kind: ParamType::parse(kind)?,
+                        indexed: input["indexed"].as_bool().unwrap_or(false),
+                    });
+                }
+            }
+            self.add(EventDef { name, inputs });
+        }
+        Ok(())
+    }
+
+    pub fn add(&mut self, def: EventDef) {
+        let list = self.events.entry(def.topic()).or_insert(vec![]);
+        // the same event is declared in several contracts
+        if !list.contains(&def) {
+            list.push(def);
+        }
+    }
+
+    pub fn get(&self, topic: &H256) -> Option<&Vec<EventDef>> {
+        self.events.get(topic)
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// decodes the log with the definition that matches its topics,
+    /// returns None if the event is not in ABI
+    pub fn decode(&self, log: &web3::types::Log) -> Result<Option<AbiEvent>, EventParseError> {
+        let t0 = log.topics.first().ok_or(EventParseError::NoTopics)?;
+        let defs = match self.events.get(t0) {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        // same signature could differ in indexed params
+        let def = defs
+            .iter()
+            .find(|d| d.num_indexed() + 1 == log.topics.len())
+            .unwrap_or(&defs[0]);
+        def.decode(log).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+    use web3::types::Log;
+
+    fn log(topics: Vec<H256>, data: Vec<u8>) -> Log {
+        Log {
+            address: H160::from_low_u64_be(1),
+            topics,
+            data: Bytes(data),
+            block_hash: None,
+            block_number: None,
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        }
+    }
+
+    const VOTING_ABI: &str = r#"[
+        {"type": "event", "name": "StartVote", "anonymous": false, "inputs": [
+            {"name": "voteId", "type": "uint256", "indexed": true},
+            {"name": "creator", "type": "address", "indexed": true},
+            {"name": "metadata", "type": "string", "indexed": false}
+        ]},
+        {"type": "event", "name": "SetErc20Addresses", "anonymous": false, "inputs": [
+            {"name": "erc20Addresses", "type": "address[]", "indexed": false}
+        ]},
+        {"type": "function", "name": "vote", "inputs": []}
+    ]"#;
+
+    #[test]
+    pub fn it_computes_topics() {
+        let abi = Abi::from_json(VOTING_ABI).unwrap();
+        assert_eq!(abi.len(), 2);
+        let topic: H256 =
+            hex!("4d72fe0577a3a3f7da968d7b892779dde102519c25527b29cf7054f245c791b9").into();
+        assert_eq!(
+            abi.get(&topic).unwrap()[0].signature(),
+            "StartVote(uint256,address,string)"
+        );
+        assert_eq!(
+            ParamType::parse("uint[3][]").unwrap().canonical(),
+            "uint256[3][]"
+        );
+    }
+
+    #[test]
+    pub fn it_decodes_string() {
+        let abi = Abi::from_json(VOTING_ABI).unwrap();
+        let l = log(
+            vec![
+                hex!("4d72fe0577a3a3f7da968d7b892779dde102519c25527b29cf7054f245c791b9").into(),
+                hex!("0000000000000000000000000000000000000000000000000000000000000007").into(),
+                hex!("000000000000000000000000061b8335e1d2042975c4ed849943334bd07fb504").into(),
+            ],
+            hex!(
+                "00000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000047311f7472616e7366657228616464726573732c75696e74323536291f4d7920666972737420415049332070726f706f73616c1f466f722074657374696e6720707572706f73657300000000000000000000000000000000000000000000000000"
+            ).to_vec(),
+        );
+        let ev = abi.decode(&l).unwrap().unwrap();
+        assert_eq!(ev.name, "StartVote");
+        assert_eq!(ev.value(0).unwrap(), U256::from(7));
+        assert_eq!(
+            ev.address(1).unwrap(),
+            hex!("061b8335e1d2042975c4ed849943334bd07fb504").into()
+        );
+        assert_eq!(
+            ev.text(2).unwrap(),
+            "1|transfer(address,uint256)|My first API3 proposal|For testing purposes"
+        );
+    }
+
+    #[test]
+    pub fn it_decodes_arrays() {
+        let abi = Abi::from_json(VOTING_ABI).unwrap();
+        let l = log(
+            vec![hex!("220c5b95388e82dd8e3a0abed6143750f9bfa4bf73bb6f742e10cf79e551b168").into()],
+            hex!(
+                "0000000000000000000000000000000000000000000000000000000000000020
+                 0000000000000000000000000000000000000000000000000000000000000002
+                 000000000000000000000000061b8335e1d2042975c4ed849943334bd07fb504
+                 0000000000000000000000000000000000000000000000000000000000000001"
+            )
+            .to_vec(),
+        );
+        let ev = abi.decode(&l).unwrap().unwrap();
+        assert_eq!(
+            ev.addresses(0).unwrap(),
+            vec![
+                hex!("061b8335e1d2042975c4ed849943334bd07fb504").into(),
+                H160::from_low_u64_be(1)
+            ]
+        );
+        // truncated data is an error, not a panic
+        let l = log(l.topics.clone(), l.data.0[..70].to_vec());
+        assert!(abi.decode(&l).is_err());
+    }
+}
@@ -6,6 +6,7 @@ use std::str::FromStr;
 use web3::types::{H160, U256};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum ActionSignature {
     Transfer,
     // transfer which is invalid
@@ -14,11 +15,14 @@ pub enum ActionSignature {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct VotingAction {
     pub action: ActionSignature,       // i.e. "Transfer"
     pub token: String,        // i.e. "USDC"
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub amount: U256,         // amount to be transferred
     pub decimals: usize,      // decimals for the token, i.e. 18
+    #[cfg_attr(feature = "schema", schemars(with = "Option<crate::schema::Address>"))]
     pub wallet: Option<H160>, // wallet-destination in case of Transfer or similar methods
 }
 impl fmt::Display for VotingAction {
@@ -68,7 +72,7 @@ impl VotingAction {
             .map(|x| x.clone())
             .collect::<Vec<u8>>();
         let signature_str = hex::encode(&signature);
-        println!("signature={}", signature_str);
+        debug!("signature={}", signature_str);
         let action = if signature_str == "a9059cbb" {
             ActionSignature::Transfer
         } else if signature_str == "9d61d234" { // invalid case of the misleading docs
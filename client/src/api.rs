@@ -0,0 +1,243 @@
+//! Envelopes, errors and filters of the versioned API, shared by the server and its clients
+use crate::state::{Voting, Wallet};
+use serde::{Deserialize, Serialize};
+use web3::types::H160;
+
+/// number of items on the page when the limit is not given
+pub const DEFAULT_PAGE_LIMIT: usize = 100;
+/// the largest page that is served at once
+pub const MAX_PAGE_LIMIT: usize = 1000;
+
+/// Successful response of the versioned API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Envelope<T> {
+    pub result: T,
+    /// last block of the state the result is built from
+    pub last_block: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidAddress,
+    InvalidVotingId,
+    InvalidQuery,
+    NotMember,
+    VotingNotFound,
+    NotFound,
+}
+
+impl ErrorCode {
+    /// HTTP status of the response with this error
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::InvalidAddress | Self::InvalidVotingId | Self::InvalidQuery => 400,
+            Self::NotMember | Self::VotingNotFound | Self::NotFound => 404,
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::InvalidAddress => "Invalid Ethereum address",
+            Self::InvalidVotingId => "Invalid voting ID",
+            Self::InvalidQuery => "Invalid query parameters",
+            Self::NotMember => "Not a member of the DAO",
+            Self::VotingNotFound => "Voting was not found",
+            Self::NotFound => "Unknown endpoint",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ApiError {
+    /// stable code of the error, the message might change
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode) -> Self {
+        Self {
+            code,
+            message: code.message().to_owned(),
+        }
+    }
+}
+
+/// Failed response of the versioned API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ErrorEnvelope {
+    pub error: ApiError,
+}
+
+/// Part of the list to be returned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PageRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+impl PageRequest {
+    pub fn new(offset: usize, limit: usize) -> Self {
+        Self {
+            offset: Some(offset),
+            limit: Some(limit),
+        }
+    }
+
+    pub fn limit(&self) -> Option<usize> {
+        match self.limit {
+            Some(n) if n == 0 || n > MAX_PAGE_LIMIT => None,
+            Some(n) => Some(n),
+            None => Some(DEFAULT_PAGE_LIMIT),
+        }
+    }
+
+    /// page of the items, None if the limit is out of range
+    pub fn page<T>(&self, items: impl Iterator<Item = T>) -> Option<Page<T>> {
+        let limit = self.limit()?;
+        let offset = self.offset.unwrap_or_default();
+        let mut total = 0;
+        let mut res = vec![];
+        for item in items {
+            if total >= offset && res.len() < limit {
+                res.push(item);
+            }
+            total += 1;
+        }
+        Some(Page {
+            items: res,
+            total,
+            offset,
+            limit,
+        })
+    }
+}
+
+/// Page of the filtered list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// number of items that match the filter
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl<T> Page<T> {
+    /// request of the page after this one, if there is any
+    pub fn next(&self) -> Option<PageRequest> {
+        let offset = self.offset + self.items.len();
+        match !self.items.is_empty() && offset < self.total {
+            true => Some(PageRequest::new(offset, self.limit)),
+            false => None,
+        }
+    }
+}
+
+/// Filter of the members, all members if empty
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WalletFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supporter: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vested: Option<bool>,
+    /// members that delegated their voting power
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delegating: Option<bool>,
+}
+
+impl WalletFilter {
+    pub fn matches(&self, w: &Wallet) -> bool {
+        self.supporter.is_none_or(|x| w.supporter == x)
+            && self.vested.is_none_or(|x| w.vested == x)
+            && self.delegating.is_none_or(|x| w.delegates.is_some() == x)
+    }
+}
+
+/// Filter of the votings, all votings if empty
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VotingFilter {
+    /// votings of the primary or of the secondary agent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executed: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creator: Option<H160>,
+}
+
+impl VotingFilter {
+    pub fn matches(&self, v: &Voting) -> bool {
+        self.primary.is_none_or(|x| v.primary == x)
+            && self.executed.is_none_or(|x| v.executed == x)
+            && self.creator.is_none_or(|x| v.creator == x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn it_pages() {
+        let page = PageRequest::new(4, 3).page(0..10).unwrap();
+        assert_eq!(page.items, vec![4, 5, 6]);
+        assert_eq!(page.total, 10);
+        assert_eq!(page.next(), Some(PageRequest::new(7, 3)));
+        let last = page.next().unwrap().page(0..10).unwrap();
+        assert_eq!(last.items, vec![7, 8, 9]);
+        assert_eq!(last.next(), None);
+        assert_eq!(
+            PageRequest::default().page(0..10).unwrap().limit,
+            DEFAULT_PAGE_LIMIT
+        );
+        assert!(PageRequest::new(0, MAX_PAGE_LIMIT + 1)
+            .page(0..10)
+            .is_none());
+    }
+
+    #[test]
+    pub fn it_filters() {
+        let w = Wallet {
+            supporter: true,
+            ..Default::default()
+        };
+        assert!(WalletFilter::default().matches(&w));
+        let supporters = WalletFilter {
+            supporter: Some(true),
+            vested: Some(false),
+            ..Default::default()
+        };
+        assert!(supporters.matches(&w));
+        let delegating = WalletFilter {
+            delegating: Some(true),
+            ..Default::default()
+        };
+        assert!(!delegating.matches(&w));
+
+        let v = Voting {
+            primary: true,
+            creator: H160::from_low_u64_be(1),
+            ..Default::default()
+        };
+        let by_creator = VotingFilter {
+            creator: Some(H160::from_low_u64_be(1)),
+            executed: Some(false),
+            ..Default::default()
+        };
+        assert!(by_creator.matches(&v));
+        let secondary = VotingFilter {
+            primary: Some(false),
+            ..Default::default()
+        };
+        assert!(!secondary.matches(&v));
+    }
+}
@@ -0,0 +1,455 @@
+use crate::nice;
+use sauron::prelude::*;
+
+/// size of the view box of line, area and bar charts
+pub const WIDTH: f64 = 800.0;
+pub const HEIGHT: f64 = 200.0;
+/// size of the view box of the donut chart
+const DONUT: f64 = 200.0;
+/// radius of the hole of the donut, relative to the outer radius
+const HOLE: f64 = 0.6;
+
+/// css classes of the series, in the order they are assigned
+const PALETTE: [&str; 6] = [
+    "series-0", "series-1", "series-2", "series-3", "series-4", "series-5",
+];
+
+pub fn palette(index: usize) -> &'static str {
+    PALETTE[index % PALETTE.len()]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Line,
+    StackedArea,
+    Bar,
+    Donut,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    pub name: String,
+    /// css class, which sets the color of the series
+    pub class: &'static str,
+    pub values: Vec<f64>,
+}
+
+/// SVG chart, which only depends on its data, so it is rendered
+/// the same way on the server and in the browser
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chart {
+    pub kind: Kind,
+    /// labels of the points, or names of the slices for the donut
+    pub labels: Vec<String>,
+    pub series: Vec<Series>,
+    /// suffix of the values in tooltips
+    pub unit: &'static str,
+}
+
+/// numbers in SVG attributes are always formatted with the same precision
+fn fmt(v: f64) -> String {
+    format!("{:.2}", v)
+}
+
+/// value in tooltips and legends
+pub fn value(v: f64, unit: &str) -> String {
+    if v.abs() >= 1000.0 {
+        format!("{}{}", nice::with_commas(&format!("{:.0}", v)), unit)
+    } else {
+        format!("{:.2}{}", v, unit)
+    }
+}
+
+fn tooltip<T>(s: String) -> Node<T> {
+    svg_element("title", vec![], vec![text(s)])
+}
+
+/// horizontal position of the point on line and area charts
+fn x_of(i: usize, n: usize) -> f64 {
+    if n < 2 {
+        WIDTH / 2.0
+    } else {
+        i as f64 * WIDTH / (n - 1) as f64
+    }
+}
+
+fn y_of(v: f64, top: f64) -> f64 {
+    HEIGHT - v.max(0.0) / top * HEIGHT
+}
+
+/// points of the polyline in the view box
+pub fn points(values: &[f64], top: f64) -> String {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| format!("{},{}", fmt(x_of(i, values.len())), fmt(y_of(*v, top))))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// sector of the donut between two angles, in radians clockwise from the top
+pub fn arc(start: f64, end: f64) -> String {
+    // the full circle cannot be drawn with a single arc
+    let end = end.min(start + std::f64::consts::PI * 2.0 - 0.0001);
+    let c = DONUT / 2.0;
+    let (outer, inner) = (c, c * HOLE);
+    let at = |r: f64, a: f64| format!("{},{}", fmt(c + r * a.sin()), fmt(c - r * a.cos()));
+    let large = if end - start > std::f64::consts::PI {
+        1
+    } else {
+        0
+    };
+    format!(
+        "M{} A{},{} 0 {} 1 {} L{} A{},{} 0 {} 0 {} Z",
+        at(outer, start),
+        fmt(outer),
+        fmt(outer),
+        large,
+        at(outer, end),
+        at(inner, end),
+        fmt(inner),
+        fmt(inner),
+        large,
+        at(inner, start),
+    )
+}
+
+impl Chart {
+    pub fn new(kind: Kind, labels: Vec<String>, unit: &'static str) -> Self {
+        Self {
+            kind,
+            labels,
+            series: vec![],
+            unit,
+        }
+    }
+
+    /// adds series of values, colored in the order of the palette
+    pub fn series(mut self, series_name: &str, values: Vec<f64>) -> Self {
+        let class = palette(self.series.len());
+        self.series.push(Series {
+            name: series_name.to_owned(),
+            class,
+            values,
+        });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.series.iter().all(|s| s.values.is_empty())
+    }
+
+    fn label(&self, i: usize) -> String {
+        self.labels.get(i).cloned().unwrap_or_default()
+    }
+
+    /// the highest value on the chart, series are summed up when stacked
+    pub fn max(&self) -> f64 {
+        let n = self
+            .series
+            .iter()
+            .map(|s| s.values.len())
+            .max()
+            .unwrap_or(0);
+        let highest = match self.kind {
+            Kind::StackedArea => (0..n)
+                .map(|i| {
+                    self.series
+                        .iter()
+                        .map(|s| s.values.get(i).cloned().unwrap_or(0.0).max(0.0))
+                        .sum::<f64>()
+                })
+                .fold(0.0, f64::max),
+            _ => self
+                .series
+                .iter()
+                .flat_map(|s| s.values.iter().cloned())
+                .fold(0.0, f64::max),
+        };
+        if highest > 0.0 {
+            highest
+        } else {
+            1.0
+        }
+    }
+
+    fn render_line<T>(&self, top: f64) -> Vec<Node<T>> {
+        let mut res = vec![];
+        for s in &self.series {
+            res.push(svg_element(
+                "polyline",
+                vec![
+                    class(format!("line {}", s.class)),
+                    attr("points", points(&s.values, top)),
+                ],
+                vec![tooltip(s.name.clone())],
+            ));
+            for (i, v) in s.values.iter().enumerate() {
+                res.push(svg_element(
+                    "circle",
+                    vec![
+                        class(format!("dot {}", s.class)),
+                        attr("cx", fmt(x_of(i, s.values.len()))),
+                        attr("cy", fmt(y_of(*v, top))),
+                        attr("r", "3"),
+                    ],
+                    vec![tooltip(format!(
+                        "{} {}: {}",
+                        self.label(i),
+                        s.name,
+                        value(*v, self.unit)
+                    ))],
+                ));
+            }
+        }
+        res
+    }
+
+    fn render_stacked<T>(&self, top: f64) -> Vec<Node<T>> {
+        let n = self
+            .series
+            .iter()
+            .map(|s| s.values.len())
+            .max()
+            .unwrap_or(0);
+        let mut lower: Vec<f64> = vec![0.0; n];
+        let mut res = vec![];
+        for s in &self.series {
+            let upper: Vec<f64> = (0..n)
+                .map(|i| lower[i] + s.values.get(i).cloned().unwrap_or(0.0).max(0.0))
+                .collect();
+            let mut outline: Vec<f64> = upper.clone();
+            outline.extend(lower.iter().rev());
+            let pts: Vec<String> = outline
+                .iter()
+                .enumerate()
+                .map(|(j, v)| {
+                    let i = if j < n { j } else { 2 * n - 1 - j };
+                    format!("{},{}", fmt(x_of(i, n)), fmt(y_of(*v, top)))
+                })
+                .collect();
+            let last = s.values.last().cloned().unwrap_or(0.0);
+            res.push(svg_element(
+                "polygon",
+                vec![
+                    class(format!("area {}", s.class)),
+                    attr("points", pts.join(" ")),
+                ],
+                vec![tooltip(format!("{}: {}", s.name, value(last, self.unit)))],
+            ));
+            lower = upper;
+        }
+        res
+    }
+
+    fn render_bars<T>(&self, top: f64) -> Vec<Node<T>> {
+        let n = self
+            .series
+            .iter()
+            .map(|s| s.values.len())
+            .max()
+            .unwrap_or(0);
+        if n == 0 {
+            return vec![];
+        }
+        let step = WIDTH / n as f64;
+        let width = step * 0.8 / self.series.len() as f64;
+        let mut res = vec![];
+        for (k, s) in self.series.iter().enumerate() {
+            for (i, v) in s.values.iter().enumerate() {
+                let y = y_of(*v, top);
+                res.push(svg_element(
+                    "rect",
+                    vec![
+                        class(format!("bar {}", s.class)),
+                        attr("x", fmt(i as f64 * step + step * 0.1 + k as f64 * width)),
+                        attr("y", fmt(y)),
+                        attr("width", fmt(width)),
+                        attr("height", fmt(HEIGHT - y)),
+                    ],
+                    vec![tooltip(format!(
+                        "{} {}: {}",
+                        self.label(i),
+                        s.name,
+                        value(*v, self.unit)
+                    ))],
+                ));
+            }
+        }
+        res
+    }
+
+    /// slices are the values of the first series
+    fn render_donut<T>(&self) -> Vec<Node<T>> {
+        let values: Vec<f64> = match self.series.first() {
+            Some(s) => s.values.iter().map(|v| v.max(0.0)).collect(),
+            None => return vec![],
+        };
+        let total: f64 = values.iter().sum();
+        if total <= 0.0 {
+            return vec![];
+        }
+        let mut start = 0.0;
+        let mut res = vec![];
+        for (i, v) in values.iter().enumerate() {
+            if *v <= 0.0 {
+                continue;
+            }
+            let end = start + v / total * std::f64::consts::PI * 2.0;
+            res.push(svg_element(
+                "path",
+                vec![
+                    class(format!("slice {}", palette(i))),
+                    attr("d", arc(start, end)),
+                ],
+                vec![tooltip(format!(
+                    "{}: {} ({:.2}%)",
+                    self.label(i),
+                    value(*v, self.unit),
+                    100.0 * v / total
+                ))],
+            ));
+            start = end;
+        }
+        res
+    }
+
+    fn render_legend<T>(&self) -> Node<T> {
+        let items: Vec<(String, &'static str)> = match self.kind {
+            Kind::Donut => {
+                let values = self
+                    .series
+                    .first()
+                    .map(|s| s.values.clone())
+                    .unwrap_or_default();
+                let total: f64 = values.iter().map(|v| v.max(0.0)).sum();
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| {
+                        let pct = if total > 0.0 { 100.0 * v / total } else { 0.0 };
+                        (format!("{} {:.2}%", self.label(i), pct), palette(i))
+                    })
+                    .collect()
+            }
+            _ => self
+                .series
+                .iter()
+                .map(|s| (s.name.clone(), s.class))
+                .collect(),
+        };
+        ul(
+            vec![class("chart-legend")],
+            items
+                .into_iter()
+                .map(|(entry, cls)| {
+                    li(
+                        vec![],
+                        vec![
+                            span(vec![class(format!("swatch {}", cls))], vec![]),
+                            text(entry),
+                        ],
+                    )
+                })
+                .collect::<Vec<Node<T>>>(),
+        )
+    }
+
+    /// range of the labels and the highest value under the chart
+    fn render_axis<T>(&self, top: f64) -> Node<T> {
+        let first = self.label(0);
+        let last = match self.labels.len() {
+            0 | 1 => "".to_owned(),
+            n => self.label(n - 1),
+        };
+        node! {
+            <div class="chart-axis darken">
+                <span>{text(first)}</span>
+                <span>{text(format!("max {}", value(top, self.unit)))}</span>
+                <span>{text(last)}</span>
+            </div>
+        }
+    }
+
+    pub fn render<T>(&self, divclass: &'static str) -> Node<T> {
+        if self.is_empty() {
+            return text("");
+        }
+        let top = self.max();
+        let (view, children) = match self.kind {
+            Kind::Line => ((WIDTH, HEIGHT), self.render_line(top)),
+            Kind::StackedArea => ((WIDTH, HEIGHT), self.render_stacked(top)),
+            Kind::Bar => ((WIDTH, HEIGHT), self.render_bars(top)),
+            Kind::Donut => ((DONUT, DONUT), self.render_donut()),
+        };
+        let svg = svg_element(
+            "svg",
+            vec![
+                class("chart-svg"),
+                attr("viewBox", format!("0 0 {} {}", view.0, view.1)),
+            ],
+            children,
+        );
+        let axis = match self.kind {
+            Kind::Donut => text(""),
+            _ => self.render_axis(top),
+        };
+        let kind = match self.kind {
+            Kind::Line => "chart chart-line",
+            Kind::StackedArea => "chart chart-area",
+            Kind::Bar => "chart chart-bar",
+            Kind::Donut => "chart chart-donut",
+        };
+        div(
+            vec![class(format!("{} {}", kind, divclass).trim().to_owned())],
+            vec![svg, axis, self.render_legend()],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(n: usize) -> Vec<String> {
+        (1..=n).map(|i| format!("#{}", i)).collect()
+    }
+
+    #[test]
+    pub fn it_scales_points() {
+        assert_eq!(
+            points(&[0.0, 5.0, 10.0], 10.0),
+            "0.00,200.00 400.00,100.00 800.00,0.00"
+        );
+        assert_eq!(points(&[2.0], 4.0), "400.00,100.00");
+        let chart = Chart::new(Kind::StackedArea, labels(2), "")
+            .series("a", vec![1.0, 3.0])
+            .series("b", vec![2.0, 2.0]);
+        assert_eq!(chart.max(), 5.0);
+        let chart = Chart::new(Kind::Line, labels(2), "").series("a", vec![0.0, 0.0]);
+        assert_eq!(chart.max(), 1.0);
+    }
+
+    #[test]
+    pub fn it_draws_donut_arcs() {
+        let half = arc(0.0, std::f64::consts::PI);
+        assert_eq!(
+            half,
+            "M100.00,0.00 A100.00,100.00 0 0 1 100.00,200.00 L100.00,160.00 A60.00,60.00 0 0 0 100.00,40.00 Z"
+        );
+        // the full circle is drawn as a large arc
+        assert!(arc(1.0, 10.0).contains(" 0 1 1 "));
+    }
+
+    #[test]
+    pub fn it_renders_the_same_markup() {
+        let chart =
+            Chart::new(Kind::Donut, labels(3), " API3").series("power", vec![1.0, 1.0, 2.0]);
+        let html = chart.render::<()>("").render_to_string();
+        assert_eq!(html.matches("<path").count(), 3);
+        assert!(html.contains("#3: 2.00 API3 (50.00%)"));
+        assert_eq!(html, chart.clone().render::<()>("").render_to_string());
+        let empty = Chart::new(Kind::Bar, vec![], "").series("none", vec![]);
+        assert_eq!(empty.render::<()>("").render_to_string(), "");
+    }
+}
@@ -1,14 +1,14 @@
 use crate::nice;
-use crate::state::AppState;
+use crate::views::Site;
 use sauron::prelude::*;
 
-pub fn render<T>(state: &AppState) -> Node<T> {
-    let testnet: Node<T> = if state.chain_id == 4 {
+pub fn render<T>(site: &Site) -> Node<T> {
+    let testnet: Node<T> = if site.chain_id == 4 {
         span(
             vec![class("mdiv badge badge-testnet")],
             vec![text("rinkeby")],
         )
-    } else if state.chain_id != 1 {
+    } else if site.chain_id != 1 {
         span(
             vec![class("mdiv badge badge-testnet")],
             vec![text("testnet")],
@@ -16,7 +16,7 @@ pub fn render<T>(state: &AppState) -> Node<T> {
     } else {
         span(vec![], vec![])
     };
-    let footer_class = if state.chain_id == 1 { "" } else { "testnet" };
+    let footer_class = if site.chain_id == 1 { "" } else { "testnet" };
     node! {
         <footer class={footer_class}>
             <div class="inner">
@@ -35,7 +35,7 @@ pub fn render<T>(state: &AppState) -> Node<T> {
                     <span class="desktop-only">" | "</span>
                     <span class="mdiv">
                         { text("Last block: ")}
-                        { text(nice::int(state.last_block)) }
+                        { text(nice::int(site.last_block)) }
                     </span>
                 </div>
             </div>
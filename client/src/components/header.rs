@@ -1,4 +1,5 @@
-use crate::state::AppState;
+use crate::state::NetworkInfo;
+use crate::views::Site;
 use sauron::prelude::*;
 
 pub struct MenuItem {
@@ -17,14 +18,55 @@ impl MenuItem {
     }
 }
 
+// links to other DAO deployments, served by the same server
+fn render_networks<T>(network: &NetworkInfo) -> Node<T> {
+    if network.networks.len() < 2 {
+        return span(vec![], vec![]);
+    }
+    let links: Vec<Node<T>> = network
+        .networks
+        .iter()
+        .map(|net| {
+            let mut attr = vec![href(network.network_url(net)), class("badge")];
+            if *net == network.name {
+                attr.push(class("active"));
+            }
+            a(attr, vec![text(net)])
+        })
+        .collect();
+    div(vec![class("network-switcher")], links)
+}
+
+// search of wallets, votings and transactions, the server opens the exact match at once
+fn render_search<T>() -> Node<T> {
+    form(
+        vec![
+            class("search"),
+            attr("action", "./search"),
+            attr("method", "get"),
+        ],
+        vec![input(
+            vec![
+                attr("type", "search"),
+                attr("name", "q"),
+                attr("placeholder", "Address, ENS, voting or tx"),
+                attr("aria-label", "Search"),
+            ],
+            vec![],
+        )],
+    )
+}
+
 const TITLE: &'static str = "API3 DAO Tracker";
 const SLOGAN: &'static str = "on-chain analytics: members, staking rewards, API3 token supply";
 
-pub fn render<T>(active_menu: &'static str, state: &AppState) -> Node<T> {
+pub fn render<T>(active_menu: &'static str, site: &Site) -> Node<T> {
     let is_default = !active_menu.starts_with("/rewards")
         && !active_menu.starts_with("/wallets")
         && !active_menu.starts_with("/votings")
-        && !active_menu.starts_with("/treasury");
+        && !active_menu.starts_with("/treasury")
+        && !active_menu.starts_with("/projections")
+        && !active_menu.starts_with("/unstakes");
 
     let menu: Vec<MenuItem> = vec![
         MenuItem {
@@ -47,6 +89,16 @@ pub fn render<T>(active_menu: &'static str, state: &AppState) -> Node<T> {
             title: "Votings",
             is_active: active_menu.starts_with("/votings"),
         },
+        MenuItem {
+            href: "./unstakes",
+            title: "Unstaking",
+            is_active: active_menu.starts_with("/unstakes"),
+        },
+        MenuItem {
+            href: "./projections",
+            title: "Calculator",
+            is_active: active_menu.starts_with("/projections"),
+        },
         MenuItem {
             href: "./treasury",
             title: "Treasury",
@@ -54,15 +106,15 @@ pub fn render<T>(active_menu: &'static str, state: &AppState) -> Node<T> {
         },
     ];
 
-    let testnet: Node<T> = if state.chain_id == 4 {
+    let testnet: Node<T> = if site.chain_id == 4 {
         span(vec![class("badge badge-testnet")], vec![text("rinkeby")])
-    } else if state.chain_id != 1 {
+    } else if site.chain_id != 1 {
         span(vec![class("badge badge-testnet")], vec![text("testnet")])
     } else {
         span(vec![], vec![])
     };
 
-    let header_class = if state.chain_id == 1 { "" } else { "testnet" };
+    let header_class = if site.chain_id == 1 { "" } else { "testnet" };
     node! {
       <header class={header_class}>
         <div class="inner">
@@ -75,6 +127,8 @@ pub fn render<T>(active_menu: &'static str, state: &AppState) -> Node<T> {
             </span>
           </div>
           <div class="mid"></div>
+          {render_search()}
+          {render_networks(&site.network)}
           {
             div(
               vec![class("desktop-menu")],
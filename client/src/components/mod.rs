@@ -1,3 +1,4 @@
+pub mod chart;
 pub mod footer;
 pub mod header;
 pub mod panel;
@@ -1,9 +1,11 @@
+use crate::abi::{Abi, AbiEvent};
 use crate::logreader::{EventParseError, LogReader};
 use hex_literal::hex;
 use serde::{Deserialize, Serialize};
 use web3::types::{H160, U256};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum VotingAgent {
     Primary,
     Secondary,
@@ -18,18 +20,22 @@ impl VotingAgent {
     }
 }
 
-pub fn voting_from_str(str: &str) -> (VotingAgent, u64) {
-    let parts: Vec<&str> = str.split("-").collect();
-    let first_char = parts[0][0..1].to_lowercase();
-    let vote_id = parts[1].parse::<u64>().unwrap_or_default();
-    (
-        if first_char == "p" {
-            VotingAgent::Primary
-        } else {
-            VotingAgent::Secondary
-        },
-        vote_id,
-    )
+/// parses voting ID with the agent prefix, i.e. p-12 or s-3
+pub fn voting_from_str(src: &str) -> Option<(VotingAgent, u64)> {
+    let (prefix, id) = src.split_once('-')?;
+    let agent = match prefix {
+        "p" => VotingAgent::Primary,
+        "s" => VotingAgent::Secondary,
+        _ => return None,
+    };
+    if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    match id.parse::<u64>() {
+        // agent is kept in the lowest bit of the voting reference
+        Ok(vote_id) if vote_id <= u64::MAX / 2 => Some((agent, vote_id)),
+        _ => None,
+    }
 }
 
 pub fn voting_to_string(agent: &VotingAgent, vote_id: u64) -> String {
@@ -56,165 +62,259 @@ pub fn voting_to_u64(agent: &VotingAgent, vote_id: u64) -> u64 {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type")]
 pub enum Api3 {
     // Pool contract events
     SetDaoApps {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         agent_app_primary: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         agent_app_secondary: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         voting_app_primary: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         voting_app_secondary: H160,
     },
     Delegated {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         from: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         to: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         shares: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         total_delegated_to: U256,
     },
     DelegatedV0 {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         from: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         to: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         shares: U256,
     },
     Undelegated {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         from: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         to: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         shares: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         total_delegated_to: U256,
     },
     UndelegatedV0 {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         from: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         to: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         shares: U256,
     },
     UpdatedDelegation {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         user: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         delegate: H160,
         delta: bool,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         shares: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         total_delegated_to: U256,
     },
     Staked {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         user: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         minted_shares: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         user_unstaked: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         user_shares: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         total_shares: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         total_stake: U256,
     },
     StakedV0 {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         user: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         minted_shares: U256,
     },
     Unstaked {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         user: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         user_unstaked: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         total_shares: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         total_stake: U256,
     },
     UnstakedV0 {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         user: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
     },
     ScheduledUnstake {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         user: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         shares: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         scheduled_for: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         user_shares: U256,
     },
     ScheduledUnstakeV0 {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         user: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         shares: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         scheduled_for: U256,
     },
     Deposited {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         user: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         user_unstaked: U256,
     },
     DepositedV0 {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         user: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
     },
     DepositedVesting {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         user: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         start: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         end: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         user_unstaked: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         user_vesting: U256,
     },
     DepositedByTimelockManager {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         user: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         user_unstaked: U256,
     },
     VestedTimelock {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         user: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         user_vesting: U256,
     },
     Withdrawn {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         user: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         user_unstaked: U256,
     },
     WithdrawnV0 {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         user: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
     },
     WithdrawnToPool {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         recipient: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         api3_pool_address: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         beneficiary: H160,
     },
     UpdatedLastProposalTimestamp {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         user: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         last_proposal_timestamp: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         voting_app: H160,
     },
     SetStakeTarget {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         stake_target: U256,
     },
     MintedReward {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         epoch_index: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         new_apr: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         total_stake: U256,
     },
     MintedRewardV0 {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         epoch_index: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         new_apr: U256,
     },
     // never happened yet
     PaidOutClaim {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         recipient: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         total_stake: U256,
     },
 
     // Voting
     StartVote {
         agent: VotingAgent,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         vote_id: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         creator: H160,
         metadata: String,
     },
     CastVote {
         agent: VotingAgent,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         vote_id: U256,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         voter: H160,
         supports: bool,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         stake: U256,
     },
     ExecuteVote {
         agent: VotingAgent,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         vote_id: U256,
     },
 
@@ -223,23 +323,32 @@ pub enum Api3 {
 
     // Convenience contract events
     SetErc20Addresses {
+        #[cfg_attr(feature = "schema", schemars(with = "Vec<crate::schema::Address>"))]
         addresses: Vec<H160>,
     },
     SetVestingAddresses {
+        #[cfg_attr(feature = "schema", schemars(with = "Vec<crate::schema::Address>"))]
         addresses: Vec<H160>,
     },
     OwnershipTransferred {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         from: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         to: H160,
     },
 
     // ERC20 events:
     Transfer {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         from: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
         to: H160,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
         amount: U256,
     },
 
+    // event from ABI that is not tracked in the state
+    Decoded(AbiEvent),
     // unknown, but ignored, do not fail on this type
     Unclassified,
     // unknown and fail on that
@@ -389,6 +498,11 @@ impl Api3 {
                 amount: _,
                 total_stake: _,
             } => res.push(recipient.clone()),
+            Self::UpdatedLastProposalTimestamp {
+                user,
+                last_proposal_timestamp: _,
+                voting_app: _,
+            } => res.push(user.clone()),
 
             Self::StartVote {
                 agent: _,
@@ -434,313 +548,211 @@ impl Api3 {
         }
     }
 
+    /// maps the event, decoded with ABI, into the known event type.
+    /// Events that are in ABI, but are not tracked, are kept as decoded
+    pub fn from_event(voting: Option<VotingAgent>, e: AbiEvent) -> Result<Self, EventParseError> {
+        let agent = || voting.clone().ok_or(EventParseError::NoVotingAgent);
+        let entry = match (e.name.as_str(), e.params.len()) {
+            ("Delegated", 4) => Self::Delegated {
+                from: e.address(0)?,
+                to: e.address(1)?,
+                shares: e.value(2)?,
+                total_delegated_to: e.value(3)?,
+            },
+            ("Delegated", 3) => Self::DelegatedV0 {
+                from: e.address(0)?,
+                to: e.address(1)?,
+                shares: e.value(2)?,
+            },
+            ("Undelegated", 4) => Self::Undelegated {
+                from: e.address(0)?,
+                to: e.address(1)?,
+                shares: e.value(2)?,
+                total_delegated_to: e.value(3)?,
+            },
+            ("Undelegated", 3) => Self::UndelegatedV0 {
+                from: e.address(0)?,
+                to: e.address(1)?,
+                shares: e.value(2)?,
+            },
+            ("UpdatedDelegation", 5) => Self::UpdatedDelegation {
+                user: e.address(0)?,
+                delegate: e.address(1)?,
+                delta: e.bool(2)?,
+                shares: e.value(3)?,
+                total_delegated_to: e.value(4)?,
+            },
+            ("ScheduledUnstake", 5) => Self::ScheduledUnstake {
+                user: e.address(0)?,
+                amount: e.value(1)?,
+                shares: e.value(2)?,
+                scheduled_for: e.value(3)?,
+                user_shares: e.value(4)?,
+            },
+            ("ScheduledUnstake", 4) => Self::ScheduledUnstakeV0 {
+                user: e.address(0)?,
+                amount: e.value(1)?,
+                shares: e.value(2)?,
+                scheduled_for: e.value(3)?,
+            },
+            ("Staked", 7) => Self::Staked {
+                user: e.address(0)?,
+                amount: e.value(1)?,
+                minted_shares: e.value(2)?,
+                user_unstaked: e.value(3)?,
+                user_shares: e.value(4)?,
+                total_shares: e.value(5)?,
+                total_stake: e.value(6)?,
+            },
+            ("Staked", 3) => Self::StakedV0 {
+                user: e.address(0)?,
+                amount: e.value(1)?,
+                minted_shares: e.value(2)?,
+            },
+            ("Unstaked", 5) => Self::Unstaked {
+                user: e.address(0)?,
+                amount: e.value(1)?,
+                user_unstaked: e.value(2)?,
+                total_shares: e.value(3)?,
+                total_stake: e.value(4)?,
+            },
+            ("Unstaked", 2) => Self::UnstakedV0 {
+                user: e.address(0)?,
+                amount: e.value(1)?,
+            },
+            ("Withdrawn", 3) => Self::Withdrawn {
+                user: e.address(0)?,
+                amount: e.value(1)?,
+                user_unstaked: e.value(2)?,
+            },
+            ("Withdrawn", 2) => Self::WithdrawnV0 {
+                user: e.address(0)?,
+                amount: e.value(1)?,
+            },
+            ("WithdrawnToPool", 3) => Self::WithdrawnToPool {
+                recipient: e.address(0)?,
+                api3_pool_address: e.address(1)?,
+                beneficiary: e.address(2)?,
+            },
+            ("UpdatedLastProposalTimestamp", 3) => Self::UpdatedLastProposalTimestamp {
+                user: e.address(0)?,
+                last_proposal_timestamp: e.value(1)?,
+                voting_app: e.address(2)?,
+            },
+            ("SetStakeTarget", 1) => Self::SetStakeTarget {
+                stake_target: e.value(0)?,
+            },
+            ("SetDaoApps", 4) => Self::SetDaoApps {
+                agent_app_primary: e.address(0)?,
+                agent_app_secondary: e.address(1)?,
+                voting_app_primary: e.address(2)?,
+                voting_app_secondary: e.address(3)?,
+            },
+            ("MintedReward", 4) => Self::MintedReward {
+                epoch_index: e.value(0)?,
+                amount: e.value(1)?,
+                new_apr: e.value(2)?,
+                total_stake: e.value(3)?,
+            },
+            ("MintedReward", 3) => Self::MintedRewardV0 {
+                epoch_index: e.value(0)?,
+                amount: e.value(1)?,
+                new_apr: e.value(2)?,
+            },
+            ("PaidOutClaim", 3) => Self::PaidOutClaim {
+                recipient: e.address(0)?,
+                amount: e.value(1)?,
+                total_stake: e.value(2)?,
+            },
+            ("Transfer", 3) => Self::Transfer {
+                from: e.address(0)?,
+                to: e.address(1)?,
+                amount: e.value(2)?,
+            },
+            ("Deposited", 3) => Self::Deposited {
+                user: e.address(0)?,
+                amount: e.value(1)?,
+                user_unstaked: e.value(2)?,
+            },
+            ("Deposited", 2) => Self::DepositedV0 {
+                user: e.address(0)?,
+                amount: e.value(1)?,
+            },
+            ("DepositedVesting", 6) => Self::DepositedVesting {
+                user: e.address(0)?,
+                amount: e.value(1)?,
+                start: e.value(2)?,
+                end: e.value(3)?,
+                user_unstaked: e.value(4)?,
+                user_vesting: e.value(5)?,
+            },
+            ("DepositedByTimelockManager", 3) => Self::DepositedByTimelockManager {
+                user: e.address(0)?,
+                amount: e.value(1)?,
+                user_unstaked: e.value(2)?,
+            },
+            ("VestedTimelock", 3) => Self::VestedTimelock {
+                user: e.address(0)?,
+                amount: e.value(1)?,
+                user_vesting: e.value(2)?,
+            },
+            ("SetErc20Addresses", 1) => Self::SetErc20Addresses {
+                addresses: e.addresses(0)?,
+            },
+            ("SetVestingAddresses", 1) => Self::SetVestingAddresses {
+                addresses: e.addresses(0)?,
+            },
+            ("OwnershipTransferred", 2) => Self::OwnershipTransferred {
+                from: e.address(0)?,
+                to: e.address(1)?,
+            },
+            ("StartVote", 3) => Self::StartVote {
+                agent: agent()?,
+                vote_id: e.value(0)?,
+                creator: e.address(1)?,
+                metadata: e.text(2)?,
+            },
+            ("CastVote", 4) => Self::CastVote {
+                agent: agent()?,
+                vote_id: e.value(0)?,
+                voter: e.address(1)?,
+                supports: e.bool(2)?,
+                stake: e.value(3)?,
+            },
+            ("ExecuteVote", 1) => Self::ExecuteVote {
+                agent: agent()?,
+                vote_id: e.value(0)?,
+            },
+            _ => Self::Decoded(e),
+        };
+        Ok(entry)
+    }
+
     pub fn from_log(
+        abi: &Abi,
         voting: Option<VotingAgent>,
         log: &web3::types::Log,
     ) -> Result<Self, EventParseError> {
-        let t0 = log.topics[0];
-
-        if t0 == hex!("24d7bda8602b916d64417f0dbfe2e2e88ec9b1157bd9f596dfdb91ba26624e04").into() {
-            let mut r = LogReader::new(&log, 2, Some(2)).unwrap();
-            return Ok(Self::Delegated {
-                from: r.address(),
-                to: r.address(),
-                shares: r.value(),
-                total_delegated_to: r.value(),
-            });
-        }
-        if t0 == hex!("e5541a6b6103d4fa7e021ed54fad39c66f27a76bd13d374cf6240ae6bd0bb72b").into() {
-            let mut r = LogReader::new(&log, 2, Some(1)).unwrap();
-            return Ok(Self::DelegatedV0 {
-                from: r.address(),
-                to: r.address(),
-                shares: r.value(),
-            });
-        }
-        if t0 == hex!("3aace7340547de7b9156593a7652dc07ee900cea3fd8f82cb6c9d38b40829802").into() {
-            let mut r = LogReader::new(&log, 2, Some(2)).unwrap();
-            return Ok(Self::Undelegated {
-                from: r.address(),
-                to: r.address(),
-                shares: r.value(),
-                total_delegated_to: r.value(),
-            });
-        }
-        if t0 == hex!("f310def5b4718cefe3603eb46259d8061fd58003695cf952de94c53e14dbb309").into() {
-            let mut r = LogReader::new(&log, 2, Some(3)).unwrap();
-            return Ok(Self::UpdatedDelegation {
-                user: r.address(),
-                delegate: r.address(),
-                delta: r.bool(),
-                shares: r.value(),
-                total_delegated_to: r.value(),
-            });
-        }
-        if t0 == hex!("4d10bd049775c77bd7f255195afba5088028ecb3c7c277d393ccff7934f2f92c").into() {
-            let mut r = LogReader::new(&log, 2, Some(1)).unwrap();
-            return Ok(Self::UndelegatedV0 {
-                from: r.address(),
-                to: r.address(),
-                shares: r.value(),
-            });
-        }
-        if t0 == hex!("251830cd12788c7474148132132ab205112e7b9bba739f0e69c8d4a6a54e2159").into() {
-            let mut r = LogReader::new(&log, 1, Some(4)).unwrap();
-            return Ok(Self::ScheduledUnstake {
-                user: r.address(),
-                amount: r.value(),
-                shares: r.value(),
-                scheduled_for: r.value(),
-                user_shares: r.value(),
-            });
-        }
-        if t0 == hex!("06fbd2297e6f6f7701a9cf99685a6af911cab275ec5c75ac7aaaf13b5cf3d61f").into() {
-            let mut r = LogReader::new(&log, 1, Some(3)).unwrap();
-            return Ok(Self::ScheduledUnstakeV0 {
-                user: r.address(),
-                amount: r.value(),
-                shares: r.value(),
-                scheduled_for: r.value(),
-            });
-        }
-        if t0 == hex!("c16be9a586414a157dd46b4d023aa9997a025dd1cbbaa67ac0c1b8273a5eaf55").into() {
-            let mut r = LogReader::new(&log, 1, Some(6)).unwrap();
-            return Ok(Self::Staked {
-                user: r.address(),
-                amount: r.value(),
-                minted_shares: r.value(),
-                user_unstaked: r.value(),
-                user_shares: r.value(),
-                total_shares: r.value(),
-                total_stake: r.value(),
-            });
-        }
-        if t0 == hex!("1449c6dd7851abc30abf37f57715f492010519147cc2652fbc38202c18a6ee90").into() {
-            let mut r = LogReader::new(&log, 1, Some(2)).unwrap();
-            return Ok(Self::StakedV0 {
-                user: r.address(),
-                amount: r.value(),
-                minted_shares: r.value(),
-            });
-        }
-        if t0 == hex!("dcfd2b4017d03f7e541021db793b2f9b31e4acdee005f789e52853c390e3e962").into() {
-            let mut r = LogReader::new(&log, 1, Some(4)).unwrap();
-            return Ok(Self::Unstaked {
-                user: r.address(),
-                amount: r.value(),
-                user_unstaked: r.value(),
-                total_shares: r.value(),
-                total_stake: r.value(),
-            });
-        }
-        if t0 == hex!("0f5bb82176feb1b5e747e28471aa92156a04d9f3ab9f45f28e2d704232b93f75").into() {
-            let mut r = LogReader::new(&log, 1, Some(1)).unwrap();
-            return Ok(Self::UnstakedV0 {
-                user: r.address(),
-                amount: r.value(),
-            });
-        }
-        if t0 == hex!("92ccf450a286a957af52509bc1c9939d1a6a481783e142e41e2499f0bb66ebc6").into() {
-            let mut r = LogReader::new(&log, 1, Some(2)).unwrap();
-            return Ok(Self::Withdrawn {
-                user: r.address(),
-                amount: r.value(),
-                user_unstaked: r.value(),
-            });
-        }
-        if t0 == hex!("7084f5476618d8e60b11ef0d7d3f06914655adb8793e28ff7f018d4c76d505d5").into() {
-            let mut r = LogReader::new(&log, 1, Some(1)).unwrap();
-            return Ok(Self::WithdrawnV0 {
-                user: r.address(),
-                amount: r.value(),
-            });
-        }
-        if t0 == hex!("a2fd4f03989448c5a69bab0c0454f2baf5667413a4e4b87fd7379a8ab69fae3f").into() {
-            let mut r = LogReader::new(&log, 1, Some(2)).unwrap();
-            return Ok(Self::WithdrawnToPool {
-                recipient: r.address(),
-                api3_pool_address: r.address(),
-                beneficiary: r.address(),
-            });
-        }
-        if t0 == hex!("ceaef3a8d9336089c649bcf1ea9dd1ae52f5c42ea01f8707ecdd57ea773aa3ee").into() {
-            let mut r = LogReader::new(&log, 1, Some(2)).unwrap();
-            return Ok(Self::UpdatedLastProposalTimestamp {
-                user: r.address(),
-                last_proposal_timestamp: r.value(),
-                voting_app: r.address(),
-            });
+        if let Some(e) = abi.decode(log)? {
+            return Self::from_event(voting, e);
         }
-        if t0 == hex!("30df07121af80c9a50a8fcfddf8aa9f537a550edb930294c6370d4c05632ba15").into() {
-            let mut r = LogReader::new(&log, 0, Some(1)).unwrap();
-            return Ok(Self::SetStakeTarget {
-                stake_target: r.value(),
-            });
-        }
-        if t0 == hex!("71b1ce304e98c2a645f0c32f4c9e3ae4d5dbe6717a8c17ccefb0083635afdc15").into() {
-            let mut r = LogReader::new(&log, 0, Some(4)).unwrap();
-            return Ok(Self::SetDaoApps {
-                agent_app_primary: r.address(),
-                agent_app_secondary: r.address(),
-                voting_app_primary: r.address(),
-                voting_app_secondary: r.address(),
-            });
-        }
-        if t0 == hex!("6e0fc10bac330e97bc2fd6c13cbb1c1189ddb48a8ce96395650ba8f2bd28f6fc").into() {
-            let mut r = LogReader::new(&log, 1, Some(3)).unwrap();
-            return Ok(Self::MintedReward {
-                epoch_index: r.value(),
-                amount: r.value(),
-                new_apr: r.value(),
-                total_stake: r.value(),
-            });
-        }
-        if t0 == hex!("78fe37d5a5b277d7ec6fe20169a339795b44f3f903e0b793440f63fbccc7d7d9").into() {
-            let mut r = LogReader::new(&log, 1, Some(2)).unwrap();
-            return Ok(Self::MintedRewardV0 {
-                epoch_index: r.value(),
-                amount: r.value(),
-                new_apr: r.value(),
-            });
-        }
-        if t0 == hex!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef").into() {
-            let mut r = LogReader::new(&log, 2, Some(1)).unwrap();
-            return Ok(Self::Transfer {
-                from: r.address(),
-                to: r.address(),
-                amount: r.value(),
-            });
-        }
-        if t0 == hex!("73a19dd210f1a7f902193214c0ee91dd35ee5b4d920cba8d519eca65a7b488ca").into() {
-            let mut r = LogReader::new(&log, 1, Some(2)).unwrap();
-            return Ok(Self::Deposited {
-                user: r.address(),
-                amount: r.value(),
-                user_unstaked: r.value(),
-            });
-        }
-        if t0 == hex!("2da466a7b24304f47e87fa2e1e5a81b9831ce54fec19055ce277ca2f39ba42c4").into() {
-            let mut r = LogReader::new(&log, 1, Some(1)).unwrap();
-            return Ok(Self::DepositedV0 {
-                user: r.address(),
-                amount: r.value(),
-            });
-        }
-        if t0 == hex!("14ab87851ecf43dc38c282e0307cd24257a3d01d0265ae2ba28764befac8c6cc").into() {
-            let mut r = LogReader::new(&log, 1, Some(5)).unwrap();
-            return Ok(Self::DepositedVesting {
-                user: r.address(),
-                amount: r.value(),
-                start: r.value(),
-                end: r.value(),
-                user_unstaked: r.value(),
-                user_vesting: r.value(),
-            });
-        }
-        if t0 == hex!("d0d7fef3966369afd08c0683ee833a06f6b91787b85a26fa3ef3004ae37484c2").into() {
-            let mut r = LogReader::new(&log, 1, Some(2)).unwrap();
-            return Ok(Self::DepositedByTimelockManager {
-                user: r.address(),
-                amount: r.value(),
-                user_unstaked: r.value(),
-            });
-        }
-        if t0 == hex!("dd8c2c092b990b8e3ae25447982d1c2f7f08c6b9bf7303986a4279f946ebd2ea").into() {
-            let mut r = LogReader::new(&log, 1, Some(2)).unwrap();
-            return Ok(Self::VestedTimelock {
-                user: r.address(),
-                amount: r.value(),
-                user_vesting: r.value(),
-            });
-        }
-
-        if t0 == hex!("220c5b95388e82dd8e3a0abed6143750f9bfa4bf73bb6f742e10cf79e551b168").into() {
-            let mut r = LogReader::new(&log, 0, None).unwrap();
-            return Ok(Self::SetErc20Addresses {
-                addresses: r.addresses(),
-            });
-        }
-        if t0 == hex!("8be0079c531659141344cd1fd0a4f28419497f9722a3daafe3b4186f6b6457e0").into() {
-            let mut r = LogReader::new(&log, 2, Some(0)).unwrap();
-            return Ok(Self::OwnershipTransferred {
-                from: r.address(),
-                to: r.address(),
-            });
-        }
-        if t0 == hex!("4d72fe0577a3a3f7da968d7b892779dde102519c25527b29cf7054f245c791b9").into() {
-            let mut r = LogReader::new(&log, 2, None).unwrap();
-            return Ok(Self::StartVote {
-                agent: voting.unwrap(),
-                vote_id: r.value(),
-                creator: r.address(),
-                metadata: r.text(),
-            });
-        }
-        if t0 == hex!("b34ee265e3d4f5ec4e8b52d59b2a9be8fceca2f274ebc080d8fba797fea9391f").into() {
-            let mut r = LogReader::new(&log, 2, Some(2)).unwrap();
-            return Ok(Self::CastVote {
-                agent: voting.unwrap(),
-                vote_id: r.value(),
-                voter: r.address(),
-                supports: r.bool(),
-                stake: r.value(),
-            });
-        }
-        if t0 == hex!("bf8e2b108bb7c980e08903a8a46527699d5e84905a082d56dacb4150725c8cab").into() {
-            let mut r = LogReader::new(&log, 1, Some(0)).unwrap();
-            return Ok(Self::ExecuteVote {
-                agent: voting.unwrap(),
-                vote_id: r.value(),
-            });
-        }
-
+        let t0 = match log.topics.first() {
+            Some(x) => *x,
+            None => return Err(EventParseError::NoTopics),
+        };
         if t0 == hex!("9dcff9d94fbfdb4622d11edb383005f95e78efb446c72d92f8e615c6025c4703").into() {
             // happens right before agent app call,
             // parameters of agent call are changed in the reverse order
             // the last parameter is the agent address
             // found in: 0xc59489a810a16d84f59a04fb90817354d9afac3bd0a0b6787c8ccb4ff25ed119
-            let _ = LogReader::new(&log, 3, Some(0)).unwrap();
-            // return Ok(Self::???(r.address(), r.address.(), r.address()));
+            let _ = LogReader::new(&log, 3, Some(0))?;
             return Ok(Self::Unclassified);
         }
         if t0 == hex!("c59489a810a16d84f59a04fb90817354d9afac3bd0a0b6787c8ccb4ff25ed119").into() {
             // that agent call
-            let _ = LogReader::new(&log, 2, None).unwrap();
-            // return Ok(Self::???(r.address(), r.address.(), r.data_ending_with_size()));
-            return Ok(Self::Unclassified);
-        }
-        if t0 == hex!("5229a5dba83a54ae8cb5b51bdd6de9474cacbe9dd332f5185f3a4f4f2e3f4ad9").into() {
-            // happens right after agent app call,
-            // probably stores some return from the agent
-            // found in: 0xc59489a810a16d84f59a04fb90817354d9afac3bd0a0b6787c8ccb4ff25ed119
-            // found in: 0xa4e6695fd3d2185da9a004bbea2d82aedb548d081f68c116308b23339ccd36bf
-            let _ = LogReader::new(&log, 1, None).unwrap();
-            // return Ok(Self::???(r.address(),r.metadata()));
+            let _ = LogReader::new(&log, 2, None)?;
             return Ok(Self::Unclassified);
         }
-
-        if t0 == hex!("2790b90165fd3973ad7edde4eca71b4f8808dd4857a2a3a3e8ae5642a5cb196e").into() {
-            // most likely this is interaction with faucet
-            let _ = LogReader::new(&log, 2, Some(1)).unwrap();
-            // return Ok(Self::???(r.value(),r.address(),r.value()));
-            return Ok(Self::Unclassified);
-        }
-
-        if t0 == hex!("c25cfed0b22da6a56f0e5ff784979a0b8623eddf2aee4acd33c2adefb09cbab6").into() {
-            // most likely this is interaction with faucet
-            let _ = LogReader::new(&log, 2, None).unwrap();
-            // return Ok(Self::???(r.value(),r.address(),r.value()));
-            return Ok(Self::Unclassified);
-        }
-        if t0 == hex!("20d5cc5c404f7bcf167ea08ea1136482041e05e5641946d3e3de6690a23fbe39").into() {
-            let mut r = LogReader::new(&log, 0, None).unwrap();
-            // skipping 2 entries (second is the size)
-            let _ = r.address();
-            let _ = r.address();
-            return Ok(Self::SetVestingAddresses {
-                addresses: r.addresses(),
-            });
-        }
         Ok(Self::Unknown)
     }
 }
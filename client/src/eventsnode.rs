@@ -1,7 +1,8 @@
+use crate::abi::AbiValue;
 use crate::action::ActionSignature;
 use crate::events::{Api3, VotingAgent};
 use crate::nice;
-use crate::state::{AppState, Voting, VotingDetails};
+use crate::state::{Voting, VotingDetails};
 use sauron::prelude::*;
 use std::collections::BTreeMap;
 use web3::types::{H160, U256};
@@ -96,7 +97,7 @@ pub fn wrap_label<T>(label: &str, node: Node<T>) -> Node<T> {
     span(vec![], vec![text(label), node])
 }
 
-pub fn entry_node<T>(entry: &Api3, addr: H160, state: &AppState) -> Node<T> {
+pub fn entry_node<T>(entry: &Api3, addr: H160, votings: &BTreeMap<u64, Voting>) -> Node<T> {
     match entry {
         Api3::Delegated {
             from,
@@ -365,10 +366,30 @@ pub fn entry_node<T>(entry: &Api3, addr: H160, state: &AppState) -> Node<T> {
         ]),
 
         Api3::WithdrawnToPool {
-            recipient: _,
+            recipient,
             api3_pool_address: _,
-            beneficiary: _,
-        } => wrap_line(vec![Some(hl_text("WithdrawnToPool"))]),
+            beneficiary,
+        } => wrap_line(vec![
+            Some(hl_text("WithdrawnToPool")),
+            if *recipient != addr {
+                Some(wrap_label("recipient: ", wrap_address(*recipient)))
+            } else {
+                None
+            },
+            if *beneficiary != addr {
+                Some(wrap_label("beneficiary: ", wrap_address(*beneficiary)))
+            } else {
+                None
+            },
+        ]),
+        Api3::UpdatedLastProposalTimestamp {
+            user: _,
+            last_proposal_timestamp,
+            voting_app: _,
+        } => wrap_line(vec![
+            Some(hl_text("UpdatedLastProposalTimestamp")),
+            Some(normal_text(&nice::date(last_proposal_timestamp.as_u64()))),
+        ]),
 
         // never happened yet
         Api3::PaidOutClaim {
@@ -391,7 +412,7 @@ pub fn entry_node<T>(entry: &Api3, addr: H160, state: &AppState) -> Node<T> {
             metadata: _,
         } => wrap_line(vec![
             Some(hl_text("StartVote")),
-            wrap_vote(*vote_id, agent, &state.votings),
+            wrap_vote(*vote_id, agent, votings),
         ]),
         Api3::CastVote {
             agent,
@@ -401,7 +422,7 @@ pub fn entry_node<T>(entry: &Api3, addr: H160, state: &AppState) -> Node<T> {
             stake,
         } => wrap_line(vec![
             Some(hl_text("CastVote")),
-            wrap_vote(*vote_id, agent, &state.votings),
+            wrap_vote(*vote_id, agent, votings),
             Some(hl_text(if *supports { "YEA" } else { "NEI" })),
             Some(normal_text("stake: ")),
             Some(wrap_amt(*stake)),
@@ -409,6 +430,17 @@ pub fn entry_node<T>(entry: &Api3, addr: H160, state: &AppState) -> Node<T> {
         Api3::SetVestingAddresses { addresses: _ } => {
             wrap_line(vec![Some(hl_text("SetVestingAddresses"))])
         }
+        Api3::Decoded(e) => {
+            let mut nodes = vec![Some(hl_text(&e.name))];
+            for p in &e.params {
+                let value = match &p.value {
+                    AbiValue::Address(x) => wrap_address(*x),
+                    x => normal_text(&x.to_string()),
+                };
+                nodes.push(Some(wrap_label(&format!(" {}: ", p.name), value)));
+            }
+            wrap_line(nodes)
+        }
         _ => text(format!("{:?}", entry)),
     }
 }
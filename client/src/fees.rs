@@ -8,14 +8,18 @@ use web3::types::TransactionReceipt as Receipt;
 use web3::types::{Transaction, H256, U256};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TxFee {
     /// Gas Price
     #[serde(rename = "gasPrice")]
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub gas_price: U256,
     /// Gas amount (limit on transaction creation)
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub gas: U256,
     /// Gas that was actually used from receipt
     #[serde(rename = "gasUsed")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<crate::schema::Uint>"))]
     pub gas_used: Option<U256>,
     /// USD equivalent of the price paid for gas
     pub usd: Option<f64>,
@@ -69,7 +73,9 @@ impl TxFee {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TxFeeTotal {
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     eth: U256,
     usd: Option<f64>,
 }
@@ -0,0 +1,105 @@
+use sauron::dom::{CreatedNode, Program};
+use sauron::prelude::*;
+use sauron::wasm_bindgen::closure::Closure;
+use std::collections::HashMap;
+
+/// listeners of the elements, as sauron keeps them in the DOM updater
+type Closures = HashMap<u32, Vec<(&'static str, Closure<dyn FnMut(web_sys::Event)>)>>;
+
+/// Attaches the application to the markup that the server rendered from the same view.
+/// Listeners are added to the existing elements, and the only change to the markup
+/// is splitting the texts the browser merged while parsing the page.
+/// If the markup does not match the view, it is replaced
+pub fn hydrate<APP, MSG>(app: APP, rendered: &web_sys::Node) -> Program<APP, MSG>
+where
+    APP: Component<MSG> + 'static,
+    MSG: 'static,
+{
+    let program = Program::new(app, rendered);
+    let res = {
+        let mut updater = program.dom_updater.borrow_mut();
+        let updater = &mut *updater;
+        attach(
+            &program,
+            &mut updater.active_closures,
+            &updater.current_vdom,
+            rendered,
+        )
+    };
+    if let Err(e) = res {
+        warn!("page is rendered again, {}", e);
+        program.dom_updater.borrow_mut().replace_mount(&program);
+    }
+    program
+}
+
+/// attaches listeners of the virtual element and its children to the DOM element
+fn attach<APP, MSG>(
+    program: &Program<APP, MSG>,
+    closures: &mut Closures,
+    vnode: &sauron::Node<MSG>,
+    node: &web_sys::Node,
+) -> Result<(), String>
+where
+    APP: Component<MSG> + 'static,
+    MSG: 'static,
+{
+    let velem = match vnode {
+        sauron::Node::Element(x) => x,
+        sauron::Node::Text(_) => return Err("text instead of the element".to_owned()),
+    };
+    let el: &web_sys::Element = match node.dyn_ref() {
+        Some(x) => x,
+        None => return Err(format!("no element for <{}>", velem.tag())),
+    };
+    if !el.tag_name().eq_ignore_ascii_case(velem.tag()) {
+        return Err(format!("<{}> instead of <{}>", el.tag_name(), velem.tag()));
+    }
+    let listeners: Vec<&Attribute<MSG>> = velem
+        .get_attributes()
+        .iter()
+        .filter(|a| a.value().iter().any(|v| v.as_event_listener().is_some()))
+        .collect();
+    if !listeners.is_empty() {
+        CreatedNode::set_element_attributes(program, closures, el, &listeners);
+    }
+
+    // patches find nodes by the index of the virtual child,
+    // so every child needs its own node
+    let mut index = 0;
+    for vchild in velem.get_children() {
+        let child = node.child_nodes().item(index);
+        match vchild {
+            sauron::Node::Text(txt) if txt.text.is_empty() => {
+                // empty texts are not rendered at all
+                let empty = sauron::dom::document().create_text_node("");
+                node.insert_before(&empty, child.as_ref())
+                    .map_err(|e| format!("{:?}", e))?;
+            }
+            sauron::Node::Text(txt) => {
+                let child: web_sys::Text = match child.and_then(|c| c.dyn_into().ok()) {
+                    Some(x) => x,
+                    None => return Err(format!("no text node for {:?}", txt.text)),
+                };
+                let content = child.data();
+                if content != txt.text {
+                    if !content.starts_with(&txt.text) {
+                        return Err(format!("{:?} instead of {:?}", content, txt.text));
+                    }
+                    // neighbour texts were merged by the browser
+                    let offset = txt.text.encode_utf16().count() as u32;
+                    child.split_text(offset).map_err(|e| format!("{:?}", e))?;
+                }
+            }
+            sauron::Node::Element(_) => match child {
+                Some(child) => attach(program, closures, vchild, &child)?,
+                None => return Err(format!("<{}> has less children", velem.tag())),
+            },
+        }
+        index += 1;
+    }
+    if node.child_nodes().length() != index {
+        return Err(format!("<{}> has more children", velem.tag()));
+    }
+    Ok(())
+}
@@ -1,20 +1,33 @@
+pub mod abi;
 pub mod action;
+pub mod api;
 pub mod components;
 pub mod events;
 pub mod eventsnode;
 pub mod fees;
+pub mod hydrate;
 pub mod logreader;
 pub mod nice;
+pub mod participation;
+pub mod poolhistory;
+pub mod projections;
 pub mod router;
+pub mod routes;
+#[cfg(feature = "schema")]
+pub mod schema;
 pub mod screens;
+pub mod search;
 pub mod state;
+pub mod timeline;
+pub mod unstakes;
 pub mod usdprice;
+pub mod vesting;
+pub mod views;
+pub mod votetimeline;
 
-use crate::events::{voting_from_str, voting_to_u64};
+use routes::{Payload, Route, Switch};
 use sauron::prelude::*;
 use state::AppState;
-use std::str::FromStr;
-use web3::types::H160;
 
 #[macro_use]
 extern crate log;
@@ -24,52 +37,44 @@ pub fn main(serialized_state: String) {
     console_log::init_with_level(log::Level::Trace).unwrap();
     console_error_panic_hook::set_once();
 
-    let mut appstate = AppState::new(1);
-    if serialized_state.len() > 4 {
-        match serde_json::from_str::<AppState>(&serialized_state) {
-            Ok(state) => {
-                info!("parsing state ok");
-                appstate = state;
-            }
-            Err(e) => {
-                info!("parsing error {}", e);
-            }
-        };
-    }
     let document = sauron::dom::document();
-    let root = document.query_selector_all("main").unwrap().get(0).unwrap();
-    let pathname = sauron::dom::window()
-        .location()
-        .pathname()
-        .expect("cannot get window.location");
-    match pathname.as_str() {
-        "/votings" => {
-            Program::replace_mount(screens::votings::Screen::new(appstate), &root);
-        }
-        "/rewards" => {
-            Program::replace_mount(screens::rewards::Screen::new(appstate), &root);
-        }
-        "/wallets" => {
-            Program::replace_mount(screens::wallets::Screen::new(appstate), &root);
+    let location = sauron::dom::window().location();
+    let pathname = location.pathname().expect("cannot get window.location");
+    let search = location.search().unwrap_or_default();
+    let (root, route) = Route::locate(&pathname, &search);
+    // the page rendered by the server is hydrated with the payload it was rendered from
+    let rendered = document.query_selector("main > .switch").ok().flatten();
+    let (route, payload) = match Payload::parse(&route, &serialized_state) {
+        Ok(payload) => {
+            info!("parsing state ok");
+            (route, payload)
         }
-        "/treasury" => {
-            Program::replace_mount(screens::treasury::Screen::new(appstate), &root);
-        }
-        _ => {
-            if pathname.starts_with("/votings/") {
-                let offs = "/votings/".len();
-                let vote_str: String = pathname.chars().skip(offs).collect();
-                let (agent, vote_id) = voting_from_str(&vote_str);
-                let vote_ref = voting_to_u64(&agent, vote_id);
-                Program::replace_mount(screens::voting::Screen::new(appstate, vote_ref), &root);
-            } else if pathname.starts_with("/wallets/") {
-                let offs = "/wallets/".len();
-                let wallet_str: String = pathname.chars().skip(offs).collect();
-                let addr = H160::from_str(&wallet_str).unwrap();
-                Program::replace_mount(screens::wallet::Screen::new(appstate, &addr), &root);
-            } else {
-                Program::replace_mount(screens::home::Screen::new(appstate), &root);
+        Err(e) => {
+            info!("parsing error {}", e);
+            if rendered.is_some() {
+                return;
             }
+            // the whole state, as it is downloaded to develop only client-side
+            let state = match serde_json::from_str::<AppState>(&serialized_state) {
+                Ok(state) => state,
+                Err(_) => AppState::new(1),
+            };
+            let route = route.check(&state);
+            let payload = route.payload(&state);
+            (route, payload)
         }
     };
+    if rendered.is_some() && !route.is_client() {
+        return;
+    }
+    let switch = Switch::new(route, payload, &root);
+    match rendered {
+        Some(el) => {
+            hydrate::hydrate(switch, &el.into());
+        }
+        None => {
+            let mount = document.query_selector_all("main").unwrap().get(0).unwrap();
+            Program::replace_mount(switch, &mount);
+        }
+    }
 }
@@ -10,6 +10,31 @@ pub enum EventParseError {
     InvalidTopics(usize, usize),
     #[error("{0} data length, {1} bytes expected")]
     InvalidDataSize(usize, usize),
+    #[error("voting event from unknown contract")]
+    NoVotingAgent,
+    #[error("{0} event has no valid param #{1}")]
+    InvalidParam(String, usize),
+}
+
+/// converts bytes of the meta data into the printable text
+pub fn safe_text(bts: &[u8]) -> String {
+    let mut s = String::from("");
+    bts.iter().filter(|ch| **ch != 0).for_each(|ch| {
+        if *ch == 0x1F {
+            s.push('|');
+        } else if *ch == '\\' as u8
+            || *ch == '"' as u8
+            || *ch == '\'' as u8
+            || *ch == '<' as u8
+            || *ch == '>' as u8
+        {
+            // preventing HTML injection
+            s.push(' ');
+        } else if *ch > 0x1F && *ch < 0x80 {
+            s.push(*ch as char);
+        }
+    });
+    s
 }
 
 pub struct LogReader {
@@ -80,21 +105,7 @@ impl LogReader {
         while self.has_data() {
             let nextword = self.next32();
             let bts: Vec<u8> = hex::decode(nextword).unwrap();
-            bts.iter().filter(|ch| **ch != 0).for_each(|ch| {
-                if *ch == 0x1F {
-                    s.push('|');
-                } else if *ch == '\\' as u8
-                    || *ch == '"' as u8
-                    || *ch == '\'' as u8
-                    || *ch == '<' as u8
-                    || *ch == '>' as u8
-                {
-                    // preventing HTML injection
-                    s.push(' ');
-                } else if *ch > 0x1F && *ch < 0x80 {
-                    s.push(*ch as char);
-                }
-            });
+            s.push_str(&safe_text(&bts));
         }
         s
     }
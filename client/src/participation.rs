@@ -0,0 +1,356 @@
+use crate::nice;
+use crate::state::{AppState, Voting, Wallet};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use web3::types::{H160, U256};
+
+/// number of votings that are used for turnout moving average
+pub const TREND_WINDOW: usize = 5;
+/// number of the most active voters to be reported
+pub const MOST_ACTIVE_LIMIT: usize = 10;
+
+// Turnout of the single voting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VotingTurnout {
+    /// reference of the voting (see events::voting_to_u64)
+    pub vote_ref: u64,
+    /// human readable key of the voting, i.e. "p-12"
+    pub key: String,
+    /// whether it is primary voting
+    pub primary: bool,
+    /// title of the voting
+    pub title: String,
+    /// timestamp of the voting start
+    pub tm: u64,
+    /// total voting power at the start of the voting
+    pub votes_total: U256,
+    /// voting power that was cast, both for and against
+    pub votes_cast: U256,
+    /// number of members that voted
+    pub voters: u64,
+    /// number of members that were eligible to vote
+    pub eligible: u64,
+    /// share of the voting power that was cast, from 0 to 1
+    pub turnout: f64,
+}
+
+// Participation of the wallet in the votings it was eligible for
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WalletParticipation {
+    pub address: H160,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ens: Option<String>,
+    /// number of votings the wallet was eligible for
+    pub eligible: u64,
+    /// number of votings the wallet voted in
+    pub voted: u64,
+    /// share of eligible votings with a vote, from 0 to 1
+    pub rate: f64,
+}
+
+// Turnout of the voting together with moving average of the previous ones
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnoutTrendPoint {
+    pub vote_ref: u64,
+    pub tm: u64,
+    pub turnout: f64,
+    /// average turnout of the last TREND_WINDOW votings
+    pub average: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Participation {
+    /// turnout of every voting, ordered by start time
+    pub votings: Vec<VotingTurnout>,
+    /// average turnout across all votings
+    pub avg_turnout: f64,
+    /// participation of every wallet
+    pub wallets: BTreeMap<H160, WalletParticipation>,
+    /// wallets with the most votes
+    pub most_active: Vec<WalletParticipation>,
+    /// turnout trend across votings
+    pub trend: Vec<TurnoutTrendPoint>,
+}
+
+// ratio of two token amounts, with 0 for empty total
+pub fn ratio(part: U256, total: U256) -> f64 {
+    let t = nice::dec(total, 12);
+    if t == 0.0 {
+        return 0.0;
+    }
+    nice::dec(part, 12) / t
+}
+
+// The wallet is eligible if it had voting power at the snapshot block.
+// Votings without snapshot fall back to the wallet joining the DAO before the start
+pub fn is_eligible(v: &Voting, w: &Wallet) -> bool {
+    if v.has_voted(&w.address) {
+        return true;
+    }
+    if v.snapshot.is_empty() {
+        return w.created_at < v.tm;
+    }
+    v.snapshot.contains_key(&w.address)
+}
+
+pub fn voting_turnout(state: &AppState, v: &Voting) -> VotingTurnout {
+    let votes_cast = v.voted_yes + v.voted_no;
+    let eligible = state.wallets.values().filter(|w| is_eligible(v, w)).count() as u64;
+    let voters = v
+        .yes
+        .keys()
+        .chain(v.no.keys().filter(|a| !v.yes.contains_key(a)))
+        .count();
+    VotingTurnout {
+        vote_ref: v.as_u64(),
+        key: v.key(),
+        primary: v.primary,
+        title: v.title.clone(),
+        tm: v.tm,
+        votes_total: v.votes_total,
+        votes_cast,
+        voters: voters as u64,
+        eligible,
+        turnout: ratio(votes_cast, v.votes_total),
+    }
+}
+
+pub fn wallet_participation(state: &AppState, w: &Wallet) -> WalletParticipation {
+    let mut eligible = 0u64;
+    let mut voted = 0u64;
+    for v in state.votings.values() {
+        if is_eligible(v, w) {
+            eligible += 1;
+            if v.has_voted(&w.address) {
+                voted += 1;
+            }
+        }
+    }
+    WalletParticipation {
+        address: w.address,
+        ens: w.ens.clone(),
+        eligible,
+        voted,
+        rate: if eligible > 0 {
+            voted as f64 / eligible as f64
+        } else {
+            0.0
+        },
+    }
+}
+
+pub fn turnout_trend(votings: &[VotingTurnout]) -> Vec<TurnoutTrendPoint> {
+    votings
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            let from = (i + 1).saturating_sub(TREND_WINDOW);
+            let window = &votings[from..i + 1];
+            let average = window.iter().map(|x| x.turnout).sum::<f64>() / window.len() as f64;
+            TurnoutTrendPoint {
+                vote_ref: v.vote_ref,
+                tm: v.tm,
+                turnout: v.turnout,
+                average,
+            }
+        })
+        .collect()
+}
+
+impl Participation {
+    pub fn from_state(state: &AppState) -> Self {
+        let mut votings: Vec<VotingTurnout> = state
+            .votings
+            .values()
+            .map(|v| voting_turnout(state, v))
+            .collect();
+        votings.sort_by_key(|v| (v.tm, v.vote_ref));
+        let avg_turnout = if !votings.is_empty() {
+            votings.iter().map(|v| v.turnout).sum::<f64>() / votings.len() as f64
+        } else {
+            0.0
+        };
+
+        let wallets: BTreeMap<H160, WalletParticipation> = state
+            .wallets
+            .iter()
+            .map(|(addr, w)| (*addr, wallet_participation(state, w)))
+            .collect();
+        let mut most_active: Vec<WalletParticipation> =
+            wallets.values().filter(|w| w.voted > 0).cloned().collect();
+        most_active.sort_by(|a, b| {
+            b.voted.cmp(&a.voted).then(
+                b.rate
+                    .partial_cmp(&a.rate)
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            )
+        });
+        most_active.truncate(MOST_ACTIVE_LIMIT);
+
+        let trend = turnout_trend(&votings);
+        Self {
+            votings,
+            avg_turnout,
+            wallets,
+            most_active,
+            trend,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{Api3, VotingAgent};
+    use crate::fees::TxFee;
+    use crate::state::OnChainEvent;
+    use web3::types::{Bytes, Log, H256};
+
+    fn tokens(n: u64) -> U256 {
+        U256::from(n) * U256::exp10(18)
+    }
+
+    fn wallet(n: u64, created_at: u64) -> Wallet {
+        Wallet {
+            address: H160::from_low_u64_be(n),
+            created_at,
+            ..Default::default()
+        }
+    }
+
+    fn voting(vote_id: u64, tm: u64, yes: Vec<(u64, u64)>, no: Vec<(u64, u64)>) -> Voting {
+        let mut v = Voting {
+            primary: true,
+            vote_id,
+            tm,
+            votes_total: tokens(1000),
+            ..Default::default()
+        };
+        for (addr, amt) in yes {
+            v.yes.insert(H160::from_low_u64_be(addr), tokens(amt));
+            v.voted_yes += tokens(amt);
+        }
+        for (addr, amt) in no {
+            v.no.insert(H160::from_low_u64_be(addr), tokens(amt));
+            v.voted_no += tokens(amt);
+        }
+        v
+    }
+
+    fn sample_state() -> AppState {
+        let mut state = AppState::new(1);
+        for w in [wallet(1, 10), wallet(2, 10), wallet(3, 150)] {
+            state.wallets.insert(w.address, w);
+        }
+        for v in [
+            voting(0, 100, vec![(1, 300)], vec![(2, 100)]),
+            voting(1, 200, vec![(1, 300)], vec![]),
+        ] {
+            state.votings.insert(v.as_u64(), v);
+        }
+        state
+    }
+
+    #[test]
+    pub fn it_computes_turnout() {
+        let p = Participation::from_state(&sample_state());
+        assert_eq!(p.votings.len(), 2);
+        assert_eq!(p.votings[0].voters, 2);
+        assert_eq!(p.votings[0].eligible, 2);
+        assert!((p.votings[0].turnout - 0.4).abs() < 1e-9);
+        assert_eq!(p.votings[1].eligible, 3);
+        assert!((p.votings[1].turnout - 0.3).abs() < 1e-9);
+        assert!((p.trend[1].average - 0.35).abs() < 1e-9);
+    }
+
+    #[test]
+    pub fn it_computes_wallet_rate() {
+        let p = Participation::from_state(&sample_state());
+        let w1 = p.wallets.get(&H160::from_low_u64_be(1)).unwrap();
+        assert_eq!((w1.eligible, w1.voted), (2, 2));
+        let w2 = p.wallets.get(&H160::from_low_u64_be(2)).unwrap();
+        assert_eq!((w2.eligible, w2.voted), (2, 1));
+        let w3 = p.wallets.get(&H160::from_low_u64_be(3)).unwrap();
+        assert_eq!((w3.eligible, w3.voted), (1, 0));
+        assert_eq!(p.most_active.len(), 2);
+        assert_eq!(p.most_active[0].address, H160::from_low_u64_be(1));
+    }
+
+    #[test]
+    pub fn it_uses_voting_power_snapshot() {
+        let mut state = sample_state();
+        let v = state.votings.values_mut().last().unwrap();
+        v.snapshot.insert(H160::from_low_u64_be(1), tokens(300));
+        v.snapshot.insert(H160::from_low_u64_be(2), tokens(100));
+        let p = Participation::from_state(&state);
+        assert_eq!(p.votings[1].eligible, 2);
+        let w3 = p.wallets.get(&H160::from_low_u64_be(3)).unwrap();
+        assert_eq!(w3.eligible, 0);
+        let non_voters = state.votings.values().last().unwrap().non_voters();
+        assert_eq!(non_voters, vec![(H160::from_low_u64_be(2), tokens(100))]);
+    }
+
+    fn apply(state: &mut AppState, block_number: u64, entry: Api3) {
+        let log = Log {
+            address: H160::from_low_u64_be(99),
+            topics: vec![],
+            data: Bytes(vec![]),
+            block_hash: None,
+            block_number: Some(block_number.into()),
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        };
+        let e = OnChainEvent {
+            entry,
+            tm: block_number * 15,
+            block_number,
+            tx: H256::from_low_u64_be(block_number),
+            log_index: 0,
+            fees: TxFee {
+                gas_price: U256::from(0),
+                gas: U256::from(0),
+                gas_used: None,
+                usd: None,
+            },
+        };
+        state.update(e, log).unwrap();
+    }
+
+    fn staked(user: u64, amount: u64) -> Api3 {
+        Api3::StakedV0 {
+            user: H160::from_low_u64_be(user),
+            amount: tokens(amount),
+            minted_shares: tokens(amount),
+        }
+    }
+
+    #[test]
+    pub fn it_takes_snapshot_before_the_block() {
+        let mut state = AppState::new(1);
+        apply(&mut state, 10, staked(1, 100));
+        apply(&mut state, 11, staked(2, 50));
+        // stake in the block of the voting is not counted, as in userVotingPowerAt(block - 1)
+        apply(&mut state, 11, staked(1, 20));
+        apply(&mut state, 11, staked(3, 70));
+        apply(
+            &mut state,
+            11,
+            Api3::StartVote {
+                agent: VotingAgent::Primary,
+                vote_id: U256::from(1),
+                creator: H160::from_low_u64_be(1),
+                metadata: String::new(),
+            },
+        );
+        let v = state.votings.values().next().unwrap();
+        assert_eq!(v.snapshot.len(), 1);
+        assert_eq!(v.snapshot[&H160::from_low_u64_be(1)], tokens(100));
+        // the next block sees all the stakes
+        apply(&mut state, 12, staked(2, 1));
+        assert_eq!(state.get_voting_power_before_block().len(), 3);
+    }
+}
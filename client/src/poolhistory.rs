@@ -0,0 +1,150 @@
+use crate::state::{AppState, ClaimPayout, DaoAppsChange, OwnershipChange, StakeTargetChange};
+use serde::{Deserialize, Serialize};
+use web3::types::{H160, U256};
+
+// Changes of the pool parameters and DAO contracts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolHistory {
+    /// changes of the staking target, ordered by time
+    pub stake_targets: Vec<StakeTargetChange>,
+    /// DAO apps that were set in the pool, the last one is current
+    pub dao_apps: Vec<DaoAppsChange>,
+    /// ERC20 tokens, listed in convenience contract
+    pub erc20_addresses: Vec<H160>,
+    /// transfers of contracts ownership
+    pub ownership: Vec<OwnershipChange>,
+    /// claims, paid out from the pool
+    pub claims: Vec<ClaimPayout>,
+    /// total amount of paid out claims
+    pub claims_total: U256,
+}
+
+impl PoolHistory {
+    pub fn from_state(state: &AppState) -> Self {
+        let claims_total = state.claims.iter().fold(U256::from(0), |a, c| a + c.amount);
+        Self {
+            stake_targets: state.stake_targets.clone(),
+            dao_apps: state.dao_apps.clone(),
+            erc20_addresses: state.erc20_addresses.clone(),
+            ownership: state.ownership.clone(),
+            claims: state.claims.clone(),
+            claims_total,
+        }
+    }
+
+    /// DAO apps that are currently set in the pool
+    pub fn current_dao_apps(&self) -> Option<&DaoAppsChange> {
+        self.dao_apps.last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Api3;
+    use crate::fees::TxFee;
+    use crate::state::{OnChainEvent, Wallet};
+    use web3::types::{Bytes, Log, H256};
+
+    fn tokens(n: u64) -> U256 {
+        U256::from(n) * U256::exp10(18)
+    }
+
+    fn apply(state: &mut AppState, tm: u64, entry: Api3) {
+        let log = Log {
+            address: H160::from_low_u64_be(99),
+            topics: vec![],
+            data: Bytes(vec![]),
+            block_hash: None,
+            block_number: Some(tm.into()),
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        };
+        let e = OnChainEvent {
+            entry,
+            tm,
+            block_number: tm,
+            tx: H256::from_low_u64_be(tm),
+            log_index: 0,
+            fees: TxFee {
+                gas_price: U256::from(0),
+                gas: U256::from(0),
+                gas_used: None,
+                usd: None,
+            },
+        };
+        state.update(e, log).unwrap();
+    }
+
+    #[test]
+    pub fn it_tracks_pool_events() {
+        let (a, b) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+        let mut state = AppState::new(1);
+        for (addr, staked) in [(a, 300), (b, 100)] {
+            let w = Wallet {
+                address: addr,
+                staked: tokens(staked),
+                shares: tokens(staked),
+                ..Default::default()
+            };
+            state.wallets_events.insert(addr, vec![]);
+            state.wallets.insert(addr, w);
+        }
+
+        apply(
+            &mut state,
+            10,
+            Api3::SetStakeTarget {
+                stake_target: tokens(50),
+            },
+        );
+        apply(
+            &mut state,
+            20,
+            Api3::PaidOutClaim {
+                recipient: H160::from_low_u64_be(3),
+                amount: tokens(40),
+                total_stake: tokens(360),
+            },
+        );
+        apply(
+            &mut state,
+            30,
+            Api3::VestedTimelock {
+                user: b,
+                amount: tokens(10),
+                user_vesting: tokens(5),
+            },
+        );
+        apply(
+            &mut state,
+            40,
+            Api3::UpdatedLastProposalTimestamp {
+                user: a,
+                last_proposal_timestamp: U256::from(40),
+                voting_app: H160::from_low_u64_be(4),
+            },
+        );
+        apply(
+            &mut state,
+            50,
+            Api3::OwnershipTransferred { from: a, to: b },
+        );
+
+        let h = PoolHistory::from_state(&state);
+        assert_eq!(h.stake_targets[0].stake_target, tokens(50));
+        assert_eq!(h.claims_total, tokens(40));
+        assert_eq!(h.ownership[0].contract, H160::from_low_u64_be(99));
+        // claim is paid proportionally to the stake
+        assert_eq!(state.wallets[&a].staked, tokens(270));
+        assert_eq!(state.wallets[&a].claim_loss, tokens(30));
+        assert_eq!(state.wallets[&b].staked, tokens(90));
+        assert_eq!(state.wallets[&b].vesting_remaining, Some(tokens(5)));
+        assert_eq!(state.timelocks[&b][0].vested, tokens(10));
+        assert_eq!(state.wallets[&a].next_proposal_tm(100), Some(140));
+    }
+}
@@ -0,0 +1,352 @@
+use crate::nice;
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use web3::types::H160;
+
+/// APR is changed by 1% on every epoch
+pub const APR_UPDATE_STEP: f64 = 0.01;
+/// default number of epochs to project
+pub const DEFAULT_EPOCHS: usize = 52;
+/// seconds in the year of the pool, as APR is annual
+pub const YEAR: u64 = 365 * 24 * 60 * 60;
+
+// Parameters of the pool that are used by the projection,
+// amounts are in tokens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectionParams {
+    /// index of the next epoch
+    pub epoch_index: u64,
+    /// timestamp of the next epoch
+    pub tm: u64,
+    /// APR for the next epoch
+    pub apr: f64,
+    pub min_apr: f64,
+    pub max_apr: f64,
+    /// length of epoch in seconds
+    pub epoch_length: u64,
+    /// number of epochs before rewards are unlocked
+    pub reward_vesting_period: u64,
+    /// total stake in the pool
+    pub total_stake: f64,
+    /// total supply of API3 token
+    pub total_supply: f64,
+    /// staking target, as a share of total supply
+    pub stake_target: f64,
+}
+
+// Projected state of the pool after the epoch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectedEpoch {
+    pub index: u64,
+    pub tm: u64,
+    /// APR that was used for minting rewards in this epoch
+    pub apr: f64,
+    /// amount of rewards minted
+    pub minted: f64,
+    /// total stake after minting
+    pub total_stake: f64,
+    /// total supply after minting
+    pub total_supply: f64,
+    /// whether staking target was reached after minting
+    pub target_reached: bool,
+}
+
+// Projected rewards of the wallet in the epoch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletProjectedEpoch {
+    pub index: u64,
+    pub tm: u64,
+    /// wallet stake after minting, including locked rewards
+    pub stake: f64,
+    pub reward: f64,
+    /// time when this reward is unlocked
+    pub unlock_tm: u64,
+}
+
+// Rewards that become unlocked at the given time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VestingUnlock {
+    pub epoch: u64,
+    pub tm: u64,
+    pub amount: f64,
+    /// false for rewards that were already minted
+    pub projected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletProjection {
+    pub address: H160,
+    /// current stake of the wallet, including locked rewards
+    pub stake: f64,
+    pub epochs: Vec<WalletProjectedEpoch>,
+    pub unlocks: Vec<VestingUnlock>,
+    /// sum of projected rewards
+    pub total_rewards: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Projection {
+    pub params: ProjectionParams,
+    pub epochs: Vec<ProjectedEpoch>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet: Option<WalletProjection>,
+}
+
+impl ProjectionParams {
+    pub fn from_state(state: &AppState) -> Option<Self> {
+        let pool = match &state.pool_info {
+            Some(x) => x,
+            None => return None,
+        };
+        let circulation = match &state.circulation {
+            Some(x) => x,
+            None => return None,
+        };
+        let tm = match state.epochs.values().last() {
+            Some(ep) => ep.tm + pool.epoch_length,
+            None => 0,
+        };
+        Some(Self {
+            epoch_index: state.epoch_index,
+            tm,
+            apr: state.apr,
+            min_apr: pool.min_apr,
+            max_apr: pool.max_apr,
+            epoch_length: pool.epoch_length,
+            reward_vesting_period: pool.reward_vesting_period,
+            total_stake: nice::dec(pool.total_stake, 18),
+            total_supply: nice::dec(circulation.total_supply, 18),
+            stake_target: nice::dec(pool.stake_target, 18),
+        })
+    }
+
+    pub fn release_offset(&self) -> u64 {
+        self.reward_vesting_period * self.epoch_length
+    }
+
+    // rewards that are minted per epoch for the given stake,
+    // the same way the pool mints them: totalStake * apr * EPOCH_LENGTH / 365 days
+    pub fn epoch_rewards(&self, stake: f64, apr: f64) -> f64 {
+        stake * apr * self.epoch_length as f64 / YEAR as f64
+    }
+
+    // APR for the next epoch, the same way the pool updates it after minting:
+    // it goes down by a step when staking target is reached and up otherwise
+    pub fn next_apr(&self, apr: f64, total_stake: f64, total_supply: f64) -> f64 {
+        let reached = total_supply > 0.0 && total_stake / total_supply > self.stake_target;
+        let apr = if reached {
+            (apr - APR_UPDATE_STEP).max(0.0)
+        } else {
+            apr + APR_UPDATE_STEP
+        };
+        apr.max(self.min_apr).min(self.max_apr)
+    }
+}
+
+// Simulates the pool for the number of epochs.
+// stake_changes are the amounts that are staked (or unstaked, if negative)
+// before every epoch, missing values mean no change
+pub fn simulate(
+    params: &ProjectionParams,
+    num_epochs: usize,
+    stake_changes: &[f64],
+) -> Vec<ProjectedEpoch> {
+    let mut out = vec![];
+    let mut apr = params.apr;
+    let mut total_stake = params.total_stake;
+    let mut total_supply = params.total_supply;
+    for i in 0..num_epochs {
+        let change = stake_changes.get(i).cloned().unwrap_or(0.0);
+        total_stake = (total_stake + change).max(0.0);
+        let minted = params.epoch_rewards(total_stake, apr);
+        total_stake += minted;
+        total_supply += minted;
+        out.push(ProjectedEpoch {
+            index: params.epoch_index + i as u64,
+            tm: params.tm + params.epoch_length * i as u64,
+            apr,
+            minted,
+            total_stake,
+            total_supply,
+            target_reached: total_supply > 0.0 && total_stake / total_supply > params.stake_target,
+        });
+        apr = params.next_apr(apr, total_stake, total_supply);
+    }
+    out
+}
+
+// Estimates rewards of the wallet with the given stake over the projected epochs.
+// Rewards are locked and keep being staked, so they are compounding
+pub fn project_wallet(
+    params: &ProjectionParams,
+    epochs: &[ProjectedEpoch],
+    address: H160,
+    stake: f64,
+) -> WalletProjection {
+    let mut wallet_stake = stake;
+    let mut total_rewards = 0.0;
+    let mut out = vec![];
+    for ep in epochs {
+        let before = ep.total_stake - ep.minted;
+        let reward = if before > 0.0 {
+            ep.minted * wallet_stake / before
+        } else {
+            0.0
+        };
+        wallet_stake += reward;
+        total_rewards += reward;
+        out.push(WalletProjectedEpoch {
+            index: ep.index,
+            tm: ep.tm,
+            stake: wallet_stake,
+            reward,
+            unlock_tm: ep.tm + params.release_offset(),
+        });
+    }
+    let unlocks = out
+        .iter()
+        .map(|ep| VestingUnlock {
+            epoch: ep.index,
+            tm: ep.unlock_tm,
+            amount: ep.reward,
+            projected: true,
+        })
+        .collect();
+    WalletProjection {
+        address,
+        stake,
+        epochs: out,
+        unlocks,
+        total_rewards,
+    }
+}
+
+// Rewards of the wallet that were minted but are still locked
+pub fn locked_unlocks(state: &AppState, addr: &H160, now: u64) -> Vec<VestingUnlock> {
+    match state.rewards_ledger.get(addr) {
+        Some(entries) => entries
+            .iter()
+            .filter(|r| r.is_locked(now))
+            .filter_map(|r| {
+                // rewards with unknown unlock time cannot be projected
+                Some(VestingUnlock {
+                    epoch: r.epoch,
+                    tm: r.unlock_tm?,
+                    amount: nice::dec(r.amount, 18),
+                    projected: false,
+                })
+            })
+            .collect(),
+        None => vec![],
+    }
+}
+
+impl Projection {
+    pub fn from_state(
+        state: &AppState,
+        num_epochs: usize,
+        stake_changes: &[f64],
+        wallet: Option<(H160, Option<f64>)>,
+    ) -> Option<Self> {
+        let params = ProjectionParams::from_state(state)?;
+        let epochs = simulate(&params, num_epochs, stake_changes);
+        let wallet = wallet.map(|(addr, stake)| {
+            let stake = match stake {
+                Some(x) => x,
+                None => match state.wallets.get(&addr) {
+                    Some(w) => nice::dec(w.staked + w.rewards, 18),
+                    None => 0.0,
+                },
+            };
+            let mut p = project_wallet(&params, &epochs, addr, stake);
+            let mut unlocks = locked_unlocks(state, &addr, params.tm);
+            unlocks.append(&mut p.unlocks);
+            p.unlocks = unlocks;
+            p
+        });
+        Some(Self {
+            params,
+            epochs,
+            wallet,
+        })
+    }
+
+    /// projection of the stake that does not belong to any member yet
+    pub fn from_params(
+        params: &ProjectionParams,
+        num_epochs: usize,
+        stake_changes: &[f64],
+        stake: f64,
+    ) -> Self {
+        let epochs = simulate(params, num_epochs, stake_changes);
+        let wallet = project_wallet(params, &epochs, H160::zero(), stake);
+        Self {
+            params: params.clone(),
+            epochs,
+            wallet: Some(wallet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> ProjectionParams {
+        ProjectionParams {
+            epoch_index: 10,
+            tm: 1_000_000,
+            apr: 0.3,
+            min_apr: 0.025,
+            max_apr: 0.75,
+            epoch_length: 604800,
+            reward_vesting_period: 52,
+            total_stake: 40_000_000.0,
+            total_supply: 100_000_000.0,
+            stake_target: 0.5,
+        }
+    }
+
+    #[test]
+    pub fn it_increases_apr_below_target() {
+        let epochs = simulate(&params(), 3, &[]);
+        assert_eq!(epochs.len(), 3);
+        assert!((epochs[0].apr - 0.3).abs() < 1e-9);
+        assert!((epochs[1].apr - 0.31).abs() < 1e-9);
+        assert!((epochs[2].apr - 0.32).abs() < 1e-9);
+        assert!((epochs[0].minted - 40_000_000.0 * 0.3 * 7.0 / 365.0).abs() < 1e-6);
+        assert_eq!(epochs[2].tm, 1_000_000 + 2 * 604800);
+    }
+
+    #[test]
+    pub fn it_mints_per_epoch_length() {
+        let mut p = params();
+        let weekly = p.epoch_rewards(1000.0, 0.365);
+        assert!((weekly - 7.0).abs() < 1e-9);
+        // vesting period only delays the unlock
+        p.reward_vesting_period = 26;
+        assert!((p.epoch_rewards(1000.0, 0.365) - weekly).abs() < 1e-9);
+        p.epoch_length = 3600;
+        assert!((p.epoch_rewards(1000.0, 0.365) - 1.0 / 24.0).abs() < 1e-9);
+    }
+
+    #[test]
+    pub fn it_decreases_apr_above_target() {
+        let mut p = params();
+        p.apr = 0.03;
+        let epochs = simulate(&p, 3, &[20_000_000.0]);
+        assert!(epochs[0].target_reached);
+        assert!((epochs[1].apr - 0.025).abs() < 1e-9);
+        assert!((epochs[2].apr - 0.025).abs() < 1e-9);
+    }
+
+    #[test]
+    pub fn it_projects_wallet_rewards() {
+        let p = params();
+        let epochs = simulate(&p, 2, &[]);
+        let w = project_wallet(&p, &epochs, H160::zero(), 400_000.0);
+        assert!((w.epochs[0].reward - epochs[0].minted / 100.0).abs() < 1e-6);
+        assert_eq!(w.unlocks[0].tm, p.tm + 52 * 604800);
+        assert!(w.total_rewards > 0.0);
+    }
+}
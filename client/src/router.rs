@@ -1,15 +1,12 @@
 use crate::nice;
-use crate::state::AppState;
+use crate::state::{AppState, NetworkInfo};
+use crate::views::Member;
 use sauron::prelude::*;
+use std::collections::BTreeMap;
 use web3::types::{H160, H256};
 
-pub fn link_eventlog<T>(chain_id: u64, block_number: u64, tx: H256) -> Node<T> {
-    let link = match chain_id {
-        1 => Some(format!("https://etherscan.io/tx/{:?}#eventlog", tx)),
-        4 => Some(format!("https://rinkeby.etherscan.io/tx/{:?}#eventlog", tx)),
-        _ => None,
-    };
-    match link {
+pub fn link_eventlog<T>(network: &NetworkInfo, block_number: u64, tx: H256) -> Node<T> {
+    match network.tx_url(tx) {
         Some(link) => node! {
             <a href={link} rel="nofollow noopener noreferrer" target="_blank">
                 {text(nice::int(block_number))}
@@ -51,17 +48,9 @@ pub fn icon_external<T>() -> Node<T> {
     }
 }
 
-pub fn link_address<T>(chain_id: u64, address: H160, show_text: bool) -> Node<T> {
+pub fn link_address<T>(network: &NetworkInfo, address: H160, show_text: bool) -> Node<T> {
     let address_str = format!("{:?}", address);
-    let link = match chain_id {
-        1 => Some(format!("https://etherscan.io/address/{}", address_str)),
-        4 => Some(format!(
-            "https://rinkeby.etherscan.io/address/{}",
-            address_str
-        )),
-        _ => None,
-    };
-    match link {
+    match network.address_url(address) {
         Some(link) => node! {
             <a href={link} class="icon" title="View on Etherscan" rel="nofollow noopener noreferrer" target="_blank">
                 {if show_text { text(address_str)} else { icon_external()}}
@@ -84,29 +73,42 @@ pub fn link<T>(link: String) -> Node<T> {
 
 pub fn link_wallet<T>(state: &AppState, addr: H160) -> Node<T> {
     match state.wallets.get(&addr) {
-        Some(w) => {
-            let labels = state.get_labels(w);
-            node! {
-                <span>
-                    <a style="display: inline-block; margin-right: 10px;" href={format!("wallets/{:?}", w.address)}>
-                        <div>
-                            {span(vec![class("badges")], labels.iter().map(|v| {
-                                let title = format!("{}", v.title);
-                                node! {
-                                    <span class={format!("badge {}", v.class)} title={title}>{text(v.text.clone().as_str())}</span>
-                                }
-                            }).collect::<Vec<Node<T>>>())}
-                            {match &w.ens {
-                                Some(ens) => strong(vec![class("ens")],vec![text(ens)]),
-                                None => span(vec![],vec![]),
-                            }}
-                        </div>
-                        <div>{text(format!("{:?}", w.address))}</div>
-                    </a>
-                    {link_address(state.chain_id, w.address, false)}
-                </span>
-            }
-        }
+        Some(_) => link_member(&state.network, &Member::from_state(state, &addr)),
+        None => span(vec![], vec![text(format!("{:?}", addr))]),
+    }
+}
+
+pub fn link_member<T>(network: &NetworkInfo, m: &Member) -> Node<T> {
+    node! {
+        <span>
+            <a style="display: inline-block; margin-right: 10px;" href={format!("wallets/{:?}", m.address)}>
+                <div>
+                    {span(vec![class("badges")], m.labels.iter().map(|v| {
+                        let title = format!("{}", v.title);
+                        node! {
+                            <span class={format!("badge {}", v.class)} title={title}>{text(v.text.clone().as_str())}</span>
+                        }
+                    }).collect::<Vec<Node<T>>>())}
+                    {match &m.ens {
+                        Some(ens) => strong(vec![class("ens")],vec![text(ens)]),
+                        None => span(vec![],vec![]),
+                    }}
+                </div>
+                <div>{text(format!("{:?}", m.address))}</div>
+            </a>
+            {link_address(network, m.address, false)}
+        </span>
+    }
+}
+
+/// link to the member, mentioned on the page, or just the address
+pub fn link_known<T>(
+    network: &NetworkInfo,
+    members: &BTreeMap<H160, Member>,
+    addr: H160,
+) -> Node<T> {
+    match members.get(&addr) {
+        Some(m) => link_member(network, m),
         None => span(vec![], vec![text(format!("{:?}", addr))]),
     }
 }
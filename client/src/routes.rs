@@ -1,48 +1,750 @@
-// use crate::components::themeswitcher;
-use crate::screens::home;
+use crate::events::{voting_from_str, voting_from_u64, voting_to_string, voting_to_u64};
+use crate::screens;
+use crate::screens::meta::{MetaProvider, PageMetaInfo};
+use crate::search::SearchIndex;
 use crate::state::AppState;
+use crate::views::{
+    HomeSummary, ProjectionsPage, RewardsTable, Site, TreasuryPage, UnstakesPage, VotingPage,
+    VotingsPage, WalletPage, WalletsPage,
+};
 use sauron::prelude::*;
-use serde::{Deserialize, Serialize};
-// use std::{cell::RefCell, rc::Rc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use web3::types::H160;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Switch {
-    /// server side state
-    state: AppState,
-    screen_home: Option<home::Screen>,
+/// Pages of the tracker. The same table is used by the server to render pages
+/// and by the browser to navigate between them, so URLs cannot drift
+#[derive(Debug, Clone, PartialEq)]
+pub enum Route {
+    Home,
+    Rewards,
+    Wallets,
+    Wallet(H160),
+    Votings,
+    Voting(u64),
+    Treasury,
+    Projections,
+    Unstakes,
+    Search(String),
+    /// page that cannot be shown, with the reason
+    Failure(String),
 }
 
-impl Switch {
-    pub fn new() -> Self {
-        Self {
-            state: AppState::new(),
-            screen_home: Some(home::Screen::new(AppState::new())),
+/// decodes the parameter of the query string
+pub fn query_param(query: &str, key: &str) -> Option<String> {
+    let prefix = format!("{}=", key);
+    let value = query
+        .trim_start_matches('?')
+        .split('&')
+        .find_map(|p| p.strip_prefix(prefix.as_str()))?;
+    let bytes = value.as_bytes();
+    let mut res: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'+', _) => res.push(b' '),
+            (b'%', Some(b)) => {
+                res.push(b);
+                i += 2;
+            }
+            (b, _) => res.push(b),
         }
+        i += 1;
     }
-    pub fn from_state(app: AppState) -> Self {
-        Self {
-            state: app.clone(),
-            screen_home: None,
+    Some(String::from_utf8_lossy(&res).to_string())
+}
+
+/// encodes the value for the query string
+pub fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            b' ' => "+".to_owned(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// absolute path of the link, which is relative to the base URL,
+/// links to other sites and anchors are not resolved
+pub fn resolve(base: &str, href: &str) -> Option<String> {
+    if href.is_empty() || href.starts_with('#') || href.starts_with("//") || href.contains(':') {
+        return None;
+    }
+    // origin of the base is not needed
+    let base = match base.find("://") {
+        Some(pos) => match base[pos + 3..].find('/') {
+            Some(slash) => &base[pos + 3 + slash..],
+            None => "/",
+        },
+        None => base,
+    };
+    let (href, query) = match href.find('?') {
+        Some(pos) => (&href[..pos], &href[pos..]),
+        None => (href, ""),
+    };
+    let joined = if href.starts_with('/') {
+        href.to_owned()
+    } else {
+        let dir = match base.rfind('/') {
+            Some(pos) => &base[..pos + 1],
+            None => "/",
+        };
+        format!("{}{}", dir, href)
+    };
+    let mut parts: Vec<&str> = vec![];
+    let segments: Vec<&str> = joined.split('/').collect();
+    for (i, s) in segments.iter().enumerate() {
+        match *s {
+            "" | "." if i + 1 < segments.len() => {}
+            ".." => {
+                parts.pop();
+            }
+            "." => {}
+            s => parts.push(s),
+        }
+    }
+    Some(format!("/{}{}", parts.join("/"), query))
+}
+
+impl Route {
+    /// route of the path relative to the root of the network
+    pub fn parse(path: &str, query: &str) -> Option<Self> {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let route = match segments.as_slice() {
+            [] => Self::Home,
+            ["rewards"] => Self::Rewards,
+            ["wallets"] => Self::Wallets,
+            ["wallets", id] => match H160::from_str(id) {
+                Ok(addr) => Self::Wallet(addr),
+                Err(_) => Self::Failure("Invalid Ethereum address".to_owned()),
+            },
+            ["votings"] => Self::Votings,
+            ["votings", id] => match voting_from_str(id) {
+                Some((agent, vote_id)) => Self::Voting(voting_to_u64(&agent, vote_id)),
+                None => Self::Failure("Invalid voting ID".to_owned()),
+            },
+            ["treasury"] => Self::Treasury,
+            ["projections"] => Self::Projections,
+            ["unstakes"] => Self::Unstakes,
+            ["search"] => Self::Search(query_param(query, "q").unwrap_or_default()),
+            _ => return None,
+        };
+        Some(route)
+    }
+
+    /// root of the network and the route of the page, parsed from the location in the browser
+    pub fn locate(pathname: &str, query: &str) -> (String, Self) {
+        let path = pathname.trim_end_matches('/');
+        let segments: Vec<&str> = path.split('/').collect();
+        for n in [2, 1] {
+            if segments.len() <= n {
+                continue;
+            }
+            let tail = segments[segments.len() - n..].join("/");
+            if let Some(route) = Self::parse(&tail, query) {
+                let root = &path[..path.len() - tail.len()];
+                return (root.to_owned(), route);
+            }
+        }
+        (format!("{}/", path), Self::Home)
+    }
+
+    /// link to the page, relative to the root of the network
+    pub fn path(&self) -> String {
+        match self {
+            Self::Home | Self::Failure(_) => "".to_owned(),
+            Self::Rewards => "rewards".to_owned(),
+            Self::Wallets => "wallets".to_owned(),
+            Self::Wallet(addr) => format!("wallets/{:?}", addr),
+            Self::Votings => "votings".to_owned(),
+            Self::Voting(vote_ref) => {
+                let (agent, vote_id) = voting_from_u64(*vote_ref);
+                format!("votings/{}", voting_to_string(&agent, vote_id))
+            }
+            Self::Treasury => "treasury".to_owned(),
+            Self::Projections => "projections".to_owned(),
+            Self::Unstakes => "unstakes".to_owned(),
+            Self::Search(phrase) => format!("search?q={}", encode(phrase)),
+        }
+    }
+
+    /// endpoint with the state the page is built from, relative to the root of the network
+    pub fn api(&self) -> String {
+        match self {
+            Self::Home => "api/views/home".to_owned(),
+            _ if self.has_view() => format!("api/views/{}", self.path()),
+            _ => format!("api/page/{}", self.path()),
+        }
+    }
+
+    /// pages that are built from the view models instead of the state
+    pub fn has_view(&self) -> bool {
+        !matches!(self, Self::Search(_) | Self::Failure(_))
+    }
+
+    /// pages that change with time and not only with the state: vesting of the wallet,
+    /// expired votings and unstaking countdowns
+    pub fn is_timed(&self) -> bool {
+        matches!(self, Self::Wallet(_) | Self::Votings | Self::Unstakes)
+    }
+
+    /// search needs events of all wallets, so its results are only rendered by the server
+    pub fn is_client(&self) -> bool {
+        !matches!(self, Self::Search(_))
+    }
+
+    /// failure for the wallets and votings that are not in the state
+    pub fn check(self, app: &AppState) -> Self {
+        match &self {
+            Self::Wallet(addr) if !app.wallets.contains_key(addr) => {
+                Self::Failure("Not a member of the DAO".to_owned())
+            }
+            Self::Voting(vote_ref) if !app.votings.contains_key(vote_ref) => {
+                Self::Failure("Invalid voting ID".to_owned())
+            }
+            _ => self,
+        }
+    }
+
+    /// view model of the page or the part of the state that is needed to render it
+    pub fn payload(&self, app: &AppState) -> Payload {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let page = match self {
+            Self::Home => Some(Payload::Home(Box::new(HomeSummary::from_state(app)))),
+            Self::Rewards => Some(Payload::Rewards(RewardsTable::from_state(app))),
+            Self::Wallets => Some(Payload::Wallets(WalletsPage::from_state(app))),
+            Self::Votings => Some(Payload::Votings(VotingsPage::from_state(app, now))),
+            Self::Treasury => Some(Payload::Treasury(TreasuryPage::from_state(app))),
+            Self::Projections => Some(Payload::Projections(ProjectionsPage::from_state(app))),
+            Self::Unstakes => Some(Payload::Unstakes(UnstakesPage::from_state(app, now))),
+            Self::Wallet(addr) => {
+                WalletPage::from_state(app, addr, now).map(|p| Payload::Wallet(Box::new(p)))
+            }
+            Self::Voting(vote_ref) => {
+                VotingPage::from_state(app, *vote_ref).map(|p| Payload::Voting(Box::new(p)))
+            }
+            // search results are rendered by the server, the browser only needs the site
+            Self::Search(_) => Some(Payload::State(Arc::new(light(app)))),
+            Self::Failure(_) => None,
+        };
+        page.unwrap_or_else(|| Payload::Failure(Site::from_state(app)))
+    }
+}
+
+/// Data the page is built from, it is serialized without the name of the variant
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Payload {
+    Home(Box<HomeSummary>),
+    Rewards(RewardsTable),
+    Wallets(WalletsPage),
+    Wallet(Box<WalletPage>),
+    Votings(VotingsPage),
+    Voting(Box<VotingPage>),
+    Treasury(TreasuryPage),
+    Projections(ProjectionsPage),
+    Unstakes(UnstakesPage),
+    State(Arc<AppState>),
+    Failure(Site),
+}
+
+impl Payload {
+    /// payload of the route, as it was sent by the server
+    pub fn parse(route: &Route, json: &str) -> serde_json::Result<Self> {
+        let res = match route {
+            Route::Home => Self::Home(serde_json::from_str(json)?),
+            Route::Rewards => Self::Rewards(serde_json::from_str(json)?),
+            Route::Wallets => Self::Wallets(serde_json::from_str(json)?),
+            Route::Wallet(_) => Self::Wallet(serde_json::from_str(json)?),
+            Route::Votings => Self::Votings(serde_json::from_str(json)?),
+            Route::Voting(_) => Self::Voting(serde_json::from_str(json)?),
+            Route::Treasury => Self::Treasury(serde_json::from_str(json)?),
+            Route::Projections => Self::Projections(serde_json::from_str(json)?),
+            Route::Unstakes => Self::Unstakes(serde_json::from_str(json)?),
+            Route::Search(_) => Self::State(serde_json::from_str(json)?),
+            Route::Failure(_) => Self::Failure(serde_json::from_str(json)?),
+        };
+        Ok(res)
+    }
+
+    pub fn site(&self) -> Site {
+        match self {
+            Self::Home(x) => x.site.clone(),
+            Self::Rewards(x) => x.site.clone(),
+            Self::Wallets(x) => x.site.clone(),
+            Self::Wallet(x) => x.site.clone(),
+            Self::Votings(x) => x.site.clone(),
+            Self::Voting(x) => x.site.clone(),
+            Self::Treasury(x) => x.site.clone(),
+            Self::Projections(x) => x.site.clone(),
+            Self::Unstakes(x) => x.site.clone(),
+            Self::State(x) => Site::from_state(x),
+            Self::Failure(x) => x.clone(),
         }
     }
 }
 
+/// copy of the state without events, fees and rewards of every wallet
+fn light(app: &AppState) -> AppState {
+    AppState {
+        version: app.version.clone(),
+        chain_id: app.chain_id,
+        epoch_index: app.epoch_index,
+        apr: app.apr,
+        last_block: app.last_block,
+        pool_info: app.pool_info.clone(),
+        circulation: app.circulation.clone(),
+        epochs: app.epochs.clone(),
+        votings: app.votings.clone(),
+        votings_events: BTreeMap::new(),
+        wallets: app.wallets.clone(),
+        wallets_events: BTreeMap::new(),
+        rewards_ledger: BTreeMap::new(),
+        vested: app.vested.clone(),
+        treasuries: app.treasuries.clone(),
+        decimals: app.decimals.clone(),
+        grants: app.grants.clone(),
+        fees: BTreeMap::new(),
+        the_last: app.the_last.clone(),
+        quarantine: vec![],
+        stake_targets: app.stake_targets.clone(),
+        dao_apps: app.dao_apps.clone(),
+        erc20_addresses: app.erc20_addresses.clone(),
+        ownership: app.ownership.clone(),
+        claims: app.claims.clone(),
+        timelocks: BTreeMap::new(),
+        unhandled: vec![],
+        network: app.network.clone(),
+        search: SearchIndex::default(),
+        block_power: (0, BTreeMap::new()),
+    }
+}
+
+/// Screen of the current route
+#[derive(Debug)]
+pub enum Page {
+    Home(Box<screens::home::Screen>),
+    Rewards(screens::rewards::Screen),
+    Wallets(screens::wallets::Screen),
+    Wallet(Box<screens::wallet::Screen>),
+    Votings(screens::votings::Screen),
+    Voting(Box<screens::voting::Screen>),
+    Treasury(screens::treasury::Screen),
+    Projections(screens::projections::Screen),
+    Unstakes(screens::unstakes::Screen),
+    Search(screens::search::Screen),
+    Failure(screens::failure::Screen),
+}
+
+impl Page {
+    pub fn new(route: &Route, payload: Payload) -> Self {
+        match (route, payload) {
+            (Route::Home, Payload::Home(x)) => Self::Home(Box::new(screens::home::Screen::new(*x))),
+            (Route::Rewards, Payload::Rewards(x)) => {
+                Self::Rewards(screens::rewards::Screen::new(x))
+            }
+            (Route::Wallet(_), Payload::Wallet(x)) => {
+                Self::Wallet(Box::new(screens::wallet::Screen::new(*x)))
+            }
+            (Route::Voting(_), Payload::Voting(x)) => {
+                Self::Voting(Box::new(screens::voting::Screen::new(*x)))
+            }
+            (Route::Wallets, Payload::Wallets(x)) => {
+                Self::Wallets(screens::wallets::Screen::new(x))
+            }
+            (Route::Votings, Payload::Votings(x)) => {
+                Self::Votings(screens::votings::Screen::new(x))
+            }
+            (Route::Treasury, Payload::Treasury(x)) => {
+                Self::Treasury(screens::treasury::Screen::new(x))
+            }
+            (Route::Projections, Payload::Projections(x)) => {
+                Self::Projections(screens::projections::Screen::new(x))
+            }
+            (Route::Unstakes, Payload::Unstakes(x)) => {
+                Self::Unstakes(screens::unstakes::Screen::new(x))
+            }
+            (Route::Search(phrase), Payload::State(x)) => {
+                Self::Search(screens::search::Screen::new(x, phrase))
+            }
+            (Route::Failure(msg), payload) => Self::Failure(screens::failure::Screen {
+                msg: msg.clone(),
+                site: payload.site(),
+            }),
+            (_, payload) => Self::Failure(screens::failure::Screen {
+                msg: "The page could not be loaded".to_owned(),
+                site: payload.site(),
+            }),
+        }
+    }
+}
+
+/// Application in the browser, which switches screens without reloading the page
+#[derive(Debug)]
+pub struct Switch {
+    pub route: Route,
+    pub page: Page,
+    /// path of the network root, which routes are relative to
+    pub root: String,
+    /// state of the next page is being loaded
+    pub loading: bool,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Msg {
-    OnMounted,
+    /// link to the page of the tracker was clicked
+    Navigate(Route),
+    /// back or forward button of the browser
+    PopState,
+    /// state of the page was loaded
+    Loaded(Route, String),
+    Failed(String),
+    Projections(screens::projections::Msg),
+    Noop,
+}
+
+/// route of the clicked link, if it leads to a page that could be switched to
+fn clicked_route(e: &web_sys::MouseEvent, root: &str) -> Option<Route> {
+    if e.button() != 0 || e.ctrl_key() || e.meta_key() || e.shift_key() || e.alt_key() {
+        return None;
+    }
+    let el: web_sys::Element = e.target()?.dyn_into().ok()?;
+    let anchor = el.closest("a").ok()??;
+    if anchor.get_attribute("target").is_some() {
+        return None;
+    }
+    let href = anchor.get_attribute("href")?;
+    let base = sauron::dom::document().base_uri().ok()??;
+    let path = resolve(&base, &href)?;
+    let rest = path.strip_prefix(root)?;
+    let (rest, query) = match rest.find('?') {
+        Some(pos) => (&rest[..pos], &rest[pos..]),
+        None => (rest, ""),
+    };
+    Route::parse(rest, query).filter(|r| r.is_client())
+}
+
+impl Switch {
+    pub fn new(route: Route, payload: Payload, root: &str) -> Self {
+        Self {
+            page: Page::new(&route, payload),
+            route,
+            root: root.to_owned(),
+            loading: false,
+        }
+    }
+
+    fn site(&self) -> Site {
+        match &self.page {
+            Page::Home(s) => s.summary.site.clone(),
+            Page::Rewards(s) => s.rewards.site.clone(),
+            Page::Wallets(s) => s.page.site.clone(),
+            Page::Wallet(s) => s.page.site.clone(),
+            Page::Votings(s) => s.page.site.clone(),
+            Page::Voting(s) => s.page.site.clone(),
+            Page::Treasury(s) => s.page.site.clone(),
+            Page::Projections(s) => s.page.site.clone(),
+            Page::Unstakes(s) => s.page.site.clone(),
+            Page::Search(s) => Site::from_state(&s.state),
+            Page::Failure(s) => s.site.clone(),
+        }
+    }
+
+    /// loads the state of the page from the server
+    fn load(&mut self, route: Route) -> Cmd<Self, Msg> {
+        if let Route::Failure(_) = route {
+            return self.fail(route);
+        }
+        self.loading = true;
+        let url = format!("{}{}", self.root, route.api());
+        Http::fetch_with_text_response_decoder(
+            &url,
+            move |json| Msg::Loaded(route.clone(), json),
+            |e| Msg::Failed(format!("{:?}", e)),
+        )
+    }
+
+    fn fail(&mut self, route: Route) -> Cmd<Self, Msg> {
+        let site = Payload::Failure(self.site());
+        self.show(route, site)
+    }
+
+    fn show(&mut self, route: Route, payload: Payload) -> Cmd<Self, Msg> {
+        self.loading = false;
+        self.page = Page::new(&route, payload);
+        self.route = route;
+        Window::set_title(&self.meta().title);
+        Window::scroll_to_top()
+    }
 }
 
 impl Component<Msg> for Switch {
+    fn init(&self) -> Cmd<Self, Msg> {
+        Window::add_event_listeners(vec![on_popstate(|_| Msg::PopState)])
+    }
+
     fn view(&self) -> Node<Msg> {
-        // let _ = themeswitcher::init();
-        if let Some(s) = &self.screen_home {
-            return s.view().map_msg(move |_| Msg::OnMounted);
-        }
-        return div(vec![], vec![]);
+        let screen = match &self.page {
+            Page::Home(s) => s.view().map_msg(|m| match m {}),
+            Page::Rewards(s) => s.view().map_msg(|m| match m {}),
+            Page::Wallets(s) => s.view().map_msg(|m| match m {}),
+            Page::Wallet(s) => s.view().map_msg(|m| match m {}),
+            Page::Votings(s) => s.view().map_msg(|m| match m {}),
+            Page::Voting(s) => s.view().map_msg(|m| match m {}),
+            Page::Treasury(s) => s.view().map_msg(|m| match m {}),
+            Page::Projections(s) => s.view().map_msg(Msg::Projections),
+            Page::Unstakes(s) => s.view().map_msg(|m| match m {}),
+            Page::Search(s) => s.view().map_msg(|m| match m {}),
+            Page::Failure(s) => s.view().map_msg(|m| match m {}),
+        };
+        let root = self.root.clone();
+        div(
+            vec![
+                class(if self.loading {
+                    "switch loading"
+                } else {
+                    "switch"
+                }),
+                on_click(move |e| match clicked_route(&e, &root) {
+                    Some(route) => {
+                        e.prevent_default();
+                        Msg::Navigate(route)
+                    }
+                    None => Msg::Noop,
+                }),
+            ],
+            vec![screen],
+        )
     }
 
     fn update(&mut self, msg: Msg) -> Cmd<Self, Msg> {
-        info!("routes: UPDATE: {:?}", msg);
-        Cmd::none()
+        match msg {
+            Msg::Navigate(route) => {
+                let url = format!("{}{}", self.root, route.path());
+                if let Ok(history) = sauron::dom::window().history() {
+                    let _ = history.push_state_with_url(&JsValue::NULL, "", Some(&url));
+                }
+                self.load(route)
+            }
+            Msg::PopState => {
+                let location = sauron::dom::window().location();
+                let pathname = location.pathname().unwrap_or_default();
+                let search = location.search().unwrap_or_default();
+                let (root, route) = Route::locate(&pathname, &search);
+                if root != self.root || !route.is_client() {
+                    let _ = location.reload();
+                    return Cmd::none();
+                }
+                self.load(route)
+            }
+            Msg::Loaded(route, json) => match Payload::parse(&route, &json) {
+                Ok(payload) => self.show(route, payload),
+                Err(e) => {
+                    warn!("page state {}", e);
+                    // the server explains why the page cannot be shown
+                    let msg = serde_json::from_str::<BTreeMap<String, String>>(&json)
+                        .ok()
+                        .and_then(|res| res.get("error").cloned())
+                        .unwrap_or_else(|| "The page could not be loaded".to_owned());
+                    self.fail(Route::Failure(msg))
+                }
+            },
+            Msg::Failed(e) => {
+                warn!("page request {}", e);
+                self.fail(Route::Failure("The page could not be loaded".to_owned()))
+            }
+            Msg::Projections(m) => {
+                if let Page::Projections(s) = &mut self.page {
+                    // the calculator only keeps its inputs, there are no commands
+                    let _ = s.update(m);
+                }
+                Cmd::none()
+            }
+            Msg::Noop => Cmd::none(),
+        }
+    }
+}
+
+impl MetaProvider for Switch {
+    fn meta(&self) -> PageMetaInfo {
+        match &self.page {
+            Page::Home(s) => s.meta(),
+            Page::Rewards(s) => s.meta(),
+            Page::Wallets(s) => s.meta(),
+            Page::Wallet(s) => s.meta(),
+            Page::Votings(s) => s.meta(),
+            Page::Voting(s) => s.meta(),
+            Page::Treasury(s) => s.meta(),
+            Page::Projections(s) => s.meta(),
+            Page::Unstakes(s) => s.meta(),
+            Page::Search(s) => s.meta(),
+            Page::Failure(s) => s.meta(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn it_parses_and_builds_paths() {
+        let addr = H160::from_low_u64_be(0xabcd);
+        let routes = vec![
+            Route::Home,
+            Route::Rewards,
+            Route::Wallets,
+            Route::Wallet(addr),
+            Route::Votings,
+            Route::Voting(voting_to_u64(&crate::events::VotingAgent::Secondary, 7)),
+            Route::Treasury,
+            Route::Projections,
+            Route::Unstakes,
+            Route::Search("enormous.eth 1%".to_owned()),
+        ];
+        for route in routes {
+            let path = route.path();
+            let (path, query) = match path.find('?') {
+                Some(pos) => (&path[..pos], &path[pos..]),
+                None => (path.as_str(), ""),
+            };
+            assert_eq!(Route::parse(path, query), Some(route));
+        }
+        assert_eq!(
+            Route::parse("/votings/s-7", "").unwrap().path(),
+            "votings/s-7"
+        );
+        assert_eq!(
+            Route::parse("/wallets/xyz", ""),
+            Some(Route::Failure("Invalid Ethereum address".to_owned()))
+        );
+        assert_eq!(Route::parse("/favicon.ico", ""), None);
+    }
+
+    #[test]
+    pub fn it_rejects_invalid_voting_ids() {
+        let invalid = Some(Route::Failure("Invalid voting ID".to_owned()));
+        for id in &[
+            "-12", "p-", "p-abc", "p-x", "q-3", "x-1", "p-+1", "P-1", "p-1-2", "s",
+        ] {
+            assert_eq!(
+                Route::parse(&format!("/votings/{}", id), ""),
+                invalid,
+                "{}",
+                id
+            );
+        }
+        let overflow = format!("/votings/s-{}", u64::MAX);
+        assert_eq!(Route::parse(&overflow, ""), invalid);
+        assert_eq!(
+            Route::parse("/votings/p-0", ""),
+            Some(Route::Voting(voting_to_u64(
+                &crate::events::VotingAgent::Primary,
+                0
+            )))
+        );
+    }
+
+    #[test]
+    pub fn it_renders_the_same_page_from_the_payload() {
+        use crate::state::{ScheduledUnstake, Treasury, Voting, Wallet};
+        use web3::types::U256;
+        let mut app = AppState::new(1);
+        let addr = H160::from_low_u64_be(0xabcd);
+        app.wallets.insert(
+            addr,
+            Wallet {
+                address: addr,
+                ens: Some("enormous.eth".to_owned()),
+                shares: U256::exp10(20),
+                voting_power: U256::exp10(20),
+                scheduled_unstake: Some(ScheduledUnstake {
+                    amount: U256::exp10(19),
+                    shares: U256::exp10(19),
+                    tm: 1_000_000,
+                }),
+                ..Default::default()
+            },
+        );
+        let v = Voting {
+            primary: true,
+            vote_id: 3,
+            title: "Grant <one>".to_owned(),
+            votes_total: U256::exp10(20),
+            ..Default::default()
+        };
+        app.votings.insert(v.as_u64(), v.clone());
+        app.treasuries
+            .insert("primary".to_owned(), Treasury::new("Primary", &addr));
+        let routes = vec![
+            Route::Home,
+            Route::Rewards,
+            Route::Wallets,
+            Route::Wallet(addr),
+            Route::Votings,
+            Route::Voting(v.as_u64()),
+            Route::Treasury,
+            Route::Projections,
+            Route::Unstakes,
+        ];
+        for route in routes {
+            // the browser builds the page from the payload the server rendered it from
+            let payload = route.payload(&app);
+            assert!(!matches!(payload, Payload::State(_)), "{:?}", route);
+            let json = serde_json::to_string(&payload).unwrap();
+            let parsed = Payload::parse(&route, &json).unwrap();
+            let rendered = Switch::new(route.clone(), payload, "").view();
+            let hydrated = Switch::new(route.clone(), parsed, "").view();
+            assert_eq!(
+                rendered.render_to_string(),
+                hydrated.render_to_string(),
+                "{:?}",
+                route
+            );
+        }
+    }
+
+    #[test]
+    pub fn it_locates_the_root() {
+        let (root, route) = Route::locate("/dao/tracker/mainnet/wallets", "");
+        assert_eq!(root, "/dao/tracker/mainnet/");
+        assert_eq!(route, Route::Wallets);
+        let (root, route) = Route::locate("/mainnet/votings/p-12", "");
+        assert_eq!(root, "/mainnet/");
+        assert_eq!(route.path(), "votings/p-12");
+        assert_eq!(Route::locate("/mainnet", "").0, "/mainnet/");
+        assert_eq!(Route::locate("/", "").0, "/");
+        assert_eq!(
+            Route::locate("/search", "?q=grant+one").1,
+            Route::Search("grant one".to_owned())
+        );
+    }
+
+    #[test]
+    pub fn it_resolves_links() {
+        let base = "https://enormous.cloud/dao/tracker/mainnet/wallets";
+        assert_eq!(
+            resolve(base, "./rewards").unwrap(),
+            "/dao/tracker/mainnet/rewards"
+        );
+        assert_eq!(
+            resolve("/mainnet/", "wallets/0xab?x=1").unwrap(),
+            "/mainnet/wallets/0xab?x=1"
+        );
+        assert_eq!(resolve("/mainnet/votings/", "../").unwrap(), "/mainnet/");
+        assert_eq!(resolve("/mainnet/", "/rinkeby/").unwrap(), "/rinkeby/");
+        assert_eq!(resolve("/mainnet/", "https://etherscan.io"), None);
+        assert_eq!(query_param("?q=a%2Fb+c&limit=2", "q").unwrap(), "a/b c");
+        assert_eq!(encode("a/b c"), "a%2Fb+c");
     }
 }
@@ -0,0 +1,80 @@
+//! JSON schema of the web3 types, which are serialized as hex strings
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Metadata, Schema, SchemaObject, StringValidation};
+use schemars::JsonSchema;
+
+fn hex_string(description: &str, pattern: &str) -> Schema {
+    Schema::Object(SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        metadata: Some(Box::new(Metadata {
+            description: Some(description.to_owned()),
+            ..Default::default()
+        })),
+        string: Some(Box::new(StringValidation {
+            pattern: Some(pattern.to_owned()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    })
+}
+
+/// U256, i.e. token amounts in wei or shares
+pub struct Uint;
+
+impl JsonSchema for Uint {
+    fn schema_name() -> String {
+        "Uint".to_owned()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        hex_string(
+            "256-bit unsigned integer, hex with 0x prefix and without leading zeros",
+            "^0x(0|[1-9a-f][0-9a-f]*)$",
+        )
+    }
+}
+
+/// H160, address of the wallet or the contract
+pub struct Address;
+
+impl JsonSchema for Address {
+    fn schema_name() -> String {
+        "Address".to_owned()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        hex_string(
+            "Ethereum address, lowercase hex with 0x prefix",
+            "^0x[0-9a-f]{40}$",
+        )
+    }
+}
+
+/// H256, hash of the transaction
+pub struct Hash;
+
+impl JsonSchema for Hash {
+    fn schema_name() -> String {
+        "Hash".to_owned()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        hex_string(
+            "32-byte hash, lowercase hex with 0x prefix",
+            "^0x[0-9a-f]{64}$",
+        )
+    }
+}
+
+/// bytes of the event data or of the script
+pub struct Bytes;
+
+impl JsonSchema for Bytes {
+    fn schema_name() -> String {
+        "Bytes".to_owned()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        hex_string("bytes, lowercase hex with 0x prefix", "^0x([0-9a-f]{2})*$")
+    }
+}
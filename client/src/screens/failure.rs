@@ -1,7 +1,7 @@
 use crate::components::footer;
 use crate::components::header;
 use crate::screens::meta::{MetaProvider, PageMetaInfo};
-use crate::state::AppState;
+use crate::views::Site;
 use sauron::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -9,8 +9,8 @@ use serde::{Deserialize, Serialize};
 pub struct Screen {
     /// failure message
     pub msg: String,
-    /// server side state
-    pub state: AppState,
+    /// header and footer of the page
+    pub site: Site,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -20,11 +20,11 @@ impl Component<Msg> for Screen {
     fn view(&self) -> Node<Msg> {
         node! {
             <div class="screen-failure">
-                { header::render("", &self.state) }
+                { header::render("", &self.site) }
                 <div class="inner">
                     <h1>{ text(self.msg.as_str()) }</h1>
                 </div>
-                { footer::render(&self.state) }
+                { footer::render(&self.site) }
             </div>
         }
     }
@@ -3,24 +3,21 @@ use crate::components::header;
 use crate::components::panel;
 use crate::components::target::staking_note;
 use crate::nice;
-use crate::router::link_address;
+use crate::router::{link_address, link_eventlog};
 use crate::screens::meta::{MetaProvider, PageMetaInfo};
-use crate::state::AppState;
+use crate::views::HomeSummary;
 use sauron::prelude::*;
 use serde::{Deserialize, Serialize};
-use web3::types::U256;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Screen {
-    /// server side state
-    pub state: AppState,
+    /// summary of the DAO, built by the server
+    pub summary: HomeSummary,
 }
 
 impl Screen {
-    pub fn new(state: AppState) -> Self {
-        Self {
-            state: state.clone(),
-        }
+    pub fn new(home: HomeSummary) -> Self {
+        Self { summary: home }
     }
 }
 
@@ -33,28 +30,14 @@ pub enum Msg {}
 
 impl Screen {
     pub fn rewards_coeff(&self) -> f64 {
-        match &self.state.pool_info {
-            Some(x) => x.clone().rewards_coeff,
-            None => 1f64,
-        }
+        self.summary.rewards_coeff
     }
 
     pub fn render_supply(&self) -> Node<Msg> {
-        match &self.state.circulation {
+        match &self.summary.circulation {
             Some(c) => {
-                let stake_target: U256 = match &self.state.pool_info {
-                    Some(x) => c.total_supply * x.stake_target / U256::exp10(26),
-                    None => U256::from(0),
-                };
-                let minted = self.state.get_minted_total();
-                let total_shares = match &self.state.pool_info {
-                    Some(x) => x.total_shares,
-                    None => self.state.get_shares_total(),
-                };
-                let total_stake: U256 = match &self.state.pool_info {
-                    Some(x) => x.total_stake,
-                    None => total_shares + minted,
-                };
+                let stake_target = self.summary.stake_target;
+                let total_stake = self.summary.total_stake;
                 node! {
                     <div>
                         {panel::render("API3 Circulating Supply", "", node! {
@@ -88,7 +71,7 @@ impl Screen {
                             </div>
                         </div>
                         <div class="dash-row">
-                            {staking_note(self.state.apr, stake_target, total_stake)}
+                            {staking_note(self.summary.apr, stake_target, total_stake)}
                         </div>
                     </div>
                 }
@@ -98,7 +81,7 @@ impl Screen {
     }
 
     pub fn render_locked(&self) -> Node<Msg> {
-        match &self.state.circulation {
+        match &self.summary.circulation {
             Some(c) => node! {
                 <div class="dash-row" id="api3-locked-tokens">
                     <div class="dash-col dash-col-4 cell-t">
@@ -136,7 +119,7 @@ impl Screen {
     }
 
     pub fn render_contracts(&self) -> Node<Msg> {
-        match &self.state.circulation {
+        match &self.summary.circulation {
             Some(c) => panel::render(
                 "API3 Smart Contracts",
                 "",
@@ -146,55 +129,55 @@ impl Screen {
                             <li>
                                 <label class="cell-title">"API3 pool contract address: "</label>
                                 <div class="eth-address">
-                                    {link_address(self.state.chain_id, c.addr_pool, true)}
+                                    {link_address(&self.summary.site.network, c.addr_pool, true)}
                                 </div>
                             </li>
                             <li>
                                 <label class="cell-title">"API3 token contract address: "</label>
                                 <div class="eth-address">
-                                    {link_address(self.state.chain_id, c.addr_token, true)}
+                                    {link_address(&self.summary.site.network, c.addr_token, true)}
                                 </div>
                             </li>
                             <li>
                                 <label class="cell-title">"Time-lock manager contract: "</label>
                                 <div class="eth-address">
-                                    {link_address(self.state.chain_id, c.addr_time_lock, true)}
+                                    {link_address(&self.summary.site.network, c.addr_time_lock, true)}
                                 </div>
                             </li>
                             <li>
                                 <label class="cell-title">"Primary voting contract: "</label>
                                 <div class="eth-address">
-                                    {link_address(self.state.chain_id, c.addr_primary_contract, true)}
+                                    {link_address(&self.summary.site.network, c.addr_primary_contract, true)}
                                 </div>
                             </li>
                             <li>
                                 <label class="cell-title">"Primary treasury agent: "</label>
                                 <div class="eth-address">
-                                    {link_address(self.state.chain_id, c.addr_primary_treasury, true)}
+                                    {link_address(&self.summary.site.network, c.addr_primary_treasury, true)}
                                 </div>
                             </li>
                             <li>
                                 <label class="cell-title">"Secondary voting contract: "</label>
                                 <div class="eth-address">
-                                    {link_address(self.state.chain_id, c.addr_secondary_contract, true)}
+                                    {link_address(&self.summary.site.network, c.addr_secondary_contract, true)}
                                 </div>
                             </li>
                             <li>
                                 <label class="cell-title">"Secondary treasury agent: "</label>
                                 <div class="eth-address">
-                                    {link_address(self.state.chain_id, c.addr_secondary_treasury, true)}
+                                    {link_address(&self.summary.site.network, c.addr_secondary_treasury, true)}
                                 </div>
                             </li>
                             <li>
                                 <label class="cell-title">"V1 Treasury address: "</label>
                                 <div class="eth-address">
-                                    {link_address(self.state.chain_id, c.addr_v1_treasury, true)}
+                                    {link_address(&self.summary.site.network, c.addr_v1_treasury, true)}
                                 </div>
                             </li>
                             <li>
                                 <label class="cell-title">"Convenience contract: "</label>
                                 <div class="eth-address">
-                                    {link_address(self.state.chain_id, c.addr_convenience, true)}
+                                    {link_address(&self.summary.site.network, c.addr_convenience, true)}
                                 </div>
                             </li>
                         </ul>
@@ -205,22 +188,78 @@ impl Screen {
         }
     }
 
+    pub fn render_pool_history(&self) -> Node<Msg> {
+        let h = &self.summary.pool_history;
+        if h.stake_targets.is_empty() && h.dao_apps.is_empty() && h.claims.is_empty() {
+            return text("");
+        }
+        let network = &self.summary.site.network;
+        node! {
+            <div>
+                <h2 class="m20">"API3 Pool Governance"</h2>
+                <div class="dash-row" id="pool-history">
+                    <div class="dash-col dash-col-3 cell-t">
+                        <h3 class="cell-title">"Staking Target Changes"</h3>
+                        {ul(vec![], h.stake_targets.iter().rev().map(|t| node! {
+                            <li>
+                                <strong>{text(format!("{:.2}%", nice::dec(t.stake_target, 16)))}</strong>
+                                " "
+                                <span class="darken dt">{text(nice::date(t.tm))}</span>
+                                " "
+                                {link_eventlog(network, t.block_number, t.tx)}
+                            </li>
+                        }).collect::<Vec<Node<Msg>>>())}
+                    </div>
+                    <div class="dash-col dash-col-3 cell-t">
+                        <h3 class="cell-title">"DAO Apps"</h3>
+                        {match h.current_dao_apps() {
+                            Some(apps) => node! {
+                                <ul>
+                                    <li>
+                                        <label class="darken">"Primary agent: "</label>
+                                        {link_address(network, apps.agent_app_primary, true)}
+                                    </li>
+                                    <li>
+                                        <label class="darken">"Secondary agent: "</label>
+                                        {link_address(network, apps.agent_app_secondary, true)}
+                                    </li>
+                                    <li>
+                                        <label class="darken">"Primary voting: "</label>
+                                        {link_address(network, apps.voting_app_primary, true)}
+                                    </li>
+                                    <li>
+                                        <label class="darken">"Secondary voting: "</label>
+                                        {link_address(network, apps.voting_app_secondary, true)}
+                                    </li>
+                                    <li class="darken dt">
+                                        {text(format!("set {}, changed {} times", nice::date(apps.tm), h.dao_apps.len()))}
+                                    </li>
+                                </ul>
+                            },
+                            None => text("-"),
+                        }}
+                    </div>
+                    <div class="dash-col dash-col-3 cell-t">
+                        <h3 class="cell-title">"Paid Out Claims"</h3>
+                        <strong title={nice::amount(h.claims_total, 18)}>
+                            {text(nice::ceil(h.claims_total, 18))}
+                            " tokens"
+                        </strong>
+                        <div class="darken">
+                            {text(format!("in {} claims", h.claims.len()))}
+                        </div>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+
     pub fn current_epoch(&self, divclass: &'static str) -> Node<Msg> {
-        let minted = self.state.get_minted_total();
-        let staked256 = self.state.get_shares_total() + minted;
+        let minted = self.summary.minted;
+        let staked256 = self.summary.staked;
         let staked = nice::dec(staked256, 18);
-        let to_be_minted = staked * self.state.apr * self.rewards_coeff() / 52.0;
-
-        let prev_epoch = self.state.epoch_index - 1;
-        let tm = if let Some(ep) = self.state.epochs.get(&prev_epoch) {
-            if let Some(pool_info) = &self.state.pool_info {
-                ep.tm + pool_info.epoch_length
-            } else {
-                0
-            }
-        } else {
-            0
-        };
+        let to_be_minted = staked * self.summary.apr * self.rewards_coeff() / 52.0;
+        let tm = self.summary.next_epoch_tm;
         panel::render(
             "Current Epoch",
             divclass,
@@ -228,18 +267,18 @@ impl Screen {
                 <div>
                     <div class="cell-title">
                         <span class="darken">"Epoch #"</span>
-                        {text(nice::int(self.state.epoch_index))}
+                        {text(nice::int(self.summary.epoch_index))}
                     </div>
                     <h2 class="stats-row">
                         "APR: "
                         <strong class="big-title">
-                            { text(format!("{:.2}%", 100.0*self.state.apr)) }
+                            { text(format!("{:.2}%", 100.0*self.summary.apr)) }
                         </strong>
                     </h2>
                     <div class="stats-row m20">
                         <span class="darken cell-title">"Epoch Rewards: "</span>
                         <strong class="accent">
-                            { text(format!("{:.4}%", 100.0*self.state.apr*self.rewards_coeff() / 52.0)) }
+                            { text(format!("{:.4}%", 100.0*self.summary.apr*self.rewards_coeff() / 52.0)) }
                         </strong>
                     </div>
                     <div class="stats-row">
@@ -274,11 +313,11 @@ impl Screen {
     }
 
     pub fn render_epoch(&self, epoch: u64, divclass: &'static str) -> Node<Msg> {
-        if self.state.epochs.len() == 0 {
+        if self.summary.epochs.is_empty() {
             return div(vec![], vec![]);
         }
-        let prev_epoch = self.state.epoch_index - epoch;
-        if let Some(ep) = self.state.epochs.get(&prev_epoch) {
+        let prev_epoch = self.summary.epoch_index.saturating_sub(epoch);
+        if let Some(ep) = self.summary.epochs.get(&prev_epoch) {
             panel::render(
                 "Previous Epoch",
                 divclass,
@@ -332,19 +371,19 @@ impl Component<Msg> for Screen {
     fn view(&self) -> Node<Msg> {
         node! {
             <div class="screen-home">
-                { header::render("", &self.state) }
+                { header::render("", &self.summary.site) }
                 <div class="inner">
                     <div class="centered">
                         <h1>"API3 DAO Tracker"</h1>
                         <p class="m20">
                             "API3 DAO currently involves "
                             <a href="./wallets">
-                                { text(nice::int(self.state.wallets.len())) }
+                                { text(nice::int(self.summary.members)) }
                                 " members"
                             </a>
                             " participated in "
                             <a href="./votings">
-                                { text(nice::int(self.state.votings.len())) }
+                                { text(nice::int(self.summary.votings)) }
                                 " votings"
                             </a>
                         </p>
@@ -357,7 +396,7 @@ impl Component<Msg> for Screen {
                             {self.render_epoch(2, "dash-col dash-col-3")}
                         </div>
 
-                        {match &self.state.circulation {
+                        {match &self.summary.circulation {
                             Some(_) => node!{
                                 <div>
                                     <h2 class="m20">"API3 Token Supply"</h2>
@@ -374,10 +413,10 @@ impl Component<Msg> for Screen {
                             },
                             None => text(""),
                         }}
-
+                        {self.render_pool_history()}
                     </div>
                 </div>
-                { footer::render(&self.state) }
+                { footer::render(&self.summary.site) }
             </div>
         }
     }
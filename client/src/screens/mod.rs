@@ -1,8 +1,11 @@
 pub mod failure;
 pub mod home;
 pub mod meta;
+pub mod projections;
 pub mod rewards;
+pub mod search;
 pub mod treasury;
+pub mod unstakes;
 pub mod voting;
 pub mod votings;
 pub mod wallet;
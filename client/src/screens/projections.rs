@@ -0,0 +1,244 @@
+use crate::components::footer;
+use crate::components::header;
+use crate::nice;
+use crate::projections::{self, Projection, DEFAULT_EPOCHS};
+use crate::screens::meta::{MetaProvider, PageMetaInfo};
+use crate::views::ProjectionsPage;
+use sauron::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Screen {
+    /// parameters of the pool, as they were sent by the server
+    pub page: ProjectionsPage,
+    /// number of epochs to project
+    pub epochs: String,
+    /// tokens staked (or unstaked) by all members every epoch
+    pub change: String,
+    /// stake of the wallet to estimate rewards
+    pub stake: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Msg {
+    SetEpochs(String),
+    SetChange(String),
+    SetStake(String),
+}
+
+fn tokens(x: f64) -> String {
+    nice::int(x.round() as i64)
+}
+
+impl Screen {
+    pub fn new(page: ProjectionsPage) -> Self {
+        Self {
+            page,
+            epochs: format!("{}", DEFAULT_EPOCHS),
+            change: "0".to_owned(),
+            stake: "1000".to_owned(),
+        }
+    }
+
+    pub fn projection(&self) -> Option<Projection> {
+        let num_epochs: usize = self
+            .epochs
+            .trim()
+            .parse()
+            .unwrap_or(DEFAULT_EPOCHS)
+            .min(520);
+        let change: f64 = self.change.trim().parse().unwrap_or(0.0);
+        let stake: f64 = self.stake.trim().parse().unwrap_or(0.0);
+        let changes: Vec<f64> = (0..num_epochs).map(|_| change).collect();
+        let params = self.page.params.as_ref()?;
+        Some(Projection::from_params(params, num_epochs, &changes, stake))
+    }
+
+    pub fn render_input(
+        &self,
+        caption: &'static str,
+        val: &str,
+        msg: fn(String) -> Msg,
+    ) -> Node<Msg> {
+        div(
+            vec![class("dash-col dash-col-3 cell-t")],
+            vec![
+                label(vec![class("cell-title")], vec![text(caption)]),
+                input(
+                    vec![
+                        r#type("text"),
+                        class("calc-input"),
+                        value(val.to_owned()),
+                        on_input(move |e: InputEvent| msg(e.value)),
+                    ],
+                    vec![],
+                ),
+            ],
+        )
+    }
+
+    pub fn render_epoch_header(&self) -> Node<Msg> {
+        node! {
+            <tr>
+                <th class="c">"Epoch"</th>
+                <th class="c">"Rewards Date"</th>
+                <th class="r">"APR"</th>
+                <th class="r">"Minted"</th>
+                <th class="r">"Total Staked"</th>
+                <th class="c">"Target"</th>
+                <th class="r">"Your Reward"</th>
+                <th class="c">"Release Date"</th>
+            </tr>
+        }
+    }
+
+    pub fn render_epoch_tr(
+        &self,
+        ep: &projections::ProjectedEpoch,
+        w: &projections::WalletProjectedEpoch,
+    ) -> Node<Msg> {
+        node! {
+            <tr>
+                <td class="c">{text(nice::int(ep.index))}</td>
+                <td class="c darken dt">{text(nice::date(ep.tm))}</td>
+                <td class="r">{text(format!("{:.2}%", 100.0 * ep.apr))}</td>
+                <td class="r">{text(tokens(ep.minted))}</td>
+                <td class="r">{text(tokens(ep.total_stake))}</td>
+                <td class="c">{
+                    if ep.target_reached {
+                        span(vec![class("warning")], vec![text("reached")])
+                    } else {
+                        span(vec![class("darken")], vec![text("-")])
+                    }
+                }</td>
+                <td class="r accent">{text(format!("{:.2}", w.reward))}</td>
+                <td class="c darken dt">{text(nice::date(w.unlock_tm))}</td>
+            </tr>
+        }
+    }
+
+    pub fn render_epoch(
+        &self,
+        ep: &projections::ProjectedEpoch,
+        w: &projections::WalletProjectedEpoch,
+    ) -> Node<Msg> {
+        node! {
+            <li>
+                <strong>{text(format!("Epoch #{}", ep.index))}</strong>
+                <span class="darken">{text(format!(" {} ", nice::date(ep.tm)))}</span>
+                <span>{text(format!("APR {:.2}%, ", 100.0 * ep.apr))}</span>
+                <span class="accent">{text(format!("your reward {:.2}", w.reward))}</span>
+            </li>
+        }
+    }
+
+    pub fn render_projection(&self) -> Node<Msg> {
+        let p = match self.projection() {
+            Some(x) => x,
+            None => {
+                return div(
+                    vec![class("epochs-empty")],
+                    vec![text("Pool information is not loaded yet")],
+                )
+            }
+        };
+        let wallet = match &p.wallet {
+            Some(x) => x,
+            None => return text(""),
+        };
+        let last_apr = p.epochs.last().map(|ep| ep.apr).unwrap_or(p.params.apr);
+        let last_stake = p
+            .epochs
+            .last()
+            .map(|ep| ep.total_stake)
+            .unwrap_or(p.params.total_stake);
+        node! {
+            <div>
+                <div class="dash-row">
+                    <div class="dash-col dash-col-3 cell-t">
+                        <h3 class="cell-title">"APR after projection"</h3>
+                        <strong class="big-title">{text(format!("{:.2}%", 100.0 * last_apr))}</strong>
+                    </div>
+                    <div class="dash-col dash-col-3 cell-t">
+                        <h3 class="cell-title">"Total Staked"</h3>
+                        <strong class="big-title">{text(tokens(last_stake))}</strong>
+                    </div>
+                    <div class="dash-col dash-col-3 cell-t">
+                        <h3 class="cell-title">"Your Rewards"</h3>
+                        <strong class="big-title accent">{text(tokens(wallet.total_rewards))}</strong>
+                    </div>
+                </div>
+                <div class="desktop-only">
+                    {table(
+                        vec![class("table epochs-table")],
+                        vec![
+                            thead(vec![], vec![self.render_epoch_header()]),
+                            tbody(
+                                vec![],
+                                p.epochs
+                                    .iter()
+                                    .zip(wallet.epochs.iter())
+                                    .map(|(ep, w)| self.render_epoch_tr(ep, w))
+                                    .collect::<Vec<Node<Msg>>>(),
+                            ),
+                        ],
+                    )}
+                </div>
+                <div class="mobile-only">
+                    {ol(
+                        vec![class("epochs-list")],
+                        p.epochs
+                            .iter()
+                            .zip(wallet.epochs.iter())
+                            .map(|(ep, w)| self.render_epoch(ep, w))
+                            .collect::<Vec<Node<Msg>>>(),
+                    )}
+                </div>
+            </div>
+        }
+    }
+}
+
+impl Component<Msg> for Screen {
+    fn view(&self) -> Node<Msg> {
+        let site = &self.page.site;
+        node! {
+            <div class="screen-projections">
+                { header::render("/projections", site) }
+                <div class="inner">
+                    <div class="centered">
+                        <h1>"API3 DAO Rewards Calculator"</h1>
+                        <p class="darken" style="text-align: center">
+                            "Projection of APR and staking rewards. After every epoch APR goes down by 1% if the staking target is reached and goes up by 1% otherwise, staying between minimal and maximal APR of the pool. Rewards are locked and keep being staked."
+                        </p>
+                        <div class="dash-row calc-inputs">
+                            {self.render_input("Number of Epochs", &self.epochs, Msg::SetEpochs)}
+                            {self.render_input("Staked by members every epoch", &self.change, Msg::SetChange)}
+                            {self.render_input("Your Stake", &self.stake, Msg::SetStake)}
+                        </div>
+                        {self.render_projection()}
+                    </div>
+                </div>
+                { footer::render(site) }
+            </div>
+        }
+    }
+
+    fn update(&mut self, msg: Msg) -> Cmd<Self, Msg> {
+        match msg {
+            Msg::SetEpochs(x) => self.epochs = x,
+            Msg::SetChange(x) => self.change = x,
+            Msg::SetStake(x) => self.stake = x,
+        };
+        Cmd::none()
+    }
+}
+
+impl MetaProvider for Screen {
+    fn meta(&self) -> PageMetaInfo {
+        let page_title = "API3 DAO Rewards Calculator";
+        let description =
+            "Project API3 DAO staking APR and estimate your future rewards and their release dates. No wallet connection is needed";
+        PageMetaInfo::new(page_title, description)
+    }
+}
@@ -1,41 +1,81 @@
+use crate::components::chart::{Chart, Kind};
 use crate::components::footer;
 use crate::components::header;
 use crate::components::target::staking_note;
 use crate::nice;
 use crate::router::link_eventlog;
 use crate::screens::meta::{MetaProvider, PageMetaInfo};
-use crate::state::{AppState, Epoch};
+use crate::views::{EpochRow, RewardsTable};
 use sauron::prelude::*;
 use serde::{Deserialize, Serialize};
 use web3::types::U256;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Screen {
-    /// server side state
-    pub state: AppState,
+    /// rewards of every epoch, built by the server
+    pub rewards: RewardsTable,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Msg {}
 
 impl Screen {
-    pub fn new(state: AppState) -> Self {
-        Self {
-            state: state.clone(),
-        }
+    pub fn new(rewards: RewardsTable) -> Self {
+        Self { rewards }
     }
 
     pub fn rewards_coeff(&self) -> f64 {
-        match &self.state.pool_info {
-            Some(x) => x.clone().rewards_coeff,
-            None => 1f64,
+        self.rewards.rewards_coeff
+    }
+
+    pub fn release_offset(&self) -> Option<u64> {
+        self.rewards.release_offset
+    }
+
+    /// date when rewards of the epoch are released, empty if pool is not read yet
+    pub fn release_date(&self, tm: u64) -> String {
+        match self.release_offset() {
+            Some(offset) => nice::date(tm + offset),
+            None => "".to_owned(),
         }
     }
 
-    pub fn release_offset(&self) -> u64 {
-        match &self.state.pool_info {
-            Some(pool_info) => pool_info.reward_vesting_period * pool_info.epoch_length,
-            None => 0u64,
+    /// APR, total stake and minted rewards of each epoch
+    pub fn render_charts(&self) -> Node<Msg> {
+        let epochs: Vec<&EpochRow> = self.rewards.epochs.iter().collect();
+        let labels: Vec<String> = epochs
+            .iter()
+            .map(|ep| {
+                let day = nice::date(ep.tm).split(' ').next().unwrap_or("").to_owned();
+                format!("#{} {}", ep.index, day)
+            })
+            .collect();
+        let apr = Chart::new(Kind::Line, labels.clone(), "%")
+            .series("APR", epochs.iter().map(|ep| 100.0 * ep.apr).collect());
+        let mut rewards = U256::from(0);
+        let (mut staked, mut minted) = (vec![], vec![]);
+        for ep in &epochs {
+            rewards += ep.minted;
+            // rewards are part of the total stake since they are minted
+            staked.push(nice::dec(ep.total.saturating_sub(rewards), 18));
+            minted.push(nice::dec(rewards.min(ep.total), 18));
+        }
+        let total = Chart::new(Kind::StackedArea, labels.clone(), " API3")
+            .series("Staked", staked)
+            .series("Rewards", minted);
+        let minted = Chart::new(Kind::Bar, labels, " API3").series(
+            "Minted",
+            epochs.iter().map(|ep| nice::dec(ep.minted, 18)).collect(),
+        );
+        node! {
+            <div class="charts">
+                <h2>"APR per Epoch"</h2>
+                {apr.render("")}
+                <h2>"Total Stake"</h2>
+                {total.render("")}
+                <h2>"Minted Rewards"</h2>
+                {minted.render("")}
+            </div>
         }
     }
 
@@ -55,23 +95,23 @@ impl Screen {
         }
     }
 
-    pub fn render_epoch_tr(&self, ep: &Epoch) -> Node<Msg> {
+    pub fn render_epoch_tr(&self, ep: &EpochRow) -> Node<Msg> {
         node! {
             <tr>
                 <td class="c">{text(nice::int(ep.index))}</td>
-                <td class="c">{link_eventlog(self.state.chain_id, ep.block_number, ep.tx)}</td>
+                <td class="c">{link_eventlog(&self.rewards.site.network, ep.block_number, ep.tx)}</td>
                 <td class="c">{ text(nice::date(ep.tm)) }</td>
                 <td class="r darken">{ text(format!("{:.2}%", 100.0*ep.apr)) }</td>
                 <td class="r accent">{ text(format!("{:.4}%", 100.0*ep.apr*self.rewards_coeff() / 52.0)) }</td>
-                <td class="r darken">{ text(nice::int(ep.stake.len())) }</td>
+                <td class="r darken">{ text(nice::int(ep.members)) }</td>
                 <td class="r darken" title={nice::amount(ep.total, 18)}>{ text(nice::ceil(ep.total, 18)) }</td>
                 <td class="r accent" title={nice::amount(ep.minted, 18)}>{ text(nice::ceil(ep.minted, 18)) }</td>
-                <td class="c">{ text(nice::date(ep.tm + self.release_offset())) }</td>
+                <td class="c">{ text(self.release_date(ep.tm)) }</td>
             </tr>
         }
     }
 
-    pub fn render_epoch(&self, ep: &Epoch) -> Node<Msg> {
+    pub fn render_epoch(&self, ep: &EpochRow) -> Node<Msg> {
         node! {
             <li>
                 <div class="epoch">
@@ -111,7 +151,7 @@ impl Screen {
                         </div>
                         <div class="stats-row cell-title">
                             <strong>
-                                { text(nice::int(ep.stake.len())) }
+                                { text(nice::int(ep.members)) }
                             </strong>
                             <span class="darken">
                                 " members were rewarded"
@@ -122,7 +162,7 @@ impl Screen {
                                 "Will be released: "
                             </span>
                             <strong>
-                                {text(nice::date(ep.tm + self.release_offset()))}
+                                {text(self.release_date(ep.tm))}
                             </strong>
                         </div>
                     </div>
@@ -134,25 +174,12 @@ impl Screen {
 
 impl Component<Msg> for Screen {
     fn view(&self) -> Node<Msg> {
-        let minted = self.state.get_minted_total();
-        let total_shares = match &self.state.pool_info {
-            Some(x) => x.total_shares,
-            None => self.state.get_shares_total(),
-        };
-        let total_stake: U256 = match &self.state.pool_info {
-            Some(x) => x.total_stake,
-            None => total_shares + minted,
-        };
-        let stake_target: U256 = match &self.state.pool_info {
-            Some(x) => match &self.state.circulation {
-                Some(c) => c.total_supply * x.stake_target / U256::exp10(26),
-                None => x.stake_target,
-            },
-            None => U256::from(0),
-        };
+        let minted = self.rewards.minted;
+        let total_stake = self.rewards.total_stake;
+        let stake_target = self.rewards.stake_target;
         node! {
             <div class="screen-rewards">
-                { header::render("/rewards", &self.state) }
+                { header::render("/rewards", &self.rewards.site) }
                 <div class="inner">
                     <div class="centered">
                         <h1>"API3 DAO Staking Rewards History"</h1>
@@ -165,33 +192,34 @@ impl Component<Msg> for Screen {
                         </p>
                         <p style="text-align: center">
                             <span class="darken">" Current Epoch is "</span>
-                            <strong>{text(nice::int(self.state.epoch_index))}</strong>
+                            <strong>{text(nice::int(self.rewards.epoch_index))}</strong>
                             <span class="darken">" with APR "</span>
                             <strong class="big-title">
-                                { text(format!("{:.2}%", 100.0*self.state.apr)) }
+                                { text(format!("{:.2}%", 100.0*self.rewards.apr)) }
                             </strong>
                             <span class="darken">" which means the next reward will be "</span>
                             <strong class="accent">
-                                { text(format!("{:.4}%", 100.0*self.state.apr*self.rewards_coeff() / 52.0)) }
+                                { text(format!("{:.4}%", 100.0*self.rewards.apr*self.rewards_coeff() / 52.0)) }
                             </strong>
                             <span class="darken">" to your current stake and your locked rewards."</span>
                         </p>
-                        {staking_note(self.state.apr, stake_target, total_stake)}
-                        {if self.state.epochs.len() > 0 {
+                        {staking_note(self.rewards.apr, stake_target, total_stake)}
+                        {if !self.rewards.epochs.is_empty() {
                             div(vec![], vec![
+                                self.render_charts(),
                                 div(vec![class("desktop-only")], vec![
                                     table(vec
                                         ![class("table epochs-table")],
                                         vec![
                                             thead(vec![], vec![ self.render_epoch_header() ]),
-                                            tbody(vec![], self.state.epochs.iter().map(|(_, epoch)| self.render_epoch_tr(epoch)).collect::<Vec<Node<Msg>>>()),
+                                            tbody(vec![], self.rewards.epochs.iter().map(|epoch| self.render_epoch_tr(epoch)).collect::<Vec<Node<Msg>>>()),
                                         ]
                                     )
                                 ]),
                                 div(vec![class("mobile-only")], vec![
                                     ol(vec
                                         ![class("epochs-list")],
-                                        self.state.epochs.iter().map(|(_, epoch)| self.render_epoch(epoch)).collect::<Vec<Node<Msg>>>()
+                                        self.rewards.epochs.iter().map(|epoch| self.render_epoch(epoch)).collect::<Vec<Node<Msg>>>()
                                     )
                                 ])
                             ])
@@ -202,7 +230,7 @@ impl Component<Msg> for Screen {
                         }}
                     </div>
                 </div>
-                { footer::render(&self.state) }
+                { footer::render(&self.rewards.site) }
             </div>
         }
     }
@@ -214,7 +242,7 @@ impl Component<Msg> for Screen {
 
 impl MetaProvider for Screen {
     fn meta(&self) -> PageMetaInfo {
-        let minted = self.state.get_minted_total();
+        let minted = self.rewards.minted;
         let title = "API3 DAO Staking Rewards History";
         let description = format!(
             "Explore API3 DAO staking rewards - {} API3 tokens minted as rewards for DAO members. No wallet connection needed.",
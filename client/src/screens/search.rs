@@ -0,0 +1,92 @@
+use crate::components::footer;
+use crate::components::header;
+use crate::screens::meta::{MetaProvider, PageMetaInfo};
+use crate::search::{Hit, HitKind, SearchIndex, DEFAULT_LIMIT};
+use crate::state::AppState;
+use crate::views::Site;
+use sauron::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Screen {
+    /// search query
+    pub query: String,
+    /// found wallets, votings and transactions
+    pub hits: Vec<Hit>,
+    /// server side state
+    pub state: Arc<AppState>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Msg {}
+
+impl Screen {
+    /// search in the state, which index is rebuilt as it is not serialized
+    pub fn new(state: Arc<AppState>, query: &str) -> Self {
+        let hits = match state.search.is_empty() {
+            true => SearchIndex::build(&state).search(query, DEFAULT_LIMIT),
+            false => state.search.search(query, DEFAULT_LIMIT),
+        };
+        Self {
+            query: query.to_owned(),
+            hits,
+            state,
+        }
+    }
+
+    fn render_hit(&self, hit: &Hit) -> Node<Msg> {
+        let badge = match hit.kind {
+            HitKind::Wallet => "Wallet",
+            HitKind::Voting => "Voting",
+            HitKind::Transaction => "Transaction",
+        };
+        node! {
+            <li>
+                <span class="badge">{text(badge)}</span>
+                " "
+                <a href={hit.url.clone()}>{text(hit.label.clone())}</a>
+            </li>
+        }
+    }
+}
+
+impl Component<Msg> for Screen {
+    fn view(&self) -> Node<Msg> {
+        let site = Site::from_state(&self.state);
+        let found = if self.hits.is_empty() {
+            div(vec![class("search-empty")], vec![text("Nothing was found")])
+        } else {
+            ol(
+                vec![class("search-results")],
+                self.hits
+                    .iter()
+                    .map(|hit| self.render_hit(hit))
+                    .collect::<Vec<Node<Msg>>>(),
+            )
+        };
+        node! {
+            <div class="screen-search">
+                { header::render("", &site) }
+                <div class="inner">
+                    <h1>{text(format!("Search: {}", self.query))}</h1>
+                    {found}
+                </div>
+                { footer::render(&site) }
+            </div>
+        }
+    }
+
+    fn update(&mut self, _: Msg) -> Cmd<Self, Msg> {
+        Cmd::none()
+    }
+}
+
+impl MetaProvider for Screen {
+    fn meta(&self) -> PageMetaInfo {
+        PageMetaInfo::new(
+            "API3 DAO Tracker - Search",
+            "Search API3 DAO members by address or ENS name, votings by title, and transactions by hash.",
+        )
+    }
+}
@@ -4,7 +4,7 @@ use crate::components::panel;
 use crate::nice;
 use crate::router::link_address;
 use crate::screens::meta::{MetaProvider, PageMetaInfo};
-use crate::state::AppState;
+use crate::views::TreasuryPage;
 use sauron::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap as Map;
@@ -12,15 +12,13 @@ use web3::types::{H160, U256};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Screen {
-    /// server side state
-    pub state: AppState,
+    /// balances of the treasuries, as they were sent by the server
+    pub page: TreasuryPage,
 }
 
 impl Screen {
-    pub fn new(state: AppState) -> Self {
-        Self {
-            state: state.clone(),
-        }
+    pub fn new(page: TreasuryPage) -> Self {
+        Self { page }
     }
 }
 
@@ -47,7 +45,7 @@ impl Screen {
             node!{
                 <div style="text-align:center; margin-bottom: 20px;">
                     <span class="darken">{text(format!("{} ", wallet))}</span>
-                    {link_address(self.state.chain_id, wallet, false)}
+                    {link_address(&self.page.site.network, wallet, false)}
                 </div>
             },
             div(vec![], tokens.iter().map(|(tokenname, value)| {
@@ -72,10 +70,11 @@ impl Screen {
 
 impl Component<Msg> for Screen {
     fn view(&self) -> Node<Msg> {
-        let decimals = self.state.decimals.clone();
+        let site = &self.page.site;
+        let decimals = self.page.decimals.clone();
         node! {
             <div class="screen-treasury">
-                { header::render("/treasury", &self.state) }
+                { header::render("/treasury", site) }
                 <div class="inner">
                     <div class="centered">
                         <h1>"API3 DAO Treasury"</h1>
@@ -86,14 +85,14 @@ impl Component<Msg> for Screen {
                         </p>
                         <div style="height: 20px">" "</div>
 
-                        {div(vec![class("dash-row")], self.state.treasuries.iter().map(|(_, t)| {
+                        {div(vec![class("dash-row")], self.page.treasuries.values().map(|t| {
                             self.render_treasury("dash-col dash-col-3", t.name.clone(), t.wallet.clone(), t.balances.clone(), decimals.clone())
                         }).collect())}
 
                         <div style="height: 30px">" "</div>
                     </div>
                 </div>
-                { footer::render(&self.state) }
+                { footer::render(site) }
             </div>
         }
     }
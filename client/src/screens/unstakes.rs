@@ -0,0 +1,202 @@
+use crate::components::footer;
+use crate::components::header;
+use crate::nice;
+use crate::router::link_known;
+use crate::screens::meta::{MetaProvider, PageMetaInfo};
+use crate::unstakes::{QueuedUnstake, UnstakeDay, UnstakeQueue};
+use crate::views::UnstakesPage;
+use sauron::prelude::*;
+use serde::{Deserialize, Serialize};
+use web3::types::H160;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Screen {
+    /// unstaking queue at the time of the request, as it was sent by the server
+    pub page: UnstakesPage,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Msg {}
+
+pub fn countdown(seconds: u64) -> String {
+    let days = seconds / 86400;
+    let hours = (seconds % 86400) / 3600;
+    let minutes = (seconds % 3600) / 60;
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+impl Screen {
+    pub fn new(page: UnstakesPage) -> Self {
+        Self { page }
+    }
+
+    fn link_member(&self, addr: H160) -> Node<Msg> {
+        link_known(&self.page.site.network, &self.page.members, addr)
+    }
+
+    pub fn render_unstake_header(&self) -> Node<Msg> {
+        node! {
+            <tr>
+                <th class="c">"#"</th>
+                <th class="l">"Member"</th>
+                <th class="r">"Amount"</th>
+                <th class="r">"Shares"</th>
+                <th class="c">"Scheduled"</th>
+                <th class="c">"Executable"</th>
+                <th class="c">"Countdown"</th>
+            </tr>
+        }
+    }
+
+    pub fn render_unstake_tr(&self, index: usize, u: &QueuedUnstake) -> Node<Msg> {
+        node! {
+            <tr>
+                <td class="c">{text(format!("{}.", index + 1))}</td>
+                <td class="l eth-address">{self.link_member(u.address)}</td>
+                <td class="r" title={nice::amount(u.amount, 18)}>{text(nice::ceil(u.amount, 18))}</td>
+                <td class="r darken" title={nice::amount(u.shares, 18)}>{text(nice::ceil(u.shares, 18))}</td>
+                <td class="c darken dt">{text(nice::date(u.scheduled_at))}</td>
+                <td class="c dt">{text(nice::date(u.executable_at))}</td>
+                <td class="c">{
+                    if u.overdue {
+                        span(vec![class("warning")], vec![text("overdue")])
+                    } else {
+                        span(vec![class("accent")], vec![text(countdown(u.seconds_left))])
+                    }
+                }</td>
+            </tr>
+        }
+    }
+
+    pub fn render_unstake(&self, u: &QueuedUnstake) -> Node<Msg> {
+        node! {
+            <li>
+                <div class="eth-address">{self.link_member(u.address)}</div>
+                <div>
+                    <strong>{text(nice::ceil(u.amount, 18))}</strong>
+                    <span class="darken">{text(format!(" API3 at {} ", nice::date(u.executable_at)))}</span>
+                    {if u.overdue {
+                        span(vec![class("warning")], vec![text("overdue")])
+                    } else {
+                        span(vec![class("accent")], vec![text(countdown(u.seconds_left))])
+                    }}
+                </div>
+            </li>
+        }
+    }
+
+    pub fn render_day_header(&self) -> Node<Msg> {
+        node! {
+            <tr>
+                <th class="c">"Date"</th>
+                <th class="r">"Unstakes"</th>
+                <th class="r">"Leaving the Pool"</th>
+                <th class="r">"Total Staked After"</th>
+                <th class="r">"% of Target"</th>
+                <th class="r">"APR"</th>
+            </tr>
+        }
+    }
+
+    pub fn render_day_tr(&self, d: &UnstakeDay) -> Node<Msg> {
+        let target_class = if d.target_reached { "r" } else { "r warning" };
+        node! {
+            <tr>
+                <td class="c dt">{text(nice::date(d.day).replace(" 00:00:00", ""))}</td>
+                <td class="r">{text(nice::int(d.count))}</td>
+                <td class="r" title={nice::amount(d.amount, 18)}>{text(nice::ceil(d.amount, 18))}</td>
+                <td class="r" title={nice::amount(d.total_stake, 18)}>{text(nice::ceil(d.total_stake, 18))}</td>
+                <td class={target_class}>{text(format!("{:.2}%", 100.0 * d.target_share))}</td>
+                <td class="r darken">{text(format!("{:.2}%", 100.0 * d.apr))}</td>
+            </tr>
+        }
+    }
+
+    pub fn render_timeline(&self, queue: &UnstakeQueue) -> Node<Msg> {
+        div(
+            vec![],
+            vec![
+                h2(
+                    vec![styles([("text-align", "center")])],
+                    vec![text("Stake Leaving the Pool")],
+                ),
+                table(
+                    vec![class("table unstakes-timeline")],
+                    vec![
+                        thead(vec![], vec![self.render_day_header()]),
+                        tbody(
+                            vec![],
+                            queue
+                                .timeline
+                                .iter()
+                                .map(|d| self.render_day_tr(d))
+                                .collect::<Vec<Node<Msg>>>(),
+                        ),
+                    ],
+                ),
+            ],
+        )
+    }
+}
+
+impl Component<Msg> for Screen {
+    fn view(&self) -> Node<Msg> {
+        let site = &self.page.site;
+        let queue = &self.page.queue;
+        node! {
+            <div class="screen-unstakes">
+                { header::render("/unstakes", site) }
+                <div class="inner">
+                    <h1>"API3 DAO Unstaking Queue"</h1>
+                    {if !queue.items.is_empty() {
+                        div(vec![], vec![
+                            node! {
+                                <p style="text-align: center">
+                                    <strong>{text(nice::int(queue.items.len()))}</strong>
+                                    <span class="darken">" members scheduled unstaking of "</span>
+                                    <strong title={nice::amount(queue.total, 18)}>{text(nice::ceil(queue.total, 18))}</strong>
+                                    <span class="darken">" API3 tokens, "</span>
+                                    <strong class="warning" title={nice::amount(queue.overdue, 18)}>{text(nice::ceil(queue.overdue, 18))}</strong>
+                                    <span class="darken">" of them can be unstaked already"</span>
+                                </p>
+                            },
+                            div(vec![class("desktop-only")], vec![
+                                table(vec![class("table unstakes-table")], vec![
+                                    thead(vec![], vec![self.render_unstake_header()]),
+                                    tbody(vec![], queue.items.iter().enumerate().map(|(i, u)| self.render_unstake_tr(i, u)).collect::<Vec<Node<Msg>>>()),
+                                ]),
+                            ]),
+                            div(vec![class("mobile-only")], vec![
+                                ol(vec![class("unstakes-list")], queue.items.iter().map(|u| self.render_unstake(u)).collect::<Vec<Node<Msg>>>()),
+                            ]),
+                            self.render_timeline(queue),
+                        ])
+                    } else {
+                        div(vec![class("unstakes-empty")], vec![
+                            text("There are no scheduled unstakes")
+                        ])
+                    }}
+                </div>
+                { footer::render(site) }
+            </div>
+        }
+    }
+
+    fn update(&mut self, _: Msg) -> Cmd<Self, Msg> {
+        Cmd::none()
+    }
+}
+
+impl MetaProvider for Screen {
+    fn meta(&self) -> PageMetaInfo {
+        let page_title = "API3 DAO Unstaking Queue";
+        let description = "Explore scheduled unstakes of API3 DAO members and how much stake leaves the pool. No wallet connection is needed";
+        PageMetaInfo::new(page_title, description)
+    }
+}
@@ -1,13 +1,16 @@
-use crate::components::err_box;
+use crate::components::chart::{Chart, Kind};
 use crate::components::footer;
 use crate::components::header;
 use crate::events::{self, Api3, VotingAgent};
 use crate::eventsnode::wrap_vote_details;
 use crate::fees::TxFeeTotal;
 use crate::nice;
-use crate::router::{link_eventlog, link_wallet};
+use crate::participation;
+use crate::router::{link_eventlog, link_known};
 use crate::screens::meta::{MetaProvider, PageMetaInfo};
-use crate::state::{AppState, OnChainEvent};
+use crate::state::{OnChainEvent, Voting};
+use crate::views::VotingPage;
+use crate::votetimeline::{Crossing, Threshold, VoteTimeline};
 use sauron::prelude::*;
 use serde::{Deserialize, Serialize};
 use web3::types::{H160, U256};
@@ -20,24 +23,31 @@ pub struct Screen {
     pub vote_id: u64,
     // agent of the voting
     pub agent: VotingAgent,
-    /// server side state
-    pub state: AppState,
+    /// voting with its history, built by the server
+    pub page: VotingPage,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Msg {}
 
 impl Screen {
-    pub fn new(state: AppState, vote_ref: u64) -> Self {
+    pub fn new(mut page: VotingPage) -> Self {
+        // the snapshot is not serialized with the voting
+        page.voting.snapshot = page.snapshot.clone();
+        let vote_ref = page.voting.as_u64();
         let (agent, vote_id) = events::voting_from_u64(vote_ref);
         Self {
             vote_ref,
             vote_id,
             agent,
-            state: state.clone(),
+            page,
         }
     }
 
+    fn link_member(&self, addr: H160) -> Node<Msg> {
+        link_known(&self.page.site.network, &self.page.members, addr)
+    }
+
     pub fn render_event_header(&self) -> Node<Msg> {
         node! {
             <tr>
@@ -52,6 +62,210 @@ impl Screen {
             </tr>
         }
     }
+    pub fn render_non_voters(&self, v: &Voting) -> Node<Msg> {
+        let non_voters = v.non_voters();
+        if v.snapshot.is_empty() {
+            return text("");
+        }
+        if non_voters.is_empty() {
+            return div(
+                vec![class("events-empty")],
+                vec![text("Every eligible member has voted")],
+            );
+        }
+        let power: U256 = non_voters
+            .iter()
+            .map(|(_, p)| *p)
+            .fold(U256::from(0), |a, b| a + b);
+        let total = v.votes_total;
+        div(
+            vec![],
+            vec![
+                h2(
+                    vec![styles([("text-align", "center")])],
+                    vec![text(format!("{} Eligible Members Did Not Vote", non_voters.len()))],
+                ),
+                node! {
+                    <p style="text-align: center">
+                        <span class="darken">"They were holding "</span>
+                        <strong title={nice::amount(power, 18)}>{ text(nice::ceil(power, 18)) }</strong>
+                        <span class="darken">{text(format!(
+                            " shares of voting power ({}%) at the voting snapshot",
+                            nice::pct3_of(power, total, 18)
+                        ))}</span>
+                    </p>
+                },
+                div(
+                    vec![class("desktop-only")],
+                    vec![table(
+                        vec![class("table non-voters-table")],
+                        vec![
+                            thead(
+                                vec![],
+                                vec![node! {
+                                    <tr>
+                                        <th class="c">"#"</th>
+                                        <th class="l">"Member"</th>
+                                        <th class="r">"Voting Power"</th>
+                                        <th class="r">"%"</th>
+                                    </tr>
+                                }],
+                            ),
+                            tbody(
+                                vec![],
+                                non_voters
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(i, (addr, p))| {
+                                        node! {
+                                            <tr>
+                                                <td class="c">{text(format!("{}.", i + 1))}</td>
+                                                <td class="l eth-address">{self.link_member(*addr)}</td>
+                                                <td class="r" title={nice::amount(*p, 18)}>{text(nice::ceil(*p, 18))}</td>
+                                                <td class="r darken">{text(nice::pct3_of(*p, total, 18))}"%"</td>
+                                            </tr>
+                                        }
+                                    })
+                                    .collect::<Vec<Node<Msg>>>(),
+                            ),
+                        ],
+                    )],
+                ),
+                div(
+                    vec![class("mobile-only")],
+                    vec![ol(
+                        vec![class("non-voters-list")],
+                        non_voters
+                            .iter()
+                            .map(|(addr, p)| {
+                                node! {
+                                    <li>
+                                        <div class="eth-address">{self.link_member(*addr)}</div>
+                                        <div class="darken">{text(format!("{} shares", nice::ceil(*p, 18)))}</div>
+                                    </li>
+                                }
+                            })
+                            .collect::<Vec<Node<Msg>>>(),
+                    )],
+                ),
+            ],
+        )
+    }
+
+    /// cumulative votes in favour and against, as shares of the total voting power
+    pub fn render_timeline(&self, v: &Voting, tl: &VoteTimeline) -> Node<Msg> {
+        if tl.points.is_empty() {
+            return text("");
+        }
+        let mut labels = vec![nice::date(v.tm)];
+        let (mut yes, mut no) = (vec![0.0], vec![0.0]);
+        for pt in &tl.points {
+            labels.push(nice::date(pt.tm));
+            yes.push(pt.quorum * 100.0);
+            no.push(participation::ratio(pt.no, tl.votes_total) * 100.0);
+        }
+        let quorum = vec![tl.min_quorum * 100.0; labels.len()];
+        let chart = Chart::new(Kind::Line, labels, "%")
+            .series("Yes", yes)
+            .series("No", no)
+            .series("Quorum", quorum);
+        node! {
+            <div class="charts">
+                <h2>"Voting Timeline"</h2>
+                {chart.render("")}
+                {div(
+                    vec![class("vote-crossings")],
+                    tl.crossings.iter().map(|c| self.render_crossing(tl, c)).collect::<Vec<Node<Msg>>>(),
+                )}
+            </div>
+        }
+    }
+
+    fn render_crossing(&self, tl: &VoteTimeline, c: &Crossing) -> Node<Msg> {
+        let what = match c.threshold {
+            Threshold::Support => format!("Support of {:.0}%", tl.support_required * 100.0),
+            Threshold::Quorum => format!("Quorum of {:.0}%", tl.min_quorum * 100.0),
+        };
+        let (cls, how) = match c.reached {
+            true => ("accent", " was reached with the vote of "),
+            false => ("warning", " was lost with the vote of "),
+        };
+        node! {
+            <p style="text-align: center">
+                <strong class={cls}>{text(what)}</strong>
+                <span class="darken">{text(how)}</span>
+                <span class="eth-address">{self.link_member(tl.points[c.index].voter)}</span>
+                <span class="darken">{text(format!(" on {}", nice::date(c.tm)))}</span>
+            </p>
+        }
+    }
+
+    /// voters ranked by their voting power, with votes that decided the outcome
+    pub fn render_voters(&self, tl: &VoteTimeline) -> Node<Msg> {
+        if tl.voters.is_empty() {
+            return text("");
+        }
+        let decisive = tl.decisive().len();
+        let note = match decisive {
+            0 => "No single vote decided the outcome".to_owned(),
+            1 => "1 vote decided the outcome: without it the result would be different".to_owned(),
+            n => format!(
+                "{} votes decided the outcome: without any of them the result would be different",
+                n
+            ),
+        };
+        div(
+            vec![],
+            vec![
+                h2(
+                    vec![styles([("text-align", "center")])],
+                    vec![text(format!("{} Members Voted", tl.voters.len()))],
+                ),
+                p(
+                    vec![styles([("text-align", "center")]), class("darken")],
+                    vec![text(note)],
+                ),
+                table(
+                    vec![class("table voters-table")],
+                    vec![
+                        thead(
+                            vec![],
+                            vec![node! {
+                                <tr>
+                                    <th class="c">"#"</th>
+                                    <th class="l">"Member"</th>
+                                    <th class="c">"Cast"</th>
+                                    <th class="r">"Voting Power"</th>
+                                    <th class="r">"%"</th>
+                                    <th class="c">"Decisive"</th>
+                                </tr>
+                            }],
+                        ),
+                        tbody(
+                            vec![],
+                            tl.voters
+                                .iter()
+                                .enumerate()
+                                .map(|(i, x)| {
+                                    node! {
+                                        <tr>
+                                            <td class="c">{text(format!("{}.", i + 1))}</td>
+                                            <td class="l eth-address">{self.link_member(x.address)}</td>
+                                            <td class="c">{text(if x.supports { "Supports" } else { "Rejects" })}</td>
+                                            <td class="r" title={nice::amount(x.stake, 18)}>{text(nice::ceil(x.stake, 18))}</td>
+                                            <td class="r darken">{text(format!("{:.2}%", x.share * 100.0))}</td>
+                                            <td class="c accent">{text(if x.decisive { "Yes" } else { "" })}</td>
+                                        </tr>
+                                    }
+                                })
+                                .collect::<Vec<Node<Msg>>>(),
+                        ),
+                    ],
+                ),
+            ],
+        )
+    }
+
     pub fn render_event(&self, _e: &OnChainEvent, _total_shares: U256) -> Node<Msg> {
         div(vec![], vec![])
     }
@@ -116,17 +330,17 @@ impl Screen {
             } => Some(supports),
             _ => None,
         };
-        let fees = self.state.fees.get(&e.tx);
+        let fees = self.page.fees.get(&e.tx);
         node! {
             <tr>
                 <td class="c">{text(format!("{}.", index + 1))}</td>
                 <td class="c darken dt">{text(nice::date(e.tm))}</td>
-                <td class="c">{link_eventlog(self.state.chain_id, e.block_number, e.tx)}</td>
+                <td class="c">{link_eventlog(&self.page.site.network, e.block_number, e.tx)}</td>
                 <td class="c darken entry">{text(event)}</td>
                 <td class="l">
                     <div class="eth-address">{
                         match voter {
-                            Some(x) => link_wallet(&self.state, x),
+                            Some(x) => self.link_member(x),
                             None => text(""),
                         }
                     }
@@ -178,10 +392,7 @@ impl Screen {
 
 impl Component<Msg> for Screen {
     fn view(&self) -> Node<Msg> {
-        let v = match self.state.votings.get(&self.vote_ref) {
-            Some(v) => v,
-            None => return err_box("voting was not found"),
-        };
+        let v = &self.page.voting;
 
         let subtitle = format!(
             "API3 DAO {} Proposal Voting",
@@ -192,16 +403,13 @@ impl Component<Msg> for Screen {
         let required = total * U256::from(pct_required) / U256::from(100);
         let pct_yes = nice::pct3_of(v.voted_yes, v.votes_total, 18);
         let pct_no = nice::pct3_of(v.voted_no, v.votes_total, 18);
-        let sorted: Vec<OnChainEvent> = self.state.votings_events.get(&v.as_u64()).unwrap().clone();
+        let sorted: &Vec<OnChainEvent> = &self.page.events;
         let decision = "text-align:center; border: 1px #888 solid; padding: 30px";
-        let totals = self
-            .state
-            .votings_events
-            .get(&v.as_u64())
-            .map(|e| TxFeeTotal::new(e).to_string());
+        let totals = Some(TxFeeTotal::new(sorted).to_string());
+        let timeline = VoteTimeline::new(v, sorted);
         node! {
             <div class="screen-voting">
-                { header::render("/votings", &self.state) }
+                { header::render("/votings", &self.page.site) }
                 <div class="inner">
                     <h1>{text(v.title.clone())}</h1>
                     <h2 style="text-align: center">{text(subtitle)}</h2>
@@ -257,13 +465,16 @@ impl Component<Msg> for Screen {
                         text("")
                     }}
 
+                    {self.render_timeline(v, &timeline)}
+                    {self.render_voters(&timeline)}
+
                     <h2 style="text-align: center">"Voting History Log"</h2>
                     { match totals {
                         Some(t) => node!(<h4 style="text-align:center"><small class="darken">{text(t)}</small></h4>),
                         None => text(""),
                     }}
 
-                    {if self.state.votings_events.len() > 0 {
+                    {if !sorted.is_empty() {
                         div(vec![], vec![
                             div(vec![class("desktop-only")], vec![
                                 table(vec
@@ -286,8 +497,9 @@ impl Component<Msg> for Screen {
                             text("There were no votings events in the DAO")
                         ])
                     }}
+                    {self.render_non_voters(v)}
                 </div>
-                { footer::render(&self.state) }
+                { footer::render(&self.page.site) }
             </div>
         }
     }
@@ -302,13 +514,11 @@ impl MetaProvider for Screen {
         let description =
             "Explore API3 DAO proposal full voting history. No wallet connection is needed"
                 .to_owned();
-        let title = match self.state.votings.get(&self.vote_ref) {
-            Some(v) => format!(
-                "API3 DAO {} Proposal Voting History",
-                if v.primary { "Primary" } else { "Secondary" }
-            ),
-            None => "API3 DAO Voting was not found".to_owned(),
-        };
+        let v = &self.page.voting;
+        let title = format!(
+            "API3 DAO {} Proposal Voting History",
+            if v.primary { "Primary" } else { "Secondary" }
+        );
         PageMetaInfo::new(&title, &description)
     }
 }
@@ -1,28 +1,24 @@
 use crate::components::footer;
 use crate::components::header;
 use crate::eventsnode::wrap_vote_details;
-use crate::fees::TxFeeTotal;
 use crate::nice;
 use crate::screens::meta::{MetaProvider, PageMetaInfo};
-use crate::state::{AppState, Voting};
+use crate::views::{VotingRow, VotingsPage};
 use sauron::prelude::*;
 use serde::{Deserialize, Serialize};
-use web3::types::U256;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Screen {
-    /// server side state
-    pub state: AppState,
+    /// votings of the DAO, as they were sent by the server
+    pub page: VotingsPage,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Msg {}
 
 impl Screen {
-    pub fn new(state: AppState) -> Self {
-        Self {
-            state: state.clone(),
-        }
+    pub fn new(page: VotingsPage) -> Self {
+        Self { page }
     }
 
     pub fn render_voting_header(&self) -> Node<Msg> {
@@ -38,13 +34,12 @@ impl Screen {
             </tr>
         }
     }
-    pub fn render_voting_tr(&self, index: usize, v: &Voting) -> Node<Msg> {
-        let pct_required = if v.primary { 50u64 } else { 15u64 };
-        let required = v.votes_total * U256::from(pct_required) / U256::from(100);
+    pub fn render_voting_tr(&self, index: usize, v: &VotingRow) -> Node<Msg> {
+        let required = v.required();
         let pct_yes = nice::pct3_of(v.voted_yes, v.votes_total, 18);
         let pct_no = nice::pct3_of(v.voted_no, v.votes_total, 18);
-        let rejected = v.voted_no >= required;
-        let passing = !rejected && v.voted_yes >= required && v.voted_no < required;
+        let rejected = v.rejected();
+        let passing = v.passing();
 
         let class_yes = if v.voted_yes > required {
             "r accent"
@@ -57,11 +52,7 @@ impl Screen {
             "r"
         };
 
-        let totals = self
-            .state
-            .votings_events
-            .get(&v.as_u64())
-            .map(|e| TxFeeTotal::new(e).to_string());
+        let totals = v.fees.as_ref().map(|f| f.to_string());
         node! {
             <tr>
                 <td class="c">{text(format!("{}.", index + 1))}</td>
@@ -75,7 +66,7 @@ impl Screen {
                 }</td>
                 <td class="l">
                     <div>
-                        <a href={format!("votings/{}", v.key()) }>
+                        <a href={format!("votings/{}", v.key) }>
                             <strong>{text(v.title.clone())}</strong>
                         </a>
                     </div>
@@ -98,7 +89,7 @@ impl Screen {
                         span(vec![class("badge")], vec![text("Executed ")])
                     } else if !passing && rejected {
                         span(vec![class(class_no)], vec![text("Rejected ")])
-                    } else if !passing && v.is_expired() {
+                    } else if !passing && v.expired {
                         span(vec![class(class_no)], vec![text("Expired ")])
                     } else {
                         span(vec![class(class_no)], vec![text("NO ")])
@@ -108,11 +99,94 @@ impl Screen {
         }
     }
 
-    pub fn render_voting(&self, voting: &Voting) -> Node<Msg> {
+    pub fn render_turnout_chart(&self) -> Node<Msg> {
+        let n = self.page.trend.len();
+        if n == 0 {
+            return text("");
+        }
+        let (w, h) = (800.0, 160.0);
+        let step = w / n as f64;
+        let mut children: Vec<Node<Msg>> = self
+            .page
+            .turnout
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let bar_h = v.turnout.min(1.0) * h;
+                svg_element(
+                    "rect",
+                    vec![
+                        class(if v.primary {
+                            "bar bar-primary"
+                        } else {
+                            "bar bar-secondary"
+                        }),
+                        attr("x", format!("{:.2}", i as f64 * step + step * 0.1)),
+                        attr("y", format!("{:.2}", h - bar_h)),
+                        attr("width", format!("{:.2}", step * 0.8)),
+                        attr("height", format!("{:.2}", bar_h)),
+                    ],
+                    vec![svg_element(
+                        "title",
+                        vec![],
+                        vec![text(format!(
+                            "{} {}: {:.1}% turnout, {} voters",
+                            v.key,
+                            v.title,
+                            v.turnout * 100.0,
+                            v.voters
+                        ))],
+                    )],
+                )
+            })
+            .collect();
+        let points: Vec<String> = self
+            .page
+            .trend
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                format!(
+                    "{:.2},{:.2}",
+                    i as f64 * step + step * 0.5,
+                    h - p.average.min(1.0) * h
+                )
+            })
+            .collect();
+        children.push(svg_element(
+            "polyline",
+            vec![class("trend"), attr("points", points.join(" "))],
+            vec![],
+        ));
+        let chart = svg_element(
+            "svg",
+            vec![
+                class("turnout-chart"),
+                attr("viewBox", format!("0 0 {} {}", w, h)),
+                attr("preserveAspectRatio", "none"),
+            ],
+            children,
+        );
+        node! {
+            <div class="turnout">
+                <h2 style="text-align: center">"Votings Turnout"</h2>
+                <p class="darken" style="text-align: center">
+                    {text(format!(
+                        "Average turnout is {:.1}% of the voting power, the line shows the average of the last {} votings",
+                        self.page.avg_turnout * 100.0,
+                        crate::participation::TREND_WINDOW,
+                    ))}
+                </p>
+                {chart}
+            </div>
+        }
+    }
+
+    pub fn render_voting(&self, voting: &VotingRow) -> Node<Msg> {
         node! {
             <li>
                 <div class="voting">
-                    <a href={format!("votings/{}", voting.key()) }>
+                    <a href={format!("votings/{}", voting.key) }>
                         { text(format!("{}: {:?}",
                             if voting.primary {
                                 "Primary"
@@ -127,7 +201,7 @@ impl Screen {
 
     pub fn render_votings_group(
         &self,
-        sorted: &Vec<Voting>,
+        sorted: &[VotingRow],
         title: &str,
         empty_msg: &str,
     ) -> Node<Msg> {
@@ -176,58 +250,20 @@ impl Screen {
 
 impl Component<Msg> for Screen {
     fn view(&self) -> Node<Msg> {
-        // votings are 1 - pending, 2 - executed, 3 - rejected
-        let pending: Vec<Voting> = self
-            .state
-            .votings
-            .values()
-            .cloned()
-            .filter(|v| {
-                let pct_required = if v.primary { 50u64 } else { 15u64 };
-                let required = v.votes_total * U256::from(pct_required) / U256::from(100);
-                let rejected = v.voted_no >= required;
-                let passing = !rejected && v.voted_yes >= required && v.voted_no < required;
-                !v.executed && !v.is_invalid() && (passing || !v.is_expired())
-            })
-            .collect();
-        let invalid: Vec<Voting> = self
-            .state
-            .votings
-            .values()
-            .cloned()
-            .filter(|v| !v.executed && v.is_invalid())
-            .collect();
-        let executed: Vec<Voting> = self
-            .state
-            .votings
-            .values()
-            .cloned()
-            .filter(|v| v.executed)
-            .collect();
-        let rejected: Vec<Voting> = self
-            .state
-            .votings
-            .values()
-            .cloned()
-            .filter(|v| {
-                let pct_required = if v.primary { 50u64 } else { 15u64 };
-                let required = v.votes_total * U256::from(pct_required) / U256::from(100);
-                let rejected = v.voted_no >= required;
-                let passing = !rejected && v.voted_yes >= required && v.voted_no < required;
-                !v.executed && (rejected || (!passing && v.is_expired()))
-            })
-            .collect();
+        let site = &self.page.site;
+        let page = &self.page;
         node! {
             <div class="screen-votings">
-                { header::render("/votings", &self.state) }
+                { header::render("/votings", site) }
                 <div class="inner">
                     <h1>"API3 DAO Votings"</h1>
-                    {if self.state.votings.len() > 0 {
+                    {if page.total > 0 {
                         div(vec![], vec![
-                            self.render_votings_group(&pending, "Pending Proposals", "There are no pending proposals"),
-                            self.render_votings_group(&executed, "Executed Proposals", "There are no executed proposals"),
-                            self.render_votings_group(&invalid, "Invalid Proposals", ""),
-                            self.render_votings_group(&rejected, "Rejected Proposals", "There are no rejected proposals"),
+                            self.render_turnout_chart(),
+                            self.render_votings_group(&page.pending, "Pending Proposals", "There are no pending proposals"),
+                            self.render_votings_group(&page.executed, "Executed Proposals", "There are no executed proposals"),
+                            self.render_votings_group(&page.invalid, "Invalid Proposals", ""),
+                            self.render_votings_group(&page.rejected, "Rejected Proposals", "There are no rejected proposals"),
                         ])
                     } else {
                         div(vec![class("votings-empty")], vec![
@@ -235,7 +271,7 @@ impl Component<Msg> for Screen {
                         ])
                     }}
                 </div>
-                { footer::render(&self.state) }
+                { footer::render(site) }
             </div>
         }
     }
@@ -250,7 +286,7 @@ impl MetaProvider for Screen {
         let title = "API3 DAO Tracker - Full Votings History";
         let description = format!(
             "Explore {} votings history of API3 DAO. No wallet connection is needed",
-            self.state.votings.len()
+            self.page.total
         );
         PageMetaInfo::new(&title, &description)
     }
@@ -1,13 +1,15 @@
-use crate::components::err_box;
+use crate::components::chart::{Chart, Kind};
 use crate::components::footer;
 use crate::components::header;
 use crate::components::panel;
 use crate::eventsnode::entry_node;
 use crate::fees::TxFeeTotal;
 use crate::nice;
-use crate::router::{link_address, link_eventlog, link_wallet};
+use crate::router::{link_address, link_eventlog, link_known};
 use crate::screens::meta::{MetaProvider, PageMetaInfo};
-use crate::state::{AppState, Epoch, OnChainEvent, Wallet};
+use crate::state::{OnChainEvent, Wallet};
+use crate::timeline::TimelinePoint;
+use crate::views::{WalletEpoch, WalletPage};
 use sauron::prelude::*;
 use serde::{Deserialize, Serialize};
 use web3::types::{H160, U256};
@@ -16,18 +18,22 @@ use web3::types::{H160, U256};
 pub struct Screen {
     // address of the wallet
     pub addr: H160,
-    /// server side state
-    pub state: AppState,
+    /// wallet with its history, built by the server
+    pub page: WalletPage,
 }
 
 impl Screen {
-    pub fn new(state: AppState, addr: &H160) -> Self {
+    pub fn new(page: WalletPage) -> Self {
         Self {
-            state: state.clone(),
-            addr: addr.clone(),
+            addr: page.wallet.address,
+            page,
         }
     }
 
+    fn link_member(&self, addr: H160) -> Node<Msg> {
+        link_known(&self.page.site.network, &self.page.members, addr)
+    }
+
     pub fn render_event_header(&self) -> Node<Msg> {
         node! {
             <tr>
@@ -35,6 +41,7 @@ impl Screen {
                 <th class="c">"Date"</th>
                 <th class="c" style="white-space:nowrap; width:133px">"Block #"</th>
                 <th class="l">"Event"</th>
+                <th class="r">"Balance"</th>
             </tr>
         }
     }
@@ -42,15 +49,57 @@ impl Screen {
         div(vec![], vec![])
     }
 
-    pub fn render_event_tr(&self, index: usize, e: &OnChainEvent) -> Node<Msg> {
-        let fees = self.state.fees.get(&e.tx);
+    /// running balances of the wallet after the event
+    pub fn render_balance(&self, p: &TimelinePoint) -> Node<Msg> {
+        let mut lines: Vec<(&str, String)> = vec![
+            ("Staked", nice::ceil(p.staked, 18)),
+            ("Shares", nice::ceil(p.shares, 18)),
+        ];
+        if p.pending_unstake > U256::from(0) {
+            lines.push(("Unstaking", nice::ceil(p.pending_unstake, 18)));
+        }
+        if p.rewards > U256::from(0) {
+            lines.push(("Rewards", nice::ceil(p.rewards, 18)));
+        }
+        if p.delegated_from > U256::from(0) {
+            lines.push(("Delegated to it", nice::ceil(p.delegated_from, 18)));
+        }
+        let mut children: Vec<Node<Msg>> = lines
+            .into_iter()
+            .map(|(caption, amount)| {
+                node! {
+                    <div>
+                        <span class="darken">{text(format!("{} ", caption))}</span>
+                        <span class="amt">{text(amount)}</span>
+                    </div>
+                }
+            })
+            .collect();
+        if let Some(to) = &p.delegated_to {
+            children.push(node! {
+                <div>
+                    <span class="darken">"Delegates to "</span>
+                    {self.link_member(*to)}
+                </div>
+            });
+        }
+        div(vec![class("balance")], children)
+    }
+
+    pub fn render_event_tr(
+        &self,
+        index: usize,
+        e: &OnChainEvent,
+        point: Option<&TimelinePoint>,
+    ) -> Node<Msg> {
+        let fees = self.page.fees.get(&e.tx);
         node! {
             <tr>
                 <td class="c">{text(format!("{}.", index + 1))}</td>
                 <td class="c darken dt">{text(nice::date(e.tm))}</td>
-                <td class="c">{link_eventlog(self.state.chain_id, e.block_number, e.tx)}</td>
+                <td class="c">{link_eventlog(&self.page.site.network, e.block_number, e.tx)}</td>
                 <td class="l entry darken">
-                    <div>{entry_node(&e.entry, self.addr, &self.state)}</div>
+                    <div>{entry_node(&e.entry, self.addr, &self.page.votings)}</div>
                     <div>
                         <small class="darken">
                             {
@@ -62,6 +111,14 @@ impl Screen {
                         </small>
                     </div>
                 </td>
+                <td class="r">
+                    {
+                        match point {
+                            Some(p) => self.render_balance(p),
+                            None => text(""),
+                        }
+                    }
+                </td>
             </tr>
         }
     }
@@ -82,7 +139,7 @@ impl Screen {
     pub fn render_delegation_tr(&self, addr: &H160, shares: &U256) -> Node<Msg> {
         node! {
             <tr>
-                <td class="l">{link_wallet(&self.state, addr.clone())}</td>
+                <td class="l">{self.link_member(*addr)}</td>
                 <td class="r" title={nice::amount(*shares, 18)}>{text(nice::ceil(*shares,18))}</td>
             </tr>
         }
@@ -126,13 +183,11 @@ impl Screen {
     }
 
     pub fn render_wallet_info(&self, w: &Wallet) -> Node<Msg> {
-        let labels = self.state.get_labels(w);
-        let total_shares = match &self.state.pool_info {
-            Some(x) => x.total_shares,
-            None => self.state.get_shares_total(),
-        };
+        let labels = &self.page.labels;
+        let total_shares = self.page.total_shares;
         let pct = format!("{}%", nice::pct3_of(w.voting_power, total_shares, 18));
         let pct6 = format!("{}%", nice::pct6_of(w.voting_power, total_shares, 18));
+        let participation = &self.page.participation;
 
         let mut out: Vec<Node<Msg>> = vec![
             // text(format!("{}", serde_json::to_string_pretty(&w).unwrap())),
@@ -141,7 +196,7 @@ impl Screen {
             out.push(node! {
                 <div class="delegates-all">
                     "This member delegates all his voting power to "
-                    {link_wallet(&self.state, d.address.clone())}
+                    {self.link_member(d.address)}
                 </div>
             });
         };
@@ -218,6 +273,22 @@ impl Screen {
                         }
                     }).collect::<Vec<Node<Msg>>>())}
 
+                    <h3 class="cell-title border-t" style="padding-top:30px;"> "Votings Participation" </h3>
+                    {if participation.eligible > 0 {
+                        node! {
+                            <p class="participation">
+                                <strong class="accent">{text(format!("{:.1}%", participation.rate * 100.0))}</strong>
+                                <span class="darken">{text(format!(
+                                    " - voted in {} of {} votings since joining the DAO",
+                                    participation.voted, participation.eligible
+                                ))}</span>
+                            </p>
+                        }
+                    } else {
+                        node! {
+                            <p class="participation darken">"There were no votings since this member joined the DAO"</p>
+                        }
+                    }}
                 </div>
             </div>
         });
@@ -226,16 +297,123 @@ impl Screen {
     }
 
     pub fn rewards_coeff(&self) -> f64 {
-        match &self.state.pool_info {
-            Some(x) => x.clone().rewards_coeff,
-            None => 1f64,
+        self.page.rewards_coeff
+    }
+
+    pub fn release_offset(&self) -> Option<u64> {
+        self.page.release_offset
+    }
+
+    /// date when rewards of the epoch are released, empty if pool is not read yet
+    pub fn release_date(&self, tm: u64) -> String {
+        match self.release_offset() {
+            Some(offset) => nice::date(tm + offset),
+            None => "".to_owned(),
+        }
+    }
+
+    pub fn render_vesting(&self, _w: &Wallet) -> Node<Msg> {
+        let schedule = &self.page.vesting;
+        if schedule.entries.is_empty() {
+            return text("");
+        }
+        node! {
+            <div class="dash-row">
+                <div class="dash-col dash-col-3 cell-t">
+                    <h3 class="cell-title">"Locked Rewards"</h3>
+                    <strong class="big-title accent" title={nice::amount(schedule.locked, 18)}>
+                        {text(nice::ceil(schedule.locked, 18))}
+                    </strong>
+                </div>
+                <div class="dash-col dash-col-3 cell-t">
+                    <h3 class="cell-title">"Unlocked Rewards"</h3>
+                    <strong class="big-title" title={nice::amount(schedule.unlocked, 18)}>
+                        {text(nice::ceil(schedule.unlocked, 18))}
+                    </strong>
+                </div>
+                <div class="dash-col dash-col-3 cell-t">
+                    <h3 class="cell-title">"Next Unlock"</h3>
+                    <strong class="big-title">
+                        {text(match schedule.next_unlock {
+                            Some(tm) => nice::date(tm),
+                            None => "-".to_owned(),
+                        })}
+                    </strong>
+                </div>
+            </div>
         }
     }
 
-    pub fn release_offset(&self) -> u64 {
-        match &self.state.pool_info {
-            Some(pool_info) => pool_info.reward_vesting_period * pool_info.epoch_length,
-            None => 0u64,
+    pub fn render_governance(&self, w: &Wallet) -> Node<Msg> {
+        let timelocks = &self.page.timelocks;
+        if w.vesting_remaining.is_none()
+            && w.last_proposal_tm.is_none()
+            && w.claim_loss == U256::from(0)
+            && timelocks.is_empty()
+        {
+            return text("");
+        }
+        let now = self.page.now;
+        let next_proposal = w.next_proposal_tm(self.page.epoch_length);
+        node! {
+            <div>
+                <div class="dash-row">
+                    <div class="dash-col dash-col-4 cell-t">
+                        <h3 class="cell-title">"Still Vesting"</h3>
+                        <strong class="big-title">
+                            {match w.vesting_remaining {
+                                Some(x) => span(vec![attr("title", nice::amount(x, 18))], vec![text(nice::ceil(x, 18))]),
+                                None => text("-"),
+                            }}
+                        </strong>
+                    </div>
+                    <div class="dash-col dash-col-4 cell-t">
+                        <h3 class="cell-title">"Vested from Timelock"</h3>
+                        <strong class="big-title" title={nice::amount(w.vesting_unlocked, 18)}>
+                            {text(nice::ceil(w.vesting_unlocked, 18))}
+                        </strong>
+                    </div>
+                    <div class="dash-col dash-col-4 cell-t">
+                        <h3 class="cell-title">"Next Proposal Allowed"</h3>
+                        <strong class="big-title">
+                            {match next_proposal {
+                                Some(tm) if tm > now => text(nice::date(tm)),
+                                Some(_) => text("now"),
+                                None => text("-"),
+                            }}
+                        </strong>
+                    </div>
+                    <div class="dash-col dash-col-4 cell-t">
+                        <h3 class="cell-title">"Lost to Claims"</h3>
+                        <strong class="big-title" title={nice::amount(w.claim_loss, 18)}>
+                            {text(nice::ceil(w.claim_loss, 18))}
+                        </strong>
+                    </div>
+                </div>
+                {if timelocks.is_empty() {
+                    text("")
+                } else {
+                    ul(vec![class("timelocks-list")], timelocks.iter().map(|t| {
+                        node! {
+                            <li>
+                                <span class="darken dt">{text(nice::date(t.tm))}</span>
+                                " "
+                                {link_eventlog(&self.page.site.network, t.block_number, t.tx)}
+                                " "
+                                {match t.beneficiary {
+                                    Some(addr) => span(vec![], vec![
+                                        text("timelocked tokens moved to the pool for "),
+                                        self.link_member(addr),
+                                    ]),
+                                    None => span(vec![attr("title", nice::amount(t.vested, 18))], vec![
+                                        text(format!("{} tokens vested", nice::ceil(t.vested, 18))),
+                                    ]),
+                                }}
+                            </li>
+                        }
+                    }).collect::<Vec<Node<Msg>>>())
+                }}
+            </div>
         }
     }
 
@@ -256,13 +434,20 @@ impl Screen {
         }
     }
 
-    pub fn render_epoch_tr(&self, ep: &Epoch, addr: &H160) -> Node<Msg> {
-        let staked = self.state.get_staked_for_epoch(addr, ep.index);
-        let reward = self.state.get_rewards_for_epoch(addr, ep.index);
+    pub fn render_epoch_tr(&self, we: &WalletEpoch) -> Node<Msg> {
+        let ep = &we.epoch;
+        let staked = we.stake.unwrap_or_default();
+        let reward = we.reward;
+        let now = self.page.now;
+        let locked = match self.release_offset() {
+            Some(offset) => ep.tm + offset > now,
+            None => true,
+        };
+        let release_class = if locked { "c" } else { "c darken" };
         node! {
             <tr>
                 <td class="c">{text(nice::int(ep.index))}</td>
-                <td class="c">{link_eventlog(self.state.chain_id, ep.block_number, ep.tx)}</td>
+                <td class="c">{link_eventlog(&self.page.site.network, ep.block_number, ep.tx)}</td>
                 <td class="c">{ text(nice::date(ep.tm)) }</td>
                 <td class="r darken">{ text(format!("{:.2}%", 100.0*ep.apr)) }</td>
                 <td class="r darken" title={nice::amount(ep.total, 18)}>{ text(nice::ceil(ep.total, 18)) }</td>
@@ -270,12 +455,13 @@ impl Screen {
                 <td class="r accent">{ text(format!("{:.4}%", 100.0*ep.apr*self.rewards_coeff() / 52.0)) }</td>
                 <td class="r" title={nice::amount(staked, 18)}>{ text(nice::ceil(staked, 18)) }</td>
                 <td class="r accent" title={nice::amount(reward, 18)}>{ text(nice::ceil(reward, 18)) }</td>
-                <td class="c">{ text(nice::date(ep.tm + self.release_offset())) }</td>
+                <td class={release_class}>{ text(self.release_date(ep.tm)) }</td>
             </tr>
         }
     }
 
-    pub fn render_epoch(&self, ep: &Epoch, _addr: &H160) -> Node<Msg> {
+    pub fn render_epoch(&self, we: &WalletEpoch) -> Node<Msg> {
+        let ep = &we.epoch;
         node! {
             <li>
                 <div class="epoch">
@@ -318,7 +504,7 @@ impl Screen {
                                 "Will be released: "
                             </span>
                             <strong>
-                                {text(nice::date(ep.tm + self.release_offset()))}
+                                {text(self.release_date(ep.tm))}
                             </strong>
                         </div>
                     </div>
@@ -327,8 +513,34 @@ impl Screen {
         }
     }
 
-    pub fn render_rewards(&self, w: &Wallet) -> Node<Msg> {
-        if self.state.epochs.len() > 0 {
+    /// stake of the wallet in each epoch since it joined the pool
+    pub fn render_stake_history(&self, _w: &Wallet) -> Node<Msg> {
+        let (mut labels, mut values) = (vec![], vec![]);
+        for we in &self.page.epochs {
+            let ep = &we.epoch;
+            let stake = match we.stake {
+                Some(x) => x,
+                None if values.is_empty() => continue,
+                None => U256::from(0),
+            };
+            let day = nice::date(ep.tm).split(' ').next().unwrap_or("").to_owned();
+            labels.push(format!("#{} {}", ep.index, day));
+            values.push(nice::dec(stake, 18));
+        }
+        if values.len() < 2 {
+            return text("");
+        }
+        let chart = Chart::new(Kind::Line, labels, " API3").series("Stake", values);
+        node! {
+            <div class="charts">
+                <h2>"Stake History"</h2>
+                {chart.render("")}
+            </div>
+        }
+    }
+
+    pub fn render_rewards(&self, _w: &Wallet) -> Node<Msg> {
+        if !self.page.epochs.is_empty() {
             div(
                 vec![],
                 vec![
@@ -340,10 +552,10 @@ impl Screen {
                                 thead(vec![], vec![self.render_epoch_header()]),
                                 tbody(
                                     vec![],
-                                    self.state
+                                    self.page
                                         .epochs
                                         .iter()
-                                        .map(|(_, epoch)| self.render_epoch_tr(epoch, &w.address))
+                                        .map(|we| self.render_epoch_tr(we))
                                         .collect::<Vec<Node<Msg>>>(),
                                 ),
                             ],
@@ -353,10 +565,10 @@ impl Screen {
                         vec![class("mobile-only")],
                         vec![ol(
                             vec![class("epochs-list")],
-                            self.state
+                            self.page
                                 .epochs
                                 .iter()
-                                .map(|(_, epoch)| self.render_epoch(epoch, &w.address))
+                                .map(|we| self.render_epoch(we))
                                 .collect::<Vec<Node<Msg>>>(),
                         )],
                     ),
@@ -371,6 +583,49 @@ impl Screen {
     }
 }
 
+/// avatar and links from the text records of the ENS name
+pub fn render_ens_profile(w: &Wallet) -> Node<Msg> {
+    let ens = match &w.ens {
+        Some(x) => x,
+        None => return text(""),
+    };
+    let r = &w.ens_records;
+    let mut links: Vec<Node<Msg>> = vec![];
+    if let Some(url) = r.website() {
+        links.push(node! {
+            <a href={url.clone()} rel="nofollow noopener noreferrer" target="_blank">{text(&url)}</a>
+        });
+    }
+    if let Some(handle) = r.twitter_handle() {
+        links.push(node! {
+            <a href={format!("https://twitter.com/{}", handle)} rel="nofollow noopener noreferrer" target="_blank">
+                {text(format!("@{}", handle))}
+            </a>
+        });
+    }
+    let avatar = r.avatar_url(ens);
+    if avatar.is_none() && links.is_empty() {
+        return text("");
+    }
+    div(
+        vec![class("ens-profile")],
+        vec![
+            match avatar {
+                Some(image) => img(
+                    vec![
+                        class("ens-avatar"),
+                        attr("src", image),
+                        attr("alt", ens.clone()),
+                    ],
+                    vec![],
+                ),
+                None => text(""),
+            },
+            div(vec![class("ens-links")], links),
+        ],
+    )
+}
+
 pub fn get_wallet_title(w: &Wallet) -> Node<Msg> {
     if let Some(ens) = &w.ens {
         return span(
@@ -392,72 +647,60 @@ pub enum Msg {}
 
 impl Component<Msg> for Screen {
     fn view(&self) -> Node<Msg> {
-        let totals = self
-            .state
-            .wallets_events
-            .get(&self.addr)
-            .map(|e| TxFeeTotal::new(e).to_string());
+        let w = &self.page.wallet;
+        let events = &self.page.events;
+        let totals = TxFeeTotal::new(events).to_string();
+        let timeline = &self.page.timeline;
         node! {
             <div class="screen-wallet">
-                { header::render("/wallets", &self.state) }
+                { header::render("/wallets", &self.page.site) }
                 <div class="inner">
-                    {
-                        match self.state.wallets.get(&self.addr) {
-                            Some(w) => div(
-                                vec![class("wallets-details")],
-                                vec![
-                                    h1(vec![], vec![get_wallet_title(&w)]),
-                                    h2(vec![styles([("text-align", "center")])], vec![
-                                        text(format!("{:?} ", w.address)),
-                                        link_address(self.state.chain_id, w.address, false),
-                                    ]),
-                                    self.render_wallet_info(&w),
-                                    h2(vec![styles([("text-align", "center")])], vec![text("User Rewards")]),
-                                    self.render_rewards(&w),
-                                    self.render_delegation_info(&w),
-                                ]
-                            ),
-                            None => err_box("member wallet was not found")
-                        }
-                    }
+                    {div(
+                        vec![class("wallets-details")],
+                        vec![
+                            h1(vec![], vec![get_wallet_title(w)]),
+                            h2(vec![styles([("text-align", "center")])], vec![
+                                text(format!("{:?} ", w.address)),
+                                link_address(&self.page.site.network, w.address, false),
+                            ]),
+                            render_ens_profile(w),
+                            self.render_wallet_info(w),
+                            self.render_governance(w),
+                            self.render_stake_history(w),
+                            h2(vec![styles([("text-align", "center")])], vec![text("User Rewards")]),
+                            self.render_vesting(w),
+                            self.render_rewards(w),
+                            self.render_delegation_info(w),
+                        ]
+                    )}
                     <h2 style="text-align:center">"User Events History"</h2>
-                    { match totals {
-                        Some(t) => node!(<h4 style="text-align:center"><small class="darken">{text(t)}</small></h4>),
-                        None => text(""),
-                    }}
-
+                    <h4 style="text-align:center"><small class="darken">{text(totals)}</small></h4>
                     {
-                        match self.state.wallets_events.get(&self.addr) {
-                            Some(w) => {
-                                {
-                                    if w.len() > 0 {
-                                        div(vec![], vec![
-                                            div(vec![class("desktop-only")], vec![
-                                                table(vec![class("table events-table")],
-                                                    vec![
-                                                        thead(vec![], vec![ self.render_event_header() ]),
-                                                        tbody(vec![], w.iter().enumerate().map(|(i, e)| self.render_event_tr(i, e)).collect::<Vec<Node<Msg>>>()),
-                                                    ]
-                                                )
-                                            ]),
-                                            div(vec![class("mobile-only")], vec![
-                                                ol(vec
-                                                    ![class("events-list")],
-                                                    w.iter().enumerate().map(|(_, e)| self.render_event(e)).collect::<Vec<Node<Msg>>>()
-                                                )
-                                            ])
-                                        ])
-                                    } else {
-                                        div(vec![class("events-empty")], vec![
-                                            text("There were no wallet events in the DAO")
-                                        ])
-                                    }}
-                               }
-                            None => err_box("member wallet was not found")
+                        if !events.is_empty() {
+                            div(vec![], vec![
+                                div(vec![class("desktop-only")], vec![
+                                    table(vec![class("table events-table")],
+                                        vec![
+                                            thead(vec![], vec![ self.render_event_header() ]),
+                                            tbody(vec![], events.iter().enumerate().map(|(i, e)| self.render_event_tr(i, e, timeline.get(i))).collect::<Vec<Node<Msg>>>()),
+                                        ]
+                                    )
+                                ]),
+                                div(vec![class("mobile-only")], vec![
+                                    ol(vec
+                                        ![class("events-list")],
+                                        events.iter().map(|e| self.render_event(e)).collect::<Vec<Node<Msg>>>()
+                                    )
+                                ])
+                            ])
+                        } else {
+                            div(vec![class("events-empty")], vec![
+                                text("There were no wallet events in the DAO")
+                            ])
                         }
                     }
                 </div>
-                { footer::render(&self.state) }
+                { footer::render(&self.page.site) }
             </div>
         }
     }
@@ -469,20 +712,14 @@ impl Component<Msg> for Screen {
 
 impl MetaProvider for Screen {
     fn meta(&self) -> PageMetaInfo {
-        let (title, description) = match self.state.wallets.get(&self.addr) {
-            Some(w) => {
-                let total_votes = self.state.get_votes_total();
-                let power = nice::pct_of(w.voting_power, total_votes, 18);
-                (
-                    format!("API3 DAO Member - {} has voting power of {}%", w.get_name(), power ),
-                    format!("Explore API3 DAO voting power, shares and full staking history of {}. No wallet connection is needed", w.get_name()),
-                )
-            },
-            None => (
-                "API3 DAO Member was not found".to_owned(), 
-                format!("Explore API3 DAO voting power, shares and full staking history of {} members. No wallet connection is needed", self.state.wallets.len()),
-            ),
-        };
+        let w = &self.page.wallet;
+        let power = nice::pct_of(w.voting_power, self.page.votes_total, 18);
+        let title = format!(
+            "API3 DAO Member - {} has voting power of {}%",
+            w.get_name(),
+            power
+        );
+        let description = format!("Explore API3 DAO voting power, shares and full staking history of {}. No wallet connection is needed", w.get_name());
         PageMetaInfo::new(&title, &description)
     }
 }
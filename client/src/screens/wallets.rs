@@ -1,33 +1,31 @@
+use crate::components::chart::{Chart, Kind};
 use crate::components::footer;
 use crate::components::header;
-use crate::fees::TxFeeTotal;
 use crate::nice;
 use crate::screens::meta::{MetaProvider, PageMetaInfo};
-use crate::state::{AppState, Wallet};
+use crate::views::{WalletRow, WalletsPage};
 use sauron::prelude::*;
 use serde::{Deserialize, Serialize};
 use web3::types::U256;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Screen {
-    /// server side state
-    pub state: AppState,
+    /// members of the DAO, as they were sent by the server
+    pub page: WalletsPage,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Msg {}
 
 impl Screen {
-    pub fn new(state: AppState) -> Self {
-        Self {
-            state: state.clone(),
-        }
+    pub fn new(page: WalletsPage) -> Self {
+        Self { page }
     }
 
     pub fn total_with_power(&self, total_votes: U256) -> u32 {
-        self.state
+        self.page
             .wallets
-            .values()
+            .iter()
             .map(|w| {
                 if nice::pct_val(w.voting_power, total_votes, 16) >= 0.001 {
                     1
@@ -38,23 +36,49 @@ impl Screen {
             .sum()
     }
 
+    /// share of the voting power of the largest members and the rest of the DAO
+    pub fn render_distribution(&self, sorted: &[WalletRow]) -> Node<Msg> {
+        let top = 9;
+        let mut labels: Vec<String> = vec![];
+        let mut values: Vec<f64> = vec![];
+        for w in sorted.iter().take(top) {
+            labels.push(match &w.ens {
+                Some(ens) => ens.clone(),
+                None => format!("{:?}", w.address).chars().take(10).collect(),
+            });
+            values.push(nice::dec(w.voting_power, 18));
+        }
+        if sorted.len() > top {
+            let others = sorted
+                .iter()
+                .skip(top)
+                .fold(U256::from(0), |a, w| a + w.voting_power);
+            labels.push("Others".to_owned());
+            values.push(nice::dec(others, 18));
+        }
+        let chart = Chart::new(Kind::Donut, labels, " API3").series("Voting Power", values);
+        node! {
+            <div class="charts">
+                <h2>"Voting Power Distribution"</h2>
+                {chart.render("")}
+            </div>
+        }
+    }
+
     pub fn render_info(&self) -> Node<Msg> {
-        let total_shares = match &self.state.pool_info {
-            Some(x) => x.total_shares,
-            None => self.state.get_shares_total(),
-        };
-        let total_minted = self.state.get_minted_total();
-        let total_vesting_members = self.state.get_vested_num();
-        let total_vesting_shares = self.state.get_vested_shares();
-        let total_delegating_members = self.state.get_delegating_num();
-        let total_delegating_shares = self.state.get_delegating_shares();
-        let total_left = self.state.get_withdrawn_num();
+        let total_shares = self.page.total_shares;
+        let total_minted = self.page.minted;
+        let total_vesting_members = self.page.vested_members;
+        let total_vesting_shares = self.page.vested_shares;
+        let total_delegating_members = self.page.delegating_members;
+        let total_delegating_shares = self.page.delegating_shares;
+        let total_left = self.page.left;
         node! {
             <div>
                 <p style="text-align: center">
                     <span class="darken">"API3 DAO currently has "</span>
                     <strong>
-                        { text(nice::int(self.state.wallets.len())) }
+                        { text(nice::int(self.page.wallets.len())) }
                     </strong>
                     <span class="darken">" participants, staking "</span>
                     <strong title={nice::amount(total_shares, 18)}>
@@ -150,7 +174,7 @@ impl Screen {
         }
     }
 
-    pub fn render_wallet_tr(&self, index: usize, w: &Wallet, total_votes: U256) -> Node<Msg> {
+    pub fn render_wallet_tr(&self, index: usize, w: &WalletRow, total_votes: U256) -> Node<Msg> {
         let pct = nice::pct3_of(w.voting_power, total_votes, 18);
         let pct6 = nice::pct6_of(w.voting_power, total_votes, 18);
         let voting_class = if nice::pct_val(w.voting_power, total_votes, 16) >= 0.001 {
@@ -158,13 +182,8 @@ impl Screen {
         } else {
             "r darken"
         };
-        // let rewards = self.state.get_rewards(&w.address, 10000000000u64);
-        let labels = self.state.get_labels(w);
-        let totals = self
-            .state
-            .wallets_events
-            .get(&w.address)
-            .map(|e| TxFeeTotal::new(e).to_string());
+        let labels = &w.labels;
+        let totals = w.fees.as_ref().map(|f| f.to_string());
         node! {
             <tr>
                 <td class="c">{text(format!("{}.", index + 1))}</td>
@@ -213,7 +232,7 @@ impl Screen {
         }
     }
 
-    pub fn render_wallet(&self, w: &Wallet, total_votes: U256) -> Node<Msg> {
+    pub fn render_wallet(&self, w: &WalletRow, total_votes: U256) -> Node<Msg> {
         node! {
             <li>
                 <div class="wallet">
@@ -270,28 +289,18 @@ impl Screen {
 
 impl Component<Msg> for Screen {
     fn view(&self) -> Node<Msg> {
-        let mut sorted: Vec<Wallet> = self.state.wallets.values().cloned().collect();
-        // sorted.sort_by_key(|w| std::cmp::Reverse(w.voting_power));
-        sorted.sort_by(|a, b| {
-            a.voting_power
-                .cmp(&b.voting_power)
-                .reverse()
-                .then((a.rewards).cmp(&b.rewards).reverse())
-        });
-
-        // let total_votes = self.state.get_votes_total();
-        let total_shares = match &self.state.pool_info {
-            Some(x) => x.total_shares,
-            None => self.state.get_shares_total(),
-        };
+        let site = &self.page.site;
+        let sorted = &self.page.wallets;
+        let total_shares = self.page.total_shares;
         node! {
             <div class="screen-wallets">
-                { header::render("/wallets", &self.state) }
+                { header::render("/wallets", site) }
                 <div class="inner">
-                    <h1>{text(format!("API3 DAO: {} Member Wallets", self.state.wallets.len()))}</h1>
+                    <h1>{text(format!("API3 DAO: {} Member Wallets", self.page.wallets.len()))}</h1>
                     {self.render_info()}
-                    {if self.state.wallets.len() > 0 {
+                    {if !self.page.wallets.is_empty() {
                         div(vec![], vec![
+                            self.render_distribution(sorted),
                             div(vec![class("desktop-only")], vec![
                                 table(vec
                                     ![class("table wallets-table")],
@@ -314,7 +323,7 @@ impl Component<Msg> for Screen {
                         ])
                     }}
                 </div>
-                { footer::render(&self.state) }
+                { footer::render(site) }
             </div>
         }
     }
@@ -328,9 +337,9 @@ impl MetaProvider for Screen {
     fn meta(&self) -> PageMetaInfo {
         let title = format!(
             "API3 DAO Explore all {} members wallets",
-            self.state.wallets.len()
+            self.page.wallets.len()
         );
-        let description = format!("Explore API3 DAO: voting power, shares and full staking rewards history of {} members. No wallet connection is needed", self.state.wallets.len());
+        let description = format!("Explore API3 DAO: voting power, shares and full staking rewards history of {} members. No wallet connection is needed", self.page.wallets.len());
         PageMetaInfo::new(&title, &description)
     }
 }
@@ -0,0 +1,336 @@
+use crate::state::{AppState, Voting, Wallet};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use web3::types::{H160, H256};
+
+/// max number of results of the search
+pub const DEFAULT_LIMIT: usize = 20;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HitKind {
+    Wallet,
+    Voting,
+    Transaction,
+}
+
+/// Found wallet, voting or transaction with the link to its page
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Hit {
+    pub kind: HitKind,
+    /// address, voting ID or transaction hash
+    pub key: String,
+    pub label: String,
+    /// relative link to the page
+    pub url: String,
+    /// the higher, the better the match: exact, prefix or fuzzy
+    pub score: u32,
+    /// whether the query is the key or the name of the hit
+    pub exact: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Doc {
+    kind: HitKind,
+    label: String,
+    url: String,
+    terms: Vec<String>,
+}
+
+/// Index of wallets with their ENS names, votings and transactions of the wallets,
+/// which is updated as events are applied to the state
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    /// documents by their keys
+    docs: BTreeMap<String, Doc>,
+    /// keys of the documents by their terms
+    terms: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// lowercased words of the text, dotted names are also kept as a whole
+pub fn tokenize(src: &str) -> Vec<String> {
+    let lower = src.to_lowercase();
+    let mut res: Vec<String> = vec![];
+    for word in lower.split_whitespace() {
+        let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+        if word.contains('.') || word.contains('-') {
+            res.push(word.to_owned());
+        }
+        for part in word.split(|c: char| !c.is_alphanumeric()) {
+            if !part.is_empty() {
+                res.push(part.to_owned());
+            }
+        }
+    }
+    res.sort();
+    res.dedup();
+    res
+}
+
+/// lowercased words of the query, dotted names and voting IDs are not split
+pub fn words(query: &str) -> Vec<String> {
+    query
+        .to_lowercase()
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_owned())
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+/// number of edits to turn one word into another
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+/// number of typos that are allowed in the word
+fn typos(word: &str) -> usize {
+    match word.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+impl SearchIndex {
+    pub fn build(app: &AppState) -> Self {
+        let mut res = Self::default();
+        for w in app.wallets.values() {
+            res.wallet(w);
+        }
+        for v in app.votings.values() {
+            res.voting(v);
+        }
+        for (addr, events) in &app.wallets_events {
+            for e in events {
+                res.transaction(e.tx, *addr);
+            }
+        }
+        res
+    }
+
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    /// adds or replaces the document
+    fn upsert(&mut self, key: String, doc: Doc) {
+        if let Some(prev) = self.docs.remove(&key) {
+            for t in &prev.terms {
+                if let Some(keys) = self.terms.get_mut(t) {
+                    keys.remove(&key);
+                    if keys.is_empty() {
+                        self.terms.remove(t);
+                    }
+                }
+            }
+        }
+        for t in &doc.terms {
+            self.terms.entry(t.clone()).or_default().insert(key.clone());
+        }
+        self.docs.insert(key, doc);
+    }
+
+    pub fn wallet(&mut self, w: &Wallet) {
+        let key = format!("{:?}", w.address);
+        let mut terms = vec![key.clone()];
+        if let Some(ens) = &w.ens {
+            terms.extend(tokenize(ens));
+        }
+        let doc = Doc {
+            kind: HitKind::Wallet,
+            label: w.ens.clone().unwrap_or_else(|| key.clone()),
+            url: format!("wallets/{}", key),
+            terms,
+        };
+        self.upsert(key, doc);
+    }
+
+    pub fn voting(&mut self, v: &Voting) {
+        let key = v.key();
+        let mut terms = vec![key.clone()];
+        terms.extend(tokenize(&v.title));
+        terms.extend(tokenize(&v.description));
+        terms.extend(tokenize(&v.metadata));
+        terms.sort();
+        terms.dedup();
+        let doc = Doc {
+            kind: HitKind::Voting,
+            label: format!("#{} {}", v.vote_id, v.title),
+            url: format!("votings/{}", key),
+            terms,
+        };
+        self.upsert(key, doc);
+    }
+
+    /// transaction is linked to the first wallet it was found for
+    pub fn transaction(&mut self, tx: H256, wallet: H160) {
+        let key = format!("{:?}", tx);
+        if self.docs.contains_key(&key) {
+            return;
+        }
+        let doc = Doc {
+            kind: HitKind::Transaction,
+            label: key.clone(),
+            url: format!("wallets/{:?}", wallet),
+            terms: vec![key.clone()],
+        };
+        self.upsert(key, doc);
+    }
+
+    /// scores of the documents that match the word of the query
+    fn matches(&self, word: &str) -> BTreeMap<&String, u32> {
+        let mut res: BTreeMap<&String, u32> = BTreeMap::new();
+        let mut found: Vec<(&BTreeSet<String>, u32)> = vec![];
+        for (term, keys) in self.terms.range(word.to_owned()..) {
+            if !term.starts_with(word) {
+                break;
+            }
+            found.push((keys, if term == word { 3 } else { 2 }));
+        }
+        // addresses and hashes are matched by prefix only
+        let allowed = typos(word);
+        if allowed > 0 && !word.starts_with("0x") {
+            let len = word.chars().count();
+            for (term, keys) in &self.terms {
+                if term.starts_with("0x") || term.starts_with(word) {
+                    continue;
+                }
+                let term_len = term.chars().count();
+                let close = term_len + allowed >= len && len + allowed >= term_len;
+                if close && distance(word, term) <= allowed {
+                    found.push((keys, 1));
+                }
+            }
+        }
+        for (keys, score) in found {
+            for key in keys {
+                let entry = res.entry(key).or_insert(0);
+                *entry = (*entry).max(score);
+            }
+        }
+        res
+    }
+
+    /// documents that match all words of the query, the best ones first
+    pub fn search(&self, query: &str, limit: usize) -> Vec<Hit> {
+        let query = query.trim().to_lowercase();
+        let words = words(&query);
+        if words.is_empty() {
+            return vec![];
+        }
+        let mut scores: Option<BTreeMap<&String, u32>> = None;
+        for word in &words {
+            let found = self.matches(word);
+            scores = Some(match scores {
+                None => found,
+                Some(prev) => prev
+                    .into_iter()
+                    .filter_map(|(k, s)| found.get(k).map(|x| (k, s + x)))
+                    .collect(),
+            });
+        }
+        let mut res: Vec<Hit> = scores
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(key, score)| {
+                let doc = self.docs.get(key)?;
+                Some(Hit {
+                    kind: doc.kind,
+                    key: key.clone(),
+                    label: doc.label.clone(),
+                    url: doc.url.clone(),
+                    score,
+                    exact: *key == query || doc.label.to_lowercase() == query,
+                })
+            })
+            .collect();
+        res.sort_by(|a, b| {
+            b.exact
+                .cmp(&a.exact)
+                .then(b.score.cmp(&a.score))
+                .then(a.kind.cmp(&b.kind))
+                .then(a.label.cmp(&b.label))
+        });
+        res.truncate(limit);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wallet(ens: &str) -> Wallet {
+        Wallet {
+            address: H160::from_low_u64_be(0xabcd),
+            ens: Some(ens.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    fn index() -> SearchIndex {
+        let mut index = SearchIndex::default();
+        let w = wallet("enormous.eth");
+        index.wallet(&w);
+        index.voting(&Voting {
+            primary: true,
+            vote_id: 12,
+            title: "Grant for ChainAPI integration".to_owned(),
+            ..Default::default()
+        });
+        index.transaction(H256::from_low_u64_be(0x1234), w.address);
+        index
+    }
+
+    #[test]
+    pub fn it_finds_by_prefix() {
+        let index = index();
+        let hits = index.search("enorm", 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].kind, HitKind::Wallet);
+        assert_eq!(
+            hits[0].url,
+            "wallets/0x000000000000000000000000000000000000abcd"
+        );
+        // both the wallet and the transaction start with zeros
+        assert_eq!(index.search("0x0000", 10).len(), 2);
+        let tx = format!("{:?}", H256::from_low_u64_be(0x1234));
+        let hits = index.search(&tx, 10);
+        assert_eq!(hits[0].kind, HitKind::Transaction);
+        assert!(hits[0].exact);
+        assert!(index.search("p-12", 10)[0].exact);
+        assert!(index.search("Enormous.eth", 10)[0].exact);
+    }
+
+    #[test]
+    pub fn it_finds_with_typos() {
+        let index = index();
+        let hits = index.search("chainapi grnt", 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].url, "votings/p-12");
+        assert_eq!(index.search("integraton", 10).len(), 1);
+        assert!(index.search("xyz", 10).is_empty());
+    }
+
+    #[test]
+    pub fn it_updates_documents() {
+        let mut index = index();
+        index.wallet(&wallet("renamed.eth"));
+        assert!(index.search("enormous", 10).is_empty());
+        assert_eq!(index.search("renamed", 10).len(), 1);
+        assert_eq!(index.len(), 3);
+    }
+}
@@ -2,13 +2,16 @@ use crate::action::{ActionSignature, VotingAction};
 use crate::events::{Api3, VotingAgent};
 use crate::fees::TxFee;
 use crate::nice;
+use crate::search::SearchIndex;
 use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use thiserror::Error;
 use web3::types::{H160, H256, U256};
 
 // General API3 Pool information
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Api3PoolInfo {
     /// APR at genesis (min+max) / 2
     pub genesis_apr: f64,
@@ -23,10 +26,13 @@ pub struct Api3PoolInfo {
     /// number of epochs before rewards are unlocked
     pub reward_vesting_period: u64,
     /// total stake - from API3 pool contract
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub total_stake: U256,
     /// total shares - from API3 pool contract
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub total_shares: U256,
     /// staking target
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub stake_target: U256,
     /// number of seconds before unstaking is allowed after claim
     pub unstake_wait_period: u64,
@@ -34,58 +40,80 @@ pub struct Api3PoolInfo {
 
 // General API3 Circulation information
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Api3Circulation {
     /// tokens circulating supply
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub circulating_supply: U256,
     /// total api3 token supply
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub total_supply: U256,
     /// tokens, locked by governance
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub locked_by_governance: U256,
     /// tokens, locked in rewards
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub locked_rewards: U256,
     /// tokens, locked in vestings
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub locked_vestings: U256,
     /// time locked tokens
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub time_locked: U256,
     /// total locked tokens
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub total_locked: U256,
     /// address of API3 pool contract
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
     pub addr_pool: H160,
     /// address of API3 token
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
     pub addr_token: H160,
     /// address of Time lock manager
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
     pub addr_time_lock: H160,
     /// address of API3 primary treasury
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
     pub addr_primary_treasury: H160,
     /// address of API3 secondary treasury
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
     pub addr_secondary_treasury: H160,
     /// address of V1 treasury
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
     pub addr_v1_treasury: H160,
     /// address of API3 primary voting contract
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
     pub addr_primary_contract: H160,
     /// address of API3 secondary voting contract
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
     pub addr_secondary_contract: H160,
     /// address of API3 convenience contract
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
     pub addr_convenience: H160,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct OnChainEvent {
     pub entry: Api3,
     pub tm: u64,
     pub block_number: u64,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Hash"))]
     pub tx: H256,
     pub log_index: u64,
     pub fees: TxFee,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct VotingStaticData {
     pub start_date: u64,
     pub support_required: f64, // typically 0.5
     pub min_quorum: f64,       //typically 0.15 for secondary
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub voting_power: U256,
     pub script: Vec<u8>,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub user_voting_power_at: U256,
     pub discussion_url: String,
 }
@@ -105,34 +133,55 @@ impl VotingStaticData {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct VotingDetails {
     pub start_date: u64,
     pub support_required: f64, // typically 0.5
     pub min_quorum: f64,       //typically 0.15 for secondary
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub voting_power: U256,
     pub action: Option<VotingAction>,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub user_voting_power_at: U256,
     pub discussion_url: String,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Voting {
     pub primary: bool,
     pub vote_id: u64,
     pub tm: u64,
     pub block_number: u64,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Hash"))]
     pub tx: H256,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
     pub creator: H160,
     pub metadata: String,
     pub title: String,
     pub description: String,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub voted_yes: U256,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub voted_no: U256,
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "BTreeMap<crate::schema::Address, crate::schema::Uint>")
+    )]
     pub yes: BTreeMap<H160, U256>,
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "BTreeMap<crate::schema::Address, crate::schema::Uint>")
+    )]
     pub no: BTreeMap<H160, U256>,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub votes_total: U256,
     pub executed: bool,
     pub details: Option<VotingDetails>,
+    /// voting power of every member at the snapshot block of the voting.
+    /// It is not serialized with the state, the voting page carries it
+    #[serde(skip)]
+    pub snapshot: BTreeMap<H160, U256>,
 }
 
 impl Voting {
@@ -154,11 +203,33 @@ impl Voting {
     }
 
     pub fn is_expired(&self) -> bool {
-        let now = Utc::now().naive_utc();
+        self.is_expired_at(Utc::now().timestamp() as u64)
+    }
+
+    /// whether the voting period was over at the given time
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        let now = NaiveDateTime::from_timestamp(now as i64, 0);
         let tmv = NaiveDateTime::from_timestamp(self.tm as i64, 0);
         (now - tmv) > chrono::Duration::weeks(1)
     }
 
+    pub fn has_voted(&self, addr: &H160) -> bool {
+        self.yes.contains_key(addr) || self.no.contains_key(addr)
+    }
+
+    // members that had voting power at the snapshot block but did not vote,
+    // sorted by voting power, the largest first
+    pub fn non_voters(&self) -> Vec<(H160, U256)> {
+        let mut out: Vec<(H160, U256)> = self
+            .snapshot
+            .iter()
+            .filter(|(addr, _)| !self.has_voted(addr))
+            .map(|(addr, power)| (*addr, *power))
+            .collect();
+        out.sort_by(|a, b| b.1.cmp(&a.1));
+        out
+    }
+
     pub fn is_invalid(&self) -> bool {
         if let Some(details) = &self.details {
             if let Some(action) = &details.action {
@@ -172,48 +243,157 @@ impl Voting {
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Delegation {
     // adderss to which share are being delegated
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
     pub address: H160,
     // number of delegated shares
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub shares: U256,
     // timestamp of the last delegation
     pub tm: u64,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ScheduledUnstake {
     // amount that is being unstaked
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub amount: U256,
     // number of shares that are unstaking
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub shares: U256,
     // timestamp of the last delegation
     pub tm: u64,
 }
 
+/// gateway for avatars that are stored in IPFS
+const IPFS_GATEWAY: &str = "https://ipfs.io/ipfs/";
+/// service that resolves NFT avatars of ENS names to images
+const ENS_AVATARS: &str = "https://metadata.ens.domains/mainnet/avatar/";
+
+/// Text records of the ENS name of the wallet
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EnsRecords {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// value of the com.twitter record
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub twitter: Option<String>,
+}
+
+impl EnsRecords {
+    pub fn is_empty(&self) -> bool {
+        self.avatar.is_none() && self.url.is_none() && self.twitter.is_none()
+    }
+
+    /// image of the avatar, NFT avatars are resolved by the ENS metadata service
+    pub fn avatar_url(&self, name: &str) -> Option<String> {
+        let avatar = self.avatar.as_deref()?.trim();
+        if avatar.starts_with("https://") || avatar.starts_with("http://") {
+            return Some(avatar.to_owned());
+        }
+        if let Some(cid) = avatar.strip_prefix("ipfs://") {
+            return Some(format!(
+                "{}{}",
+                IPFS_GATEWAY,
+                cid.trim_start_matches("ipfs/")
+            ));
+        }
+        match avatar.is_empty() {
+            true => None,
+            false => Some(format!("{}{}", ENS_AVATARS, name)),
+        }
+    }
+
+    /// website of the member, only http links are followed
+    pub fn website(&self) -> Option<String> {
+        let url = self.url.as_deref()?.trim();
+        if url.starts_with("https://") || url.starts_with("http://") {
+            return Some(url.to_owned());
+        }
+        match !url.is_empty() && !url.contains(':') && url.contains('.') {
+            true => Some(format!("https://{}", url)),
+            false => None,
+        }
+    }
+
+    /// twitter handle without @, if it is valid
+    pub fn twitter_handle(&self) -> Option<String> {
+        let handle = self.twitter.as_deref()?.trim().trim_end_matches('/');
+        let handle = handle.rsplit('/').next().unwrap_or_default();
+        let handle = handle.trim_start_matches('@');
+        let valid = !handle.is_empty()
+            && handle.len() <= 15
+            && handle
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        match valid {
+            true => Some(handle.to_owned()),
+            false => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Wallet {
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
     pub address: H160,
+    /// ENS name, which resolves back to the address
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ens: Option<String>,
+    /// text records of the ENS name
+    #[serde(default, skip_serializing_if = "EnsRecords::is_empty")]
+    pub ens_records: EnsRecords,
     pub vested: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<crate::schema::Uint>"))]
     pub vested_amount: Option<U256>,
     pub supporter: bool,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub deposited: U256,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub withdrawn: U256,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub staked: U256,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub scheduled_unstake: Option<ScheduledUnstake>,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub shares: U256,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub delegates: Option<Delegation>,
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "BTreeMap<crate::schema::Address, crate::schema::Uint>")
+    )]
     pub delegated: BTreeMap<H160, U256>,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub voting_power: U256,
     pub votes: u64,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub rewards: U256,
     pub created_at: u64,
     pub updated_at: u64,
+    /// tokens that are still vesting in the pool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<crate::schema::Uint>"))]
+    pub vesting_remaining: Option<U256>,
+    /// tokens that were vested from the timelock
+    #[serde(default)]
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
+    pub vesting_unlocked: U256,
+    /// time of the last voting this wallet created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_proposal_tm: Option<u64>,
+    /// stake that was lost to claim payouts
+    #[serde(default)]
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
+    pub claim_loss: U256,
 }
 
 impl Wallet {
@@ -240,25 +420,76 @@ impl Wallet {
             sum
         };
     }
+
+    /// time when the wallet is allowed to create the next voting,
+    /// as only one proposal per epoch is allowed
+    pub fn next_proposal_tm(&self, epoch_length: u64) -> Option<u64> {
+        self.last_proposal_tm.map(|tm| tm + epoch_length)
+    }
+}
+
+// Reward of the wallet for the epoch, recorded at MintedReward
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RewardEntry {
+    /// index of an epoch
+    pub epoch: u64,
+    /// Timestamp of the epoch
+    pub tm: u64,
+    /// Block number of the epoch
+    pub block_number: u64,
+    /// reward amount
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
+    pub amount: U256,
+    /// stake of the wallet during the epoch (including locked rewards)
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
+    pub stake: U256,
+    /// shares of the wallet during the epoch
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
+    pub shares: U256,
+    /// total rewards of the wallet after this epoch
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
+    pub total: U256,
+    /// timestamp when the reward is unlocked, unknown until the pool parameters are read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unlock_tm: Option<u64>,
+}
+
+impl RewardEntry {
+    /// rewards with unknown unlock time are considered locked
+    pub fn is_locked(&self, now: u64) -> bool {
+        match self.unlock_tm {
+            Some(tm) => tm > now,
+            None => true,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Epoch {
     /// index of an epoch
     pub index: u64,
     /// APR during this epoch
     pub apr: f64,
     /// minted amount in the last MintedReward event
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub minted: U256,
     /// Total stake during the last MintedReward event
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
     pub total: U256,
     /// Staking amount for each wallet (including locked rewards)
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "BTreeMap<crate::schema::Address, crate::schema::Uint>")
+    )]
     pub stake: BTreeMap<H160, U256>,
     /// Timestamp of the epoch
     pub tm: u64,
     /// Block number of the epoch
     pub block_number: u64,
     /// Transaction of minting rewards
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Hash"))]
     pub tx: H256,
 }
 
@@ -286,7 +517,95 @@ impl Epoch {
     }
 }
 
-#[derive(Debug, Clone)]
+// Change of the staking target, recorded at SetStakeTarget
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StakeTargetChange {
+    pub tm: u64,
+    pub block_number: u64,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Hash"))]
+    pub tx: H256,
+    /// new staking target, percentage of total supply with 18 decimals
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
+    pub stake_target: U256,
+}
+
+// Addresses of DAO apps, set in the pool at SetDaoApps
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DaoAppsChange {
+    pub tm: u64,
+    pub block_number: u64,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Hash"))]
+    pub tx: H256,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
+    pub agent_app_primary: H160,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
+    pub agent_app_secondary: H160,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
+    pub voting_app_primary: H160,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
+    pub voting_app_secondary: H160,
+}
+
+// Transfer of the contract ownership, recorded at OwnershipTransferred
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OwnershipChange {
+    pub tm: u64,
+    pub block_number: u64,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Hash"))]
+    pub tx: H256,
+    /// contract which ownership was transferred
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
+    pub contract: H160,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
+    pub from: H160,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
+    pub to: H160,
+}
+
+// Claim that was paid out from the pool, recorded at PaidOutClaim
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ClaimPayout {
+    pub tm: u64,
+    pub block_number: u64,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Hash"))]
+    pub tx: H256,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
+    pub recipient: H160,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
+    pub amount: U256,
+    /// total stake of the pool after the payout
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
+    pub total_stake: U256,
+}
+
+// Change of the timelocked tokens of the wallet,
+// recorded at VestedTimelock and WithdrawnToPool
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TimelockEntry {
+    pub tm: u64,
+    pub block_number: u64,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Hash"))]
+    pub tx: H256,
+    /// tokens that were vested
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Uint"))]
+    pub vested: U256,
+    /// tokens that are still vesting after this change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<crate::schema::Uint>"))]
+    pub remaining: Option<U256>,
+    /// wallet in the pool that received timelocked tokens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<crate::schema::Address>"))]
+    pub beneficiary: Option<H160>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct LabelBadge {
     pub class: String,
     pub text: String,
@@ -304,9 +623,15 @@ impl LabelBadge {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Treasury {
     pub name: String,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
     pub wallet: H160,
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "BTreeMap<String, crate::schema::Uint>")
+    )]
     pub balances: BTreeMap<String, U256>,
     pub updated_at: i64,
 }
@@ -328,16 +653,94 @@ impl Treasury {
     }
 }
 
+/// DAO deployment that is served, as it is configured on the server
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NetworkInfo {
+    /// name of the network, it is also the prefix of URLs
+    pub name: String,
+    /// block explorer, like https://etherscan.io
+    pub explorer_url: Option<String>,
+    /// whether this is a test network
+    pub testnet: bool,
+    /// names of all networks that are served by the same server
+    pub networks: Vec<String>,
+    /// public path of the server, networks are served under it
+    pub public_url: String,
+}
+
+impl NetworkInfo {
+    fn explorer(&self, path: String) -> Option<String> {
+        self.explorer_url
+            .as_ref()
+            .map(|url| format!("{}/{}", url.trim_end_matches('/'), path))
+    }
+
+    /// link to the transaction logs in the block explorer
+    pub fn tx_url(&self, tx: H256) -> Option<String> {
+        self.explorer(format!("tx/{:?}#eventlog", tx))
+    }
+
+    /// link to the home page of another network
+    pub fn network_url(&self, name: &str) -> String {
+        format!("{}/{}/", self.public_url.trim_end_matches('/'), name)
+    }
+
+    /// base of relative links of the pages, when every network is served under its prefix.
+    /// The only network keeps the base of the build
+    pub fn base_url(&self) -> Option<String> {
+        match self.networks.len() > 1 {
+            true => Some(self.network_url(&self.name)),
+            false => None,
+        }
+    }
+
+    /// link to the address in the block explorer
+    pub fn address_url(&self, address: H160) -> Option<String> {
+        self.explorer(format!("address/{:?}", address))
+    }
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Times {
+    /// time of the last processed event.
+    /// Background re-reads are reported by server jobs
     pub update: Option<DateTime<Utc>>,
-    pub votings: Option<DateTime<Utc>>,
-    pub ens: Option<DateTime<Utc>>,
-    pub treasuries: Option<DateTime<Utc>>,
-    pub circulation: Option<DateTime<Utc>>,
 }
 
+/// Failure to apply the event to the state
+#[derive(Error, Debug, Clone)]
+#[error("{0}")]
+pub struct TransitionError(pub String);
+
+impl From<anyhow::Error> for TransitionError {
+    fn from(err: anyhow::Error) -> Self {
+        Self(format!("{}", err))
+    }
+}
+
+/// Event log that could not be processed and was skipped during syncing
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct QuarantinedEvent {
+    /// kind of failure: decode, rpc, cache or transition
+    pub kind: String,
+    pub error: String,
+    /// address of the contract that emitted the log
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Address"))]
+    pub address: H160,
+    pub block_number: Option<u64>,
+    #[cfg_attr(feature = "schema", schemars(with = "Option<crate::schema::Hash>"))]
+    pub tx: Option<H256>,
+    pub log_index: Option<u64>,
+    /// decoded event, if the failure happened after decoding
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry: Option<Api3>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AppState {
     /// version of the state
     pub version: String,
@@ -360,22 +763,85 @@ pub struct AppState {
     /// log of events, grouped by votings
     pub votings_events: BTreeMap<u64, Vec<OnChainEvent>>,
     /// map of wallets
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "BTreeMap<crate::schema::Address, Wallet>")
+    )]
     pub wallets: BTreeMap<H160, Wallet>,
     /// log of events, groupped by wallets
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "BTreeMap<crate::schema::Address, Vec<OnChainEvent>>")
+    )]
     pub wallets_events: BTreeMap<H160, Vec<OnChainEvent>>,
+    /// rewards of every epoch, groupped by wallets
+    #[serde(default)]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "BTreeMap<crate::schema::Address, Vec<RewardEntry>>")
+    )]
+    pub rewards_ledger: BTreeMap<H160, Vec<RewardEntry>>,
     /// list of wallets that are vesting and their balance is excluded from circulating supply
+    #[cfg_attr(feature = "schema", schemars(with = "Vec<crate::schema::Address>"))]
     pub vested: Vec<H160>,
     /// list of treasuries with their balances
     pub treasuries: BTreeMap<String, Treasury>,
     /// decimals for tokens
     pub decimals: BTreeMap<String, usize>,
     /// list of wallets that were in voting actions
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "BTreeMap<crate::schema::Address, u64>")
+    )]
     pub grants: BTreeMap<H160, u64>,
     /// fees of the transactions
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "BTreeMap<crate::schema::Hash, TxFee>")
+    )]
     pub fees: BTreeMap<H256, TxFee>,
     /// seconds since
     #[serde(skip_serializing_if = "Option::is_none")]
     pub the_last: Option<Times>,
+    /// events that were skipped during syncing
+    #[serde(default)]
+    pub quarantine: Vec<QuarantinedEvent>,
+    /// history of the staking target
+    #[serde(default)]
+    pub stake_targets: Vec<StakeTargetChange>,
+    /// history of DAO apps addresses in the pool
+    #[serde(default)]
+    pub dao_apps: Vec<DaoAppsChange>,
+    /// ERC20 tokens, listed in convenience contract
+    #[serde(default)]
+    #[cfg_attr(feature = "schema", schemars(with = "Vec<crate::schema::Address>"))]
+    pub erc20_addresses: Vec<H160>,
+    /// history of contracts ownership
+    #[serde(default)]
+    pub ownership: Vec<OwnershipChange>,
+    /// claims, paid out from the pool
+    #[serde(default)]
+    pub claims: Vec<ClaimPayout>,
+    /// changes of timelocked tokens, groupped by wallets
+    #[serde(default)]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "BTreeMap<crate::schema::Address, Vec<TimelockEntry>>")
+    )]
+    pub timelocks: BTreeMap<H160, Vec<TimelockEntry>>,
+    /// events that were decoded from ABI, but are not tracked
+    #[serde(default)]
+    pub unhandled: Vec<OnChainEvent>,
+    /// network of the deployment
+    #[serde(default)]
+    pub network: NetworkInfo,
+    /// search index of wallets, votings and transactions, that is not serialized
+    #[serde(skip)]
+    pub search: SearchIndex,
+    /// block of the last event and voting power of the wallets
+    /// before that block changed it, as votings take their snapshot at the previous block
+    #[serde(skip)]
+    pub block_power: (u64, BTreeMap<H160, U256>),
 }
 
 pub fn get_known_decimals() -> BTreeMap<String, usize> {
@@ -399,6 +865,7 @@ impl AppState {
             wallets: BTreeMap::new(),
             votings_events: BTreeMap::new(),
             wallets_events: BTreeMap::new(),
+            rewards_ledger: BTreeMap::new(),
             vested: vec![],
             pool_info: None,
             circulation: None,
@@ -407,6 +874,26 @@ impl AppState {
             grants: BTreeMap::new(),
             fees: BTreeMap::new(),
             the_last: Some(Times::default()),
+            quarantine: vec![],
+            stake_targets: vec![],
+            dao_apps: vec![],
+            erc20_addresses: vec![],
+            ownership: vec![],
+            claims: vec![],
+            timelocks: BTreeMap::new(),
+            unhandled: vec![],
+            network: NetworkInfo::default(),
+            search: SearchIndex::default(),
+            block_power: (0, BTreeMap::new()),
+        }
+    }
+
+    /// sets ENS name of the wallet, so it could be found by the name
+    pub fn set_ens(&mut self, addr: &H160, name: Option<String>, records: EnsRecords) {
+        if let Some(w) = self.wallets.get_mut(addr) {
+            w.ens = name;
+            w.ens_records = records;
+            self.search.wallet(w);
         }
     }
 
@@ -490,6 +977,53 @@ impl AppState {
             .fold(U256::from(0), |a, b| a + b)
     }
 
+    // voting power of every member that has any
+    pub fn get_voting_power_snapshot(&self) -> BTreeMap<H160, U256> {
+        self.wallets
+            .values()
+            .filter(|w| w.voting_power > U256::from(0))
+            .map(|w| (w.address, w.voting_power))
+            .collect()
+    }
+
+    // voting power of every member that had any before the block of the last event,
+    // like userVotingPowerAt(block - 1) of the voting contract
+    pub fn get_voting_power_before_block(&self) -> BTreeMap<H160, U256> {
+        let mut res = self.get_voting_power_snapshot();
+        for (addr, power) in &self.block_power.1 {
+            if power.is_zero() {
+                res.remove(addr);
+            } else {
+                res.insert(*addr, *power);
+            }
+        }
+        res
+    }
+
+    /// remembers voting power of the wallets, that the event could change,
+    /// unless it was already changed in the same block
+    fn remember_block_power(&mut self, e: &OnChainEvent) {
+        if self.block_power.0 != e.block_number {
+            self.block_power = (e.block_number, BTreeMap::new());
+        }
+        for addr in e.entry.get_wallets() {
+            let w = match self.wallets.get(&addr) {
+                Some(w) => w,
+                None => continue,
+            };
+            // the delegate gets voting power of staking or unstaking wallet
+            let delegate = w.delegates.as_ref().map(|d| d.address);
+            for a in std::iter::once(addr).chain(delegate) {
+                let power = self
+                    .wallets
+                    .get(&a)
+                    .map(|w| w.voting_power)
+                    .unwrap_or_default();
+                self.block_power.1.entry(a).or_insert(power);
+            }
+        }
+    }
+
     pub fn get_shares_total(&self) -> U256 {
         self.wallets
             .values()
@@ -523,35 +1057,40 @@ impl AppState {
     }
 
     pub fn get_rewards_for_epoch(&self, addr: &H160, epoch_index: u64) -> U256 {
-        if epoch_index > 1u64 {
-            self.get_rewards(addr, epoch_index) - self.get_rewards(addr, epoch_index - 1)
-        } else {
-            U256::from(0)
+        match self.rewards_ledger.get(addr) {
+            Some(entries) => entries
+                .iter()
+                .find(|r| r.epoch == epoch_index)
+                .map(|r| r.amount)
+                .unwrap_or(U256::from(0)),
+            None => U256::from(0),
         }
     }
 
     pub fn get_rewards(&self, addr: &H160, epoch_index: u64) -> U256 {
-        self.epochs
-            .iter()
-            .map(|(_, epoch)| {
-                let staked = match epoch.stake.get(addr) {
-                    Some(val) => *val,
-                    None => return U256::from(0),
-                };
-                if staked == U256::from(0) || epoch.index > epoch_index {
-                    return U256::from(0);
-                }
+        match self.rewards_ledger.get(addr) {
+            Some(entries) => entries
+                .iter()
+                .filter(|r| r.epoch <= epoch_index)
+                .map(|r| r.amount)
+                .fold(U256::from(0), |a, b| a + b),
+            None => U256::from(0),
+        }
+    }
 
-                // if *addr == hex_literal::hex!("6518c695cdcbefa272a4e5ef73bd46e801983e19").into() {
-                //     println!("EPOCH {}", epoch.index);
-                //     println!("epoch.minted {}", epoch.minted);
-                //     println!("epoch.total {}", epoch.total);
-                //     println!("staked {}", staked);
-                // }
+    // number of seconds before rewards are unlocked, None if pool is not read yet
+    pub fn release_offset(&self) -> Option<u64> {
+        self.pool_info
+            .as_ref()
+            .map(|pool_info| pool_info.reward_vesting_period * pool_info.epoch_length)
+    }
 
-                (epoch.minted * staked) / epoch.total
-            })
-            .fold(U256::from(0), |a, b| a + b)
+    // length of epoch in seconds, one week if pool is not read yet
+    pub fn epoch_length(&self) -> u64 {
+        match &self.pool_info {
+            Some(pool_info) => pool_info.epoch_length,
+            None => 7 * 86400,
+        }
     }
 
     pub fn is_vested_deposit(&self, addr: &H160) -> bool {
@@ -631,6 +1170,15 @@ impl AppState {
             Some(x) => (x.clone().address, x.clone().delegates),
             None => return Err(anyhow::Error::msg("invalid from- wallet")),
         };
+        // wallets are checked before anything is changed
+        if !self.wallets.contains_key(to) {
+            return Err(anyhow::Error::msg("invalid to- wallet"));
+        }
+        if let Some(existing) = &delegates {
+            if !self.wallets.contains_key(&existing.address) {
+                return Err(anyhow::Error::msg("no record of delegation wallet"));
+            }
+        }
         // info!("delegated from={:?}, to: {:?}, shares: {:?}", from, to, shares);
         if let Some(existing) = &delegates {
             // remove existing delegation
@@ -668,14 +1216,25 @@ impl AppState {
     }
 
     pub fn undelegate(&mut self, from: &H160, to: &H160, shares: U256) -> anyhow::Result<()> {
-        let delegates = match self.wallets.get(from) {
-            Some(x) => x.clone().delegates,
+        let (delegates, from_shares) = match self.wallets.get(from) {
+            Some(x) => (x.clone().delegates, x.shares),
             None => return Err(anyhow::Error::msg("invalid from- wallet")),
         };
+        // shares are checked before the delegation is removed
+        if from_shares < shares {
+            warn!("wallet {:?}", self.wallets.get(from));
+            return Err(anyhow::Error::msg(format!(
+                "shares amount {:?} is less than undelegated",
+                from_shares
+            )));
+        }
         if let Some(existing) = &delegates {
             if existing.address != *to {
                 return Err(anyhow::Error::msg("undelegate to doesn't match"));
             }
+            if !self.wallets.contains_key(&existing.address) {
+                return Err(anyhow::Error::msg("no record of delegation wallet"));
+            }
             // remove existing delegation
             match self.wallets.get_mut(&existing.address) {
                 Some(old) => {
@@ -690,13 +1249,6 @@ impl AppState {
             Some(x) => x,
             None => return Err(anyhow::Error::msg("invalid from- wallet")),
         };
-        if w_from.shares < shares {
-            warn!("wallet {:?}", w_from);
-            return Err(anyhow::Error::msg(format!(
-                "shares amount {:?} is less than undelegated",
-                w_from.shares
-            )));
-        }
         w_from.delegates = None;
         w_from.update_voting_power();
         Ok(())
@@ -791,6 +1343,9 @@ impl AppState {
     pub fn unstaked(&mut self, user: &H160, amount: &U256) -> anyhow::Result<()> {
         let total_stake = self.get_staked_total();
         let total_shares = self.get_shares_total();
+        if total_stake == U256::from(0) {
+            return Err(anyhow::Error::msg("unstaking from the empty pool"));
+        }
         if let Some(w) = self.wallets.get_mut(&user) {
             let _ww = w.clone();
             let shares = *amount * total_shares / total_stake;
@@ -811,6 +1366,25 @@ impl AppState {
         Ok(())
     }
 
+    // payout reduces stake and rewards of every wallet proportionally, shares stay the same
+    pub fn paid_out_claim(&mut self, amount: U256, total_stake: U256) -> anyhow::Result<()> {
+        let before = total_stake + amount;
+        if before == U256::from(0) {
+            return Err(anyhow::Error::msg("claim payout from the empty pool"));
+        }
+        self.wallets.values_mut().for_each(|w| {
+            let staked_loss = w.staked * amount / before;
+            let rewards_loss = w.rewards * amount / before;
+            w.staked -= staked_loss;
+            w.rewards -= rewards_loss;
+            w.claim_loss += staked_loss + rewards_loss;
+        });
+        if let Some(pool_info) = &mut self.pool_info {
+            pool_info.total_stake = total_stake;
+        }
+        Ok(())
+    }
+
     pub fn distribute(
         &mut self,
         epoch_index: U256,
@@ -828,9 +1402,10 @@ impl AppState {
             .into_iter()
             .collect();
         let total = match total_stake {
-            Some(x) => x - amount,
+            Some(x) => x.checked_sub(amount).unwrap_or_default(),
             None => stake.values().clone().fold(U256::from(0), |a, b| a + b),
         };
+
         let epoch: Epoch = Epoch::new(
             epoch_index.as_u64(),
             self.apr,
@@ -843,9 +1418,27 @@ impl AppState {
         );
         self.epochs.insert(epoch.index, epoch.clone());
         // distribute individual rewards
-        self.wallets.iter_mut().for_each(|(_, w)| {
+        let unlock_tm = self.release_offset().map(|offset| tm + offset);
+        let ledger = &mut self.rewards_ledger;
+        self.wallets.iter_mut().for_each(|(addr, w)| {
             let staked = w.staked + w.rewards;
-            w.rewards += (epoch.minted * staked) / total;
+            // nobody is rewarded in the empty pool
+            let reward = (epoch.minted * staked)
+                .checked_div(total)
+                .unwrap_or_default();
+            w.rewards += reward;
+            if reward > U256::from(0) {
+                ledger.entry(*addr).or_insert(vec![]).push(RewardEntry {
+                    epoch: epoch.index,
+                    tm,
+                    block_number,
+                    amount: reward,
+                    stake: staked,
+                    shares: w.shares,
+                    total: w.rewards,
+                    unlock_tm,
+                });
+            }
         });
 
         // setting up new epoch
@@ -854,41 +1447,71 @@ impl AppState {
         Ok(())
     }
 
-    pub fn update(&mut self, e: OnChainEvent, log: web3::types::Log) -> () {
-        log.block_number.map(|block_number| {
+    pub fn update(
+        &mut self,
+        e: OnChainEvent,
+        log: web3::types::Log,
+    ) -> Result<(), TransitionError> {
+        // wallets that appear for the first time are removed again if the event is rejected
+        let created: Vec<H160> = e
+            .entry
+            .get_wallets()
+            .into_iter()
+            .filter(|wallet| !self.wallets.contains_key(wallet))
+            .collect();
+        for wallet in &created {
+            let w = Wallet {
+                address: *wallet,
+                created_at: e.tm,
+                ..Default::default()
+            };
+            self.wallets.insert(*wallet, w);
+        }
+        let block_power = self.block_power.clone();
+        self.remember_block_power(&e);
+        if let Err(err) = self.transition(&e, &log) {
+            for wallet in &created {
+                self.wallets.remove(wallet);
+            }
+            self.block_power = block_power;
+            return Err(err);
+        }
+
+        // the event is recorded only after the transition succeeded
+        if let Some(block_number) = log.block_number {
             self.last_block = block_number.as_u64();
-        });
+        }
+        if let Some(the_last) = &mut self.the_last {
+            the_last.update = Some(Utc::now());
+        }
         self.fees.insert(e.tx, e.fees.clone());
-
-        // if e.entry.is_broadcast() {
-        //     self.wallets_events.iter_mut().for_each(|(_, w)| {
-        //         w.push(e.clone());
-        //     });
-        // }
-
-        e.entry.get_wallets().iter().for_each(|wallet| {
-            if !self.wallets_events.contains_key(&wallet) {
-                self.wallets_events.insert(wallet.clone(), vec![]);
-                let mut w = Wallet::default();
-                w.delegated = BTreeMap::new();
-                w.address = wallet.clone();
-                w.created_at = e.tm;
-                self.wallets.insert(wallet.clone(), w);
+        for wallet in &created {
+            if let Some(w) = self.wallets.get(wallet) {
+                self.search.wallet(w);
             }
-            if let Some(w) = self.wallets_events.get_mut(&wallet) {
-                w.push(e.clone());
-            }
-            self.wallets.get_mut(&wallet).unwrap().updated_at = e.tm;
-        });
-        e.entry.get_voting().map(|id| {
-            if !self.votings_events.contains_key(&id) {
-                self.votings_events.insert(id, vec![]);
-            }
-            if let Some(v) = self.votings_events.get_mut(&id) {
-                v.push(e.clone());
+        }
+        for wallet in e.entry.get_wallets() {
+            self.search.transaction(e.tx, wallet);
+            if let Some(w) = self.wallets.get_mut(&wallet) {
+                w.updated_at = e.tm;
             }
-        });
+            self.wallets_events
+                .entry(wallet)
+                .or_default()
+                .push(e.clone());
+        }
+        if let Some(id) = e.entry.get_voting() {
+            self.votings_events.entry(id).or_default().push(e.clone());
+        }
+        Ok(())
+    }
 
+    // changes of the state by the event, that fail without changing anything
+    fn transition(
+        &mut self,
+        e: &OnChainEvent,
+        log: &web3::types::Log,
+    ) -> Result<(), TransitionError> {
         match &e.entry {
             Api3::MintedReward {
                 epoch_index,
@@ -896,8 +1519,8 @@ impl AppState {
                 new_apr,
                 total_stake,
             } => {
-                println!("{:?}", e.entry);
-                if let Err(err) = self.distribute(
+                debug!("{:?}", e.entry);
+                self.distribute(
                     *epoch_index,
                     *amount,
                     *new_apr,
@@ -905,17 +1528,16 @@ impl AppState {
                     e.tm,
                     e.block_number,
                     e.tx,
-                ) {
-                    warn!("{:?} {:?}", err, e);
-                }
+                )
+                .map_err(TransitionError::from)?;
             }
             Api3::MintedRewardV0 {
                 epoch_index,
                 amount,
                 new_apr,
             } => {
-                println!("{:?}", e.entry);
-                if let Err(err) = self.distribute(
+                debug!("{:?}", e.entry);
+                self.distribute(
                     *epoch_index,
                     *amount,
                     *new_apr,
@@ -923,9 +1545,8 @@ impl AppState {
                     e.tm,
                     e.block_number,
                     e.tx,
-                ) {
-                    warn!("{:?} {:?}", err, e);
-                }
+                )
+                .map_err(TransitionError::from)?;
             }
             Api3::Deposited {
                 user,
@@ -947,7 +1568,7 @@ impl AppState {
                 start: _,
                 end: _,
                 user_unstaked: _,
-                user_vesting: _,
+                user_vesting,
             } => {
                 if let Some(w) = self.wallets.get_mut(&user) {
                     w.deposited += *amount;
@@ -958,6 +1579,7 @@ impl AppState {
                                 Some(v) => v,
                             },
                     );
+                    w.vesting_remaining = Some(*user_vesting);
                     w.supporter = false;
                 }
             }
@@ -996,18 +1618,16 @@ impl AppState {
                 total_shares: _,
                 total_stake: _,
             } => {
-                if let Err(err) = self.staked(user, amount, minted_shares) {
-                    warn!("{:?} {:?}", err, e);
-                }
+                self.staked(user, amount, minted_shares)
+                    .map_err(TransitionError::from)?;
             }
             Api3::StakedV0 {
                 user,
                 amount,
                 minted_shares,
             } => {
-                if let Err(err) = self.staked(user, amount, minted_shares) {
-                    warn!("{:?} {:?}", err, e);
-                }
+                self.staked(user, amount, minted_shares)
+                    .map_err(TransitionError::from)?;
             }
             Api3::ScheduledUnstake {
                 user,
@@ -1016,12 +1636,8 @@ impl AppState {
                 scheduled_for,
                 user_shares: _,
             } => {
-                if let Err(err) =
-                    self.scheduled_unstake(user, amount, shares, scheduled_for.as_u64())
-                {
-                    warn!("{:?} {:?}", err, e);
-                    std::process::exit(0);
-                }
+                self.scheduled_unstake(user, amount, shares, scheduled_for.as_u64())
+                    .map_err(TransitionError::from)?;
             }
             Api3::ScheduledUnstakeV0 {
                 user,
@@ -1029,12 +1645,8 @@ impl AppState {
                 shares,
                 scheduled_for,
             } => {
-                if let Err(err) =
-                    self.scheduled_unstake(user, amount, shares, scheduled_for.as_u64())
-                {
-                    warn!("{:?} {:?}", err, e);
-                    std::process::exit(0);
-                }
+                self.scheduled_unstake(user, amount, shares, scheduled_for.as_u64())
+                    .map_err(TransitionError::from)?;
             }
 
             Api3::Unstaked {
@@ -1044,14 +1656,10 @@ impl AppState {
                 total_shares: _,
                 total_stake: _,
             } => {
-                if let Err(err) = self.unstaked(user, amount) {
-                    warn!("{:?} {:?}", err, e);
-                }
+                self.unstaked(user, amount).map_err(TransitionError::from)?;
             }
             Api3::UnstakedV0 { user, amount } => {
-                if let Err(err) = self.unstaked(user, amount) {
-                    warn!("{:?} {:?}", err, e);
-                }
+                self.unstaked(user, amount).map_err(TransitionError::from)?;
             }
 
             // You can't trust amount of shares from this event
@@ -1066,18 +1674,16 @@ impl AppState {
                 shares: _,
                 total_delegated_to: _,
             } => {
-                if let Err(err) = self.delegate(from, to, e.tm) {
-                    warn!("{:?} {:?}", err, e);
-                }
+                self.delegate(from, to, e.tm)
+                    .map_err(TransitionError::from)?;
             }
             Api3::DelegatedV0 {
                 from,
                 to,
                 shares: _,
             } => {
-                if let Err(err) = self.delegate(from, to, e.tm) {
-                    warn!("{:?} {:?}", err, e);
-                }
+                self.delegate(from, to, e.tm)
+                    .map_err(TransitionError::from)?;
             }
             Api3::Undelegated {
                 from,
@@ -1085,14 +1691,12 @@ impl AppState {
                 shares,
                 total_delegated_to: _,
             } => {
-                if let Err(err) = self.undelegate(from, to, *shares) {
-                    warn!("{:?} {:?}", err, e);
-                }
+                self.undelegate(from, to, *shares)
+                    .map_err(TransitionError::from)?;
             }
             Api3::UndelegatedV0 { from, to, shares } => {
-                if let Err(err) = self.undelegate(from, to, *shares) {
-                    warn!("{:?} {:?}", err, e);
-                }
+                self.undelegate(from, to, *shares)
+                    .map_err(TransitionError::from)?;
             }
 
             Api3::StartVote {
@@ -1134,7 +1738,9 @@ impl AppState {
                     no,
                     executed: false,
                     details: None,
+                    snapshot: self.get_voting_power_before_block(),
                 };
+                self.search.voting(&v);
                 self.votings.insert(v.as_u64(), v);
                 if let Some(w) = self.wallets.get_mut(&creator) {
                     w.votes = w.votes + 1;
@@ -1178,10 +1784,303 @@ impl AppState {
                 // println!("{:?}", e.entry);
                 self.set_vesting_addresses(addresses);
             }
+
+            // Delegated shares are already kept in sync on staking and unstaking,
+            // this only makes sure the delegate has the record of the current amount
+            Api3::UpdatedDelegation {
+                user,
+                delegate,
+                delta: _,
+                shares: _,
+                total_delegated_to: _,
+            } => {
+                let shares = match self.wallets.get(user) {
+                    Some(w) => w.shares,
+                    None => return Err(TransitionError("invalid user wallet".to_owned())),
+                };
+                if let Some(w) = self.wallets.get_mut(delegate) {
+                    w.delegated.insert(*user, shares);
+                    w.update_voting_power();
+                }
+            }
+            Api3::VestedTimelock {
+                user,
+                amount,
+                user_vesting,
+            } => {
+                if let Some(w) = self.wallets.get_mut(user) {
+                    w.vesting_unlocked += *amount;
+                    w.vesting_remaining = Some(*user_vesting);
+                }
+                self.timelocks
+                    .entry(*user)
+                    .or_insert(vec![])
+                    .push(TimelockEntry {
+                        tm: e.tm,
+                        block_number: e.block_number,
+                        tx: e.tx,
+                        vested: *amount,
+                        remaining: Some(*user_vesting),
+                        beneficiary: None,
+                    });
+            }
+            Api3::WithdrawnToPool {
+                recipient,
+                api3_pool_address: _,
+                beneficiary,
+            } => {
+                self.timelocks
+                    .entry(*recipient)
+                    .or_insert(vec![])
+                    .push(TimelockEntry {
+                        tm: e.tm,
+                        block_number: e.block_number,
+                        tx: e.tx,
+                        vested: U256::from(0),
+                        remaining: None,
+                        beneficiary: Some(*beneficiary),
+                    });
+            }
+            Api3::UpdatedLastProposalTimestamp {
+                user,
+                last_proposal_timestamp,
+                voting_app: _,
+            } => {
+                if let Some(w) = self.wallets.get_mut(user) {
+                    w.last_proposal_tm = Some(last_proposal_timestamp.as_u64());
+                }
+            }
+            Api3::SetStakeTarget { stake_target } => {
+                if let Some(pool_info) = &mut self.pool_info {
+                    pool_info.stake_target = *stake_target;
+                }
+                self.stake_targets.push(StakeTargetChange {
+                    tm: e.tm,
+                    block_number: e.block_number,
+                    tx: e.tx,
+                    stake_target: *stake_target,
+                });
+            }
+            Api3::PaidOutClaim {
+                recipient,
+                amount,
+                total_stake,
+            } => {
+                self.paid_out_claim(*amount, *total_stake)
+                    .map_err(TransitionError::from)?;
+                self.claims.push(ClaimPayout {
+                    tm: e.tm,
+                    block_number: e.block_number,
+                    tx: e.tx,
+                    recipient: *recipient,
+                    amount: *amount,
+                    total_stake: *total_stake,
+                });
+            }
+            Api3::SetDaoApps {
+                agent_app_primary,
+                agent_app_secondary,
+                voting_app_primary,
+                voting_app_secondary,
+            } => {
+                self.dao_apps.push(DaoAppsChange {
+                    tm: e.tm,
+                    block_number: e.block_number,
+                    tx: e.tx,
+                    agent_app_primary: *agent_app_primary,
+                    agent_app_secondary: *agent_app_secondary,
+                    voting_app_primary: *voting_app_primary,
+                    voting_app_secondary: *voting_app_secondary,
+                });
+            }
+            Api3::SetErc20Addresses { addresses } => {
+                self.erc20_addresses = addresses.clone();
+            }
+            Api3::OwnershipTransferred { from, to } => {
+                self.ownership.push(OwnershipChange {
+                    tm: e.tm,
+                    block_number: e.block_number,
+                    tx: e.tx,
+                    contract: log.address,
+                    from: *from,
+                    to: *to,
+                });
+            }
+            Api3::Decoded(_) => {
+                self.unhandled.push(e.clone());
+            }
             _ => {}
         };
-        if let Some(the_last) = &mut self.the_last {
-            the_last.update = Some(Utc::now());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn it_links_ens_records() {
+        let records = EnsRecords {
+            avatar: Some("ipfs://QmAvatar".to_owned()),
+            url: Some("api3.org".to_owned()),
+            twitter: Some("@API3DAO".to_owned()),
+        };
+        assert_eq!(
+            records.avatar_url("dao.eth").as_deref(),
+            Some("https://ipfs.io/ipfs/QmAvatar")
+        );
+        assert_eq!(records.website().as_deref(), Some("https://api3.org"));
+        assert_eq!(records.twitter_handle().as_deref(), Some("API3DAO"));
+
+        let nft = EnsRecords {
+            avatar: Some(
+                "eip155:1/erc721:0xb7f7f6c52f2e2fdb1963eab30438024864c313f6/2430".to_owned(),
+            ),
+            url: Some("javascript:alert(1)".to_owned()),
+            twitter: Some("https://twitter.com/api3dao/".to_owned()),
+        };
+        assert_eq!(
+            nft.avatar_url("dao.eth").as_deref(),
+            Some("https://metadata.ens.domains/mainnet/avatar/dao.eth")
+        );
+        assert_eq!(nft.website(), None);
+        assert_eq!(nft.twitter_handle().as_deref(), Some("api3dao"));
+        assert!(EnsRecords::default().is_empty());
+    }
+
+    #[test]
+    pub fn it_leaves_unlock_time_unknown_without_pool() {
+        let addr = H160::from_low_u64_be(1);
+        let mut state = AppState::new(1);
+        let w = Wallet {
+            address: addr,
+            staked: U256::exp10(21),
+            ..Default::default()
+        };
+        state.wallets.insert(addr, w);
+        let (amount, apr) = (U256::exp10(18), U256::exp10(17));
+        state
+            .distribute(U256::from(1), amount, apr, None, 1000, 1, H256::zero())
+            .unwrap();
+        state.pool_info = Some(Api3PoolInfo {
+            genesis_apr: 0.35,
+            min_apr: 0.025,
+            max_apr: 0.75,
+            rewards_coeff: 1.0,
+            epoch_length: 100,
+            reward_vesting_period: 52,
+            total_stake: U256::from(0),
+            total_shares: U256::from(0),
+            stake_target: U256::from(0),
+            unstake_wait_period: 0,
+        });
+        state
+            .distribute(U256::from(2), amount, apr, None, 1100, 2, H256::zero())
+            .unwrap();
+
+        let ledger = state.rewards_ledger.get(&addr).unwrap();
+        assert_eq!(ledger[0].unlock_tm, None);
+        assert!(ledger[0].is_locked(u64::MAX));
+        assert_eq!(ledger[1].unlock_tm, Some(1100 + 5200));
+        assert!(!ledger[1].is_locked(6300));
+    }
+
+    #[test]
+    pub fn it_keeps_failed_events_out_of_history() {
+        let recipient = H160::from_low_u64_be(1);
+        let log = web3::types::Log {
+            address: H160::from_low_u64_be(99),
+            topics: vec![],
+            data: web3::types::Bytes(vec![]),
+            block_hash: None,
+            block_number: Some(1.into()),
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        };
+        let e = OnChainEvent {
+            entry: Api3::PaidOutClaim {
+                recipient,
+                amount: U256::from(0),
+                total_stake: U256::from(0),
+            },
+            tm: 15,
+            block_number: 1,
+            tx: H256::from_low_u64_be(1),
+            log_index: 0,
+            fees: TxFee {
+                gas_price: U256::from(0),
+                gas: U256::from(0),
+                gas_used: None,
+                usd: None,
+            },
+        };
+        let mut state = AppState::new(1);
+        // payout from the empty pool is rejected
+        assert!(state.update(e, log).is_err());
+        assert!(state.claims.is_empty());
+        // nothing of the rejected event is left in the state
+        assert!(state.wallets.is_empty());
+        assert!(state.wallets_events.is_empty());
+        assert!(state.fees.is_empty());
+        assert_eq!(state.last_block, 0);
+        assert!(state.search.is_empty());
+    }
+
+    #[test]
+    pub fn it_rejects_transitions_without_changes() {
+        let (a, b) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+        let mut state = AppState::new(1);
+        for addr in [a, b] {
+            let w = Wallet {
+                address: addr,
+                ..Default::default()
+            };
+            state.wallets.insert(addr, w);
+        }
+        // nothing is staked yet
+        assert!(state.unstaked(&a, &U256::from(10)).is_err());
+        state
+            .staked(&a, &U256::from(100), &U256::from(100))
+            .unwrap();
+        state.delegate(&a, &b, 10).unwrap();
+        assert_eq!(state.wallets[&b].delegated[&a], U256::from(100));
+
+        // undelegating more shares than the wallet has keeps the delegation
+        assert!(state.undelegate(&a, &b, U256::from(101)).is_err());
+        assert_eq!(state.wallets[&b].delegated[&a], U256::from(100));
+        assert!(state.wallets[&a].delegates.is_some());
+        // delegating to an unknown wallet keeps the previous delegation
+        assert!(state.delegate(&a, &H160::from_low_u64_be(3), 20).is_err());
+        assert_eq!(state.wallets[&b].delegated[&a], U256::from(100));
+    }
+
+    #[test]
+    pub fn it_takes_claim_loss_from_stake_and_rewards() {
+        let mut state = AppState::new(1);
+        for (n, staked, rewards) in [(1, 60, 40), (2, 100, 0)] {
+            let w = Wallet {
+                address: H160::from_low_u64_be(n),
+                staked: U256::from(staked),
+                rewards: U256::from(rewards),
+                ..Default::default()
+            };
+            state.wallets.insert(w.address, w);
         }
+        state
+            .paid_out_claim(U256::from(50), U256::from(150))
+            .unwrap();
+        // a quarter of the pool was paid out
+        let w = &state.wallets[&H160::from_low_u64_be(1)];
+        assert_eq!(w.staked, U256::from(45));
+        assert_eq!(w.rewards, U256::from(30));
+        assert_eq!(w.claim_loss, U256::from(25));
+        let w = &state.wallets[&H160::from_low_u64_be(2)];
+        assert_eq!(w.staked, U256::from(75));
+        assert_eq!(w.claim_loss, U256::from(25));
     }
 }
@@ -0,0 +1,303 @@
+use crate::events::Api3;
+use crate::state::{AppState, OnChainEvent, RewardEntry};
+use serde::{Deserialize, Serialize};
+use web3::types::{H160, H256, U256};
+
+/// Balances of the wallet right after the event
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TimelinePoint {
+    pub tm: u64,
+    pub block_number: u64,
+    pub tx: H256,
+    pub log_index: u64,
+    /// total amount that was deposited into the pool
+    pub deposited: U256,
+    /// total amount that was withdrawn from the pool
+    pub withdrawn: U256,
+    /// staked amount, not including rewards
+    pub staked: U256,
+    pub shares: U256,
+    /// amount that is scheduled for unstaking
+    pub pending_unstake: U256,
+    /// wallet the voting power is delegated to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delegated_to: Option<H160>,
+    /// shares that are delegated to this wallet by others
+    pub delegated_from: U256,
+    /// total rewards of the wallet, minted before the event
+    pub rewards: U256,
+}
+
+/// Running balances of the wallet, one point per event of its history
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WalletTimeline {
+    pub address: H160,
+    pub points: Vec<TimelinePoint>,
+}
+
+impl TimelinePoint {
+    /// balances after applying the event to the previous point
+    pub fn next(&self, addr: &H160, e: &OnChainEvent) -> Self {
+        let mut p = Self {
+            tm: e.tm,
+            block_number: e.block_number,
+            tx: e.tx,
+            log_index: e.log_index,
+            ..self.clone()
+        };
+        match &e.entry {
+            Api3::Deposited { user, amount, .. }
+            | Api3::DepositedV0 { user, amount }
+            | Api3::DepositedVesting { user, amount, .. }
+            | Api3::DepositedByTimelockManager { user, amount, .. }
+                if user == addr =>
+            {
+                p.deposited += *amount;
+            }
+            Api3::Withdrawn { user, amount, .. } | Api3::WithdrawnV0 { user, amount }
+                if user == addr =>
+            {
+                p.withdrawn += *amount;
+            }
+            Api3::Staked {
+                user,
+                amount,
+                minted_shares,
+                ..
+            }
+            | Api3::StakedV0 {
+                user,
+                amount,
+                minted_shares,
+            } if user == addr => {
+                p.staked += *amount;
+                p.shares += *minted_shares;
+            }
+            Api3::ScheduledUnstake {
+                user,
+                amount,
+                shares,
+                ..
+            }
+            | Api3::ScheduledUnstakeV0 {
+                user,
+                amount,
+                shares,
+                ..
+            } if user == addr => {
+                // the same as the state does, stake cannot go below zero
+                let amount = std::cmp::min(*amount, p.staked);
+                p.staked -= amount;
+                p.shares = p.shares.saturating_sub(*shares);
+                p.pending_unstake = amount;
+            }
+            Api3::Unstaked { user, .. } | Api3::UnstakedV0 { user, .. } if user == addr => {
+                p.pending_unstake = U256::from(0);
+            }
+            Api3::Delegated {
+                from,
+                to,
+                total_delegated_to,
+                ..
+            } => {
+                if from == addr {
+                    p.delegated_to = Some(*to);
+                }
+                if to == addr {
+                    p.delegated_from = *total_delegated_to;
+                }
+            }
+            Api3::DelegatedV0 { from, to, shares } => {
+                if from == addr {
+                    p.delegated_to = Some(*to);
+                }
+                if to == addr {
+                    p.delegated_from += *shares;
+                }
+            }
+            Api3::Undelegated {
+                from,
+                to,
+                total_delegated_to,
+                ..
+            } => {
+                if from == addr {
+                    p.delegated_to = None;
+                }
+                if to == addr {
+                    p.delegated_from = *total_delegated_to;
+                }
+            }
+            Api3::UndelegatedV0 { from, to, shares } => {
+                if from == addr {
+                    p.delegated_to = None;
+                }
+                if to == addr {
+                    p.delegated_from = p.delegated_from.saturating_sub(*shares);
+                }
+            }
+            Api3::UpdatedDelegation {
+                delegate,
+                total_delegated_to,
+                ..
+            } if delegate == addr => {
+                p.delegated_from = *total_delegated_to;
+            }
+            _ => {}
+        }
+        p
+    }
+}
+
+impl WalletTimeline {
+    /// folds the events of the wallet, rewards are taken from its ledger
+    /// as epochs are not in the wallet history
+    pub fn new(addr: &H160, events: &[OnChainEvent], ledger: &[RewardEntry]) -> Self {
+        let mut points: Vec<TimelinePoint> = Vec::with_capacity(events.len());
+        let mut prev = TimelinePoint::default();
+        let mut rewards = ledger.iter().peekable();
+        for e in events {
+            while let Some(r) = rewards.next_if(|r| r.block_number < e.block_number) {
+                prev.rewards = r.total;
+            }
+            prev = prev.next(addr, e);
+            points.push(prev.clone());
+        }
+        Self {
+            address: *addr,
+            points,
+        }
+    }
+
+    pub fn from_state(state: &AppState, addr: &H160) -> Self {
+        let events = state.wallets_events.get(addr).cloned().unwrap_or_default();
+        let ledger = state.rewards_ledger.get(addr).cloned().unwrap_or_default();
+        Self::new(addr, &events, &ledger)
+    }
+
+    pub fn last(&self) -> Option<&TimelinePoint> {
+        self.points.last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fees::TxFee;
+
+    fn tokens(n: u64) -> U256 {
+        U256::from(n) * U256::exp10(18)
+    }
+
+    fn event(block_number: u64, entry: Api3) -> OnChainEvent {
+        OnChainEvent {
+            entry,
+            tm: block_number * 15,
+            block_number,
+            tx: H256::from_low_u64_be(block_number),
+            log_index: 0,
+            fees: TxFee {
+                gas_price: U256::from(0),
+                gas: U256::from(0),
+                gas_used: None,
+                usd: None,
+            },
+        }
+    }
+
+    #[test]
+    pub fn it_folds_stake_and_unstake() {
+        let user = H160::from_low_u64_be(1);
+        let events = vec![
+            event(
+                1,
+                Api3::DepositedV0 {
+                    user,
+                    amount: tokens(100),
+                },
+            ),
+            event(
+                2,
+                Api3::StakedV0 {
+                    user,
+                    amount: tokens(100),
+                    minted_shares: tokens(90),
+                },
+            ),
+            event(
+                4,
+                Api3::ScheduledUnstakeV0 {
+                    user,
+                    amount: tokens(40),
+                    shares: tokens(30),
+                    scheduled_for: U256::from(1000),
+                },
+            ),
+            event(
+                5,
+                Api3::UnstakedV0 {
+                    user,
+                    amount: tokens(40),
+                },
+            ),
+        ];
+        let ledger = vec![RewardEntry {
+            epoch: 1,
+            block_number: 3,
+            amount: tokens(1),
+            total: tokens(1),
+            ..Default::default()
+        }];
+        let t = WalletTimeline::new(&user, &events, &ledger);
+        assert_eq!(t.points.len(), 4);
+        assert_eq!(t.points[0].deposited, tokens(100));
+        assert_eq!(t.points[1].staked, tokens(100));
+        assert_eq!(t.points[1].rewards, U256::from(0));
+        assert_eq!(t.points[2].staked, tokens(60));
+        assert_eq!(t.points[2].shares, tokens(60));
+        assert_eq!(t.points[2].pending_unstake, tokens(40));
+        assert_eq!(t.points[2].rewards, tokens(1));
+        assert_eq!(t.last().unwrap().pending_unstake, U256::from(0));
+    }
+
+    #[test]
+    pub fn it_tracks_delegation_both_ways() {
+        let (a, b) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+        let events = vec![
+            event(
+                1,
+                Api3::Delegated {
+                    from: a,
+                    to: b,
+                    shares: tokens(10),
+                    total_delegated_to: tokens(10),
+                },
+            ),
+            event(
+                2,
+                Api3::UpdatedDelegation {
+                    user: a,
+                    delegate: b,
+                    delta: true,
+                    shares: tokens(5),
+                    total_delegated_to: tokens(15),
+                },
+            ),
+            event(
+                3,
+                Api3::UndelegatedV0 {
+                    from: a,
+                    to: b,
+                    shares: tokens(15),
+                },
+            ),
+        ];
+        let from = WalletTimeline::new(&a, &events, &[]);
+        assert_eq!(from.points[0].delegated_to, Some(b));
+        assert_eq!(from.points[2].delegated_to, None);
+        let to = WalletTimeline::new(&b, &events, &[]);
+        assert_eq!(to.points[0].delegated_to, None);
+        assert_eq!(to.points[1].delegated_from, tokens(15));
+        assert_eq!(to.points[2].delegated_from, U256::from(0));
+    }
+}
@@ -0,0 +1,204 @@
+use crate::nice;
+use crate::projections::ProjectionParams;
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use web3::types::{H160, U256};
+
+const DAY: u64 = 86400;
+
+// Scheduled unstake of the wallet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedUnstake {
+    pub address: H160,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ens: Option<String>,
+    pub amount: U256,
+    pub shares: U256,
+    /// time when unstake was requested
+    pub scheduled_at: u64,
+    /// time when unstake becomes executable
+    pub executable_at: u64,
+    /// seconds left before unstake becomes executable
+    pub seconds_left: u64,
+    /// unstake is executable, but was not executed yet
+    pub overdue: bool,
+}
+
+// Stake that leaves the pool on the given day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnstakeDay {
+    /// timestamp of the day start
+    pub day: u64,
+    /// amount that becomes executable on that day
+    pub amount: U256,
+    /// number of unstakes
+    pub count: u64,
+    /// total stake of the pool after all unstakes till that day
+    pub total_stake: U256,
+    /// total stake as a share of staking target
+    pub target_share: f64,
+    /// whether staking target is reached after unstakes
+    pub target_reached: bool,
+    /// projected APR on that day
+    pub apr: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnstakeQueue {
+    /// scheduled unstakes, ordered by the time they become executable
+    pub items: Vec<QueuedUnstake>,
+    /// total amount that is scheduled for unstaking
+    pub total: U256,
+    /// total amount of overdue unstakes
+    pub overdue: U256,
+    /// stake leaving the pool per day
+    pub timeline: Vec<UnstakeDay>,
+}
+
+impl UnstakeQueue {
+    pub fn from_state(state: &AppState, now: u64) -> Self {
+        let wait_period = match &state.pool_info {
+            Some(x) => x.unstake_wait_period,
+            None => 0,
+        };
+        let mut items: Vec<QueuedUnstake> = state
+            .wallets
+            .values()
+            .filter_map(|w| {
+                let u = w.scheduled_unstake.as_ref()?;
+                Some(QueuedUnstake {
+                    address: w.address,
+                    ens: w.ens.clone(),
+                    amount: u.amount,
+                    shares: u.shares,
+                    scheduled_at: u.tm.saturating_sub(wait_period),
+                    executable_at: u.tm,
+                    seconds_left: u.tm.saturating_sub(now),
+                    overdue: u.tm <= now,
+                })
+            })
+            .collect();
+        items.sort_by_key(|x| (x.executable_at, x.address));
+
+        let total = items.iter().fold(U256::from(0), |a, b| a + b.amount);
+        let overdue = items
+            .iter()
+            .filter(|x| x.overdue)
+            .fold(U256::from(0), |a, b| a + b.amount);
+        let timeline = build_timeline(state, &items, now);
+        Self {
+            items,
+            total,
+            overdue,
+            timeline,
+        }
+    }
+}
+
+// Groups unstakes by day and projects total stake and APR.
+// The pool keeps scheduled stake in its total until unstake is executed,
+// so it is deducted on the day it becomes executable.
+// Overdue unstakes are counted on the current day
+fn build_timeline(state: &AppState, items: &[QueuedUnstake], now: u64) -> Vec<UnstakeDay> {
+    let mut total_stake = match &state.pool_info {
+        Some(x) => x.total_stake,
+        None => items
+            .iter()
+            .fold(state.get_staked_total(), |a, b| a + b.amount),
+    };
+    let target: Option<U256> = match (&state.pool_info, &state.circulation) {
+        (Some(p), Some(c)) => Some(c.total_supply * p.stake_target / U256::exp10(18)),
+        _ => None,
+    };
+    let params = ProjectionParams::from_state(state);
+    let mut apr = state.apr;
+    let mut next_epoch = params.as_ref().map(|p| p.tm).unwrap_or(0);
+
+    let mut out: Vec<UnstakeDay> = vec![];
+    for item in items {
+        let day = item.executable_at.max(now) / DAY * DAY;
+        if let Some(p) = &params {
+            // APR changes at every epoch depending on the stake before the day
+            while p.epoch_length > 0 && next_epoch < day {
+                apr = p.next_apr(apr, nice::dec(total_stake, 18), p.total_supply);
+                next_epoch += p.epoch_length;
+            }
+        }
+        total_stake = if total_stake > item.amount {
+            total_stake - item.amount
+        } else {
+            U256::from(0)
+        };
+        let (target_share, target_reached) = match target {
+            Some(t) if t > U256::from(0) => {
+                let share = crate::participation::ratio(total_stake, t);
+                (share, share > 1.0)
+            }
+            _ => (0.0, false),
+        };
+        match out.last_mut() {
+            Some(last) if last.day == day => {
+                last.amount += item.amount;
+                last.count += 1;
+                last.total_stake = total_stake;
+                last.target_share = target_share;
+                last.target_reached = target_reached;
+            }
+            _ => out.push(UnstakeDay {
+                day,
+                amount: item.amount,
+                count: 1,
+                total_stake,
+                target_share,
+                target_reached,
+                apr,
+            }),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{ScheduledUnstake, Wallet};
+
+    fn tokens(n: u64) -> U256 {
+        U256::from(n) * U256::exp10(18)
+    }
+
+    #[test]
+    pub fn it_orders_and_groups_unstakes() {
+        let mut state = AppState::new(1);
+        for (n, amount, tm) in [
+            (1, 100, 5 * DAY + 10),
+            (2, 50, 2 * DAY),
+            (3, 20, 5 * DAY + 20),
+        ] {
+            let w = Wallet {
+                address: H160::from_low_u64_be(n),
+                staked: tokens(1000),
+                scheduled_unstake: Some(ScheduledUnstake {
+                    amount: tokens(amount),
+                    shares: tokens(amount),
+                    tm,
+                }),
+                ..Default::default()
+            };
+            state.wallets.insert(w.address, w);
+        }
+        let q = UnstakeQueue::from_state(&state, 3 * DAY);
+        assert_eq!(q.items.len(), 3);
+        assert_eq!(q.items[0].address, H160::from_low_u64_be(2));
+        assert!(q.items[0].overdue);
+        assert!(!q.items[1].overdue);
+        assert_eq!(q.items[1].seconds_left, 2 * DAY + 10);
+        assert_eq!(q.total, tokens(170));
+        assert_eq!(q.overdue, tokens(50));
+        assert_eq!(q.timeline.len(), 2);
+        assert_eq!(q.timeline[0].day, 3 * DAY);
+        assert_eq!(q.timeline[1].amount, tokens(120));
+        assert_eq!(q.timeline[1].count, 2);
+        assert_eq!(q.timeline[1].total_stake, tokens(3000));
+    }
+}
@@ -42,7 +42,7 @@ pub fn coin_price_at(coin: &str, value: U256, decimals: usize, dt: NaiveDateTime
     let price: U256 = nice::shifted_float(*market_price, decimals).unwrap();
     let cost = nice::multiplied(value, price, decimals);
     let out = nice::float(cost, decimals, 2);
-    println!(
+    debug!(
         "market_price = {} price = {} cost = {} usd = {}",
         *market_price, price, cost, out
     );
@@ -0,0 +1,105 @@
+use crate::state::{AppState, RewardEntry};
+use serde::{Deserialize, Serialize};
+use web3::types::{H160, U256};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VestingEntry {
+    #[serde(flatten)]
+    pub reward: RewardEntry,
+    /// whether reward is still locked
+    pub locked: bool,
+}
+
+// Vesting schedule of the wallet rewards
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VestingSchedule {
+    pub address: H160,
+    /// rewards of every epoch, ordered by epoch
+    pub entries: Vec<VestingEntry>,
+    /// total rewards
+    pub total: U256,
+    /// rewards that are still locked
+    pub locked: U256,
+    /// rewards that were unlocked
+    pub unlocked: U256,
+    /// time of the next unlock, if any rewards are locked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_unlock: Option<u64>,
+}
+
+impl VestingSchedule {
+    pub fn from_state(state: &AppState, address: &H160, now: u64) -> Self {
+        let entries: Vec<VestingEntry> = match state.rewards_ledger.get(address) {
+            Some(x) => x
+                .iter()
+                .map(|r| VestingEntry {
+                    reward: r.clone(),
+                    locked: r.is_locked(now),
+                })
+                .collect(),
+            None => vec![],
+        };
+        let mut total = U256::from(0);
+        let mut locked = U256::from(0);
+        for e in &entries {
+            total += e.reward.amount;
+            if e.locked {
+                locked += e.reward.amount;
+            }
+        }
+        let next_unlock = entries
+            .iter()
+            .filter(|e| e.locked)
+            .filter_map(|e| e.reward.unlock_tm)
+            .min();
+        Self {
+            address: *address,
+            entries,
+            total,
+            locked,
+            unlocked: total - locked,
+            next_unlock,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn it_splits_locked_rewards() {
+        let addr = H160::from_low_u64_be(1);
+        let mut state = AppState::new(1);
+        state.rewards_ledger.insert(
+            addr,
+            vec![
+                RewardEntry {
+                    epoch: 1,
+                    amount: U256::from(10),
+                    unlock_tm: Some(100),
+                    ..Default::default()
+                },
+                RewardEntry {
+                    epoch: 2,
+                    amount: U256::from(20),
+                    unlock_tm: Some(200),
+                    ..Default::default()
+                },
+                RewardEntry {
+                    epoch: 3,
+                    amount: U256::from(40),
+                    unlock_tm: Some(300),
+                    ..Default::default()
+                },
+            ],
+        );
+        let s = VestingSchedule::from_state(&state, &addr, 150);
+        assert_eq!(s.total, U256::from(70));
+        assert_eq!(s.unlocked, U256::from(10));
+        assert_eq!(s.locked, U256::from(60));
+        assert_eq!(s.next_unlock, Some(200));
+        assert!(!s.entries[0].locked);
+        assert!(s.entries[1].locked);
+    }
+}
@@ -0,0 +1,719 @@
+use crate::events::Api3;
+use crate::fees::{TxFee, TxFeeTotal};
+use crate::participation::{
+    self, Participation, TurnoutTrendPoint, VotingTurnout, WalletParticipation,
+};
+use crate::poolhistory::PoolHistory;
+use crate::projections::ProjectionParams;
+use crate::state::{
+    Api3Circulation, AppState, Epoch, LabelBadge, NetworkInfo, OnChainEvent, TimelockEntry,
+    Treasury, Voting, VotingDetails, Wallet,
+};
+use crate::timeline::{TimelinePoint, WalletTimeline};
+use crate::unstakes::UnstakeQueue;
+use crate::vesting::VestingSchedule;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use web3::types::{H160, H256, U256};
+
+// Part of the state that every page needs for its header, footer and links
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Site {
+    pub chain_id: u64,
+    pub last_block: u64,
+    pub network: NetworkInfo,
+}
+
+impl Site {
+    pub fn from_state(state: &AppState) -> Self {
+        Self {
+            chain_id: state.chain_id,
+            last_block: state.last_block,
+            network: state.network.clone(),
+        }
+    }
+}
+
+// Wallet as it is linked from other pages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Member {
+    pub address: H160,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ens: Option<String>,
+    pub labels: Vec<LabelBadge>,
+}
+
+impl Member {
+    /// addresses that are not in the DAO are linked without labels
+    pub fn from_state(state: &AppState, address: &H160) -> Self {
+        match state.wallets.get(address) {
+            Some(w) => Self {
+                address: *address,
+                ens: w.ens.clone(),
+                labels: state.get_labels(w),
+            },
+            None => Self {
+                address: *address,
+                ens: None,
+                labels: vec![],
+            },
+        }
+    }
+}
+
+/// links of the members of the DAO that are mentioned on the page
+fn members<'a, I>(state: &AppState, addresses: I) -> BTreeMap<H160, Member>
+where
+    I: IntoIterator<Item = &'a H160>,
+{
+    addresses
+        .into_iter()
+        .filter(|addr| state.wallets.contains_key(addr))
+        .map(|addr| (*addr, Member::from_state(state, addr)))
+        .collect()
+}
+
+/// fees of the transactions of the events
+fn fees(state: &AppState, events: &[OnChainEvent]) -> BTreeMap<H256, TxFee> {
+    events
+        .iter()
+        .filter_map(|e| state.fees.get(&e.tx).map(|fee| (e.tx, fee.clone())))
+        .collect()
+}
+
+fn rewards_coeff(state: &AppState) -> f64 {
+    match &state.pool_info {
+        Some(x) => x.rewards_coeff,
+        None => 1f64,
+    }
+}
+
+// Epoch as it is shown in the tables, without the stake of every wallet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EpochRow {
+    pub index: u64,
+    pub apr: f64,
+    pub minted: U256,
+    pub total: U256,
+    /// number of rewarded members
+    pub members: usize,
+    pub tm: u64,
+    pub block_number: u64,
+    pub tx: H256,
+}
+
+impl From<&Epoch> for EpochRow {
+    fn from(ep: &Epoch) -> Self {
+        Self {
+            index: ep.index,
+            apr: ep.apr,
+            minted: ep.minted,
+            total: ep.total,
+            members: ep.stake.len(),
+            tm: ep.tm,
+            block_number: ep.block_number,
+            tx: ep.tx,
+        }
+    }
+}
+
+// Summary of the DAO on the home page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HomeSummary {
+    pub site: Site,
+    /// number of members
+    pub members: usize,
+    /// number of votings
+    pub votings: usize,
+    pub epoch_index: u64,
+    pub apr: f64,
+    pub rewards_coeff: f64,
+    /// total rewards that were minted
+    pub minted: U256,
+    /// shares of all members, including rewards
+    pub staked: U256,
+    /// when the current epoch ends
+    pub next_epoch_tm: u64,
+    /// two previous epochs
+    pub epochs: BTreeMap<u64, EpochRow>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub circulation: Option<Api3Circulation>,
+    pub stake_target: U256,
+    pub total_stake: U256,
+    pub pool_history: PoolHistory,
+}
+
+impl HomeSummary {
+    pub fn from_state(state: &AppState) -> Self {
+        let minted = state.get_minted_total();
+        let total_shares = match &state.pool_info {
+            Some(x) => x.total_shares,
+            None => state.get_shares_total(),
+        };
+        let total_stake = match &state.pool_info {
+            Some(x) => x.total_stake,
+            None => total_shares + minted,
+        };
+        let stake_target = match (&state.circulation, &state.pool_info) {
+            (Some(c), Some(x)) => c.total_supply * x.stake_target / U256::exp10(26),
+            _ => U256::from(0),
+        };
+        let prev_epoch = state.epoch_index.saturating_sub(1);
+        let next_epoch_tm = match (state.epochs.get(&prev_epoch), &state.pool_info) {
+            (Some(ep), Some(pool_info)) => ep.tm + pool_info.epoch_length,
+            _ => 0,
+        };
+        let epochs = (1..=2)
+            .filter_map(|n| state.epoch_index.checked_sub(n))
+            .filter_map(|index| state.epochs.get(&index))
+            .map(|ep| (ep.index, EpochRow::from(ep)))
+            .collect();
+        Self {
+            site: Site::from_state(state),
+            members: state.wallets.len(),
+            votings: state.votings.len(),
+            epoch_index: state.epoch_index,
+            apr: state.apr,
+            rewards_coeff: rewards_coeff(state),
+            minted,
+            staked: state.get_shares_total() + minted,
+            next_epoch_tm,
+            epochs,
+            circulation: state.circulation.clone(),
+            stake_target,
+            total_stake,
+            pool_history: PoolHistory::from_state(state),
+        }
+    }
+}
+
+// Staking rewards of every epoch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewardsTable {
+    pub site: Site,
+    pub epoch_index: u64,
+    pub apr: f64,
+    pub rewards_coeff: f64,
+    /// time from the epoch until its rewards are released, None if pool is not read yet
+    pub release_offset: Option<u64>,
+    /// total rewards that were minted
+    pub minted: U256,
+    pub stake_target: U256,
+    pub total_stake: U256,
+    pub epochs: Vec<EpochRow>,
+}
+
+impl RewardsTable {
+    pub fn from_state(state: &AppState) -> Self {
+        let minted = state.get_minted_total();
+        let total_shares = match &state.pool_info {
+            Some(x) => x.total_shares,
+            None => state.get_shares_total(),
+        };
+        let total_stake = match &state.pool_info {
+            Some(x) => x.total_stake,
+            None => total_shares + minted,
+        };
+        let stake_target = match &state.pool_info {
+            Some(x) => match &state.circulation {
+                Some(c) => c.total_supply * x.stake_target / U256::exp10(26),
+                None => x.stake_target,
+            },
+            None => U256::from(0),
+        };
+        Self {
+            site: Site::from_state(state),
+            epoch_index: state.epoch_index,
+            apr: state.apr,
+            rewards_coeff: rewards_coeff(state),
+            release_offset: state.release_offset(),
+            minted,
+            stake_target,
+            total_stake,
+            epochs: state.epochs.values().map(EpochRow::from).collect(),
+        }
+    }
+}
+
+// Epoch with the stake and the reward of the wallet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletEpoch {
+    pub epoch: EpochRow,
+    /// stake of the wallet, if it was in the pool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stake: Option<U256>,
+    pub reward: U256,
+}
+
+// Member of the DAO with its history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletPage {
+    pub site: Site,
+    pub wallet: Wallet,
+    pub labels: Vec<LabelBadge>,
+    /// voting power of all members
+    pub votes_total: U256,
+    pub total_shares: U256,
+    pub participation: WalletParticipation,
+    pub vesting: VestingSchedule,
+    pub timelocks: Vec<TimelockEntry>,
+    pub epoch_length: u64,
+    pub rewards_coeff: f64,
+    pub release_offset: Option<u64>,
+    pub epochs: Vec<WalletEpoch>,
+    pub events: Vec<OnChainEvent>,
+    pub fees: BTreeMap<H256, TxFee>,
+    /// balances after each event
+    pub timeline: Vec<TimelinePoint>,
+    /// wallets this one delegates to or is delegated by
+    pub members: BTreeMap<H160, Member>,
+    /// votings the wallet took part in, without snapshots
+    pub votings: BTreeMap<u64, Voting>,
+    /// time the page was built at, locked rewards and proposals are shown as of it
+    pub now: u64,
+}
+
+impl WalletPage {
+    pub fn from_state(state: &AppState, addr: &H160, now: u64) -> Option<Self> {
+        let w = state.wallets.get(addr)?;
+        let events = state.wallets_events.get(addr).cloned().unwrap_or_default();
+        let timeline = WalletTimeline::from_state(state, addr).points;
+        let timelocks = state.timelocks.get(addr).cloned().unwrap_or_default();
+        let mut linked: Vec<&H160> = w.delegated.keys().collect();
+        linked.extend(w.delegates.iter().map(|d| &d.address));
+        linked.extend(timelocks.iter().filter_map(|t| t.beneficiary.as_ref()));
+        linked.extend(timeline.iter().filter_map(|p| p.delegated_to.as_ref()));
+        let members = members(state, linked);
+        let votings = events
+            .iter()
+            .filter_map(|e| match &e.entry {
+                Api3::StartVote { agent, vote_id, .. } | Api3::CastVote { agent, vote_id, .. } => {
+                    let vote_ref = crate::events::voting_to_u64(agent, vote_id.as_u64());
+                    state.votings.get(&vote_ref).map(|v| (vote_ref, v.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        let epochs = state
+            .epochs
+            .values()
+            .map(|ep| WalletEpoch {
+                epoch: EpochRow::from(ep),
+                stake: ep.stake.get(addr).cloned(),
+                reward: state.get_rewards_for_epoch(addr, ep.index),
+            })
+            .collect();
+        Some(Self {
+            site: Site::from_state(state),
+            wallet: w.clone(),
+            labels: state.get_labels(w),
+            votes_total: state.get_votes_total(),
+            total_shares: match &state.pool_info {
+                Some(x) => x.total_shares,
+                None => state.get_shares_total(),
+            },
+            participation: participation::wallet_participation(state, w),
+            vesting: VestingSchedule::from_state(state, addr, now),
+            timelocks,
+            epoch_length: state.epoch_length(),
+            rewards_coeff: rewards_coeff(state),
+            release_offset: state.release_offset(),
+            epochs,
+            fees: fees(state, &events),
+            events,
+            timeline,
+            members,
+            votings,
+            now,
+        })
+    }
+}
+
+// Voting with its history and members who did not vote
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VotingPage {
+    pub site: Site,
+    pub voting: Voting,
+    pub events: Vec<OnChainEvent>,
+    pub fees: BTreeMap<H256, TxFee>,
+    /// creator, voters and members who did not vote
+    pub members: BTreeMap<H160, Member>,
+    /// voting power of every member at the snapshot block of the voting
+    pub snapshot: BTreeMap<H160, U256>,
+}
+
+impl VotingPage {
+    pub fn from_state(state: &AppState, vote_ref: u64) -> Option<Self> {
+        let voting = state.votings.get(&vote_ref)?;
+        let events = state
+            .votings_events
+            .get(&vote_ref)
+            .cloned()
+            .unwrap_or_default();
+        let mut linked: Vec<&H160> = voting.snapshot.keys().collect();
+        for e in &events {
+            match &e.entry {
+                Api3::StartVote { creator, .. } => linked.push(creator),
+                Api3::CastVote { voter, .. } => linked.push(voter),
+                _ => {}
+            }
+        }
+        Some(Self {
+            site: Site::from_state(state),
+            voting: voting.clone(),
+            fees: fees(state, &events),
+            members: members(state, linked),
+            snapshot: voting.snapshot.clone(),
+            events,
+        })
+    }
+}
+
+// Member of the DAO as it is listed on the wallets page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletRow {
+    pub address: H160,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ens: Option<String>,
+    pub vested: bool,
+    pub labels: Vec<LabelBadge>,
+    pub voting_power: U256,
+    pub shares: U256,
+    pub rewards: U256,
+    pub created_at: u64,
+    pub updated_at: u64,
+    /// fees of all transactions of the wallet
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fees: Option<TxFeeTotal>,
+}
+
+// Members of the DAO, ordered by voting power
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletsPage {
+    pub site: Site,
+    pub total_shares: U256,
+    /// total rewards that were minted
+    pub minted: U256,
+    pub vested_members: u32,
+    pub vested_shares: U256,
+    pub delegating_members: u32,
+    pub delegating_shares: U256,
+    /// members who withdrew most of their deposits
+    pub left: u32,
+    pub wallets: Vec<WalletRow>,
+}
+
+impl WalletsPage {
+    pub fn from_state(state: &AppState) -> Self {
+        let mut sorted: Vec<&Wallet> = state.wallets.values().collect();
+        sorted.sort_by(|a, b| {
+            a.voting_power
+                .cmp(&b.voting_power)
+                .reverse()
+                .then((a.rewards).cmp(&b.rewards).reverse())
+        });
+        let wallets = sorted
+            .into_iter()
+            .map(|w| WalletRow {
+                address: w.address,
+                ens: w.ens.clone(),
+                vested: w.vested,
+                labels: state.get_labels(w),
+                voting_power: w.voting_power,
+                shares: w.shares,
+                rewards: w.rewards,
+                created_at: w.created_at,
+                updated_at: w.updated_at,
+                fees: state.wallets_events.get(&w.address).map(TxFeeTotal::new),
+            })
+            .collect();
+        Self {
+            site: Site::from_state(state),
+            total_shares: match &state.pool_info {
+                Some(x) => x.total_shares,
+                None => state.get_shares_total(),
+            },
+            minted: state.get_minted_total(),
+            vested_members: state.get_vested_num(),
+            vested_shares: state.get_vested_shares(),
+            delegating_members: state.get_delegating_num(),
+            delegating_shares: state.get_delegating_shares(),
+            left: state.get_withdrawn_num(),
+            wallets,
+        }
+    }
+}
+
+// Voting as it is listed on the votings page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VotingRow {
+    /// human readable key of the voting, i.e. "p-12"
+    pub key: String,
+    pub primary: bool,
+    pub tm: u64,
+    pub title: String,
+    pub metadata: String,
+    pub details: Option<VotingDetails>,
+    pub voted_yes: U256,
+    pub voted_no: U256,
+    pub votes_total: U256,
+    pub executed: bool,
+    /// voting period is over
+    pub expired: bool,
+    /// fees of all transactions of the voting
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fees: Option<TxFeeTotal>,
+}
+
+impl VotingRow {
+    pub fn required(&self) -> U256 {
+        let pct_required = if self.primary { 50u64 } else { 15u64 };
+        self.votes_total * U256::from(pct_required) / U256::from(100)
+    }
+
+    pub fn rejected(&self) -> bool {
+        self.voted_no >= self.required()
+    }
+
+    pub fn passing(&self) -> bool {
+        let required = self.required();
+        !self.rejected() && self.voted_yes >= required && self.voted_no < required
+    }
+}
+
+// Votings of the DAO, grouped by their outcome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VotingsPage {
+    pub site: Site,
+    /// number of all votings
+    pub total: usize,
+    pub pending: Vec<VotingRow>,
+    pub executed: Vec<VotingRow>,
+    pub invalid: Vec<VotingRow>,
+    pub rejected: Vec<VotingRow>,
+    /// turnout of every voting, ordered by start time
+    pub turnout: Vec<VotingTurnout>,
+    pub avg_turnout: f64,
+    pub trend: Vec<TurnoutTrendPoint>,
+}
+
+impl VotingsPage {
+    pub fn from_state(state: &AppState, now: u64) -> Self {
+        let mut res = Self {
+            site: Site::from_state(state),
+            total: state.votings.len(),
+            pending: vec![],
+            executed: vec![],
+            invalid: vec![],
+            rejected: vec![],
+            turnout: vec![],
+            avg_turnout: 0.0,
+            trend: vec![],
+        };
+        for v in state.votings.values() {
+            let invalid = v.is_invalid();
+            let row = VotingRow {
+                key: v.key(),
+                primary: v.primary,
+                tm: v.tm,
+                title: v.title.clone(),
+                metadata: v.metadata.clone(),
+                details: v.details.clone(),
+                voted_yes: v.voted_yes,
+                voted_no: v.voted_no,
+                votes_total: v.votes_total,
+                executed: v.executed,
+                expired: v.is_expired_at(now),
+                fees: state.votings_events.get(&v.as_u64()).map(TxFeeTotal::new),
+            };
+            let (rejected, passing) = (row.rejected(), row.passing());
+            if row.executed {
+                res.executed.push(row);
+                continue;
+            }
+            if invalid {
+                res.invalid.push(row.clone());
+            } else if passing || !row.expired {
+                res.pending.push(row.clone());
+            }
+            if rejected || (!passing && row.expired) {
+                res.rejected.push(row);
+            }
+        }
+        let participation = Participation::from_state(state);
+        res.turnout = participation.votings;
+        res.avg_turnout = participation.avg_turnout;
+        res.trend = participation.trend;
+        res
+    }
+}
+
+// Balances of the treasuries of the DAO
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreasuryPage {
+    pub site: Site,
+    pub treasuries: BTreeMap<String, Treasury>,
+    /// decimals for tokens
+    pub decimals: BTreeMap<String, usize>,
+}
+
+impl TreasuryPage {
+    pub fn from_state(state: &AppState) -> Self {
+        Self {
+            site: Site::from_state(state),
+            treasuries: state.treasuries.clone(),
+            decimals: state.decimals.clone(),
+        }
+    }
+}
+
+// Parameters of the pool for the rewards calculator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectionsPage {
+    pub site: Site,
+    /// None until the pool is read
+    pub params: Option<ProjectionParams>,
+}
+
+impl ProjectionsPage {
+    pub fn from_state(state: &AppState) -> Self {
+        Self {
+            site: Site::from_state(state),
+            params: ProjectionParams::from_state(state),
+        }
+    }
+}
+
+// Scheduled unstakes, as they were at the time of the request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnstakesPage {
+    pub site: Site,
+    pub queue: UnstakeQueue,
+    /// members who scheduled unstaking
+    pub members: BTreeMap<H160, Member>,
+}
+
+impl UnstakesPage {
+    pub fn from_state(state: &AppState, now: u64) -> Self {
+        let queue = UnstakeQueue::from_state(state, now);
+        Self {
+            site: Site::from_state(state),
+            members: members(state, queue.items.iter().map(|u| &u.address)),
+            queue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> AppState {
+        let mut state = AppState::new(1);
+        let (a, b) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+        for addr in &[a, b] {
+            state.wallets.insert(
+                *addr,
+                Wallet {
+                    address: *addr,
+                    shares: U256::from(100),
+                    voting_power: U256::from(100),
+                    ..Default::default()
+                },
+            );
+        }
+        let mut stake = BTreeMap::new();
+        stake.insert(a, U256::from(100));
+        stake.insert(b, U256::from(100));
+        for index in 1..=3 {
+            state.epochs.insert(
+                index,
+                Epoch {
+                    index,
+                    apr: 0.3,
+                    minted: U256::from(index),
+                    total: U256::from(200),
+                    stake: stake.clone(),
+                    tm: index * 100,
+                    block_number: index,
+                    tx: H256::from_low_u64_be(index),
+                },
+            );
+        }
+        state.epoch_index = 4;
+        state
+    }
+
+    #[test]
+    pub fn it_summarizes_the_dao() {
+        let home = HomeSummary::from_state(&state());
+        assert_eq!(home.members, 2);
+        assert_eq!(home.minted, U256::from(6));
+        assert_eq!(home.staked, U256::from(206));
+        assert_eq!(
+            home.epochs.keys().cloned().collect::<Vec<u64>>(),
+            vec![2, 3]
+        );
+        assert_eq!(home.epochs[&3].members, 2);
+        let rewards = RewardsTable::from_state(&state());
+        assert_eq!(rewards.epochs.len(), 3);
+    }
+
+    #[test]
+    pub fn it_builds_wallet_page() {
+        let state = state();
+        let addr = H160::from_low_u64_be(1);
+        let page = WalletPage::from_state(&state, &addr, 0).unwrap();
+        assert_eq!(page.wallet.address, addr);
+        assert_eq!(page.votes_total, U256::from(200));
+        assert_eq!(page.epochs.len(), 3);
+        assert_eq!(page.epochs[0].stake, Some(U256::from(100)));
+        assert!(WalletPage::from_state(&state, &H160::from_low_u64_be(3), 0).is_none());
+        assert!(VotingPage::from_state(&state, 1).is_none());
+    }
+
+    #[test]
+    pub fn it_groups_votings_as_of_the_time() {
+        let mut state = state();
+        let v = Voting {
+            primary: true,
+            vote_id: 1,
+            tm: 1000,
+            votes_total: U256::from(200),
+            ..Default::default()
+        };
+        state.votings.insert(v.as_u64(), v);
+        let page = VotingsPage::from_state(&state, 1000);
+        assert_eq!((page.total, page.pending.len()), (1, 1));
+        assert!(page.rejected.is_empty());
+        let later = 1000 + 8 * 24 * 3600;
+        let page = VotingsPage::from_state(&state, later);
+        assert!(page.pending.is_empty());
+        assert!(page.rejected[0].expired);
+        let page = UnstakesPage::from_state(&state, later);
+        assert!(page.queue.items.is_empty());
+    }
+
+    #[test]
+    pub fn it_serves_snapshot_with_voting_page() {
+        let mut state = state();
+        let mut v = Voting {
+            primary: true,
+            vote_id: 1,
+            ..Default::default()
+        };
+        v.snapshot.insert(H160::from_low_u64_be(1), U256::from(100));
+        state.votings.insert(v.as_u64(), v.clone());
+        // the state does not grow with the number of votings times the number of wallets
+        let json = serde_json::to_string(&state).unwrap();
+        let back: AppState = serde_json::from_str(&json).unwrap();
+        assert!(back.votings[&v.as_u64()].snapshot.is_empty());
+
+        let page = VotingPage::from_state(&state, v.as_u64()).unwrap();
+        let json = serde_json::to_string(&page).unwrap();
+        let page: VotingPage = serde_json::from_str(&json).unwrap();
+        assert_eq!(page.snapshot, v.snapshot);
+        let screen = crate::screens::voting::Screen::new(page);
+        assert_eq!(screen.page.voting.snapshot, v.snapshot);
+    }
+}
@@ -0,0 +1,327 @@
+use crate::events::Api3;
+use crate::participation::ratio;
+use crate::state::{OnChainEvent, Voting};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use web3::types::{H160, U256};
+
+/// share of the cast votes in favour, required for the voting to pass
+pub const DEFAULT_SUPPORT: f64 = 0.5;
+/// share of the total voting power in favour, required for the primary voting
+pub const DEFAULT_PRIMARY_QUORUM: f64 = 0.5;
+/// share of the total voting power in favour, required for the secondary voting
+pub const DEFAULT_SECONDARY_QUORUM: f64 = 0.15;
+
+/// Tallies of the voting right after the vote was cast
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VotePoint {
+    pub tm: u64,
+    pub block_number: u64,
+    pub voter: H160,
+    pub supports: bool,
+    pub stake: U256,
+    /// voting power in favour, cast so far
+    pub yes: U256,
+    /// voting power against, cast so far
+    pub no: U256,
+    /// share of the cast votes in favour, from 0 to 1
+    pub support: f64,
+    /// share of the total voting power in favour, from 0 to 1
+    pub quorum: f64,
+}
+
+/// Threshold the voting has to be above to pass
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Threshold {
+    Support,
+    Quorum,
+}
+
+/// The vote which moved the voting over or under the threshold
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Crossing {
+    /// index of the point of the timeline
+    pub index: usize,
+    pub tm: u64,
+    pub threshold: Threshold,
+    /// whether the threshold was reached or lost
+    pub reached: bool,
+}
+
+/// Final vote of the member
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Voter {
+    pub address: H160,
+    pub supports: bool,
+    pub stake: U256,
+    /// share of the total voting power, from 0 to 1
+    pub share: f64,
+    /// whether the outcome would be different without this vote
+    pub decisive: bool,
+}
+
+/// How the voting developed, vote after vote
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoteTimeline {
+    pub support_required: f64,
+    pub min_quorum: f64,
+    pub votes_total: U256,
+    pub points: Vec<VotePoint>,
+    pub crossings: Vec<Crossing>,
+    /// voters ordered by their stake, the largest first
+    pub voters: Vec<Voter>,
+    /// whether the votes that were cast make the voting pass
+    pub passes: bool,
+}
+
+impl VoteTimeline {
+    pub fn new(v: &Voting, events: &[OnChainEvent]) -> Self {
+        let (support_required, min_quorum) = match &v.details {
+            Some(d) => (d.support_required, d.min_quorum),
+            None if v.primary => (DEFAULT_SUPPORT, DEFAULT_PRIMARY_QUORUM),
+            None => (DEFAULT_SUPPORT, DEFAULT_SECONDARY_QUORUM),
+        };
+        let mut tl = Self {
+            support_required,
+            min_quorum,
+            votes_total: v.votes_total,
+            points: vec![],
+            crossings: vec![],
+            voters: vec![],
+            passes: false,
+        };
+
+        // the last vote of the member replaces the previous one
+        let mut cast: BTreeMap<H160, (bool, U256)> = BTreeMap::new();
+        let (mut yes, mut no) = (U256::from(0), U256::from(0));
+        let (mut has_support, mut has_quorum) = (false, false);
+        for e in events {
+            let (voter, supports, stake) = match &e.entry {
+                Api3::CastVote {
+                    voter,
+                    supports,
+                    stake,
+                    ..
+                } => (*voter, *supports, *stake),
+                _ => continue,
+            };
+            if let Some((was, prev)) = cast.insert(voter, (supports, stake)) {
+                match was {
+                    true => yes = yes.saturating_sub(prev),
+                    false => no = no.saturating_sub(prev),
+                }
+            }
+            match supports {
+                true => yes += stake,
+                false => no += stake,
+            }
+            let point = VotePoint {
+                tm: e.tm,
+                block_number: e.block_number,
+                voter,
+                supports,
+                stake,
+                yes,
+                no,
+                support: ratio(yes, yes + no),
+                quorum: ratio(yes, v.votes_total),
+            };
+            let index = tl.points.len();
+            if tl.is_supported(yes, no) != has_support {
+                has_support = !has_support;
+                tl.crossings.push(Crossing {
+                    index,
+                    tm: e.tm,
+                    threshold: Threshold::Support,
+                    reached: has_support,
+                });
+            }
+            if tl.has_quorum(yes) != has_quorum {
+                has_quorum = !has_quorum;
+                tl.crossings.push(Crossing {
+                    index,
+                    tm: e.tm,
+                    threshold: Threshold::Quorum,
+                    reached: has_quorum,
+                });
+            }
+            tl.points.push(point);
+        }
+
+        tl.passes = tl.outcome(yes, no);
+        let mut voters: Vec<Voter> = cast
+            .iter()
+            .map(|(address, (supports, stake))| {
+                let without = match supports {
+                    true => tl.outcome(yes.saturating_sub(*stake), no),
+                    false => tl.outcome(yes, no.saturating_sub(*stake)),
+                };
+                Voter {
+                    address: *address,
+                    supports: *supports,
+                    stake: *stake,
+                    share: ratio(*stake, v.votes_total),
+                    decisive: without != tl.passes,
+                }
+            })
+            .collect();
+        voters.sort_by_key(|x| std::cmp::Reverse(x.stake));
+        tl.voters = voters;
+        tl
+    }
+
+    /// share of the cast votes in favour is above the required support
+    pub fn is_supported(&self, yes: U256, no: U256) -> bool {
+        ratio(yes, yes + no) > self.support_required
+    }
+
+    /// share of the total voting power in favour is above the minimal quorum
+    pub fn has_quorum(&self, yes: U256) -> bool {
+        ratio(yes, self.votes_total) > self.min_quorum
+    }
+
+    pub fn outcome(&self, yes: U256, no: U256) -> bool {
+        self.is_supported(yes, no) && self.has_quorum(yes)
+    }
+
+    /// voters whose removal would change the outcome
+    pub fn decisive(&self) -> Vec<&Voter> {
+        self.voters.iter().filter(|v| v.decisive).collect()
+    }
+
+    /// first vote since which the threshold was kept until the end
+    pub fn reached(&self, threshold: Threshold) -> Option<&Crossing> {
+        match self
+            .crossings
+            .iter()
+            .rev()
+            .find(|c| c.threshold == threshold)
+        {
+            Some(c) if c.reached => Some(c),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::VotingAgent;
+    use crate::fees::TxFee;
+    use crate::state::VotingDetails;
+    use web3::types::H256;
+
+    fn tokens(n: u64) -> U256 {
+        U256::from(n) * U256::exp10(18)
+    }
+
+    fn vote(tm: u64, voter: u64, supports: bool, stake: u64) -> OnChainEvent {
+        OnChainEvent {
+            entry: Api3::CastVote {
+                agent: VotingAgent::Secondary,
+                vote_id: U256::from(1),
+                voter: H160::from_low_u64_be(voter),
+                supports,
+                stake: tokens(stake),
+            },
+            tm,
+            block_number: tm,
+            tx: H256::from_low_u64_be(tm),
+            log_index: 0,
+            fees: TxFee {
+                gas_price: U256::from(0),
+                gas: U256::from(0),
+                gas_used: None,
+                usd: None,
+            },
+        }
+    }
+
+    fn voting() -> Voting {
+        Voting {
+            primary: false,
+            votes_total: tokens(1000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    pub fn it_builds_cumulative_curve() {
+        let events = vec![
+            vote(1, 1, true, 100),
+            vote(2, 2, false, 50),
+            vote(3, 3, true, 100),
+        ];
+        let tl = VoteTimeline::new(&voting(), &events);
+        assert_eq!(tl.points.len(), 3);
+        assert_eq!(tl.points[2].yes, tokens(200));
+        assert_eq!(tl.points[2].no, tokens(50));
+        assert!((tl.points[2].quorum - 0.2).abs() < 1e-9);
+        assert!((tl.points[1].support - 100.0 / 150.0).abs() < 1e-9);
+        assert!(tl.passes);
+        // support is reached with the first vote, quorum of 15% with the third
+        assert_eq!(tl.reached(Threshold::Support).map(|c| c.index), Some(0));
+        assert_eq!(tl.reached(Threshold::Quorum).map(|c| c.index), Some(2));
+    }
+
+    #[test]
+    pub fn it_finds_decisive_voters() {
+        let events = vec![
+            vote(1, 1, true, 120),
+            vote(2, 2, true, 40),
+            vote(3, 3, false, 30),
+            vote(4, 4, true, 5),
+        ];
+        let tl = VoteTimeline::new(&voting(), &events);
+        assert!(tl.passes);
+        // without the largest voter neither quorum nor support is there,
+        // without the second one 12.5% is below the quorum
+        let decisive: Vec<H160> = tl.decisive().iter().map(|v| v.address).collect();
+        assert_eq!(
+            decisive,
+            vec![H160::from_low_u64_be(1), H160::from_low_u64_be(2)]
+        );
+        assert_eq!(tl.voters[0].address, H160::from_low_u64_be(1));
+        assert_eq!(tl.voters.len(), 4);
+    }
+
+    #[test]
+    pub fn it_uses_thresholds_of_the_voting() {
+        let mut v = voting();
+        v.primary = true;
+        v.details = Some(VotingDetails {
+            start_date: 0,
+            support_required: 0.5,
+            min_quorum: 0.15,
+            voting_power: tokens(1000),
+            action: None,
+            user_voting_power_at: U256::from(0),
+            discussion_url: "".to_owned(),
+        });
+        let events = vec![vote(1, 1, true, 200), vote(2, 2, false, 100)];
+        let tl = VoteTimeline::new(&v, &events);
+        assert_eq!(tl.min_quorum, 0.15);
+        // 20% is below the default quorum of the primary voting, but above the one of the voting
+        assert!(tl.passes);
+        assert_eq!(tl.reached(Threshold::Quorum).map(|c| c.index), Some(0));
+    }
+
+    #[test]
+    pub fn it_tracks_lost_thresholds() {
+        let events = vec![
+            vote(1, 1, true, 200),
+            vote(2, 2, false, 300),
+            vote(3, 1, false, 200),
+        ];
+        let tl = VoteTimeline::new(&voting(), &events);
+        assert!(!tl.passes);
+        assert!(tl.reached(Threshold::Support).is_none());
+        assert!(tl.reached(Threshold::Quorum).is_none());
+        // changed vote replaces the previous one
+        assert_eq!(tl.points[2].yes, U256::from(0));
+        assert_eq!(tl.points[2].no, tokens(500));
+        assert_eq!(tl.voters.len(), 2);
+        // rejecting voters do not decide: the voting fails without either of them
+        assert!(tl.decisive().is_empty());
+    }
+}
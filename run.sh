@@ -64,6 +64,18 @@ export STATIC_DIR=$(pwd)/client/dist
         cargo run --release -- $@
 }
 
+[[ "$1" == "run-local-networks" ]] && {
+    shift
+    export CACHE_DIR=$(pwd)/.cache
+    mkdir -p $CACHE_DIR
+
+    cd server
+    CONFIG=$(pwd)/networks.example.toml \
+    LOG_LEVEL=api3tracker=debug,api3tracker::web3sync=info,info \
+    RUST_BACKTRACE=full \
+        cargo run --release -- $@
+}
+
 [[ "$1" == "run-local-rinkeby" ]] && {
     shift
     export CACHE_DIR=$(pwd)/.cache
@@ -121,7 +133,7 @@ export STATIC_DIR=$(pwd)/client/dist
 
 [[ "$1" == "cache-mainnet" ]] && {
     export SSH_HOST="root@enormous.cloud"
-    ssh $SSH_HOST 'docker exec -i api3tracker-mainnet /usr/src/app/api3tracker --dump unknown'
+    ssh $SSH_HOST 'docker exec -i api3tracker-mainnet /usr/src/app/api3tracker export --unknown'
 }
 
 [[ "$1" == "publish-rinkeby" ]] && {
@@ -133,5 +145,5 @@ export STATIC_DIR=$(pwd)/client/dist
 
 [[ "$1" == "cache-rinkeby" ]] && {
     export SSH_HOST="root@rinkeby.enormous.cloud"
-    ssh $SSH_HOST 'docker exec -i api3tracker-rinkeby /usr/src/app/api3tracker --dump unknown'
+    ssh $SSH_HOST 'docker exec -i api3tracker-rinkeby /usr/src/app/api3tracker export --unknown'
 }
@@ -0,0 +1,43 @@
+use client::api::{ApiError, ErrorCode};
+use thiserror::Error;
+
+/// Failure of the tracker request
+#[derive(Error, Debug)]
+pub enum Error {
+    /// tracker could not be reached or the response could not be read
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    /// versioned API responded with the error
+    #[error("API error {status}: {}", .error.message)]
+    Api { status: u16, error: ApiError },
+    /// unversioned API responded with the error message
+    #[error("tracker error {status}: {message}")]
+    Tracker { status: u16, message: String },
+    /// response is not what the route returns
+    #[error("decode error: {0}")]
+    Decode(#[from] serde_json::Error),
+    /// filter could not be converted into the query string
+    #[error("query error: {0}")]
+    Query(#[from] serde_urlencoded::ser::Error),
+    /// event stream failed
+    #[error("websocket error: {0}")]
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for Error {
+    fn from(err: tokio_tungstenite::tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(err))
+    }
+}
+
+impl Error {
+    /// code of the versioned API error
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            Self::Api { error, .. } => Some(error.code),
+            _ => None,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
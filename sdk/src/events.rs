@@ -0,0 +1,99 @@
+use crate::error::Result;
+use client::state::OnChainEvent;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio_tungstenite::tungstenite::Message;
+use web3::types::H160;
+
+/// Filter of the streamed events, all events if empty
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EventFilter {
+    /// types of the events, i.e. "Staked" or "VotedYes"
+    pub events: Vec<String>,
+    /// events that involve any of these wallets
+    pub wallets: Vec<H160>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, e: &OnChainEvent) -> bool {
+        (self.events.is_empty() || self.events.iter().any(|x| *x == kind(e)))
+            && (self.wallets.is_empty()
+                || e.entry
+                    .get_wallets()
+                    .iter()
+                    .any(|w| self.wallets.contains(w)))
+    }
+}
+
+/// type of the event, as it is serialized
+pub fn kind(e: &OnChainEvent) -> String {
+    match serde_json::to_value(&e.entry) {
+        Ok(v) => v["type"].as_str().unwrap_or_default().to_owned(),
+        Err(_) => String::new(),
+    }
+}
+
+/// events from the websocket messages, other messages are skipped
+pub fn decode<S>(messages: S, filter: EventFilter) -> impl Stream<Item = Result<OnChainEvent>>
+where
+    S: Stream<Item = std::result::Result<Message, tokio_tungstenite::tungstenite::Error>>,
+{
+    messages.filter_map(move |msg| {
+        let res = match msg {
+            Ok(Message::Text(text)) => match serde_json::from_str::<OnChainEvent>(&text) {
+                Ok(e) if filter.matches(&e) => Some(Ok(e)),
+                Ok(_) => None,
+                Err(err) => Some(Err(err.into())),
+            },
+            Ok(_) => None,
+            Err(err) => Some(Err(err.into())),
+        };
+        futures::future::ready(res)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client::events::Api3;
+
+    fn staked(user: H160) -> OnChainEvent {
+        let json = serde_json::json!({
+            "entry": {
+                "type": "StakedV0",
+                "user": user,
+                "amount": "0x64",
+                "minted_shares": "0x64",
+            },
+            "tm": 1,
+            "block_number": 2,
+            "tx": web3::types::H256::zero(),
+            "log_index": 0,
+            "fees": { "gasPrice": "0x1", "gas": "0x1" },
+        });
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    pub fn it_filters_events() {
+        let user = H160::from_low_u64_be(7);
+        let e = staked(user);
+        assert!(matches!(e.entry, Api3::StakedV0 { .. }));
+        assert!(EventFilter::default().matches(&e));
+        let by_kind = EventFilter {
+            events: vec!["StakedV0".to_owned()],
+            ..Default::default()
+        };
+        assert!(by_kind.matches(&e));
+        let other = EventFilter {
+            events: vec!["Unstaked".to_owned()],
+            wallets: vec![user],
+        };
+        assert!(!other.matches(&e));
+        let by_wallet = EventFilter {
+            wallets: vec![user],
+            ..Default::default()
+        };
+        assert!(by_wallet.matches(&e));
+    }
+}
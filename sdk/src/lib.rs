@@ -0,0 +1,433 @@
+//! Typed client of the API3 DAO Tracker.
+//! Responses are decoded into the same types the tracker is built from,
+//! so services do not need to copy them
+pub mod error;
+pub mod events;
+
+pub use crate::error::{Error, Result};
+pub use crate::events::EventFilter;
+pub use client::api::{Page, PageRequest, VotingFilter, WalletFilter};
+
+use client::api::{Envelope, ErrorEnvelope};
+use client::events::{voting_to_string, VotingAgent};
+use client::participation::Participation;
+use client::poolhistory::PoolHistory;
+use client::projections::Projection;
+use client::routes::{Payload, Route};
+use client::search::Hit;
+use client::state::{
+    AppState, Epoch, OnChainEvent, QuarantinedEvent, TimelockEntry, Voting, Wallet,
+};
+use client::timeline::WalletTimeline;
+use client::unstakes::UnstakeQueue;
+use client::vesting::VestingSchedule;
+use client::views::{HomeSummary, RewardsTable, VotingPage, WalletPage};
+use futures::{Future, Stream, TryStreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use web3::types::H160;
+
+/// Parameters of the stake projections
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectionQuery {
+    /// number of epochs to project, up to 520
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub epochs: Option<usize>,
+    /// change of the total stake every epoch, as a share
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change: Option<f64>,
+    /// stake of the wallet in tokens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stake: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet: Option<H160>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Wrapped<T> {
+    result: T,
+}
+
+#[derive(Debug, Deserialize)]
+struct Failure {
+    error: String,
+}
+
+/// Client of the tracker of one network
+#[derive(Debug, Clone)]
+pub struct Tracker {
+    http: reqwest::Client,
+    /// root of the network, without the trailing slash
+    base: String,
+}
+
+impl Tracker {
+    /// client of the network, served at the given URL, i.e. `https://enormous.cloud/dao/api3/tracker`
+    pub fn new(base_url: &str) -> Self {
+        Self::with_client(base_url, reqwest::Client::new())
+    }
+
+    pub fn with_client(base_url: &str, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            base: base_url.trim_end_matches('/').to_owned(),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base, path)
+    }
+
+    async fn fetch(&self, path: &str) -> Result<(u16, Vec<u8>)> {
+        let res = self.http.get(self.url(path)).send().await?;
+        let status = res.status().as_u16();
+        Ok((status, res.bytes().await?.to_vec()))
+    }
+
+    /// result of the versioned API
+    async fn v1<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let (status, body) = self.fetch(&format!("api/v1/{}", path)).await?;
+        if status >= 400 {
+            return match serde_json::from_slice::<ErrorEnvelope>(&body) {
+                Ok(e) => Err(Error::Api {
+                    status,
+                    error: e.error,
+                }),
+                Err(_) => Err(Error::Tracker {
+                    status,
+                    message: String::from_utf8_lossy(&body).into_owned(),
+                }),
+            };
+        }
+        let envelope: Envelope<T> = serde_json::from_slice(&body)?;
+        Ok(envelope.result)
+    }
+
+    /// response of the unversioned API, with the error message on failure
+    async fn raw<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let (status, body) = self.fetch(path).await?;
+        if status >= 400 {
+            let message = match serde_json::from_slice::<Failure>(&body) {
+                Ok(f) => f.error,
+                Err(_) => String::from_utf8_lossy(&body).into_owned(),
+            };
+            return Err(Error::Tracker { status, message });
+        }
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    /// result of the unversioned API
+    async fn wrapped<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let res: Wrapped<T> = self.raw(path).await?;
+        Ok(res.result)
+    }
+
+    /// full state of the DAO
+    pub async fn state(&self) -> Result<AppState> {
+        self.v1("state").await
+    }
+
+    /// page of the members
+    pub async fn wallets(&self, filter: &WalletFilter, page: &PageRequest) -> Result<Page<Wallet>> {
+        let query = query(filter, page)?;
+        self.v1(&format!("wallets?{}", query)).await
+    }
+
+    /// all members that match the filter, requested page by page
+    pub fn all_wallets(&self, filter: WalletFilter) -> impl Stream<Item = Result<Wallet>> + '_ {
+        paginate(move |page| {
+            let filter = filter.clone();
+            async move { self.wallets(&filter, &page).await }
+        })
+    }
+
+    pub async fn wallet(&self, address: &H160) -> Result<Wallet> {
+        self.v1(&format!("wallets/{:?}", address)).await
+    }
+
+    /// page of the votings
+    pub async fn votings(&self, filter: &VotingFilter, page: &PageRequest) -> Result<Page<Voting>> {
+        let query = query(filter, page)?;
+        self.v1(&format!("votings?{}", query)).await
+    }
+
+    /// all votings that match the filter, requested page by page
+    pub fn all_votings(&self, filter: VotingFilter) -> impl Stream<Item = Result<Voting>> + '_ {
+        paginate(move |page| {
+            let filter = filter.clone();
+            async move { self.votings(&filter, &page).await }
+        })
+    }
+
+    pub async fn voting(&self, agent: &VotingAgent, vote_id: u64) -> Result<Voting> {
+        self.v1(&format!("votings/{}", voting_to_string(agent, vote_id)))
+            .await
+    }
+
+    /// epochs with their rewards by index
+    pub async fn rewards(&self) -> Result<BTreeMap<u64, Epoch>> {
+        self.v1("rewards").await
+    }
+
+    /// OpenAPI document of the versioned API
+    pub async fn openapi(&self) -> Result<serde_json::Value> {
+        self.raw("api/v1/openapi.json").await
+    }
+
+    /// vesting schedule of the wallet rewards
+    pub async fn wallet_rewards(&self, address: &H160) -> Result<VestingSchedule> {
+        self.wrapped(&format!("api/wallets/{:?}/rewards", address))
+            .await
+    }
+
+    pub async fn wallet_timelocks(&self, address: &H160) -> Result<Vec<TimelockEntry>> {
+        self.wrapped(&format!("api/wallets/{:?}/timelocks", address))
+            .await
+    }
+
+    /// balance history of the wallet
+    pub async fn wallet_timeline(&self, address: &H160) -> Result<WalletTimeline> {
+        self.wrapped(&format!("api/wallets/{:?}/timeline", address))
+            .await
+    }
+
+    pub async fn pool_history(&self) -> Result<PoolHistory> {
+        self.wrapped("api/pool/history").await
+    }
+
+    /// turnout of the votings and participation of the members
+    pub async fn participation(&self) -> Result<Participation> {
+        self.wrapped("api/analytics/participation").await
+    }
+
+    pub async fn projections(&self, params: &ProjectionQuery) -> Result<Projection> {
+        let query = serde_urlencoded::to_string(params)?;
+        self.wrapped(&format!("api/projections?{}", query)).await
+    }
+
+    /// wallets, votings and transactions that match the phrase
+    pub async fn search(&self, phrase: &str, limit: Option<usize>) -> Result<Vec<Hit>> {
+        let query = serde_urlencoded::to_string(&[
+            ("q", Some(phrase.to_owned())),
+            ("limit", limit.map(|n| n.to_string())),
+        ])?;
+        self.wrapped(&format!("api/search?{}", query)).await
+    }
+
+    /// scheduled unstakes by day
+    pub async fn unstakes(&self) -> Result<UnstakeQueue> {
+        self.wrapped("api/unstakes").await
+    }
+
+    /// status of the background jobs of the server
+    pub async fn jobs(&self) -> Result<Vec<serde_json::Value>> {
+        self.wrapped("api/jobs").await
+    }
+
+    /// events that could not be applied to the state
+    pub async fn quarantine(&self) -> Result<Vec<QuarantinedEvent>> {
+        self.wrapped("api/diagnostics/quarantine").await
+    }
+
+    /// events without effect on the state
+    pub async fn unhandled(&self) -> Result<Vec<OnChainEvent>> {
+        self.wrapped("api/events/unhandled").await
+    }
+
+    /// view model of the home page
+    pub async fn home(&self) -> Result<HomeSummary> {
+        self.raw("api/views/home").await
+    }
+
+    /// view model of the rewards page
+    pub async fn rewards_table(&self) -> Result<RewardsTable> {
+        self.raw("api/views/rewards").await
+    }
+
+    /// view model of the wallet page
+    pub async fn wallet_page(&self, address: &H160) -> Result<WalletPage> {
+        self.raw(&format!("api/views/wallets/{:?}", address)).await
+    }
+
+    /// view model of the voting page
+    pub async fn voting_page(&self, agent: &VotingAgent, vote_id: u64) -> Result<VotingPage> {
+        let id = voting_to_string(agent, vote_id);
+        self.raw(&format!("api/views/votings/{}", id)).await
+    }
+
+    /// data the page of the route is built from
+    pub async fn page(&self, route: &Route) -> Result<Payload> {
+        let (status, body) = self.fetch(&route.api()).await?;
+        let json = String::from_utf8_lossy(&body);
+        if status >= 400 {
+            let message = match serde_json::from_str::<Failure>(&json) {
+                Ok(f) => f.error,
+                Err(_) => json.into_owned(),
+            };
+            return Err(Error::Tracker { status, message });
+        }
+        Ok(Payload::parse(route, &json)?)
+    }
+
+    /// new events of the network as they are synced.
+    /// The server streams them only in watching mode
+    pub async fn events(
+        &self,
+        filter: EventFilter,
+    ) -> Result<impl Stream<Item = Result<OnChainEvent>>> {
+        let url = format!("{}/ws", self.base)
+            .replacen("https://", "wss://", 1)
+            .replacen("http://", "ws://", 1);
+        let (socket, _) = tokio_tungstenite::connect_async(url).await?;
+        Ok(events::decode(socket, filter))
+    }
+}
+
+/// query string of the filter and the page
+fn query<F: Serialize>(filter: &F, page: &PageRequest) -> Result<String> {
+    let parts = [
+        serde_urlencoded::to_string(filter)?,
+        serde_urlencoded::to_string(page)?,
+    ];
+    Ok(parts
+        .iter()
+        .filter(|s| !s.is_empty())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("&"))
+}
+
+/// items of all pages, the next page is requested when the previous one is consumed
+fn paginate<'a, T, F, Fut>(fetch: F) -> impl Stream<Item = Result<T>> + 'a
+where
+    T: 'a,
+    F: Fn(PageRequest) -> Fut + 'a,
+    Fut: Future<Output = Result<Page<T>>> + 'a,
+{
+    let first = Some(PageRequest::new(0, client::api::DEFAULT_PAGE_LIMIT));
+    futures::stream::try_unfold((fetch, first), |(fetch, next)| async move {
+        let req = match next {
+            Some(req) => req,
+            None => return Ok::<_, Error>(None),
+        };
+        let page = fetch(req).await?;
+        let next = page.next();
+        let items = futures::stream::iter(page.items.into_iter().map(Ok));
+        Ok(Some((items, (fetch, next))))
+    })
+    .try_flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client::api::{ApiError, ErrorCode};
+    use futures::StreamExt;
+    use std::net::SocketAddr;
+    use warp::Filter;
+
+    fn wallets(n: u64) -> Vec<Wallet> {
+        (1..=n)
+            .map(|i| Wallet {
+                address: H160::from_low_u64_be(i),
+                supporter: i % 2 == 0,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    // tracker with the list of wallets and the error responses
+    async fn serve() -> SocketAddr {
+        let list = warp::path!("api" / "v1" / "wallets")
+            .and(warp::query::<BTreeMap<String, String>>())
+            .map(|q: BTreeMap<String, String>| {
+                let filter = WalletFilter {
+                    supporter: q.get("supporter").map(|x| x == "true"),
+                    ..Default::default()
+                };
+                let page = PageRequest {
+                    offset: q.get("offset").and_then(|x| x.parse().ok()),
+                    limit: q.get("limit").and_then(|x| x.parse().ok()),
+                };
+                let items = wallets(5).into_iter().filter(|w| filter.matches(w));
+                let page = PageRequest::new(page.offset.unwrap(), 2)
+                    .page(items)
+                    .unwrap();
+                warp::reply::json(&Envelope {
+                    result: page,
+                    last_block: 1,
+                })
+            });
+        let missing = warp::path!("api" / "v1" / "wallets" / String).map(|_| {
+            let body = ErrorEnvelope {
+                error: ApiError::new(ErrorCode::NotMember),
+            };
+            warp::reply::with_status(warp::reply::json(&body), warp::http::StatusCode::NOT_FOUND)
+        });
+        let search = warp::path!("api" / "search").map(|| {
+            let body = serde_json::json!({ "error": "Missing search query" });
+            warp::reply::with_status(
+                warp::reply::json(&body),
+                warp::http::StatusCode::BAD_REQUEST,
+            )
+        });
+        let (addr, server) =
+            warp::serve(list.or(missing).or(search)).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        addr
+    }
+
+    #[test]
+    pub fn it_builds_query() {
+        let filter = VotingFilter {
+            executed: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            query(&filter, &PageRequest::new(10, 5)).unwrap(),
+            "executed=true&offset=10&limit=5"
+        );
+        assert_eq!(
+            query(&WalletFilter::default(), &PageRequest::default()).unwrap(),
+            ""
+        );
+    }
+
+    #[tokio::test]
+    async fn it_walks_pages() {
+        let addr = serve().await;
+        let tracker = Tracker::new(&format!("http://{}/", addr));
+        let all: Vec<Wallet> = tracker
+            .all_wallets(WalletFilter::default())
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 5);
+        let supporters = WalletFilter {
+            supporter: Some(true),
+            ..Default::default()
+        };
+        let mut stream = Box::pin(tracker.all_wallets(supporters));
+        let mut n = 0;
+        while let Some(w) = stream.next().await {
+            assert!(w.unwrap().supporter);
+            n += 1;
+        }
+        assert_eq!(n, 2);
+    }
+
+    #[tokio::test]
+    async fn it_returns_errors() {
+        let addr = serve().await;
+        let tracker = Tracker::new(&format!("http://{}", addr));
+        let err = tracker.wallet(&H160::zero()).await.unwrap_err();
+        assert_eq!(err.code(), Some(ErrorCode::NotMember));
+        match tracker.search("", None).await.unwrap_err() {
+            Error::Tracker { status, message } => {
+                assert_eq!((status, message.as_str()), (400, "Missing search query"))
+            }
+            err => panic!("unexpected {}", err),
+        }
+    }
+}
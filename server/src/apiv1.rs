@@ -0,0 +1,509 @@
+use crate::httpcache::{self, Conditions, Entry, ResponseCache};
+use crate::store::Store;
+use client::api::{
+    ApiError, Envelope, ErrorCode, ErrorEnvelope, Page, PageRequest, VotingFilter, WalletFilter,
+};
+use client::routes::Route;
+use client::state::{AppState, Epoch, Voting, Wallet};
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use warp::http::StatusCode;
+use warp::Filter;
+use warp::Reply;
+
+pub fn error(code: ErrorCode) -> warp::reply::Response {
+    let body = ErrorEnvelope {
+        error: ApiError::new(code),
+    };
+    let status = StatusCode::from_u16(code.status()).unwrap();
+    warp::reply::with_status(warp::reply::json(&body), status).into_response()
+}
+
+pub fn envelope<T: Serialize>(app: &AppState, result: T) -> warp::reply::Response {
+    let body = Envelope {
+        result,
+        last_block: app.last_block,
+    };
+    warp::reply::json(&body).into_response()
+}
+
+/// parameter of the operation: name, location, type and description
+type Param = (&'static str, &'static str, &'static str, &'static str);
+
+const PAGE_PARAMS: [Param; 2] = [
+    ("offset", "query", "integer", "number of items to skip"),
+    (
+        "limit",
+        "query",
+        "integer",
+        "number of items on the page, up to 1000",
+    ),
+];
+
+/// endpoint of the specification, with its response and errors
+struct Operation {
+    path: &'static str,
+    summary: &'static str,
+    params: Vec<Param>,
+    result: Value,
+    errors: &'static [ErrorCode],
+}
+
+fn operation<T: JsonSchema>(
+    gen: &mut SchemaGenerator,
+    path: &'static str,
+    summary: &'static str,
+    params: Vec<Param>,
+    errors: &'static [ErrorCode],
+) -> Operation {
+    let schema = gen.subschema_for::<Envelope<T>>();
+    Operation {
+        path,
+        summary,
+        params,
+        result: serde_json::to_value(schema).unwrap(),
+        errors,
+    }
+}
+
+/// OpenAPI document of the versioned API, generated from the response types
+pub fn openapi() -> Value {
+    let settings = SchemaSettings::draft07().with(|s| {
+        s.definitions_path = "#/components/schemas/".to_owned();
+    });
+    let mut gen = settings.into_generator();
+    let failure = serde_json::to_value(gen.subschema_for::<ErrorEnvelope>()).unwrap();
+    let wallet = ("address", "path", "string", "address of the wallet");
+    let voting = (
+        "id",
+        "path",
+        "string",
+        "voting ID, i.e. p-12 for primary or s-3 for secondary",
+    );
+    let wallet_filter = vec![
+        (
+            "supporter",
+            "query",
+            "boolean",
+            "members that never withdrew",
+        ),
+        ("vested", "query", "boolean", "members with vested tokens"),
+        ("delegating", "query", "boolean", "members that delegated"),
+    ];
+    let voting_filter = vec![
+        (
+            "primary",
+            "query",
+            "boolean",
+            "votings of the primary agent",
+        ),
+        ("executed", "query", "boolean", "executed votings"),
+        ("creator", "query", "string", "address of the creator"),
+    ];
+    let paged = |filter: Vec<Param>| [filter, PAGE_PARAMS.to_vec()].concat();
+    let operations = vec![
+        operation::<AppState>(
+            &mut gen,
+            "/api/v1/state",
+            "Full state of the DAO",
+            vec![],
+            &[],
+        ),
+        operation::<Page<Wallet>>(
+            &mut gen,
+            "/api/v1/wallets",
+            "Members of the DAO, ordered by address",
+            paged(wallet_filter),
+            &[ErrorCode::InvalidQuery],
+        ),
+        operation::<Wallet>(
+            &mut gen,
+            "/api/v1/wallets/{address}",
+            "Member of the DAO",
+            vec![wallet],
+            &[ErrorCode::InvalidAddress, ErrorCode::NotMember],
+        ),
+        operation::<Page<Voting>>(
+            &mut gen,
+            "/api/v1/votings",
+            "Votings, ordered by agent and ID",
+            paged(voting_filter),
+            &[ErrorCode::InvalidQuery],
+        ),
+        operation::<Voting>(
+            &mut gen,
+            "/api/v1/votings/{id}",
+            "Voting with its votes",
+            vec![voting],
+            &[ErrorCode::InvalidVotingId, ErrorCode::VotingNotFound],
+        ),
+        operation::<BTreeMap<String, Epoch>>(
+            &mut gen,
+            "/api/v1/rewards",
+            "Epochs with their rewards by index",
+            vec![],
+            &[],
+        ),
+    ];
+
+    let mut paths = Map::new();
+    for op in operations {
+        let mut responses = Map::new();
+        responses.insert(
+            "200".to_owned(),
+            json!({
+                "description": op.summary,
+                "content": { "application/json": { "schema": op.result } },
+            }),
+        );
+        for code in op.errors {
+            let errors = responses
+                .entry(code.status().to_string())
+                .or_insert_with(|| {
+                    json!({
+                        "description": "",
+                        "content": { "application/json": { "schema": failure.clone() } },
+                    })
+                });
+            let description = errors["description"].as_str().unwrap_or_default();
+            errors["description"] = Value::String(
+                [description, code.message()]
+                    .iter()
+                    .filter(|s| !s.is_empty())
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            );
+        }
+        let params: Vec<Value> = op
+            .params
+            .iter()
+            .map(|(name, location, kind, description)| {
+                json!({
+                    "name": name,
+                    "in": location,
+                    "required": *location == "path",
+                    "description": description,
+                    "schema": { "type": kind },
+                })
+            })
+            .collect();
+        paths.insert(
+            op.path.to_owned(),
+            json!({ "get": { "summary": op.summary, "parameters": params, "responses": responses } }),
+        );
+    }
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "API3 DAO Tracker",
+            "version": "1",
+            "description": "Paths are relative to the root of the network. Amounts are U256 and addresses are H160, both as 0x-prefixed hex strings",
+        },
+        "paths": paths,
+        "components": { "schemas": gen.definitions() },
+    })
+}
+
+/// raw query string, empty if there is none
+fn query() -> impl Filter<Extract = (String,), Error = std::convert::Infallible> + Clone {
+    warp::query::raw().or(warp::any().map(String::new)).unify()
+}
+
+/// filter and the page of the list from the query string
+fn parse_query<F: DeserializeOwned>(q: &str) -> Option<(F, PageRequest)> {
+    let filter = serde_urlencoded::from_str(q).ok()?;
+    let page = serde_urlencoded::from_str(q).ok()?;
+    Some((filter, page))
+}
+
+/// routes of the versioned API, under /api/v1
+pub fn routes(
+    store: Arc<Store>,
+    cache: Arc<ResponseCache>,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone {
+    let spec = Arc::new(serde_json::to_vec(&openapi()).unwrap());
+    let openapi = warp::path!("openapi.json").map(move || {
+        warp::http::Response::builder()
+            .header("content-type", "application/json")
+            .body(warp::hyper::Body::from(spec.as_ref().clone()))
+            .unwrap()
+    });
+    let state = warp::path!("state").and(httpcache::conditions()).map({
+        let store = store.clone();
+        move |cond: Conditions| {
+            let snapshot = store.load();
+            let entry = cache.get(&snapshot, "api/v1/state", || {
+                Entry::json(&Envelope {
+                    result: snapshot.app.as_ref(),
+                    last_block: snapshot.app.last_block,
+                })
+            });
+            httpcache::reply(&snapshot, &cond, &entry)
+        }
+    });
+    let wallets = warp::path!("wallets").and(query()).map({
+        let store = store.clone();
+        move |q: String| {
+            let app = store.app();
+            let (filter, page): (WalletFilter, PageRequest) = match parse_query(&q) {
+                Some(x) => x,
+                None => return error(ErrorCode::InvalidQuery),
+            };
+            let items = app.wallets.values().filter(|w| filter.matches(w));
+            match page.page(items) {
+                Some(p) => envelope(&app, p),
+                None => error(ErrorCode::InvalidQuery),
+            }
+        }
+    });
+    let wallet = warp::path!("wallets" / String).map({
+        let store = store.clone();
+        move |id: String| {
+            let app = store.app();
+            match Route::parse(&format!("wallets/{}", id), "") {
+                Some(Route::Wallet(addr)) => match app.wallets.get(&addr) {
+                    Some(w) => envelope(&app, w),
+                    None => error(ErrorCode::NotMember),
+                },
+                _ => error(ErrorCode::InvalidAddress),
+            }
+        }
+    });
+    let votings = warp::path!("votings").and(query()).map({
+        let store = store.clone();
+        move |q: String| {
+            let app = store.app();
+            let (filter, page): (VotingFilter, PageRequest) = match parse_query(&q) {
+                Some(x) => x,
+                None => return error(ErrorCode::InvalidQuery),
+            };
+            let items = app.votings.values().filter(|v| filter.matches(v));
+            match page.page(items) {
+                Some(p) => envelope(&app, p),
+                None => error(ErrorCode::InvalidQuery),
+            }
+        }
+    });
+    let voting = warp::path!("votings" / String).map({
+        let store = store.clone();
+        move |id: String| {
+            let app = store.app();
+            match Route::parse(&format!("votings/{}", id), "") {
+                Some(Route::Voting(vote_ref)) => match app.votings.get(&vote_ref) {
+                    Some(v) => envelope(&app, v),
+                    None => error(ErrorCode::VotingNotFound),
+                },
+                _ => error(ErrorCode::InvalidVotingId),
+            }
+        }
+    });
+    let rewards = warp::path!("rewards").map(move || {
+        let app = store.app();
+        envelope(&app, &app.epochs)
+    });
+    let unknown = warp::any().map(|| error(ErrorCode::NotFound));
+    warp::path("api").and(warp::path("v1")).and(
+        openapi
+            .or(state)
+            .unify()
+            .or(wallets)
+            .unify()
+            .or(wallet)
+            .unify()
+            .or(votings)
+            .unify()
+            .or(voting)
+            .unify()
+            .or(rewards)
+            .unify()
+            .or(unknown)
+            .unify(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client::events::{voting_to_u64, VotingAgent};
+    use jsonschema::JSONSchema;
+    use web3::types::{H160, H256, U256};
+
+    fn sample_state() -> AppState {
+        let mut app = AppState::new(1);
+        app.last_block = 12_000_000;
+        let address = H160::from_low_u64_be(0xabc);
+        let mut wallet = Wallet {
+            address,
+            staked: U256::exp10(18) * 1200,
+            shares: U256::exp10(18),
+            ens: Some("member.eth".to_owned()),
+            ..Default::default()
+        };
+        wallet
+            .delegated
+            .insert(H160::from_low_u64_be(1), U256::from(5));
+        app.wallets.insert(address, wallet);
+        let mut voting = Voting {
+            primary: true,
+            vote_id: 7,
+            tx: H256::from_low_u64_be(77),
+            creator: address,
+            title: "Grant".to_owned(),
+            voted_yes: U256::from(100),
+            ..Default::default()
+        };
+        voting.yes.insert(address, U256::from(100));
+        app.votings
+            .insert(voting_to_u64(&VotingAgent::Primary, 7), voting);
+        app
+    }
+
+    async fn get(store: &Arc<Store>, path: &str) -> (StatusCode, Value) {
+        let filter = routes(store.clone(), Arc::new(ResponseCache::new()));
+        let res = warp::test::request().path(path).reply(&filter).await;
+        (res.status(), serde_json::from_slice(res.body()).unwrap())
+    }
+
+    // checks the response against the schema of the operation in the specification
+    fn check(spec: &Value, path: &str, status: StatusCode, body: &Value) {
+        let response = &spec["paths"][path]["get"]["responses"][status.as_str()];
+        let schema = &response["content"]["application/json"]["schema"];
+        assert!(schema.is_object(), "{} {} is not specified", path, status);
+        let root = json!({ "allOf": [schema], "components": spec["components"] });
+        let compiled = JSONSchema::compile(&root).unwrap();
+        let errors: Vec<String> = match compiled.validate(body) {
+            Ok(_) => vec![],
+            Err(errors) => errors
+                .map(|e| format!("{} at {}", e, e.instance_path))
+                .collect(),
+        };
+        assert!(
+            errors.is_empty(),
+            "{} {} does not match: {:?}",
+            path,
+            status,
+            errors
+        );
+    }
+
+    #[tokio::test]
+    async fn it_matches_specification() {
+        let spec = openapi();
+        let store = Arc::new(Store::new(sample_state()));
+        let address = format!("{:?}", H160::from_low_u64_be(0xabc));
+        let cases = vec![
+            ("/api/v1/state", "/api/v1/state".to_owned(), StatusCode::OK),
+            (
+                "/api/v1/wallets",
+                "/api/v1/wallets".to_owned(),
+                StatusCode::OK,
+            ),
+            (
+                "/api/v1/wallets",
+                "/api/v1/wallets?supporter=false&offset=0&limit=1".to_owned(),
+                StatusCode::OK,
+            ),
+            (
+                "/api/v1/wallets",
+                "/api/v1/wallets?limit=many".to_owned(),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                "/api/v1/votings",
+                format!("/api/v1/votings?creator={}&executed=false", address),
+                StatusCode::OK,
+            ),
+            (
+                "/api/v1/wallets/{address}",
+                format!("/api/v1/wallets/{}", address),
+                StatusCode::OK,
+            ),
+            (
+                "/api/v1/wallets/{address}",
+                "/api/v1/wallets/0x12".to_owned(),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                "/api/v1/wallets/{address}",
+                format!("/api/v1/wallets/{:?}", H160::zero()),
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                "/api/v1/votings",
+                "/api/v1/votings".to_owned(),
+                StatusCode::OK,
+            ),
+            (
+                "/api/v1/votings/{id}",
+                "/api/v1/votings/p-7".to_owned(),
+                StatusCode::OK,
+            ),
+            (
+                "/api/v1/votings/{id}",
+                "/api/v1/votings/7".to_owned(),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                "/api/v1/votings/{id}",
+                "/api/v1/votings/s-7".to_owned(),
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                "/api/v1/rewards",
+                "/api/v1/rewards".to_owned(),
+                StatusCode::OK,
+            ),
+        ];
+        for (spec_path, path, status) in cases {
+            let (actual, body) = get(&store, &path).await;
+            assert_eq!(actual, status, "{}", path);
+            check(&spec, spec_path, status, &body);
+        }
+    }
+
+    #[tokio::test]
+    async fn it_filters_lists() {
+        let store = Arc::new(Store::new(sample_state()));
+        let (_, body) = get(&store, "/api/v1/wallets?supporter=true").await;
+        let page: Envelope<Page<Wallet>> = serde_json::from_value(body).unwrap();
+        assert_eq!(page.result.total, 0);
+        let (_, body) = get(&store, "/api/v1/votings?primary=true&limit=5").await;
+        let page: Envelope<Page<Voting>> = serde_json::from_value(body).unwrap();
+        assert_eq!((page.result.total, page.result.limit), (1, 5));
+        assert_eq!(page.result.items[0].vote_id, 7);
+        assert_eq!(page.last_block, 12_000_000);
+    }
+
+    #[tokio::test]
+    async fn it_rejects_invalid_voting_ids() {
+        let spec = openapi();
+        let store = Arc::new(Store::new(sample_state()));
+        for id in &["-12", "p-", "p-abc", "x-1"] {
+            let path = format!("/api/v1/votings/{}", id);
+            let (status, body) = get(&store, &path).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", path);
+            check(&spec, "/api/v1/votings/{id}", status, &body);
+            let body: ErrorEnvelope = serde_json::from_value(body).unwrap();
+            assert_eq!(body.error.code, ErrorCode::InvalidVotingId, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn it_serves_errors_and_specification() {
+        let store = Arc::new(Store::new(sample_state()));
+        let (status, body) = get(&store, "/api/v1/missing").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let body: ErrorEnvelope = serde_json::from_value(body).unwrap();
+        assert_eq!(body.error.code, ErrorCode::NotFound);
+
+        let (status, body) = get(&store, "/api/v1/openapi.json").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, openapi());
+    }
+}
@@ -2,20 +2,129 @@ use clap::arg_enum;
 use structopt::StructOpt;
 
 arg_enum! {
-    #[derive(Debug, Clone)]
-    pub enum DumpMode {
-        Events,
-        Unknown,
-        Snapshot,
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum ExportFormat {
+        Ndjson,
+        Parquet,
+        Sql,
     }
 }
 
+#[derive(Debug, StructOpt, Clone)]
+pub enum SnapshotCommand {
+    /// Scan events of the networks and save them as a new snapshot
+    Create,
+    /// List saved snapshots with their blocks
+    List,
+    /// Remove older snapshots
+    Prune {
+        /// Number of the latest snapshots to keep
+        #[structopt(long, default_value = "1")]
+        keep: usize,
+    },
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub enum CacheCommand {
+    /// Check that cached batches of events could be read
+    Verify {
+        /// Remove broken files, so they would be read from RPC again
+        #[structopt(long)]
+        remove: bool,
+    },
+    /// Remove cached batches that are not read anymore
+    Compact,
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub enum ConfigCommand {
+    /// Check the config, its networks and contracts, report all problems and exit
+    Check,
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct ExportArgs {
+    /// Print only events that are not recognized, with their topics
+    #[structopt(long)]
+    pub unknown: bool,
+    /// Format of the exported events: JSON lines, Parquet columns or SQL script
+    #[structopt(long, default_value = "ndjson", possible_values = &ExportFormat::variants(), case_insensitive = true)]
+    pub format: ExportFormat,
+    /// Output folder, events are printed to stdout if it is not set
+    #[structopt(long)]
+    pub output: Option<String>,
+    /// Max number of events in one file, 0 to write all events into one file
+    #[structopt(long, default_value = "100000")]
+    pub rotate: usize,
+    /// Export only events after the previous export to the same folder
+    #[structopt(long)]
+    pub incremental: bool,
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub enum Command {
+    /// Sync the networks and serve them over HTTP (default)
+    Serve,
+    /// Read all events of the networks into the cache without serving them
+    Sync,
+    /// Snapshots of events, which are restored instead of scanning
+    Snapshot(SnapshotCommand),
+    /// Cached batches of events
+    Cache(CacheCommand),
+    /// Config of the networks
+    Config(ConfigCommand),
+    /// Export events of the networks as JSON lines, Parquet files or SQL script
+    Export(ExportArgs),
+    /// Write events and the state of the networks into the database, continuing after
+    /// the last mirrored event
+    Mirror {
+        /// Database URL (postgres:// or sqlite://), overrides the mirror section of the config
+        #[structopt(long, env = "DATABASE_URL")]
+        url: Option<String>,
+    },
+    /// Print the wallet with its events
+    Wallet {
+        /// Address of the wallet
+        address: String,
+    },
+    /// Print the voting with its events
+    Voting {
+        /// Voting ID with the agent prefix, i.e. p-12 or s-3
+        id: String,
+    },
+    /// Apply events up to the block and print the summary of the state
+    Replay {
+        /// The last block to apply events from
+        #[structopt(long)]
+        to_block: u64,
+        /// Save the whole state as JSON file
+        #[structopt(long)]
+        output: Option<String>,
+    },
+}
+
 #[derive(Debug, StructOpt, Clone)]
 #[structopt(name = "api3dao-tracker", about = "API3 DAO Tracker")]
 pub struct Args {
     /// Net listening address of HTTP server
     #[structopt(long, default_value = "0.0.0.0:8000", env = "LISTEN")]
     pub listen: String,
+    /// TOML file with DAO deployments to track, one per network.
+    /// Network and contracts are taken from the options below if it is not set
+    #[structopt(long, env = "CONFIG")]
+    pub config: Option<String>,
+    /// Name of the network, networks are served with this URL prefix
+    #[structopt(long, default_value = "mainnet", env = "NETWORK")]
+    pub network: String,
+    /// Block explorer for links to transactions and addresses
+    #[structopt(long, default_value = "https://etherscan.io", env = "EXPLORER_URL")]
+    pub explorer_url: String,
+    /// Whether the network is a testnet (or TESTNET=1)
+    #[structopt(long)]
+    pub testnet: bool,
+    /// Public path of the server, used for links between networks
+    #[structopt(long, default_value = "/", env = "PUBLIC_URL")]
+    pub public_url: String,
     /// Static folder to serve web client files
     #[structopt(long, default_value = "./../client/dist", env = "STATIC_DIR")]
     pub static_dir: String,
@@ -31,6 +140,10 @@ pub struct Args {
     /// Ethereum JSON+RPC batch size for reading
     #[structopt(long, default_value = "500", env = "RPC_BATCH_SIZE")]
     pub rpc_batch_size: u64,
+    /// Stop on the first event that fails to be processed instead of quarantining it
+    /// (or STRICT=1)
+    #[structopt(long)]
+    pub strict: bool,
     /// USDC token contract address
     #[structopt(long, default_value = "", env = "ADDR_USDC_TOKEN")]
     pub address_usdc_token: String,
@@ -68,24 +181,38 @@ pub struct Args {
     /// Max block to stop contract events listening
     #[structopt(long, env = "MAX_BLOCK")]
     pub max_block: Option<u64>,
-    /// Dump (show logs) mode instead of running HTTP server
-    #[structopt(short, long, possible_values = &DumpMode::variants(), case_insensitive = true)]
-    pub dump: Option<DumpMode>,
     /// Continue listening to blockchain events
     #[structopt(short, long)]
     pub watch: bool,
     /// Disable ENS reserve resolution for the wallets
     #[structopt(long)]
     pub no_ens: bool,
+    /// Max number of background jobs running at the same time
+    #[structopt(long, default_value = "2", env = "MAX_JOBS")]
+    pub max_jobs: usize,
+    #[structopt(subcommand)]
+    pub cmd: Option<Command>,
+}
+
+fn env_flag(name: &str) -> bool {
+    match std::env::var(name) {
+        Ok(x) => x == "1" || x == "true",
+        Err(_) => false,
+    }
 }
 
 pub fn parse() -> anyhow::Result<Args> {
     dotenv::dotenv().ok();
     let log_level: String = std::env::var("LOG_LEVEL").unwrap_or("info".to_owned());
+    // stdout is left for the output of the commands
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::new(log_level))
+        .with_writer(std::io::stderr)
         .init();
-    let res = Args::from_args();
+    let mut res = Args::from_args();
+    // flags cannot take values from environment
+    res.strict |= env_flag("STRICT");
+    res.testnet |= env_flag("TESTNET");
     tracing::debug!("{:?}", res);
     Ok(res)
 }
@@ -36,7 +36,7 @@ pub fn save(
     if cache_dir.len() == 0 || blocks_time.len() == 0 {
         return Ok(());
     }
-    let f = File::create(filename(cache_dir, chain_id)).expect("Unable to create blockstime file");
+    let f = File::create(filename(cache_dir, chain_id))?;
     serde_json::to_writer(&f, blocks_time)?;
     Ok(())
 }
@@ -4,7 +4,7 @@ use std::io::Read;
 use std::path::Path;
 use web3::types::{Log, H160};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct BlockBatch {
     pub from: u64,
     pub to: u64,
@@ -38,11 +38,14 @@ pub async fn load(
     checksum: u32,
     b: &BlockBatch,
 ) -> anyhow::Result<Vec<Log>> {
-    let mut f =
-        File::open(filename(cache_dir, chain_id, checksum, &b)).expect("Unable to open file");
+    let fln = filename(cache_dir, chain_id, checksum, &b);
+    let mut f = File::open(&fln)?;
     let mut data = String::new();
-    f.read_to_string(&mut data).expect("Reading failure");
-    let logs: Vec<Log> = serde_json::from_str(&data).expect("JSON parsing failure");
+    f.read_to_string(&mut data)?;
+    let logs: Vec<Log> = match serde_json::from_str(&data) {
+        Ok(x) => x,
+        Err(e) => return Err(anyhow::Error::msg(format!("{}: {}", fln, e))),
+    };
     Ok(logs)
 }
 
@@ -56,8 +59,123 @@ pub async fn save(
     if cache_dir.len() == 0 {
         return Ok(());
     }
-    let f =
-        File::create(filename(cache_dir, chain_id, checksum, &b)).expect("Unable to create file");
+    let f = File::create(filename(cache_dir, chain_id, checksum, &b))?;
     serde_json::to_writer(&f, logs)?;
     Ok(())
 }
+
+/// batch file in the cache folder
+#[derive(Clone, Debug)]
+pub struct CachedBatch {
+    pub path: String,
+    pub chain_id: u64,
+    pub checksum: u32,
+    pub batch: BlockBatch,
+    pub size: u64,
+}
+
+/// chain ID, checksum and blocks from the name of the batch file
+pub fn parse_filename(name: &str) -> Option<(u64, u32, BlockBatch)> {
+    let parts: Vec<&str> = name
+        .strip_prefix("chain")?
+        .strip_suffix(".json")?
+        .split('-')
+        .collect();
+    if parts.len() != 4 {
+        return None;
+    }
+    let batch = BlockBatch {
+        from: parts[1].parse().ok()?,
+        to: parts[2].parse().ok()?,
+    };
+    Some((parts[0].parse().ok()?, parts[3].parse().ok()?, batch))
+}
+
+/// batch files in the cache folder, ordered by blocks
+pub fn list(cache_dir: &str) -> Vec<CachedBatch> {
+    let mut res = vec![];
+    if cache_dir.is_empty() {
+        return res;
+    }
+    let entries = match std::fs::read_dir(cache_dir) {
+        Ok(x) => x,
+        Err(_) => return res,
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if let Some((chain_id, checksum, batch)) = parse_filename(&name) {
+            res.push(CachedBatch {
+                path: entry.path().display().to_string(),
+                chain_id,
+                checksum,
+                batch,
+                size: entry.metadata().map(|m| m.len()).unwrap_or_default(),
+            });
+        }
+    }
+    res.sort_by_key(|x| (x.chain_id, x.checksum, x.batch.from, x.batch.to));
+    res
+}
+
+/// number of logs in the batch file
+pub fn verify(path: &str) -> anyhow::Result<usize> {
+    let data = std::fs::read_to_string(path)?;
+    let logs: Vec<Log> = serde_json::from_str(&data)?;
+    Ok(logs.len())
+}
+
+/// batches of the chain that would not be read by the scanner: batches of other contracts,
+/// and batches that were replaced with the longer batch from the same block
+pub fn stale(batches: &[CachedBatch], chain_id: u64, checksum: u32) -> Vec<CachedBatch> {
+    batches
+        .iter()
+        .filter(|b| b.chain_id == chain_id)
+        .filter(|b| {
+            b.checksum != checksum
+                || batches.iter().any(|x| {
+                    x.chain_id == chain_id
+                        && x.checksum == checksum
+                        && x.batch.from == b.batch.from
+                        && x.batch.to > b.batch.to
+                })
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached(name: &str) -> CachedBatch {
+        let (chain_id, checksum, batch) = parse_filename(name).unwrap();
+        CachedBatch {
+            path: name.to_owned(),
+            chain_id,
+            checksum,
+            batch,
+            size: 0,
+        }
+    }
+
+    #[test]
+    pub fn it_finds_stale_batches() {
+        let b = BlockBatch { from: 100, to: 199 };
+        assert_eq!(filename("/c", 1, 42, &b), "/c/chain1-100-199-42.json");
+        assert_eq!(parse_filename("chain1-100-199-42.json"), Some((1, 42, b)));
+        assert_eq!(parse_filename("blockstime1.json"), None);
+
+        let batches: Vec<CachedBatch> = [
+            "chain1-0-99-42.json",
+            "chain1-100-150-42.json",
+            "chain1-100-199-42.json",
+            "chain1-0-99-7.json",
+            "chain4-0-99-7.json",
+        ]
+        .iter()
+        .map(|x| cached(x))
+        .collect();
+        let paths: Vec<String> = stale(&batches, 1, 42).into_iter().map(|x| x.path).collect();
+        assert_eq!(paths, vec!["chain1-100-150-42.json", "chain1-0-99-7.json"]);
+    }
+}
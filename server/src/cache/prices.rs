@@ -33,8 +33,7 @@ pub fn save(cache_dir: &str, chain_id: u64, values: &BTreeMap<H256, TxFee>) -> a
     if cache_dir.len() == 0 || values.len() == 0 {
         return Ok(());
     }
-    let f =
-        File::create(filename(cache_dir, chain_id)).expect("Unable to create prices cache file");
+    let f = File::create(filename(cache_dir, chain_id))?;
     serde_json::to_writer(&f, values)?;
     Ok(())
 }
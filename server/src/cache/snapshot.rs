@@ -46,6 +46,64 @@ fn latest(cache_dir: &str, chain_id: u64) -> Option<String> {
     result
 }
 
+/// saved snapshot of the chain
+#[derive(Debug, Clone)]
+pub struct SnapshotFile {
+    pub path: String,
+    pub chain_id: u64,
+    pub size: u64,
+}
+
+/// snapshots of all chains in the cache folder, the oldest first
+pub fn list(cache_dir: &str) -> Vec<SnapshotFile> {
+    let mut res = vec![];
+    let dirs = match std::fs::read_dir(if cache_dir.is_empty() { "." } else { cache_dir }) {
+        Ok(x) => x,
+        Err(_) => return res,
+    };
+    for dir in dirs.flatten() {
+        let name = dir.file_name().to_string_lossy().to_string();
+        let chain_id = match name.strip_prefix("snapshot").map(|x| x.parse::<u64>()) {
+            Some(Ok(x)) => x,
+            _ => continue,
+        };
+        let files = match std::fs::read_dir(dir.path()) {
+            Ok(x) => x,
+            Err(_) => continue,
+        };
+        for f in files.flatten() {
+            let path = f.path().display().to_string();
+            if path.ends_with(".json") {
+                let size = f.metadata().map(|m| m.len()).unwrap_or_default();
+                res.push(SnapshotFile {
+                    path,
+                    chain_id,
+                    size,
+                });
+            }
+        }
+    }
+    res.sort_by(|a, b| (a.chain_id, &a.path).cmp(&(b.chain_id, &b.path)));
+    res
+}
+
+/// removes all snapshots except the latest ones of every chain, returns removed files
+pub fn prune(cache_dir: &str, keep: usize) -> anyhow::Result<Vec<SnapshotFile>> {
+    let all = list(cache_dir);
+    let mut removed = vec![];
+    for (i, f) in all.iter().enumerate() {
+        let newer = all[i + 1..]
+            .iter()
+            .filter(|x| x.chain_id == f.chain_id)
+            .count();
+        if newer >= keep {
+            std::fs::remove_file(&f.path)?;
+            removed.push(f.clone());
+        }
+    }
+    Ok(removed)
+}
+
 pub fn load(cache_dir: &str, chain_id: u64) -> Option<Archive> {
     // find the latest snapshot in the snapshots folder
     let path = match latest(cache_dir, chain_id) {
@@ -55,7 +113,10 @@ pub fn load(cache_dir: &str, chain_id: u64) -> Option<Archive> {
             return None;
         }
     };
+    load_file(&path)
+}
 
+pub fn load_file(path: &str) -> Option<Archive> {
     if let Ok(mut f) = File::open(path) {
         let mut data = String::new();
         match f.read_to_string(&mut data) {
@@ -87,16 +148,39 @@ pub fn save(cache_dir: &str, chain_id: u64, archive: &Archive) -> anyhow::Result
     }
     let now = SystemTime::now();
     let fln = filename(cache_dir, chain_id, now);
-    println!("saving snapshot {}", fln);
+    tracing::info!("saving snapshot {}", fln);
     if let Err(e) = std::fs::create_dir_all(match std::path::Path::new(&fln).parent() {
         Some(x) => x,
         None => return Ok(()),
     }) {
-        println!("snapshot folder failure {:?}", e);
+        tracing::warn!("snapshot folder failure {:?}", e);
         return Ok(());
     }
 
-    let f = File::create(fln).expect("Unable to create snapshot file");
+    let f = File::create(fln)?;
     serde_json::to_writer(&f, archive)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn it_prunes_snapshots() {
+        let dir = std::env::temp_dir().join(format!("snapshots-{}", std::process::id()));
+        let cache_dir = dir.display().to_string();
+        for (chain_id, name) in [(1, "1"), (1, "2"), (1, "3"), (4, "1")] {
+            let folder = dir.join(format!("snapshot{}", chain_id));
+            std::fs::create_dir_all(&folder).unwrap();
+            std::fs::write(folder.join(format!("{}.json", name)), "{}").unwrap();
+        }
+        assert_eq!(list(&cache_dir).len(), 4);
+        let removed = prune(&cache_dir, 1).unwrap();
+        assert_eq!(removed.len(), 2);
+        let left: Vec<String> = list(&cache_dir).into_iter().map(|x| x.path).collect();
+        assert!(left[0].ends_with("snapshot1/3.json"));
+        assert!(left[1].ends_with("snapshot4/1.json"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
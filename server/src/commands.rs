@@ -0,0 +1,431 @@
+use crate::args::{
+    Args, CacheCommand, Command, ConfigCommand, ExportArgs, ExportFormat, SnapshotCommand,
+};
+use crate::cache::{logsbatch, snapshot};
+use crate::config::Config;
+use crate::errors::SyncError;
+use crate::export::{ndjson, parquetfile, sqldump, Exporter, Sink};
+use crate::mirror::MirrorHandle;
+use crate::networks::{address, NetworkConfig};
+use crate::reader::EventHandler;
+use crate::sync::{self, Synced};
+use client::state::{AppState, OnChainEvent, QuarantinedEvent, Voting, Wallet};
+use serde::Serialize;
+use std::sync::Arc;
+
+/// short description of the state, printed after syncing
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub network: String,
+    pub chain_id: u64,
+    pub last_block: u64,
+    pub wallets: usize,
+    pub votings: usize,
+    pub staked: String,
+    pub shares: String,
+    pub quarantined: usize,
+}
+
+impl Summary {
+    pub fn new(network: &str, app: &AppState) -> Self {
+        Self {
+            network: network.to_owned(),
+            chain_id: app.chain_id,
+            last_block: app.last_block,
+            wallets: app.wallets.len(),
+            votings: app.votings.len(),
+            staked: app.get_staked_total().to_string(),
+            shares: app.get_shares_total().to_string(),
+            quarantined: app.quarantine.len(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct WalletOutput<'a> {
+    network: &'a str,
+    wallet: &'a Wallet,
+    events: &'a [OnChainEvent],
+}
+
+#[derive(Debug, Serialize)]
+struct VotingOutput<'a> {
+    network: &'a str,
+    voting: &'a Voting,
+    events: &'a [OnChainEvent],
+}
+
+fn print_json<T: Serialize>(value: &T) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// runs the one-off task for every network of the config
+pub async fn run(cmd: &Command, config: &Config, args: &Args) -> anyhow::Result<()> {
+    let mut found = false;
+    for net in &config.networks {
+        match cmd {
+            Command::Serve => {}
+            Command::Sync => sync(net, args).await?,
+            Command::Snapshot(SnapshotCommand::Create) => snapshot_create(net, args).await?,
+            Command::Snapshot(SnapshotCommand::List) => snapshot_list(net),
+            Command::Snapshot(SnapshotCommand::Prune { keep }) => snapshot_prune(net, *keep)?,
+            Command::Cache(CacheCommand::Verify { remove }) => cache_verify(net, *remove)?,
+            Command::Cache(CacheCommand::Compact) => cache_compact(net).await?,
+            Command::Config(ConfigCommand::Check) => {}
+            Command::Export(opts) => export(net, args, opts).await?,
+            Command::Mirror { url } => mirror(net, config, args, url.as_deref()).await?,
+            Command::Wallet { address } => found |= wallet(net, config, args, address).await?,
+            Command::Voting { id } => found |= voting(net, args, id).await?,
+            Command::Replay { to_block, output } => {
+                replay(net, args, *to_block, output.as_deref()).await?
+            }
+        }
+    }
+    match cmd {
+        Command::Wallet { .. } if !found => Err(anyhow::Error::msg("Not a member of the DAO")),
+        Command::Voting { .. } if !found => Err(anyhow::Error::msg("Invalid voting ID")),
+        _ => Ok(()),
+    }
+}
+
+/// checks the config with all its networks, reporting all problems at once
+pub async fn config_check(args: &Args) -> anyhow::Result<()> {
+    let config = match &args.config {
+        Some(path) => Config::load_unchecked(path, &args.cache_dir)?,
+        None => Config::from_args(args),
+    };
+    let problems = config.check().await;
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    if !problems.is_empty() {
+        return Err(anyhow::Error::msg(format!(
+            "{} problem(s) in config",
+            problems.len()
+        )));
+    }
+    println!("config is valid");
+    Ok(())
+}
+
+/// reads all events of the network into the cache
+async fn sync(net: &NetworkConfig, args: &Args) -> anyhow::Result<()> {
+    let synced = Synced::sync(net, args.strict, false).await?;
+    print_json(&Summary::new(&net.name, &synced.app))
+}
+
+async fn snapshot_create(net: &NetworkConfig, args: &Args) -> anyhow::Result<()> {
+    let a = net.contracts.addresses()?;
+    let (web3, chain_id) = sync::connect(net).await?;
+    let cache_dir = sync::cache_dir(net)?;
+    if cache_dir.is_empty() {
+        return Err(anyhow::Error::msg("cache folder is required for snapshots"));
+    }
+    // new snapshot is built from batches, not from the previous snapshot
+    let mut scanner = sync::scanner(net, &a, chain_id, &cache_dir, args.strict)?.snapshots(false);
+    let mut dumper =
+        crate::dumper::SnapshotBuilder::new(cache_dir.as_str(), chain_id, net.genesis_block);
+    scanner.scan(&web3, &mut dumper).await?;
+    dumper.done();
+    Ok(())
+}
+
+fn snapshot_list(net: &NetworkConfig) {
+    let cache_dir = net.cache_dir.clone().unwrap_or_default();
+    for f in snapshot::list(&cache_dir) {
+        match snapshot::load_file(&f.path) {
+            Some(a) => println!(
+                "{}\tchain {}\tblocks {}..{}\t{} logs\t{} bytes",
+                f.path,
+                f.chain_id,
+                a.start_block,
+                a.end_block,
+                a.logs.len(),
+                f.size
+            ),
+            None => println!("{}\tchain {}\tbroken\t{} bytes", f.path, f.chain_id, f.size),
+        }
+    }
+}
+
+fn snapshot_prune(net: &NetworkConfig, keep: usize) -> anyhow::Result<()> {
+    let cache_dir = net.cache_dir.clone().unwrap_or_default();
+    let removed = snapshot::prune(&cache_dir, keep)?;
+    for f in &removed {
+        println!("removed {}", f.path);
+    }
+    println!("{}: {} snapshots removed", net.name, removed.len());
+    Ok(())
+}
+
+fn cache_verify(net: &NetworkConfig, remove: bool) -> anyhow::Result<()> {
+    let cache_dir = net.cache_dir.clone().unwrap_or_default();
+    let batches = logsbatch::list(&cache_dir);
+    let (mut logs, mut broken) = (0, 0);
+    for b in &batches {
+        match logsbatch::verify(&b.path) {
+            Ok(n) => logs += n,
+            Err(e) => {
+                broken += 1;
+                println!("broken {}: {}", b.path, e);
+                if remove {
+                    std::fs::remove_file(&b.path)?;
+                    println!("removed {}", b.path);
+                }
+            }
+        }
+    }
+    println!(
+        "{}: {} batches, {} logs, {} broken",
+        net.name,
+        batches.len(),
+        logs,
+        broken
+    );
+    if broken > 0 && !remove {
+        return Err(anyhow::Error::msg(format!(
+            "{} broken batches in {}",
+            broken, cache_dir
+        )));
+    }
+    Ok(())
+}
+
+async fn cache_compact(net: &NetworkConfig) -> anyhow::Result<()> {
+    let a = net.contracts.addresses()?;
+    let chain_id = match net.chain_id {
+        Some(x) => x,
+        None => sync::connect(net).await?.1,
+    };
+    let cache_dir = net.cache_dir.clone().unwrap_or_default();
+    let scanner = sync::scanner(net, &a, chain_id, &cache_dir, false)?;
+    let batches = logsbatch::list(&cache_dir);
+    let stale = logsbatch::stale(&batches, chain_id, scanner.cache_checksum());
+    let mut freed = 0;
+    for b in &stale {
+        std::fs::remove_file(&b.path)?;
+        freed += b.size;
+    }
+    println!(
+        "{}: {} of {} batches removed, {} bytes freed",
+        net.name,
+        stale.len(),
+        batches.len(),
+        freed
+    );
+    Ok(())
+}
+
+async fn export(net: &NetworkConfig, args: &Args, opts: &ExportArgs) -> anyhow::Result<()> {
+    let a = net.contracts.addresses()?;
+    let (web3, chain_id) = sync::connect(net).await?;
+    let cache_dir = sync::cache_dir(net)?;
+    let mut scanner = sync::scanner(net, &a, chain_id, &cache_dir, args.strict)?;
+    if opts.unknown {
+        let mut dumper = crate::dumper::Unknown::new();
+        scanner.scan(&web3, &mut dumper).await?;
+        dumper.done();
+        return Ok(());
+    }
+    let dir = opts.output.clone().unwrap_or_default();
+    match opts.format {
+        ExportFormat::Ndjson => {
+            let sink = match dir.is_empty() {
+                true => ndjson::NdjsonFile::stdout(),
+                false => ndjson::NdjsonFile::new(&dir, &net.name, opts.rotate)?,
+            };
+            export_to(net, opts, &dir, sink, &mut scanner, &web3).await
+        }
+        ExportFormat::Parquet => {
+            if dir.is_empty() {
+                return Err(anyhow::Error::msg("output folder is required for parquet"));
+            }
+            let sink = parquetfile::ParquetFile::new(&dir, &net.name, opts.rotate)?;
+            export_to(net, opts, &dir, sink, &mut scanner, &web3).await
+        }
+        ExportFormat::Sql => {
+            let mut app = sync::new_state(net, chain_id);
+            app.pool_info = crate::contracts::Pool::new(&web3, a.api3_pool).read().await;
+            let sink = sqldump::SqlDump::new(&dir, &net.name, app)?;
+            export_to(net, opts, &dir, sink, &mut scanner, &web3).await
+        }
+    }
+}
+
+/// scans events of the network into the sink
+async fn export_to<S: Sink>(
+    net: &NetworkConfig,
+    opts: &ExportArgs,
+    dir: &str,
+    sink: S,
+    scanner: &mut crate::reader::Scanner,
+    web3: &sync::Web3Http,
+) -> anyhow::Result<()> {
+    let mut exporter = Exporter::new(sink, dir, &net.name, opts.incremental);
+    scanner.scan(web3, &mut exporter).await?;
+    let written = exporter.done()?;
+    tracing::info!("{}: {} events exported", net.name, written);
+    Ok(())
+}
+
+/// applies events to the state and sends them to the mirror
+struct Mirrored<'a> {
+    app: &'a mut AppState,
+    mirror: &'a mut MirrorHandle,
+}
+
+impl<'a> EventHandler for Mirrored<'a> {
+    fn on(&mut self, e: OnChainEvent, l: web3::types::Log) -> Result<(), SyncError> {
+        // only events that were applied to the state are mirrored
+        self.app.on(e.clone(), l.clone())?;
+        self.mirror.on(e, l)
+    }
+
+    fn quarantine(&mut self, q: QuarantinedEvent) {
+        self.app.quarantine(q)
+    }
+}
+
+/// writes events that were not mirrored yet and the current state to the database
+async fn mirror(
+    net: &NetworkConfig,
+    config: &Config,
+    args: &Args,
+    url: Option<&str>,
+) -> anyhow::Result<()> {
+    let url =
+        match url.or_else(|| config.mirror.as_ref().map(|m| m.url.as_str())) {
+            Some(x) => x,
+            None => return Err(anyhow::Error::msg(
+                "database is required, set it with --url or in the mirror section of the config",
+            )),
+        };
+    let (mut handle, thread) = MirrorHandle::spawn(url, &net.name)?;
+    let a = net.contracts.addresses()?;
+    let (web3, chain_id) = sync::connect(net).await?;
+    let cache_dir = sync::cache_dir(net)?;
+    let mut scanner = sync::scanner(net, &a, chain_id, &cache_dir, args.strict)?;
+    let mut app = sync::new_state(net, chain_id);
+    // vesting parameters of the pool are required to record rewards unlock time
+    app.pool_info = crate::contracts::Pool::new(&web3, a.api3_pool).read().await;
+    let mut mirrored = Mirrored {
+        app: &mut app,
+        mirror: &mut handle,
+    };
+    scanner.scan(&web3, &mut mirrored).await?;
+    let tokens = net.treasury_tokens(&a)?;
+    let mut wallets = net.treasury_wallets(&a)?;
+    sync::enrich(&web3, &a, &mut app, &tokens, &mut wallets).await;
+    handle.state(Arc::new(app));
+    drop(handle);
+    let written = match thread.join() {
+        Ok(res) => res?,
+        Err(_) => return Err(anyhow::Error::msg("mirror thread panicked")),
+    };
+    tracing::info!("{}: {} events mirrored", net.name, written);
+    Ok(())
+}
+
+/// prints the wallet if it is a member of the DAO in the network
+async fn wallet(
+    net: &NetworkConfig,
+    config: &Config,
+    args: &Args,
+    addr: &str,
+) -> anyhow::Result<bool> {
+    let addr = address("wallet", addr)?;
+    let synced = Synced::scan(net, args.strict, true).await?;
+    let mut w = match synced.app.wallets.get(&addr) {
+        Some(x) => x.clone(),
+        None => {
+            tracing::warn!("{}: {:?} is not a member of the DAO", net.name, addr);
+            return Ok(false);
+        }
+    };
+    if !args.no_ens {
+        let ens_cache_dir = match config.cache.ens {
+            true => synced.cache_dir.as_str(),
+            false => "",
+        };
+        let ens = crate::ens::ENS::new(&synced.web3, ens_cache_dir).with_ttl(config.cache.ens_ttl);
+        match ens.lookup(&addr).await {
+            Ok(entry) => {
+                w.ens = entry.name;
+                w.ens_records = entry.records;
+                ens.save()?;
+            }
+            Err(e) => tracing::warn!("ENS for {:?} failed: {}", addr, e),
+        }
+    }
+    let events = synced.app.wallets_events.get(&addr);
+    print_json(&WalletOutput {
+        network: &net.name,
+        wallet: &w,
+        events: events.map(|x| x.as_slice()).unwrap_or_default(),
+    })?;
+    Ok(true)
+}
+
+/// prints the voting if it exists in the network
+async fn voting(net: &NetworkConfig, args: &Args, id: &str) -> anyhow::Result<bool> {
+    let (agent, vote_id) = match client::events::voting_from_str(id) {
+        Some(x) => x,
+        None => {
+            return Err(anyhow::Error::msg(format!(
+                "invalid voting ID {:?}, expected p-N or s-N",
+                id
+            )))
+        }
+    };
+    let vote_ref = client::events::voting_to_u64(&agent, vote_id);
+    let synced = Synced::scan(net, args.strict, true).await?;
+    let mut v = match synced.app.votings.get(&vote_ref) {
+        Some(x) => x.clone(),
+        None => {
+            tracing::warn!("{}: voting {} is not found", net.name, id);
+            return Ok(false);
+        }
+    };
+    if v.details.is_none() {
+        let conv = crate::contracts::Convenience::new(&synced.web3, synced.addresses.convenience);
+        if let Some(data) = conv
+            .get_voting_static_data(v.primary, v.creator, v.vote_id)
+            .await
+        {
+            v.votes_total = data.voting_power; // adjust with precise #
+            v.details = Some(data.into_details());
+        }
+    }
+    let events = synced.app.votings_events.get(&vote_ref);
+    print_json(&VotingOutput {
+        network: &net.name,
+        voting: &v,
+        events: events.map(|x| x.as_slice()).unwrap_or_default(),
+    })?;
+    Ok(true)
+}
+
+/// applies events up to the block, ignoring snapshots
+async fn replay(
+    net: &NetworkConfig,
+    args: &Args,
+    to_block: u64,
+    output: Option<&str>,
+) -> anyhow::Result<()> {
+    if to_block < net.genesis_block {
+        return Err(anyhow::Error::msg(format!(
+            "{}: block {} is before genesis block {}",
+            net.name, to_block, net.genesis_block
+        )));
+    }
+    let mut net = net.clone();
+    net.max_block = Some(to_block);
+    let synced = Synced::scan(&net, args.strict, false).await?;
+    if let Some(path) = output {
+        let f = std::fs::File::create(path)?;
+        serde_json::to_writer(&f, &synced.app)?;
+        tracing::info!("state is saved to {}", path);
+    }
+    print_json(&Summary::new(&net.name, &synced.app))
+}
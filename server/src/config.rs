@@ -0,0 +1,448 @@
+use crate::args::Args;
+use crate::mirror::MirrorConfig;
+use crate::networks::{address, ContractsConfig, NetworkConfig};
+use crate::webhooks::WebhookConfig;
+use client::state::NetworkInfo;
+use serde::Deserialize;
+use std::time::Duration;
+use web3::types::{BlockNumber, H160};
+
+/// Intervals of background jobs in watching mode, in seconds
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RefreshConfig {
+    pub treasuries: u64,
+    pub votings: u64,
+    pub pool: u64,
+    pub circulation: u64,
+    pub ens: u64,
+    /// max random delay of each job run
+    pub jitter: u64,
+}
+
+impl Default for RefreshConfig {
+    fn default() -> Self {
+        Self {
+            treasuries: 20 * 60,
+            votings: 20 * 60,
+            pool: 20 * 60,
+            circulation: 20 * 60,
+            ens: 15 * 60,
+            jitter: 60,
+        }
+    }
+}
+
+impl RefreshConfig {
+    pub fn every(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    pub fn problems(&self) -> Vec<String> {
+        let intervals = [
+            ("treasuries", self.treasuries),
+            ("votings", self.votings),
+            ("pool", self.pool),
+            ("circulation", self.circulation),
+            ("ens", self.ens),
+        ];
+        intervals
+            .iter()
+            .filter(|(_, secs)| *secs == 0)
+            .map(|(name, _)| format!("refresh.{} must be positive", name))
+            .collect()
+    }
+}
+
+/// Local cache of the responses from the networks
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// root folder of the cache, overrides CACHE_DIR
+    pub dir: Option<String>,
+    /// whether to cache ENS names of the wallets
+    pub ens: bool,
+    /// seconds before ENS names and their records are read again
+    pub ens_ttl: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            ens: true,
+            ens_ttl: crate::ens::DEFAULT_TTL,
+        }
+    }
+}
+
+/// Config file with all served DAO deployments
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    #[serde(rename = "network")]
+    pub networks: Vec<NetworkConfig>,
+    #[serde(default)]
+    pub refresh: RefreshConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default, rename = "webhook")]
+    pub webhooks: Vec<WebhookConfig>,
+    /// database where events and the state are mirrored
+    pub mirror: Option<MirrorConfig>,
+}
+
+impl Config {
+    /// reads config without validation, so all of its problems could be reported
+    pub fn parse_unchecked(src: &str, cache_dir: &str) -> anyhow::Result<Self> {
+        let mut cfg: Self = toml::from_str(src)?;
+        let cache_dir = cfg.cache.dir.clone().unwrap_or(cache_dir.to_owned());
+        // each network has its own cache namespace
+        for net in &mut cfg.networks {
+            if net.cache_dir.is_none() {
+                net.cache_dir = Some(match cache_dir.as_str() {
+                    "" => net.name.clone(),
+                    _ => format!("{}/{}", cache_dir, net.name),
+                });
+            }
+        }
+        Ok(cfg)
+    }
+
+    pub fn parse(src: &str, cache_dir: &str) -> anyhow::Result<Self> {
+        Self::parse_unchecked(src, cache_dir)?.validated()
+    }
+
+    /// problems of the config that could be found without connecting to networks
+    pub fn problems(&self) -> Vec<String> {
+        let mut res = vec![];
+        if self.networks.is_empty() {
+            res.push("no networks in config".to_owned());
+        }
+        for (i, net) in self.networks.iter().enumerate() {
+            // networks are served with their names as URL prefixes
+            let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+            if net.name.is_empty() || !net.name.chars().all(valid) {
+                res.push(format!("invalid network name {:?}", net.name));
+            }
+            if self.networks[..i].iter().any(|x| x.name == net.name) {
+                res.push(format!("duplicate network {}", net.name));
+            }
+            res.extend(net.problems());
+        }
+        res.extend(self.refresh.problems());
+        let names = self.names();
+        for hook in &self.webhooks {
+            res.extend(hook.problems(&names));
+        }
+        if let Some(mirror) = &self.mirror {
+            res.extend(mirror.problems());
+        }
+        res
+    }
+
+    /// fails with all problems of the config
+    pub fn validated(self) -> anyhow::Result<Self> {
+        let problems = self.problems();
+        if !problems.is_empty() {
+            return Err(anyhow::Error::msg(problems.join("; ")));
+        }
+        Ok(self)
+    }
+
+    /// problems of the config, including the ones that were found in networks
+    pub async fn check(&self) -> Vec<String> {
+        let mut res = self.problems();
+        for net in &self.networks {
+            res.extend(check_network(net).await);
+        }
+        res
+    }
+
+    pub fn load_unchecked(path: &str, cache_dir: &str) -> anyhow::Result<Self> {
+        let src = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::Error::msg(format!("{}: {}", path, e)))?;
+        Self::parse_unchecked(&src, cache_dir)
+            .map_err(|e| anyhow::Error::msg(format!("{}: {}", path, e)))
+    }
+
+    pub fn load(path: &str, cache_dir: &str) -> anyhow::Result<Self> {
+        Self::load_unchecked(path, cache_dir)?.validated()
+    }
+
+    /// single network, configured with command line and environment variables
+    pub fn from_args(args: &Args) -> Self {
+        let circulation = args
+            .address_circulation
+            .clone()
+            .or(args.address_api3_circulation.clone());
+        Self {
+            networks: vec![NetworkConfig {
+                name: args.network.clone(),
+                rpc_endpoint: args.rpc_endpoint.clone(),
+                watch_endpoint: args.watch_endpoint.clone(),
+                rpc_batch_size: args.rpc_batch_size,
+                explorer_url: Some(args.explorer_url.clone()).filter(|x| !x.is_empty()),
+                testnet: args.testnet,
+                chain_id: None,
+                genesis_block: args.genesis_block,
+                max_block: args.max_block,
+                cache_dir: Some(args.cache_dir.clone()),
+                contracts: ContractsConfig {
+                    api3_token: args.address_api3_token.clone(),
+                    api3_pool: args.address_api3_pool.clone(),
+                    usdc_token: args.address_usdc_token.clone(),
+                    convenience: args.address_convenience.clone(),
+                    voting_primary: args.address_voting1.clone(),
+                    agent_primary: args.address_agent1.clone(),
+                    voting_secondary: args.address_voting2.clone(),
+                    agent_secondary: args.address_agent2.clone(),
+                    circulation,
+                },
+                tokens: vec![],
+                treasuries: vec![],
+            }],
+            refresh: RefreshConfig::default(),
+            cache: CacheConfig::default(),
+            webhooks: vec![],
+            mirror: None,
+        }
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.networks.iter().map(|x| x.name.clone()).collect()
+    }
+
+    /// network description for the client
+    pub fn info(&self, net: &NetworkConfig, public_url: &str) -> NetworkInfo {
+        NetworkInfo {
+            name: net.name.clone(),
+            explorer_url: net.explorer_url.clone(),
+            testnet: net.testnet,
+            networks: self.names(),
+            public_url: public_url.to_owned(),
+        }
+    }
+}
+
+async fn has_code<T: web3::Transport>(
+    web3: &web3::Web3<T>,
+    addr: H160,
+    block: u64,
+) -> web3::Result<bool> {
+    let code = web3
+        .eth()
+        .code(addr, Some(BlockNumber::Number(block.into())))
+        .await?;
+    Ok(!code.0.is_empty())
+}
+
+/// first block where the contract has code, which it has at the `deployed` block.
+/// Requires an archive node
+async fn deployment_block<T: web3::Transport>(
+    web3: &web3::Web3<T>,
+    addr: H160,
+    deployed: u64,
+) -> web3::Result<u64> {
+    let (mut lo, mut hi) = (0, deployed);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if has_code(web3, addr, mid).await? {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    Ok(hi)
+}
+
+/// problems of the network that are found with its RPC endpoint:
+/// chain ID, contracts without code and contracts deployed before the genesis block
+pub async fn check_network(net: &NetworkConfig) -> Vec<String> {
+    let mut res = vec![];
+    let mut problem = |msg: String| res.push(format!("{}: {}", net.name, msg));
+    let transport = match web3::transports::Http::new(net.rpc_endpoint.as_str()) {
+        Ok(x) => x,
+        Err(e) => {
+            problem(format!("rpc_endpoint: {}", e));
+            return res;
+        }
+    };
+    let web3 = web3::Web3::new(transport);
+    match web3.eth().chain_id().await {
+        Ok(chain_id) => {
+            let chain_id = chain_id.as_u64();
+            match net.chain_id {
+                Some(expected) if expected != chain_id => problem(format!(
+                    "chain ID is {}, {} is expected",
+                    chain_id, expected
+                )),
+                None if net.testnet && chain_id == 1 => {
+                    problem("testnet is connected to the mainnet".to_owned())
+                }
+                _ => {}
+            }
+        }
+        Err(e) => {
+            problem(format!("rpc_endpoint is not available: {}", e));
+            return res;
+        }
+    }
+
+    // events are read from all contracts except tokens
+    let mut contracts: Vec<(String, H160, bool)> = vec![];
+    for (name, value) in net.contracts.named() {
+        if let Ok(addr) = address(name, value) {
+            let events = name != "api3_token" && name != "usdc_token";
+            contracts.push((format!("contracts.{}", name), addr, events));
+        }
+    }
+    for t in &net.tokens {
+        if let Ok(addr) = address(&t.symbol, &t.address) {
+            contracts.push((format!("token {}", t.symbol), addr, false));
+        }
+    }
+    let genesis = net.genesis_block;
+    for (name, addr, events) in contracts {
+        match web3.eth().code(addr, None).await {
+            Ok(code) if code.0.is_empty() => problem(format!("{} {:?} has no code", name, addr)),
+            Ok(_) if events && genesis > 0 => match has_code(&web3, addr, genesis - 1).await {
+                Ok(false) => {}
+                Ok(true) => {
+                    let deployed = match deployment_block(&web3, addr, genesis - 1).await {
+                        Ok(x) => format!("block {}", x),
+                        Err(_) => "earlier block".to_owned(),
+                    };
+                    problem(format!(
+                        "genesis_block {} is after deployment of {} at {}",
+                        genesis, name, deployed
+                    ))
+                }
+                Err(e) => problem(format!(
+                    "{} code at block {} is not available (archive node is required): {}",
+                    name,
+                    genesis - 1,
+                    e
+                )),
+            },
+            Ok(_) => {}
+            Err(e) => problem(format!("{} code is not available: {}", name, e)),
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[[network]]
+name = "mainnet"
+rpc_endpoint = "http://localhost:8545"
+explorer_url = "https://etherscan.io"
+genesis_block = 12786500
+
+[network.contracts]
+api3_token = "0b38210ea11411557c13457D4dA7dC6ea731B88a"
+api3_pool = "6dd655f10d4b9e242ae186d9050b68f725c76d76"
+usdc_token = "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"
+convenience = "95087266018b9637aff3d76d4e0cad7e52c19636"
+voting_primary = "db6c812e439ce5c740570578681ea7aadba5170b"
+agent_primary = "d9f80bdb37e6bad114d747e60ce6d2aaf26704ae"
+voting_secondary = "1c8058e72e4902b3431ef057e8d9a58a73f26372"
+agent_secondary = "556ecbb0311d350491ba0ec7e019c354d7723ce0"
+
+[[network]]
+name = "rinkeby"
+rpc_endpoint = "http://localhost:8546"
+testnet = true
+genesis_block = 8842400
+cache_dir = "/tmp/rinkeby"
+
+[network.contracts]
+api3_token = "d3e7bc3f88a39af6cb19394ccc4c0705f2c6f0c2"
+api3_pool = "f10952f418da8da5ece292b1b82a20479633f173"
+usdc_token = "eb8f08a975ab53e34d8a0330e0d34de942c95926"
+convenience = "269e1baceb37d22aaddddd3ed83e54ae6e8c2672"
+voting_primary = "2c5c6557d4b9874411adf1c126cb3bae7242c1c0"
+agent_primary = "43e78f2911c3a8db79cf03269b691f27a2551e2e"
+voting_secondary = "51e9737734b7ae1456ce174f046fb784c3a8d8b1"
+agent_secondary = "36d09b485fe0c3a24e92fa24cfdc0e8ebf981ef9"
+"#;
+
+    #[test]
+    pub fn it_reads_networks() {
+        let cfg = Config::parse(CONFIG, "/cache").unwrap();
+        assert_eq!(cfg.names(), vec!["mainnet", "rinkeby"]);
+        let (mainnet, rinkeby) = (&cfg.networks[0], &cfg.networks[1]);
+        assert_eq!(mainnet.cache_dir.as_deref(), Some("/cache/mainnet"));
+        assert_eq!(rinkeby.cache_dir.as_deref(), Some("/tmp/rinkeby"));
+        assert_eq!(mainnet.rpc_batch_size, 500);
+        assert!(rinkeby.testnet);
+        assert!(rinkeby.contracts.addresses().unwrap().circulation.is_none());
+        let info = cfg.info(rinkeby, "/dao/api3/tracker/");
+        assert_eq!(info.network_url("mainnet"), "/dao/api3/tracker/mainnet/");
+        assert_eq!(
+            info.base_url().as_deref(),
+            Some("/dao/api3/tracker/rinkeby/")
+        );
+        assert_eq!(cfg.refresh.ens, 15 * 60);
+        assert!(cfg.cache.ens);
+        assert_eq!(cfg.cache.ens_ttl, 24 * 60 * 60);
+    }
+
+    #[test]
+    pub fn it_rejects_duplicates() {
+        let twice = format!("{}{}", CONFIG, CONFIG.replace("rinkeby", "goerli"));
+        assert!(Config::parse(&twice, "/cache").is_err());
+    }
+
+    #[test]
+    pub fn it_reads_settings() {
+        let src = format!(
+            r#"
+[refresh]
+treasuries = 0
+jitter = 5
+
+[cache]
+dir = "/var/cache/tracker"
+ens = false
+ens_ttl = 3600
+
+[[webhook]]
+url = "https://example.com/hook"
+events = ["StartVote", "ExecuteVote"]
+
+[[webhook]]
+url = "example.com"
+networks = ["goerli"]
+{}
+[[network.token]]
+symbol = "DAI"
+address = "6b175474e89094c44da98b954eedeac495271d0f"
+decimals = 18
+
+[[network.treasury]]
+name = "Grants"
+address = "0xnothex"
+"#,
+            CONFIG
+        );
+        let cfg = Config::parse_unchecked(&src, "/cache").unwrap();
+        assert_eq!(cfg.refresh.jitter, 5);
+        assert_eq!(cfg.refresh.pool, 20 * 60);
+        assert!(!cfg.cache.ens);
+        assert_eq!(cfg.cache.ens_ttl, 3600);
+        assert_eq!(
+            cfg.networks[0].cache_dir.as_deref(),
+            Some("/var/cache/tracker/mainnet")
+        );
+        assert_eq!(cfg.webhooks.len(), 2);
+        assert_eq!(cfg.networks[1].tokens[0].decimals, Some(18));
+        // all problems are reported at once
+        let problems = cfg.problems();
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+        assert!(Config::parse(&src, "/cache").is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use client::abi::Abi;
 use client::nice;
 use client::state::{Api3Circulation, Api3PoolInfo, VotingStaticData};
 use tracing::warn;
@@ -67,8 +68,8 @@ impl<T: web3::Transport> Convenience<T> {
         };
         Some(VotingStaticData {
             start_date: start_date[0].as_u64(),
-            support_required: nice::dec(support_required[0], 14) * 0.0001, // typically 0.5
-            min_quorum: nice::dec(min_quorum[0], 14) * 0.001, //typically 0.15 for secondary
+            support_required: share(support_required[0]), // typically 0.5
+            min_quorum: share(min_quorum[0]),             //typically 0.15 for secondary
             voting_power: voting_power[0],
             script: script[0].clone(),
             user_voting_power_at: user_voting_power[0],
@@ -458,3 +459,329 @@ impl<T: web3::Transport> Erc20Contract<T> {
         }
     }
 }
+
+/// share from the voting percentage, where 10^18 is 100%
+pub fn share(pct: U256) -> f64 {
+    nice::dec(pct, 14) * 0.0001
+}
+
+/// Definitions of all events that could be emitted by the watched contracts.
+/// Pool events are listed for both versions of the pool,
+/// see README for the origin of every ABI file
+pub fn events_abi() -> anyhow::Result<Abi> {
+    let sources = [
+        ("api3_pool", include_str!("./contract/api3_pool.abi.json")),
+        (
+            "api3_pool_v1",
+            include_str!("./contract/api3_pool_v1.abi.json"),
+        ),
+        (
+            "api3_convenience",
+            include_str!("./contract/api3_convenience.abi.json"),
+        ),
+        (
+            "api3_supply",
+            include_str!("./contract/api3_supply.abi.json"),
+        ),
+        ("api3_token", include_str!("./contract/api3_token.abi.json")),
+        (
+            "api3_voting",
+            include_str!("./contract/api3_voting.abi.json"),
+        ),
+        (
+            "api3_timelock",
+            include_str!("./contract/api3_timelock.abi.json"),
+        ),
+        (
+            "aragon_agent",
+            include_str!("./contract/aragon_agent.abi.json"),
+        ),
+    ];
+    let mut abi = Abi::default();
+    for (name, src) in sources.iter() {
+        abi.extend_json(src)
+            .map_err(|e| anyhow::Error::msg(format!("{}.abi.json: {}", name, e)))?;
+    }
+    Ok(abi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client::events::{Api3, VotingAgent};
+    use client::logreader::{EventParseError, LogReader};
+    use hex_literal::hex;
+    use web3::types::{Bytes, Log, H256};
+
+    fn fixture(topics: &[&str], data: &str) -> Log {
+        Log {
+            address: H160::from_low_u64_be(1),
+            topics: topics
+                .iter()
+                .map(|t| H256::from_slice(&hex::decode(t).unwrap()))
+                .collect(),
+            data: Bytes(hex::decode(data).unwrap()),
+            block_hash: Some(H256::from_low_u64_be(2)),
+            block_number: Some(1.into()),
+            transaction_hash: Some(H256::from_low_u64_be(3)),
+            transaction_index: Some(0.into()),
+            log_index: Some(0.into()),
+            transaction_log_index: Some(0.into()),
+            log_type: None,
+            removed: Some(false),
+        }
+    }
+
+    // hand-written decoder that was used before events were read from ABI
+    fn legacy(voting: Option<VotingAgent>, log: &Log) -> Result<Api3, EventParseError> {
+        let t0 = log.topics[0];
+        if t0 == hex!("c16be9a586414a157dd46b4d023aa9997a025dd1cbbaa67ac0c1b8273a5eaf55").into() {
+            let mut r = LogReader::new(log, 1, Some(6))?;
+            return Ok(Api3::Staked {
+                user: r.address(),
+                amount: r.value(),
+                minted_shares: r.value(),
+                user_unstaked: r.value(),
+                user_shares: r.value(),
+                total_shares: r.value(),
+                total_stake: r.value(),
+            });
+        }
+        if t0 == hex!("1449c6dd7851abc30abf37f57715f492010519147cc2652fbc38202c18a6ee90").into() {
+            let mut r = LogReader::new(log, 1, Some(2))?;
+            return Ok(Api3::StakedV0 {
+                user: r.address(),
+                amount: r.value(),
+                minted_shares: r.value(),
+            });
+        }
+        if t0 == hex!("6e0fc10bac330e97bc2fd6c13cbb1c1189ddb48a8ce96395650ba8f2bd28f6fc").into() {
+            let mut r = LogReader::new(log, 1, Some(3))?;
+            return Ok(Api3::MintedReward {
+                epoch_index: r.value(),
+                amount: r.value(),
+                new_apr: r.value(),
+                total_stake: r.value(),
+            });
+        }
+        if t0 == hex!("251830cd12788c7474148132132ab205112e7b9bba739f0e69c8d4a6a54e2159").into() {
+            let mut r = LogReader::new(log, 1, Some(4))?;
+            return Ok(Api3::ScheduledUnstake {
+                user: r.address(),
+                amount: r.value(),
+                shares: r.value(),
+                scheduled_for: r.value(),
+                user_shares: r.value(),
+            });
+        }
+        if t0 == hex!("4d72fe0577a3a3f7da968d7b892779dde102519c25527b29cf7054f245c791b9").into() {
+            let mut r = LogReader::new(log, 2, None)?;
+            return Ok(Api3::StartVote {
+                agent: voting.ok_or(EventParseError::NoVotingAgent)?,
+                vote_id: r.value(),
+                creator: r.address(),
+                metadata: r.text(),
+            });
+        }
+        if t0 == hex!("b34ee265e3d4f5ec4e8b52d59b2a9be8fceca2f274ebc080d8fba797fea9391f").into() {
+            let mut r = LogReader::new(log, 2, Some(2))?;
+            return Ok(Api3::CastVote {
+                agent: voting.ok_or(EventParseError::NoVotingAgent)?,
+                vote_id: r.value(),
+                voter: r.address(),
+                supports: r.bool(),
+                stake: r.value(),
+            });
+        }
+        Ok(Api3::Unknown)
+    }
+
+    fn same_as_legacy(voting: Option<VotingAgent>, log: &Log) -> Api3 {
+        let abi = events_abi().unwrap();
+        let decoded = Api3::from_log(&abi, voting.clone(), log).unwrap();
+        let expected = legacy(voting, log).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&expected).unwrap()
+        );
+        decoded
+    }
+
+    #[test]
+    pub fn it_reads_voting_shares() {
+        assert_eq!(share(U256::exp10(17) * 5), 0.5);
+        assert!((share(U256::exp10(16) * 15) - 0.15).abs() < 1e-9);
+    }
+
+    #[test]
+    pub fn it_knows_all_events() {
+        let abi = events_abi().unwrap();
+        // topics of the events that are mapped into the state
+        let topics = [
+            hex!("24d7bda8602b916d64417f0dbfe2e2e88ec9b1157bd9f596dfdb91ba26624e04"),
+            hex!("e5541a6b6103d4fa7e021ed54fad39c66f27a76bd13d374cf6240ae6bd0bb72b"),
+            hex!("3aace7340547de7b9156593a7652dc07ee900cea3fd8f82cb6c9d38b40829802"),
+            hex!("f310def5b4718cefe3603eb46259d8061fd58003695cf952de94c53e14dbb309"),
+            hex!("4d10bd049775c77bd7f255195afba5088028ecb3c7c277d393ccff7934f2f92c"),
+            hex!("251830cd12788c7474148132132ab205112e7b9bba739f0e69c8d4a6a54e2159"),
+            hex!("06fbd2297e6f6f7701a9cf99685a6af911cab275ec5c75ac7aaaf13b5cf3d61f"),
+            hex!("c16be9a586414a157dd46b4d023aa9997a025dd1cbbaa67ac0c1b8273a5eaf55"),
+            hex!("1449c6dd7851abc30abf37f57715f492010519147cc2652fbc38202c18a6ee90"),
+            hex!("dcfd2b4017d03f7e541021db793b2f9b31e4acdee005f789e52853c390e3e962"),
+            hex!("0f5bb82176feb1b5e747e28471aa92156a04d9f3ab9f45f28e2d704232b93f75"),
+            hex!("92ccf450a286a957af52509bc1c9939d1a6a481783e142e41e2499f0bb66ebc6"),
+            hex!("7084f5476618d8e60b11ef0d7d3f06914655adb8793e28ff7f018d4c76d505d5"),
+            hex!("a2fd4f03989448c5a69bab0c0454f2baf5667413a4e4b87fd7379a8ab69fae3f"),
+            hex!("ceaef3a8d9336089c649bcf1ea9dd1ae52f5c42ea01f8707ecdd57ea773aa3ee"),
+            hex!("30df07121af80c9a50a8fcfddf8aa9f537a550edb930294c6370d4c05632ba15"),
+            hex!("71b1ce304e98c2a645f0c32f4c9e3ae4d5dbe6717a8c17ccefb0083635afdc15"),
+            hex!("6e0fc10bac330e97bc2fd6c13cbb1c1189ddb48a8ce96395650ba8f2bd28f6fc"),
+            hex!("78fe37d5a5b277d7ec6fe20169a339795b44f3f903e0b793440f63fbccc7d7d9"),
+            hex!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"),
+            hex!("73a19dd210f1a7f902193214c0ee91dd35ee5b4d920cba8d519eca65a7b488ca"),
+            hex!("2da466a7b24304f47e87fa2e1e5a81b9831ce54fec19055ce277ca2f39ba42c4"),
+            hex!("14ab87851ecf43dc38c282e0307cd24257a3d01d0265ae2ba28764befac8c6cc"),
+            hex!("d0d7fef3966369afd08c0683ee833a06f6b91787b85a26fa3ef3004ae37484c2"),
+            hex!("dd8c2c092b990b8e3ae25447982d1c2f7f08c6b9bf7303986a4279f946ebd2ea"),
+            hex!("220c5b95388e82dd8e3a0abed6143750f9bfa4bf73bb6f742e10cf79e551b168"),
+            hex!("8be0079c531659141344cd1fd0a4f28419497f9722a3daafe3b4186f6b6457e0"),
+            hex!("4d72fe0577a3a3f7da968d7b892779dde102519c25527b29cf7054f245c791b9"),
+            hex!("b34ee265e3d4f5ec4e8b52d59b2a9be8fceca2f274ebc080d8fba797fea9391f"),
+            hex!("bf8e2b108bb7c980e08903a8a46527699d5e84905a082d56dacb4150725c8cab"),
+            hex!("20d5cc5c404f7bcf167ea08ea1136482041e05e5641946d3e3de6690a23fbe39"),
+        ];
+        for t in topics.iter() {
+            let topic = H256::from(*t);
+            assert!(abi.get(&topic).is_some(), "{:?} is not in ABI", topic);
+        }
+    }
+
+    #[test]
+    pub fn it_decodes_staking_like_before() {
+        let user = "000000000000000000000000061b8335e1d2042975c4ed849943334bd07fb504";
+        let staked = fixture(
+            &[
+                "c16be9a586414a157dd46b4d023aa9997a025dd1cbbaa67ac0c1b8273a5eaf55",
+                user,
+            ],
+            "00000000000000000000000000000000000000000000003635c9adc5dea00000\
+             0000000000000000000000000000000000000000000000351b6a0c9f8b11435e\
+             0000000000000000000000000000000000000000000000000000000000000000\
+             0000000000000000000000000000000000000000000000ea13bcb00d5a38233b\
+             00000000000000000000000000000000000000000016716637425093414e060e\
+             00000000000000000000000000000000000000000016ea363da68cb3899a0e4c",
+        );
+        match same_as_legacy(None, &staked) {
+            Api3::Staked { amount, .. } => assert_eq!(amount, U256::exp10(21)),
+            x => panic!("unexpected {:?}", x),
+        }
+        let staked_v0 = fixture(
+            &[
+                "1449c6dd7851abc30abf37f57715f492010519147cc2652fbc38202c18a6ee90",
+                user,
+            ],
+            "0000000000000000000000000000000000000000000000056bc75e2d63100000\
+             0000000000000000000000000000000000000000000000056bc75e2d63100000",
+        );
+        assert!(matches!(
+            same_as_legacy(None, &staked_v0),
+            Api3::StakedV0 { .. }
+        ));
+        let scheduled = fixture(
+            &[
+                "251830cd12788c7474148132132ab205112e7b9bba739f0e69c8d4a6a54e2159",
+                user,
+            ],
+            "00000000000000000000000000000000000000000000001b1ae4d6e2ef500000\
+             00000000000000000000000000000000000000000000001a8db5064fc588a1af\
+             0000000000000000000000000000000000000000000000000000000062948c05\
+             0000000000000000000000000000000000000000000000cf8607a9bd94af818c",
+        );
+        match same_as_legacy(None, &scheduled) {
+            Api3::ScheduledUnstake { scheduled_for, .. } => {
+                assert_eq!(scheduled_for, U256::from(1_653_902_341u64))
+            }
+            x => panic!("unexpected {:?}", x),
+        }
+    }
+
+    #[test]
+    pub fn it_decodes_rewards_like_before() {
+        let minted = fixture(
+            &[
+                "6e0fc10bac330e97bc2fd6c13cbb1c1189ddb48a8ce96395650ba8f2bd28f6fc",
+                "0000000000000000000000000000000000000000000000000000000000000089",
+            ],
+            "00000000000000000000000000000000000000000000006bbef7d4362c689581\
+             000000000000000000000000000000000000000000000000054bb72d9e4a0000\
+             00000000000000000000000000000000000000000016ea363da68cb3899a0e4c",
+        );
+        match same_as_legacy(None, &minted) {
+            Api3::MintedReward { epoch_index, .. } => assert_eq!(epoch_index, U256::from(137)),
+            x => panic!("unexpected {:?}", x),
+        }
+    }
+
+    #[test]
+    pub fn it_decodes_votes_like_before() {
+        let voter = "000000000000000000000000061b8335e1d2042975c4ed849943334bd07fb504";
+        let cast = fixture(
+            &[
+                "b34ee265e3d4f5ec4e8b52d59b2a9be8fceca2f274ebc080d8fba797fea9391f",
+                "0000000000000000000000000000000000000000000000000000000000000007",
+                voter,
+            ],
+            "0000000000000000000000000000000000000000000000000000000000000001\
+             0000000000000000000000000000000000000000000000ea13bcb00d5a38233b",
+        );
+        match same_as_legacy(Some(VotingAgent::Secondary), &cast) {
+            Api3::CastVote { supports, .. } => assert!(supports),
+            x => panic!("unexpected {:?}", x),
+        }
+        let abi = events_abi().unwrap();
+        assert!(Api3::from_log(&abi, None, &cast).is_err());
+
+        let start = fixture(
+            &[
+                "4d72fe0577a3a3f7da968d7b892779dde102519c25527b29cf7054f245c791b9",
+                "0000000000000000000000000000000000000000000000000000000000000007",
+                voter,
+            ],
+            "0000000000000000000000000000000000000000000000000000000000000020\
+             0000000000000000000000000000000000000000000000000000000000000047\
+             311f7472616e7366657228616464726573732c75696e74323536291f4d792066\
+             6972737420415049332070726f706f73616c1f466f722074657374696e672070\
+             7572706f73657300000000000000000000000000000000000000000000000000",
+        );
+        let decoded = Api3::from_log(&abi, Some(VotingAgent::Primary), &start).unwrap();
+        let expected = legacy(Some(VotingAgent::Primary), &start).unwrap();
+        match (decoded, expected) {
+            (
+                Api3::StartVote {
+                    agent,
+                    vote_id,
+                    creator,
+                    metadata,
+                },
+                Api3::StartVote {
+                    agent: agent0,
+                    vote_id: vote_id0,
+                    creator: creator0,
+                    metadata: metadata0,
+                },
+            ) => {
+                assert_eq!(agent, agent0);
+                assert_eq!(vote_id, vote_id0);
+                assert_eq!(creator, creator0);
+                assert_eq!(
+                    metadata,
+                    "1|transfer(address,uint256)|My first API3 proposal|For testing purposes"
+                );
+                // the old decoder read the length word of the string as text,
+                // 0x47 got into the metadata as "G"
+                assert_eq!(metadata0, format!("G{}", metadata));
+            }
+            x => panic!("unexpected {:?}", x),
+        }
+    }
+}
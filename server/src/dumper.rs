@@ -1,3 +1,4 @@
+use crate::errors::SyncError;
 use crate::reader;
 use client::events::Api3;
 use client::state::OnChainEvent;
@@ -23,28 +24,16 @@ impl Unknown {
 }
 
 impl reader::EventHandler for Unknown {
-    fn on(&mut self, e: OnChainEvent, l: web3::types::Log) -> () {
+    fn on(&mut self, e: OnChainEvent, l: web3::types::Log) -> Result<(), SyncError> {
         if let Api3::Unknown = e.entry {
-            if !self.unknown_topics.contains_key(&l.topics[0]) {
-                self.unknown_topics
-                    .insert(l.topics[0], l.transaction_hash.unwrap());
+            if let Some(topic) = l.topics.first() {
+                if !self.unknown_topics.contains_key(topic) {
+                    self.unknown_topics.insert(*topic, e.tx);
+                }
             }
-            tracing::warn!("{:?} {:?}", l.transaction_hash.unwrap(), e.entry);
+            tracing::warn!("{:?} {:?}", e.tx, e.entry);
         }
-    }
-}
-
-pub struct Events {}
-
-impl Events {
-    pub fn new() -> Self {
-        Self {}
-    }
-}
-
-impl reader::EventHandler for Events {
-    fn on(&mut self, entry: OnChainEvent, _: web3::types::Log) -> () {
-        println!("{}", serde_json::to_string(&entry).unwrap());
+        Ok(())
     }
 }
 
@@ -79,8 +68,9 @@ impl SnapshotBuilder {
 }
 
 impl reader::EventHandler for SnapshotBuilder {
-    fn on(&mut self, _e: OnChainEvent, l: web3::types::Log) -> () {
-        self.end_block = l.block_number.unwrap().as_u64();
+    fn on(&mut self, e: OnChainEvent, l: web3::types::Log) -> Result<(), SyncError> {
+        self.end_block = e.block_number;
         self.logs.push(l);
+        Ok(())
     }
 }
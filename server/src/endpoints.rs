@@ -1,11 +1,16 @@
+use crate::apiv1;
+use crate::httpcache::{self, Conditions, Entry, ResponseCache};
 use crate::inject;
+use crate::jobs::Scheduler;
+use crate::store::Store;
+use client::routes::{Payload, Route, Switch};
 use client::screens;
 use client::screens::meta::{MetaProvider, PageMetaInfo};
-use client::state::AppState;
+use client::state::{AppState, NetworkInfo};
 use sauron::prelude::*;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::str::FromStr;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use warp::Filter;
 use warp::Reply;
 use web3::types::H160;
@@ -40,49 +45,87 @@ pub fn render_meta(content: &str, info: PageMetaInfo) -> String {
     doc.html().to_string()
 }
 
+/// relative links of the page are resolved against the prefix of its network
+pub fn render_base(content: &str, network: &NetworkInfo) -> String {
+    match network.base_url() {
+        Some(url) => {
+            let doc = nipper::Document::from(content);
+            doc.select("base")
+                .replace_with_html(format!(r#"<base href="{}">"#, url));
+            doc.html().to_string()
+        }
+        None => content.to_owned(),
+    }
+}
+
 pub fn render_html(
     static_dir: &str,
-    _app: &AppState,
+    network: &NetworkInfo,
+    payload: &Payload,
     component: Box<dyn Render>,
     meta: Box<dyn MetaProvider>,
 ) -> impl warp::Reply {
+    warp::reply::html(page_html(static_dir, network, payload, component, meta))
+}
+
+/// document of the page with the rendered component and the payload for the WASM module
+pub fn page_html(
+    static_dir: &str,
+    network: &NetworkInfo,
+    payload: &Payload,
+    component: Box<dyn Render>,
+    meta: Box<dyn MetaProvider>,
+) -> String {
     let file = format!("{}/index.html", static_dir);
     let content = std::fs::read_to_string(file.as_str()).expect("index.html not found");
-    let with_meta: String = render_meta(&content, meta.meta());
+    let with_meta: String = render_meta(&render_base(&content, network), meta.meta());
 
-    // let state_json = serde_json::to_string(app).expect("state could not be converted to JSON");
+    let state_json = serde_json::to_string(payload).expect("state could not be converted to JSON");
     let mut state_html = String::new();
-    let rendered: String = match component.render_compressed(&mut state_html) {
+    match component.render_compressed(&mut state_html) {
         Ok(_) => {
             let c1 = inject::it(&with_meta, "<main>", "</main>", &state_html);
-            // cutting all WASM modules generated by trunk
-            let c2: String =
-                inject::replace(c1.as_str(), "<script type=\"module\">", "</script>", "");
-            let c3: String = inject::replace(c2.as_str(), "<link rel=\"preload\"", ">", "");
-            let c4: String = inject::replace(c3.as_str(), "<link rel=\"modulepreload\"", ">", "");
-            c4
-        }
-        Err(_) => {
-            // inject::it(content.as_str(), "main(`", "`)", &state_json),
-            state_html.clone()
+            // WASM module takes over the page, built from the same state
+            inject::it(&c1, "main(`", "`)", &inject::template_literal(&state_json))
         }
-    };
-    warp::reply::html(rendered)
+        Err(_) => state_html.clone(),
+    }
 }
 
-pub fn render_err(static_dir: &str, app: &AppState, msg: &'static str) -> warp::reply::Response {
-    let screen = screens::failure::Screen {
-        msg: msg.to_owned(),
-        state: app.clone(),
+/// page of the route, which is rendered from its view model or the part of the state it needs
+pub fn render_page(static_dir: &str, route: Route, app: &AppState) -> warp::reply::Response {
+    let status = match route {
+        Route::Failure(_) => warp::http::StatusCode::BAD_REQUEST,
+        _ => warp::http::StatusCode::OK,
     };
-    let (comp, page) = (Box::new(screen.view()), Box::new(screen));
     warp::reply::with_status(
-        render_html(static_dir.to_owned().as_str(), app, comp, page),
-        warp::http::StatusCode::BAD_REQUEST,
+        warp::reply::html(route_html(static_dir, route, app)),
+        status,
     )
     .into_response()
 }
 
+/// document of the route page
+pub fn route_html(static_dir: &str, route: Route, app: &AppState) -> String {
+    let payload = route.payload(app);
+    let switch = Switch::new(route, payload.clone(), "");
+    let (comp, page) = (Box::new(switch.view()), Box::new(switch));
+    page_html(static_dir, &app.network, &payload, comp, page)
+}
+
+pub fn render_err(static_dir: &str, app: &Arc<AppState>, msg: &str) -> warp::reply::Response {
+    render_page(static_dir, Route::Failure(msg.to_owned()), app)
+}
+
+/// route of the tracker page from the rest of the path, other paths are rejected
+pub fn route_filter() -> impl Filter<Extract = (Route,), Error = warp::Rejection> + Clone {
+    warp::path::tail()
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .and_then(|tail: warp::path::Tail, query: String| async move {
+            Route::parse(tail.as_str(), &query).ok_or_else(warp::reject::not_found)
+        })
+}
+
 pub fn json_error(msg: &str) -> warp::reply::Response {
     let mut res: BTreeMap<String, String> = BTreeMap::new();
     res.insert("error".to_owned(), msg.to_string());
@@ -101,30 +144,39 @@ where
 
 pub fn routes(
     static_dir: String,
-    state: Arc<Mutex<crate::State>>,
-) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    store: Arc<Store>,
+    scheduler: Scheduler,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     let dir = static_dir.clone();
+    // responses of the current snapshot, dropped when the next one is published
+    let cache = Arc::new(ResponseCache::new());
 
-    let api_state = warp::path!("api" / "state").map({
-        let state_rc = state.clone();
-        move || {
-            let state = state_rc.lock().unwrap();
-            warp::reply::json(&state.app)
-        }
-    });
+    let api_state = warp::path!("api" / "state")
+        .and(httpcache::conditions())
+        .map({
+            let store = store.clone();
+            let cache = cache.clone();
+            move |cond: Conditions| {
+                let snapshot = store.load();
+                let entry = cache.get(&snapshot, "api/state", || {
+                    Entry::json(snapshot.app.as_ref())
+                });
+                httpcache::reply(&snapshot, &cond, &entry)
+            }
+        });
     let api_wallets = warp::path!("api" / "wallets").map({
-        let state_rc = state.clone();
+        let store = store.clone();
         move || {
-            let state = state_rc.lock().unwrap();
-            warp::reply::json(&wrap_result(&state.app.wallets))
+            let app = store.app();
+            warp::reply::json(&wrap_result(&app.wallets))
         }
     });
     let api_wallet = warp::path!("api" / "wallets" / String).map({
-        let state_rc = state.clone();
+        let store = store.clone();
         move |id: String| {
-            let state = state_rc.lock().unwrap();
+            let app = store.app();
             if let Ok(addr) = H160::from_str(id.clone().as_str()) {
-                if let Some(w) = state.app.wallets.get(&addr) {
+                if let Some(w) = app.wallets.get(&addr) {
                     warp::reply::json(&wrap_result(&w)).into_response()
                 } else {
                     json_error("Not a member of the DAO")
@@ -134,169 +186,324 @@ pub fn routes(
             }
         }
     });
-    let api_rewards = warp::path!("api" / "rewards").map({
-        let state_rc = state.clone();
-        move || {
-            let state = state_rc.lock().unwrap();
-            warp::reply::json(&wrap_result(&state.app.epochs))
+    let api_wallet_rewards = warp::path!("api" / "wallets" / String / "rewards").map({
+        let store = store.clone();
+        move |id: String| {
+            let app = store.app();
+            if let Ok(addr) = H160::from_str(id.clone().as_str()) {
+                if app.wallets.contains_key(&addr) {
+                    let now = chrono::Utc::now().timestamp() as u64;
+                    let schedule = client::vesting::VestingSchedule::from_state(&app, &addr, now);
+                    warp::reply::json(&wrap_result(&schedule)).into_response()
+                } else {
+                    json_error("Not a member of the DAO")
+                }
+            } else {
+                json_error("Invalid Ethereum address")
+            }
         }
     });
-    let api_votings = warp::path!("api" / "votings").map({
-        let state_rc = state.clone();
-        move || {
-            let state = state_rc.lock().unwrap();
-            warp::reply::json(&wrap_result(&state.app.votings))
+    let api_wallet_timelocks = warp::path!("api" / "wallets" / String / "timelocks").map({
+        let store = store.clone();
+        move |id: String| {
+            let app = store.app();
+            if let Ok(addr) = H160::from_str(id.clone().as_str()) {
+                if app.wallets.contains_key(&addr) {
+                    let entries = app.timelocks.get(&addr).cloned().unwrap_or_default();
+                    warp::reply::json(&wrap_result(&entries)).into_response()
+                } else {
+                    json_error("Not a member of the DAO")
+                }
+            } else {
+                json_error("Invalid Ethereum address")
+            }
         }
     });
-    let api_voting = warp::path!("api" / "votings" / String).map({
-        let state_rc = state.clone();
+    let api_wallet_timeline = warp::path!("api" / "wallets" / String / "timeline").map({
+        let store = store.clone();
         move |id: String| {
-            let (agent, vote_id) = client::events::voting_from_str(&id);
-            let vote_ref = client::events::voting_to_u64(&agent, vote_id);
-            let state = state_rc.lock().unwrap();
-            if let Some(v) = state.app.votings.get(&vote_ref) {
-                warp::reply::json(&wrap_result(&v)).into_response()
+            let app = store.app();
+            if let Ok(addr) = H160::from_str(id.clone().as_str()) {
+                if app.wallets.contains_key(&addr) {
+                    let timeline = client::timeline::WalletTimeline::from_state(&app, &addr);
+                    warp::reply::json(&wrap_result(&timeline)).into_response()
+                } else {
+                    json_error("Not a member of the DAO")
+                }
             } else {
-                json_error("Invalid voting ID")
+                json_error("Invalid Ethereum address")
             }
         }
     });
-    let api = api_state
-        .or(api_rewards)
-        .or(api_wallets)
-        .or(api_wallet)
-        .or(api_votings)
-        .or(api_voting);
-
-    let wallets = warp::path!("wallets").map({
-        let state_rc = state.clone();
-        let d = dir.clone();
+    let api_pool_history = warp::path!("api" / "pool" / "history").map({
+        let store = store.clone();
         move || {
-            let state = state_rc.lock().unwrap();
-            let screen = screens::wallets::Screen {
-                state: state.clone().app,
-            };
-            let (comp, page) = (Box::new(screen.view()), Box::new(screen));
-            render_html(&d, &state.app, comp, page).into_response()
+            let app = store.app();
+            let history = client::poolhistory::PoolHistory::from_state(&app);
+            warp::reply::json(&wrap_result(&history))
         }
     });
-    let votings = warp::path!("votings").map({
-        let state_rc = state.clone();
-        let d = dir.clone();
+    let api_rewards = warp::path!("api" / "rewards").map({
+        let store = store.clone();
         move || {
-            let state = state_rc.lock().unwrap();
-            let screen = screens::votings::Screen {
-                state: state.clone().app,
-            };
-            let (comp, page) = (Box::new(screen.view()), Box::new(screen));
-            render_html(&d, &state.app, comp, page).into_response()
+            let app = store.app();
+            warp::reply::json(&wrap_result(&app.epochs))
         }
     });
-    let rewards = warp::path!("rewards").map({
-        let state_rc = state.clone();
-        let d = dir.clone();
+    let api_votings = warp::path!("api" / "votings").map({
+        let store = store.clone();
         move || {
-            let state = state_rc.lock().unwrap();
-            let screen = screens::rewards::Screen {
-                state: state.clone().app,
-            };
-            let (comp, page) = (Box::new(screen.view()), Box::new(screen));
-            render_html(&d, &state.app, comp, page).into_response()
+            let app = store.app();
+            warp::reply::json(&wrap_result(&app.votings))
         }
     });
-
-    let wallet = warp::path!("wallets" / String).map({
-        let state_rc = state.clone();
-        let d = dir.clone();
+    let api_voting = warp::path!("api" / "votings" / String).map({
+        let store = store.clone();
         move |id: String| {
-            let state = state_rc.lock().unwrap();
-            if let Ok(addr) = H160::from_str(id.clone().as_str()) {
-                if let Some(_) = state.app.wallets.get(&addr) {
-                    let screen = screens::wallet::Screen {
-                        addr,
-                        state: state.clone().app,
-                    };
-                    let (comp, page) = (Box::new(screen.view()), Box::new(screen));
-                    render_html(&d, &state.app, comp, page).into_response()
-                } else {
-                    render_err(&d, &state.app, "Not a member of the DAO")
-                }
+            let (agent, vote_id) = match client::events::voting_from_str(&id) {
+                Some(x) => x,
+                None => return json_error("Invalid voting ID"),
+            };
+            let vote_ref = client::events::voting_to_u64(&agent, vote_id);
+            let app = store.app();
+            if let Some(v) = app.votings.get(&vote_ref) {
+                warp::reply::json(&wrap_result(&v)).into_response()
             } else {
-                render_err(&d, &state.app, "Invalid Ethereum address")
+                json_error("Invalid voting ID")
             }
         }
     });
-    let voting = warp::path!("votings" / String).map({
-        let state_rc = state.clone();
-        let d = dir.clone();
-        move |id: String| {
-            let (agent, vote_id) = client::events::voting_from_str(&id);
-            let vote_ref = client::events::voting_to_u64(&agent, vote_id);
-            let state = state_rc.lock().unwrap();
-            if let Some(_) = state.app.votings.get(&vote_ref) {
-                let screen = screens::voting::Screen {
-                    vote_ref,
-                    vote_id,
-                    agent,
-                    state: state.clone().app,
-                };
-                let (comp, page) = (Box::new(screen.view()), Box::new(screen));
-                render_html(&d, &state.app, comp, page).into_response()
-            } else {
-                render_err(&d, &state.app, "Invalid voting ID")
-            }
+    let api_participation = warp::path!("api" / "analytics" / "participation").map({
+        let store = store.clone();
+        move || {
+            let app = store.app();
+            let participation = client::participation::Participation::from_state(&app);
+            warp::reply::json(&wrap_result(&participation))
         }
     });
-    let treasury = warp::path!("treasury")
+    let api_projections = warp::path!("api" / "projections")
+        .and(warp::query::<HashMap<String, String>>())
         .map({
-            let state_rc = state.clone();
-            let d = dir.clone();
-            move || {
-                let state = state_rc.lock().unwrap();
-                let screen = screens::treasury::Screen {
-                    state: state.clone().app,
+            let store = store.clone();
+            move |query: HashMap<String, String>| {
+                let num_epochs: usize = match query.get("epochs") {
+                    Some(x) => match x.parse() {
+                        Ok(n) if n <= 520 => n,
+                        _ => return json_error("Invalid number of epochs"),
+                    },
+                    None => client::projections::DEFAULT_EPOCHS,
                 };
-                let (comp, page) = (Box::new(screen.view()), Box::new(screen));
-                render_html(&d, &state.app, comp, page).into_response()
+                let change: f64 = match query.get("change") {
+                    Some(x) => match x.parse() {
+                        Ok(v) => v,
+                        Err(_) => return json_error("Invalid stake change"),
+                    },
+                    None => 0.0,
+                };
+                let stake: Option<f64> = match query.get("stake") {
+                    Some(x) => match x.parse() {
+                        Ok(v) => Some(v),
+                        Err(_) => return json_error("Invalid stake"),
+                    },
+                    None => None,
+                };
+                let wallet: Option<(H160, Option<f64>)> = match query.get("wallet") {
+                    Some(x) => match H160::from_str(x) {
+                        Ok(addr) => Some((addr, stake)),
+                        Err(_) => return json_error("Invalid Ethereum address"),
+                    },
+                    None => stake.map(|s| (H160::zero(), Some(s))),
+                };
+                let changes: Vec<f64> = (0..num_epochs).map(|_| change).collect();
+                let app = store.app();
+                match client::projections::Projection::from_state(
+                    &app, num_epochs, &changes, wallet,
+                ) {
+                    Some(p) => warp::reply::json(&wrap_result(&p)).into_response(),
+                    None => json_error("Pool information is not loaded yet"),
+                }
             }
-        })
-        .or(warp::fs::dir(static_dir.clone()));
+        });
+    let api_search = warp::path!("api" / "search")
+        .and(warp::query::<HashMap<String, String>>())
+        .map({
+            let store = store.clone();
+            move |query: HashMap<String, String>| {
+                let phrase = match query.get("q") {
+                    Some(x) if !x.trim().is_empty() => x,
+                    _ => return json_error("Missing search query"),
+                };
+                let limit: usize = match query.get("limit") {
+                    Some(x) => match x.parse() {
+                        Ok(n) if n > 0 && n <= 100 => n,
+                        _ => return json_error("Invalid limit"),
+                    },
+                    None => client::search::DEFAULT_LIMIT,
+                };
+                let app = store.app();
+                warp::reply::json(&wrap_result(&app.search.search(phrase, limit))).into_response()
+            }
+        });
+    let api_views = warp::path("api")
+        .and(warp::path("views"))
+        .and(warp::path::tail())
+        .and(httpcache::conditions())
+        .map({
+            let store = store.clone();
+            let cache = cache.clone();
+            move |tail: warp::path::Tail, cond: Conditions| {
+                let snapshot = store.load();
+                let app = &snapshot.app;
+                let route = match tail.as_str() {
+                    "home" => Some(Route::Home),
+                    path => Route::parse(path, ""),
+                };
+                match route.map(|r| r.check(app)) {
+                    Some(Route::Failure(msg)) => json_error(&msg),
+                    Some(route) if route.is_timed() => {
+                        httpcache::reply_fresh(&cond, &Entry::json(&route.payload(app)))
+                    }
+                    Some(route) if route.has_view() => {
+                        let entry =
+                            cache.get(&snapshot, &route.api(), || Entry::json(&route.payload(app)));
+                        httpcache::reply(&snapshot, &cond, &entry)
+                    }
+                    _ => json_error("Unknown view"),
+                }
+            }
+        });
+    let api_page = warp::path("api")
+        .and(warp::path("page"))
+        .and(route_filter())
+        .and(httpcache::conditions())
+        .map({
+            let store = store.clone();
+            let cache = cache.clone();
+            move |route: Route, cond: Conditions| {
+                let snapshot = store.load();
+                let app = &snapshot.app;
+                match route.check(app) {
+                    Route::Failure(msg) => json_error(&msg),
+                    route if route.is_timed() => {
+                        httpcache::reply_fresh(&cond, &Entry::json(&route.payload(app)))
+                    }
+                    route => {
+                        // view routes share the entry with their views endpoint
+                        let entry =
+                            cache.get(&snapshot, &route.api(), || Entry::json(&route.payload(app)));
+                        httpcache::reply(&snapshot, &cond, &entry)
+                    }
+                }
+            }
+        });
+    let api_unstakes = warp::path!("api" / "unstakes").map({
+        let store = store.clone();
+        move || {
+            let app = store.app();
+            let now = chrono::Utc::now().timestamp() as u64;
+            let queue = client::unstakes::UnstakeQueue::from_state(&app, now);
+            warp::reply::json(&wrap_result(&queue))
+        }
+    });
+    let api_jobs = warp::path!("api" / "jobs").map({
+        let scheduler = scheduler.clone();
+        move || warp::reply::json(&wrap_result(&scheduler.status()))
+    });
+    let api_quarantine = warp::path!("api" / "diagnostics" / "quarantine").map({
+        let store = store.clone();
+        move || {
+            let app = store.app();
+            warp::reply::json(&wrap_result(&app.quarantine))
+        }
+    });
+    let api_unhandled = warp::path!("api" / "events" / "unhandled").map({
+        let store = store.clone();
+        move || {
+            let app = store.app();
+            warp::reply::json(&wrap_result(&app.unhandled))
+        }
+    });
+    let api = api_state
+        .or(api_rewards)
+        .or(api_wallets)
+        .or(api_wallet)
+        .or(api_wallet_rewards)
+        .or(api_wallet_timelocks)
+        .or(api_wallet_timeline)
+        .or(api_pool_history)
+        .or(api_votings)
+        .or(api_voting)
+        .or(api_participation)
+        .or(api_projections)
+        .or(api_search)
+        .or(api_views)
+        .or(api_page)
+        .or(api_unstakes)
+        .or(api_jobs)
+        .or(api_quarantine)
+        .or(api_unhandled);
 
-    let home = warp::path::end()
+    let pages = route_filter()
+        .and(httpcache::conditions())
         .map({
-            let state_rc = state.clone();
+            let store = store.clone();
+            let cache = cache.clone();
             let d = dir.clone();
-            move || {
-                let state = state_rc.lock().unwrap();
-                let screen = screens::home::Screen {
-                    state: state.clone().app,
-                };
-                let (comp, page) = (Box::new(screen.view()), Box::new(screen));
-                render_html(&d, &state.app, comp, page).into_response()
+            move |route: Route, cond: Conditions| {
+                let snapshot = store.load();
+                let app = snapshot.app.clone();
+                match route.check(&app) {
+                    Route::Search(phrase) => {
+                        // search needs events of all wallets, so it is rendered from the full state
+                        let screen = screens::search::Screen::new(app.clone(), &phrase);
+                        // the only or the exact match is opened at once
+                        if let Some(hit) = screen.hits.first() {
+                            if hit.exact || screen.hits.len() == 1 {
+                                let found = warp::reply::with_status(
+                                    warp::reply(),
+                                    warp::http::StatusCode::FOUND,
+                                );
+                                return warp::reply::with_header(
+                                    found,
+                                    "location",
+                                    hit.url.as_str(),
+                                )
+                                .into_response();
+                            }
+                        }
+                        let route = Route::Search(phrase);
+                        let payload = route.payload(&app);
+                        let (comp, page) = (Box::new(screen.view()), Box::new(screen));
+                        render_html(&d, &app.network, &payload, comp, page).into_response()
+                    }
+                    Route::Failure(msg) => render_page(&d, Route::Failure(msg), &app),
+                    route if route.is_timed() => {
+                        httpcache::reply_fresh(&cond, &Entry::html(route_html(&d, route, &app)))
+                    }
+                    route => {
+                        let key = format!("page/{}", route.path());
+                        let entry =
+                            cache.get(&snapshot, &key, || Entry::html(route_html(&d, route, &app)));
+                        httpcache::reply(&snapshot, &cond, &entry)
+                    }
+                }
             }
         })
         .or(warp::fs::dir(static_dir.clone()));
-
-    let pages = home
-        .or(treasury)
-        .or(rewards)
-        .or(wallet)
-        .or(wallets)
-        .or(voting)
-        .or(votings);
     let liveness = warp::path!("_liveness").map(|| format!("# API3 DAO Tracker"));
     let prom = warp::path!("metrics").map({
-        let state_rc = state.clone();
+        let store = store.clone();
         move || {
-            let state = state_rc.lock().unwrap();
+            let app = store.app();
             warp::reply::with_status(
-                crate::metrics::handler(&state.app),
+                crate::metrics::handler(&app, &scheduler.status()),
                 warp::http::StatusCode::OK,
             )
             .into_response()
         }
     });
-    liveness.or(prom).or(api).or(pages)
+    let v1 = apiv1::routes(store.clone(), cache.clone());
+    liveness.or(prom).or(v1).or(api).or(pages)
 }
 
 const LOADING_HTML: &'static str = r#"
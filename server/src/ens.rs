@@ -1,13 +1,20 @@
+use client::state::EnsRecords;
 use hex_literal::hex;
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Read;
-use std::path::Path;
+use std::sync::Mutex;
 use tiny_keccak::{Hasher, Keccak};
 use web3::contract::{Contract, Options};
 use web3::types::{Address, H160, H256};
 
 const ENS_REVERSE_REGISTRAR_DOMAIN: &str = "addr.reverse";
+/// seconds before the name is read again, unless it is configured
+pub const DEFAULT_TTL: u64 = 24 * 60 * 60;
+/// text records that are longer are ignored
+const MAX_RECORD_LEN: usize = 512;
 
 struct EnsSetting {
     mainnet_addr: Address,
@@ -19,62 +26,19 @@ lazy_static! {
     };
 }
 
-#[derive(Debug)]
-struct Resolver<T: web3::Transport> {
-    contract: Contract<T>,
+/// ENS name of the wallet, as it was read from the chain
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CacheEntry {
+    /// None if the wallet has no reverse record or it does not resolve back to the wallet
+    pub name: Option<String>,
+    #[serde(default)]
+    pub records: EnsRecords,
+    /// unix time after which the name is read again
+    pub expires: u64,
 }
 
-impl<T: web3::Transport> Resolver<T> {
-    async fn new(ens: &ENS<T>, resolver_addr: &str) -> anyhow::Result<Self> {
-        // tracing::debug!("resolving {:?}", resolver_addr);
-        let addr_namehash = H256::from_slice(namehash(resolver_addr).as_slice());
-        // println!("addr_namehash {:?}", addr_namehash);
-        let exists: bool = ens
-            .contract
-            .query(
-                "recordExists",
-                (addr_namehash,),
-                None,
-                Options::default(),
-                None,
-            )
-            .await
-            .expect("recordExists error");
-        if !exists {
-            return Err(anyhow::Error::msg("no resolver"));
-        }
-
-        let result =
-            ens.contract
-                .query("resolver", (addr_namehash,), None, Options::default(), None);
-        let resolver_addr: Address = result.await.expect("resolver.result.wait()");
-        println!("resolver_addr {:?}", resolver_addr);
-        if resolver_addr == H160::from(hex!("0000000000000000000000000000000000000000")) {
-            return Err(anyhow::Error::msg("no resolver addr"));
-        }
-
-        // resolve
-        let resolver_contract = Contract::from_json(
-            ens.web3.eth(),
-            resolver_addr,
-            include_bytes!("./contract/ens_reverseresolver.abi.json"),
-        )
-        .expect("fail load resolver contract");
-        Ok(Self {
-            contract: resolver_contract,
-        })
-    }
-
-    async fn name(self, resolver_addr: &str) -> Result<String, String> {
-        let addr_namehash = H256::from_slice(namehash(resolver_addr).as_slice());
-        let result = self
-            .contract
-            .query("name", (addr_namehash,), None, Options::default(), None);
-        match result.await {
-            Ok(s) => Ok(s),
-            Err(e) => Err(format!("error: name.result.wait(): {:?}", e)),
-        }
-    }
+fn now() -> u64 {
+    chrono::Utc::now().timestamp() as u64
 }
 
 #[derive(Debug)]
@@ -82,6 +46,9 @@ pub struct ENS<T: web3::Transport> {
     pub web3: web3::Web3<T>,
     pub contract: Contract<T>,
     pub cache_dir: String,
+    /// seconds the name is kept in the cache
+    pub ttl: u64,
+    cache: Mutex<BTreeMap<H160, CacheEntry>>,
 }
 
 impl<T: web3::Transport> ENS<T> {
@@ -96,59 +63,166 @@ impl<T: web3::Transport> ENS<T> {
             web3: web3.clone(),
             contract,
             cache_dir: cache_dir.to_string(),
+            ttl: DEFAULT_TTL,
+            cache: Mutex::new(load(cache_dir)),
         }
     }
 
-    pub fn cache_fn(&self, address: &str) -> String {
-        format!("{}/{}.txt", self.cache_dir, address)
+    pub fn with_ttl(mut self, ttl: u64) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// cached entry of the wallet, if it has not expired yet
+    pub fn cached(&self, address: &Address, at: u64) -> Option<CacheEntry> {
+        let cache = self.cache.lock().unwrap();
+        cache.get(address).filter(|e| e.expires > at).cloned()
     }
 
-    pub fn has_cached(&self, address: &str) -> bool {
-        if self.cache_dir.len() == 0 {
-            return false;
+    /// writes all cached names to disk, if the cache is enabled
+    pub fn save(&self) -> anyhow::Result<()> {
+        if self.cache_dir.is_empty() {
+            return Ok(());
         }
-        Path::new(self.cache_fn(address).as_str()).exists()
+        let f = File::create(filename(&self.cache_dir))?;
+        serde_json::to_writer(&f, &*self.cache.lock().unwrap())?;
+        Ok(())
     }
 
-    pub fn get_cached(&self, address: &str) -> anyhow::Result<String> {
-        let mut f = File::open(self.cache_fn(address)).expect("Unable to open file");
-        let mut data = String::new();
-        f.read_to_string(&mut data).expect("Reading failure");
-        let res = data.trim();
-        if res.len() > 0 {
-            Ok(res.to_string())
-        } else {
-            Err(anyhow::Error::msg("empty string".to_string()))
+    /// resolver of the name, if it is set in the registry
+    async fn resolver(&self, node: H256, abi: &[u8]) -> anyhow::Result<Option<Contract<T>>> {
+        let exists: bool = self
+            .contract
+            .query("recordExists", (node,), None, Options::default(), None)
+            .await?;
+        if !exists {
+            return Ok(None);
+        }
+        let resolver_addr: Address = self
+            .contract
+            .query("resolver", (node,), None, Options::default(), None)
+            .await?;
+        if resolver_addr.is_zero() {
+            return Ok(None);
         }
+        Ok(Some(Contract::from_json(
+            self.web3.eth(),
+            resolver_addr,
+            abi,
+        )?))
     }
 
-    pub fn save_cached(&self, address: &str, result: &str) -> anyhow::Result<()> {
-        if self.cache_dir.len() == 0 || address.len() == 0 {
-            return Ok(());
+    /// name from the reverse record of the address, which anyone can set to any name
+    async fn reverse(&self, address: &Address) -> anyhow::Result<Option<String>> {
+        let reverse_addr = format!("{:x}.{}", address, ENS_REVERSE_REGISTRAR_DOMAIN);
+        let node = H256::from_slice(namehash(&reverse_addr).as_slice());
+        let abi = include_bytes!("./contract/ens_reverseresolver.abi.json");
+        let resolver = match self.resolver(node, abi).await? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let name: String = resolver
+            .query("name", (node,), None, Options::default(), None)
+            .await?;
+        let name = name.trim();
+        Ok(if name.is_empty() {
+            None
+        } else {
+            Some(name.to_owned())
+        })
+    }
+
+    /// resolver of the name, if the name resolves to the address
+    async fn forward(&self, name: &str, address: &Address) -> anyhow::Result<Option<Contract<T>>> {
+        let node = H256::from_slice(namehash(name).as_slice());
+        let abi = include_bytes!("./contract/ens_publicresolver.abi.json");
+        let resolver = match self.resolver(node, abi).await? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let resolved: Address = resolver
+            .query("addr", (node,), None, Options::default(), None)
+            .await?;
+        Ok(if resolved == *address {
+            Some(resolver)
+        } else {
+            None
+        })
+    }
+
+    /// text record of the name, resolvers without text records have none of them
+    async fn text(&self, resolver: &Contract<T>, name: &str, key: &str) -> Option<String> {
+        let node = H256::from_slice(namehash(name).as_slice());
+        let value: String = resolver
+            .query(
+                "text",
+                (node, key.to_owned()),
+                None,
+                Options::default(),
+                None,
+            )
+            .await
+            .ok()?;
+        let value = value.trim();
+        if value.is_empty() || value.len() > MAX_RECORD_LEN {
+            return None;
         }
-        std::fs::write(self.cache_fn(address), result).expect("Unable to write file");
-        Ok(())
+        Some(value.to_owned())
     }
 
-    pub async fn name(&self, address: &Address) -> Option<String> {
-        let resolver_addr = format!("{:x}.{}", address, ENS_REVERSE_REGISTRAR_DOMAIN);
-        if self.has_cached(&resolver_addr) {
-            if let Ok(cached) = self.get_cached(&resolver_addr) {
-                return Some(cached);
+    /// Verified name of the wallet with its text records.
+    /// The name from the reverse record is only accepted if it resolves back to the wallet
+    pub async fn lookup(&self, address: &Address) -> anyhow::Result<CacheEntry> {
+        let at = now();
+        if let Some(cached) = self.cached(address, at) {
+            return Ok(cached);
+        }
+        let mut entry = CacheEntry {
+            name: None,
+            records: EnsRecords::default(),
+            expires: at + self.ttl,
+        };
+        if let Some(name) = self.reverse(address).await? {
+            match self.forward(&name, address).await? {
+                Some(resolver) => {
+                    entry.records = EnsRecords {
+                        avatar: self.text(&resolver, &name, "avatar").await,
+                        url: self.text(&resolver, &name, "url").await,
+                        twitter: self.text(&resolver, &name, "com.twitter").await,
+                    };
+                    entry.name = Some(name);
+                }
+                None => tracing::warn!("ENS {} does not resolve to {:?}", name, address),
             }
         }
+        self.cache.lock().unwrap().insert(*address, entry.clone());
+        Ok(entry)
+    }
+
+    /// verified name of the wallet, None if it cannot be read
+    pub async fn name(&self, address: &Address) -> Option<String> {
+        self.lookup(address).await.ok()?.name
+    }
+}
 
-        let resolver = match Resolver::new(self, resolver_addr.as_str()).await {
+fn filename(cache_dir: &str) -> String {
+    format!("{}/ens.json", cache_dir)
+}
+
+fn load(cache_dir: &str) -> BTreeMap<H160, CacheEntry> {
+    if cache_dir.is_empty() {
+        return BTreeMap::new();
+    }
+    let mut data = String::new();
+    match File::open(filename(cache_dir)).map(|mut f| f.read_to_string(&mut data)) {
+        Ok(Ok(_)) => match serde_json::from_str(&data) {
             Ok(x) => x,
-            Err(_) => return None,
-        };
-        match resolver.name(resolver_addr.as_str()).await {
-            Ok(x) => {
-                let _ = self.save_cached(&resolver_addr, &x);
-                Some(x)
+            Err(e) => {
+                tracing::info!("ENS cache JSON parsing failure {}", e);
+                BTreeMap::new()
             }
-            Err(_) => return None,
-        }
+        },
+        _ => BTreeMap::new(),
     }
 }
 
@@ -199,6 +273,35 @@ mod tests {
         });
     }
 
+    #[test]
+    pub fn it_expires_cached_names() {
+        let transport = web3::transports::Http::new("http://localhost:8545").unwrap();
+        let web3 = web3::Web3::new(transport);
+        let dir = std::env::temp_dir().join(format!("ens-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir = dir.to_str().unwrap();
+
+        let ens = ENS::new(&web3, dir);
+        let addr = H160::from_low_u64_be(1);
+        let entry = CacheEntry {
+            name: Some("dao.eth".to_owned()),
+            records: EnsRecords {
+                twitter: Some("api3dao".to_owned()),
+                ..Default::default()
+            },
+            expires: 1000,
+        };
+        ens.cache.lock().unwrap().insert(addr, entry.clone());
+        ens.save().unwrap();
+
+        let loaded = ENS::new(&web3, dir);
+        assert_eq!(loaded.cached(&addr, 999), Some(entry));
+        // expired names are read from the chain again
+        assert_eq!(loaded.cached(&addr, 1000), None);
+        assert_eq!(ENS::new(&web3, "").cached(&addr, 0), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
     #[test]
     pub fn namehash_works() {
         assert_eq!(
@@ -0,0 +1,87 @@
+use client::events::Api3;
+use client::logreader::EventParseError;
+use client::state::{QuarantinedEvent, TransitionError};
+use thiserror::Error;
+use web3::types::Log;
+
+/// Failure of the event pipeline
+#[derive(Error, Debug)]
+pub enum SyncError {
+    /// log could not be decoded into the event
+    #[error("decode error: {0}")]
+    Decode(#[from] EventParseError),
+    /// log is missing the field that is required for processing
+    #[error("decode error: log has no {0}")]
+    MissingField(&'static str),
+    /// node failed to provide block or transaction details
+    #[error("RPC error: {0}")]
+    Rpc(String),
+    /// cache could not be read or written
+    #[error("cache error: {0}")]
+    Cache(String),
+    /// event could not be applied to the state
+    #[error("state transition error: {0}")]
+    Transition(#[from] TransitionError),
+}
+
+impl SyncError {
+    pub fn rpc<E: std::fmt::Display>(err: E) -> Self {
+        Self::Rpc(format!("{}", err))
+    }
+
+    pub fn cache<E: std::fmt::Display>(err: E) -> Self {
+        Self::Cache(format!("{}", err))
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Decode(_) | Self::MissingField(_) => "decode",
+            Self::Rpc(_) => "rpc",
+            Self::Cache(_) => "cache",
+            Self::Transition(_) => "transition",
+        }
+    }
+
+    /// record of the log that failed with this error
+    pub fn quarantine(&self, l: &Log, entry: Option<Api3>) -> QuarantinedEvent {
+        QuarantinedEvent {
+            kind: self.kind().to_owned(),
+            error: format!("{}", self),
+            address: l.address,
+            block_number: l.block_number.map(|x| x.as_u64()),
+            tx: l.transaction_hash,
+            log_index: l.log_index.map(|x| x.as_u64()),
+            entry,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use web3::types::{H160, H256};
+
+    #[test]
+    pub fn it_quarantines_log() {
+        let l = Log {
+            address: H160::from_low_u64_be(1),
+            topics: vec![],
+            data: web3::types::Bytes(vec![]),
+            block_hash: None,
+            block_number: Some(100.into()),
+            transaction_hash: Some(H256::from_low_u64_be(2)),
+            transaction_index: None,
+            log_index: Some(3.into()),
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        };
+        let err = SyncError::from(EventParseError::NoTopics);
+        let q = err.quarantine(&l, None);
+        assert_eq!(q.kind, "decode");
+        assert_eq!(q.block_number, Some(100));
+        assert_eq!(q.log_index, Some(3));
+        assert_eq!(SyncError::MissingField("block hash").kind(), "decode");
+        assert_eq!(SyncError::rpc("timeout").kind(), "rpc");
+    }
+}
@@ -0,0 +1,310 @@
+pub mod ndjson;
+pub mod parquetfile;
+pub mod sqldump;
+
+use crate::errors::SyncError;
+use crate::reader;
+use client::events::Api3;
+use client::state::OnChainEvent;
+use serde_json::Value;
+use std::str::FromStr;
+use web3::types::{Log, U256};
+
+/// Output of the exported events
+pub trait Sink {
+    /// writes the event that was not exported before
+    fn write(&mut self, e: &OnChainEvent) -> anyhow::Result<()>;
+    /// called for every event, including the ones that were exported before
+    fn apply(&mut self, _e: &OnChainEvent, _l: &Log) -> Result<(), SyncError> {
+        Ok(())
+    }
+    /// flushes all buffered events
+    fn finish(&mut self) -> anyhow::Result<()>;
+}
+
+/// position of the last exported event of the network
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub block_number: u64,
+    pub log_index: u64,
+}
+
+impl Position {
+    pub fn of(e: &OnChainEvent) -> Self {
+        Self {
+            block_number: e.block_number,
+            log_index: e.log_index,
+        }
+    }
+
+    fn filename(dir: &str, network: &str) -> String {
+        format!("{}/{}.position", dir, network)
+    }
+
+    pub fn load(dir: &str, network: &str) -> Option<Self> {
+        let src = std::fs::read_to_string(Self::filename(dir, network)).ok()?;
+        let mut parts = src.split_whitespace().map(|x| x.parse::<u64>());
+        match (parts.next(), parts.next()) {
+            (Some(Ok(block_number)), Some(Ok(log_index))) => Some(Self {
+                block_number,
+                log_index,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn save(&self, dir: &str, network: &str) -> anyhow::Result<()> {
+        let src = format!("{} {}\n", self.block_number, self.log_index);
+        std::fs::write(Self::filename(dir, network), src)?;
+        Ok(())
+    }
+}
+
+/// Event handler that writes events to the sink.
+/// In incremental mode only events after the previous export are written
+pub struct Exporter<S: Sink> {
+    pub sink: S,
+    dir: String,
+    network: String,
+    from: Option<Position>,
+    last: Option<Position>,
+    written: usize,
+    /// the first failure of the sink, that stops writing
+    error: Option<anyhow::Error>,
+}
+
+impl<S: Sink> Exporter<S> {
+    pub fn new(sink: S, dir: &str, network: &str, incremental: bool) -> Self {
+        let from = match incremental && !dir.is_empty() {
+            true => Position::load(dir, network),
+            false => None,
+        };
+        if let Some(pos) = &from {
+            tracing::info!(
+                "{}: exporting events after block {} log {}",
+                network,
+                pos.block_number,
+                pos.log_index
+            );
+        }
+        Self {
+            sink,
+            dir: dir.to_owned(),
+            network: network.to_owned(),
+            from,
+            last: None,
+            written: 0,
+            error: None,
+        }
+    }
+
+    /// finishes the export, returns the number of written events
+    pub fn done(mut self) -> anyhow::Result<usize> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.sink.finish()?;
+        if let Some(pos) = self.last {
+            if !self.dir.is_empty() {
+                pos.save(&self.dir, &self.network)?;
+            }
+        }
+        Ok(self.written)
+    }
+}
+
+impl<S: Sink> reader::EventHandler for Exporter<S> {
+    fn on(&mut self, e: OnChainEvent, l: Log) -> Result<(), SyncError> {
+        let pos = Position::of(&e);
+        let exported = match self.from {
+            Some(from) => pos <= from,
+            None => false,
+        };
+        if !exported && self.error.is_none() {
+            match self.sink.write(&e) {
+                Ok(_) => {
+                    self.last = Some(pos);
+                    self.written += 1;
+                }
+                Err(err) => {
+                    tracing::error!("{}: export failure: {}", self.network, err);
+                    self.error = Some(err);
+                }
+            }
+        }
+        self.sink.apply(&e, &l)
+    }
+}
+
+/// next numbered file in the folder, so previous exports are not overwritten
+pub fn next_file(dir: &str, prefix: &str, ext: &str) -> anyhow::Result<usize> {
+    std::fs::create_dir_all(dir)?;
+    let mut next = 1;
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let index = name
+            .strip_prefix(prefix)
+            .and_then(|x| x.strip_prefix('-'))
+            .and_then(|x| x.strip_suffix(ext))
+            .and_then(|x| x.strip_suffix('.'))
+            .and_then(|x| x.parse::<usize>().ok());
+        if let Some(i) = index {
+            next = next.max(i + 1);
+        }
+    }
+    Ok(next)
+}
+
+pub fn numbered_file(dir: &str, prefix: &str, index: usize, ext: &str) -> String {
+    format!("{}/{}-{:06}.{}", dir, prefix, index, ext)
+}
+
+/// amounts are serialized as hex strings, while addresses and hashes have fixed length
+fn is_amount(s: &str) -> bool {
+    s.starts_with("0x") && s.len() != 42 && s.len() != 66 && s.len() <= 66
+}
+
+/// text of the field value: amounts as decimals, other values as they are serialized
+pub fn text(v: &Value) -> String {
+    match v {
+        Value::String(s) if is_amount(s) => match U256::from_str(&s[2..]) {
+            Ok(x) => x.to_string(),
+            Err(_) => s.clone(),
+        },
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        _ => v.to_string(),
+    }
+}
+
+/// type of the event and its fields
+pub fn flatten(entry: &Api3) -> (String, Vec<(String, Value)>) {
+    let mut kind = String::new();
+    let mut fields = vec![];
+    if let Ok(Value::Object(obj)) = serde_json::to_value(entry) {
+        for (k, v) in obj {
+            if k == "type" {
+                kind = text(&v);
+            } else {
+                fields.push((k, v));
+            }
+        }
+    }
+    (kind, fields)
+}
+
+/// fields of the event as JSON object, with amounts as decimals
+pub fn fields_json(fields: &[(String, Value)]) -> String {
+    let obj: serde_json::Map<String, Value> = fields
+        .iter()
+        .map(|(k, v)| {
+            let value = match v {
+                Value::String(_) => Value::String(text(v)),
+                _ => v.clone(),
+            };
+            (k.clone(), value)
+        })
+        .collect();
+    Value::Object(obj).to_string()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use client::fees::TxFee;
+    use web3::types::{Bytes, H160, H256};
+
+    pub fn log() -> Log {
+        Log {
+            address: H160::zero(),
+            topics: vec![],
+            data: Bytes(vec![]),
+            block_hash: None,
+            block_number: None,
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        }
+    }
+
+    pub fn event(block_number: u64, entry: Api3) -> OnChainEvent {
+        OnChainEvent {
+            entry,
+            tm: 1_600_000_000 + block_number,
+            block_number,
+            tx: H256::from_low_u64_be(block_number),
+            log_index: 0,
+            fees: TxFee {
+                gas_price: U256::from(1),
+                gas: U256::from(21000),
+                gas_used: None,
+                usd: Some(0.5),
+            },
+        }
+    }
+
+    pub fn staked(block_number: u64) -> OnChainEvent {
+        event(
+            block_number,
+            Api3::StakedV0 {
+                user: H160::from_low_u64_be(1),
+                amount: U256::exp10(20),
+                minted_shares: U256::from(255),
+            },
+        )
+    }
+
+    #[test]
+    pub fn it_flattens_events() {
+        let (kind, fields) = flatten(&staked(1).entry);
+        assert_eq!(kind, "StakedV0");
+        let json = fields_json(&fields);
+        assert!(
+            json.contains(r#""amount":"100000000000000000000""#),
+            "{}",
+            json
+        );
+        assert!(json.contains(r#""minted_shares":"255""#), "{}", json);
+        assert!(json.contains(r#""user":"0x0000000000000000000000000000000000000001""#));
+    }
+
+    struct Memory(Vec<u64>);
+
+    impl Sink for Memory {
+        fn write(&mut self, e: &OnChainEvent) -> anyhow::Result<()> {
+            self.0.push(e.block_number);
+            Ok(())
+        }
+        fn finish(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    pub fn it_exports_incrementally() {
+        use reader::EventHandler;
+        let dir = std::env::temp_dir().join(format!("export-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir = dir.display().to_string();
+        let log = log();
+
+        let mut first = Exporter::new(Memory(vec![]), &dir, "mainnet", true);
+        for b in 1..=3 {
+            first.on(staked(b), log.clone()).unwrap();
+        }
+        assert_eq!(first.done().unwrap(), 3);
+
+        let mut second = Exporter::new(Memory(vec![]), &dir, "mainnet", true);
+        for b in 1..=5 {
+            second.on(staked(b), log.clone()).unwrap();
+        }
+        assert_eq!(second.sink.0, vec![4, 5]);
+        second.done().unwrap();
+        assert_eq!(Position::load(&dir, "mainnet").unwrap().block_number, 5);
+        assert_eq!(next_file(&dir, "events-mainnet", "ndjson").unwrap(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+use super::{next_file, numbered_file, Sink};
+use client::state::OnChainEvent;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Events as JSON lines, in rotated files of the output folder or to stdout
+pub struct NdjsonFile {
+    /// output folder, events are printed to stdout if it is empty
+    dir: String,
+    prefix: String,
+    /// max number of events in one file
+    rotate: usize,
+    index: usize,
+    lines: usize,
+    out: Option<BufWriter<Box<dyn Write>>>,
+}
+
+impl NdjsonFile {
+    pub fn stdout() -> Self {
+        Self {
+            dir: String::new(),
+            prefix: String::new(),
+            rotate: 0,
+            index: 0,
+            lines: 0,
+            out: Some(BufWriter::new(Box::new(std::io::stdout()))),
+        }
+    }
+
+    pub fn new(dir: &str, network: &str, rotate: usize) -> anyhow::Result<Self> {
+        let prefix = format!("events-{}", network);
+        Ok(Self {
+            dir: dir.to_owned(),
+            index: next_file(dir, &prefix, "ndjson")?,
+            prefix,
+            rotate,
+            lines: 0,
+            out: None,
+        })
+    }
+
+    /// current file, the next one is started when it is full
+    fn writer(&mut self) -> anyhow::Result<&mut BufWriter<Box<dyn Write>>> {
+        let full = !self.dir.is_empty() && self.rotate > 0 && self.lines >= self.rotate;
+        if self.out.is_none() || full {
+            if let Some(out) = &mut self.out {
+                out.flush()?;
+            }
+            let path = numbered_file(&self.dir, &self.prefix, self.index, "ndjson");
+            tracing::info!("writing {}", path);
+            self.out = Some(BufWriter::new(Box::new(File::create(path)?)));
+            self.index += 1;
+            self.lines = 0;
+        }
+        match &mut self.out {
+            Some(out) => Ok(out),
+            None => Err(anyhow::Error::msg("output is not opened")),
+        }
+    }
+}
+
+impl Sink for NdjsonFile {
+    fn write(&mut self, e: &OnChainEvent) -> anyhow::Result<()> {
+        let line = serde_json::to_string(e)?;
+        writeln!(self.writer()?, "{}", line)?;
+        self.lines += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        if let Some(out) = &mut self.out {
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::staked;
+
+    #[test]
+    pub fn it_rotates_files() {
+        let dir = std::env::temp_dir().join(format!("ndjson-{}", std::process::id()));
+        let dir = dir.display().to_string();
+        let mut sink = NdjsonFile::new(&dir, "mainnet", 2).unwrap();
+        for b in 1..=5 {
+            sink.write(&staked(b)).unwrap();
+        }
+        sink.finish().unwrap();
+        // the next export continues numbering
+        assert_eq!(next_file(&dir, "events-mainnet", "ndjson").unwrap(), 4);
+        let last = std::fs::read_to_string(numbered_file(&dir, "events-mainnet", 3, "ndjson"));
+        let e: OnChainEvent = serde_json::from_str(last.unwrap().trim()).unwrap();
+        assert_eq!(e.block_number, 5);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,335 @@
+use super::{fields_json, flatten, next_file, numbered_file, text, Sink};
+use client::state::OnChainEvent;
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use serde_json::Value;
+use std::fs::File;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Text,
+    Int,
+    Bool,
+    Double,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Cell {
+    Text(String),
+    Int(i64),
+    Bool(bool),
+    Double(f64),
+}
+
+/// columns of the event itself
+const BASE: &[(&str, Kind)] = &[
+    ("network", Kind::Text),
+    ("block_number", Kind::Int),
+    ("log_index", Kind::Int),
+    ("tx", Kind::Text),
+    ("tm", Kind::Int),
+    ("type", Kind::Text),
+    ("gas_price", Kind::Text),
+    ("gas_used", Kind::Text),
+    ("fee_usd", Kind::Double),
+];
+
+/// fields of Api3 events, amounts are decimal texts as they do not fit into INT64.
+/// Other fields are kept as JSON in the `fields` column
+const FIELDS: &[(&str, Kind)] = &[
+    ("user", Kind::Text),
+    ("amount", Kind::Text),
+    ("from", Kind::Text),
+    ("to", Kind::Text),
+    ("shares", Kind::Text),
+    ("total_delegated_to", Kind::Text),
+    ("delegate", Kind::Text),
+    ("delta", Kind::Bool),
+    ("minted_shares", Kind::Text),
+    ("user_unstaked", Kind::Text),
+    ("user_shares", Kind::Text),
+    ("user_vesting", Kind::Text),
+    ("total_shares", Kind::Text),
+    ("total_stake", Kind::Text),
+    ("scheduled_for", Kind::Text),
+    ("start", Kind::Text),
+    ("end", Kind::Text),
+    ("recipient", Kind::Text),
+    ("beneficiary", Kind::Text),
+    ("api3_pool_address", Kind::Text),
+    ("last_proposal_timestamp", Kind::Text),
+    ("voting_app", Kind::Text),
+    ("stake_target", Kind::Text),
+    ("epoch_index", Kind::Text),
+    ("new_apr", Kind::Text),
+    ("agent", Kind::Text),
+    ("vote_id", Kind::Text),
+    ("creator", Kind::Text),
+    ("metadata", Kind::Text),
+    ("voter", Kind::Text),
+    ("supports", Kind::Bool),
+    ("stake", Kind::Text),
+    ("agent_app_primary", Kind::Text),
+    ("agent_app_secondary", Kind::Text),
+    ("voting_app_primary", Kind::Text),
+    ("voting_app_secondary", Kind::Text),
+    ("addresses", Kind::Text),
+    ("fields", Kind::Text),
+];
+
+fn columns() -> impl Iterator<Item = &'static (&'static str, Kind)> {
+    BASE.iter().chain(FIELDS.iter())
+}
+
+fn schema() -> String {
+    let fields: Vec<String> = columns()
+        .map(|(name, kind)| match kind {
+            Kind::Text => format!("OPTIONAL BYTE_ARRAY {} (UTF8);", name),
+            Kind::Int => format!("OPTIONAL INT64 {};", name),
+            Kind::Bool => format!("OPTIONAL BOOLEAN {};", name),
+            Kind::Double => format!("OPTIONAL DOUBLE {};", name),
+        })
+        .collect();
+    format!("message events {{ {} }}", fields.join(" "))
+}
+
+/// row of the event with Api3 fields flattened into columns
+fn row(network: &str, e: &OnChainEvent) -> Vec<Option<Cell>> {
+    let (kind, fields) = flatten(&e.entry);
+    let mut res = vec![
+        Some(Cell::Text(network.to_owned())),
+        Some(Cell::Int(e.block_number as i64)),
+        Some(Cell::Int(e.log_index as i64)),
+        Some(Cell::Text(format!("{:?}", e.tx))),
+        Some(Cell::Int(e.tm as i64)),
+        Some(Cell::Text(kind)),
+        Some(Cell::Text(e.fees.gas_price.to_string())),
+        e.fees.gas_used.map(|x| Cell::Text(x.to_string())),
+        e.fees.usd.map(Cell::Double),
+    ];
+    let mut other: Vec<(String, Value)> = vec![];
+    let mut values: Vec<Option<Cell>> = vec![None; FIELDS.len()];
+    for (k, v) in fields {
+        match FIELDS.iter().position(|(name, _)| *name == k) {
+            Some(i) => {
+                values[i] = match (FIELDS[i].1, &v) {
+                    (Kind::Bool, Value::Bool(b)) => Some(Cell::Bool(*b)),
+                    _ => Some(Cell::Text(text(&v))),
+                }
+            }
+            None => other.push((k, v)),
+        }
+    }
+    if !other.is_empty() {
+        values[FIELDS.len() - 1] = Some(Cell::Text(fields_json(&other)));
+    }
+    res.extend(values);
+    res
+}
+
+/// Events as Parquet files with flattened fields, in rotated files of the output folder
+pub struct ParquetFile {
+    dir: String,
+    network: String,
+    prefix: String,
+    /// max number of events in one file
+    rotate: usize,
+    /// number of events in one row group
+    row_group: usize,
+    index: usize,
+    rows: Vec<Vec<Option<Cell>>>,
+    written: usize,
+    writer: Option<SerializedFileWriter<File>>,
+}
+
+impl ParquetFile {
+    pub fn new(dir: &str, network: &str, rotate: usize) -> anyhow::Result<Self> {
+        let prefix = format!("events-{}", network);
+        Ok(Self {
+            dir: dir.to_owned(),
+            network: network.to_owned(),
+            index: next_file(dir, &prefix, "parquet")?,
+            prefix,
+            rotate,
+            row_group: 10000,
+            rows: vec![],
+            written: 0,
+            writer: None,
+        })
+    }
+
+    fn open(&mut self) -> anyhow::Result<()> {
+        let path = numbered_file(&self.dir, &self.prefix, self.index, "parquet");
+        tracing::info!("writing {}", path);
+        let schema = Arc::new(parse_message_type(&schema())?);
+        let props = Arc::new(WriterProperties::builder().build());
+        self.writer = Some(SerializedFileWriter::new(
+            File::create(path)?,
+            schema,
+            props,
+        )?);
+        self.index += 1;
+        self.written = 0;
+        Ok(())
+    }
+
+    /// writes buffered rows as a row group of the current file
+    fn flush(&mut self) -> anyhow::Result<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        if self.writer.is_none() {
+            self.open()?;
+        }
+        let writer = match &mut self.writer {
+            Some(x) => x,
+            None => return Err(anyhow::Error::msg("output is not opened")),
+        };
+        let mut rg = writer.next_row_group()?;
+        for (i, (_, kind)) in columns().enumerate() {
+            let mut col = match rg.next_column()? {
+                Some(x) => x,
+                None => return Err(anyhow::Error::msg("parquet schema mismatch")),
+            };
+            let defs: Vec<i16> = self
+                .rows
+                .iter()
+                .map(|r| if r[i].is_some() { 1 } else { 0 })
+                .collect();
+            let cells = self.rows.iter().filter_map(|r| r[i].as_ref());
+            match kind {
+                Kind::Text => {
+                    let values: Vec<ByteArray> = cells
+                        .map(|c| match c {
+                            Cell::Text(s) => ByteArray::from(s.as_str()),
+                            _ => ByteArray::from(""),
+                        })
+                        .collect();
+                    col.typed::<ByteArrayType>()
+                        .write_batch(&values, Some(&defs), None)?;
+                }
+                Kind::Int => {
+                    let values: Vec<i64> = cells
+                        .map(|c| match c {
+                            Cell::Int(x) => *x,
+                            _ => 0,
+                        })
+                        .collect();
+                    col.typed::<Int64Type>()
+                        .write_batch(&values, Some(&defs), None)?;
+                }
+                Kind::Bool => {
+                    let values: Vec<bool> = cells.map(|c| c == &Cell::Bool(true)).collect();
+                    col.typed::<BoolType>()
+                        .write_batch(&values, Some(&defs), None)?;
+                }
+                Kind::Double => {
+                    let values: Vec<f64> = cells
+                        .map(|c| match c {
+                            Cell::Double(x) => *x,
+                            _ => 0.0,
+                        })
+                        .collect();
+                    col.typed::<DoubleType>()
+                        .write_batch(&values, Some(&defs), None)?;
+                }
+            }
+            col.close()?;
+        }
+        rg.close()?;
+        self.written += self.rows.len();
+        self.rows.clear();
+        Ok(())
+    }
+
+    /// finishes the current file, the next rows are written to the new one
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.flush()?;
+        if let Some(writer) = self.writer.take() {
+            writer.close()?;
+        }
+        Ok(())
+    }
+}
+
+impl Sink for ParquetFile {
+    fn write(&mut self, e: &OnChainEvent) -> anyhow::Result<()> {
+        self.rows.push(row(&self.network, e));
+        if self.rows.len() >= self.row_group {
+            self.flush()?;
+        }
+        if self.rotate > 0 && self.written + self.rows.len() >= self.rotate {
+            self.close()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::{event, staked};
+    use client::abi::AbiEvent;
+    use client::events::{Api3, VotingAgent};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use web3::types::{H160, U256};
+
+    #[test]
+    pub fn it_flattens_fields() {
+        let vote = event(
+            7,
+            Api3::CastVote {
+                agent: VotingAgent::Primary,
+                vote_id: U256::from(12),
+                voter: H160::from_low_u64_be(2),
+                supports: true,
+                stake: U256::from(1000),
+            },
+        );
+        let r = row("mainnet", &vote);
+        assert_eq!(r.len(), columns().count());
+        let cell = |name: &str| r[columns().position(|(n, _)| *n == name).unwrap()].clone();
+        assert_eq!(cell("type"), Some(Cell::Text("CastVote".into())));
+        assert_eq!(cell("vote_id"), Some(Cell::Text("12".into())));
+        assert_eq!(cell("supports"), Some(Cell::Bool(true)));
+        assert_eq!(cell("agent"), Some(Cell::Text("Primary".into())));
+        assert_eq!(cell("fields"), None);
+
+        let decoded = event(
+            8,
+            Api3::Decoded(AbiEvent {
+                name: "Paused".into(),
+                signature: "Paused()".into(),
+                params: vec![],
+            }),
+        );
+        let r = row("mainnet", &decoded);
+        let fields = r[columns().count() - 1].clone();
+        assert!(matches!(fields, Some(Cell::Text(x)) if x.contains("Paused()")));
+    }
+
+    #[test]
+    pub fn it_writes_parquet() {
+        let dir = std::env::temp_dir().join(format!("parquet-{}", std::process::id()));
+        let dir = dir.display().to_string();
+        let mut sink = ParquetFile::new(&dir, "mainnet", 3).unwrap();
+        for b in 1..=4 {
+            sink.write(&staked(b)).unwrap();
+        }
+        sink.finish().unwrap();
+        let path = numbered_file(&dir, "events-mainnet", 1, "parquet");
+        let reader = SerializedFileReader::new(File::open(path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 3);
+        let path = numbered_file(&dir, "events-mainnet", 2, "parquet");
+        let reader = SerializedFileReader::new(File::open(path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
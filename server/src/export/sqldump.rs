@@ -0,0 +1,332 @@
+use super::{fields_json, flatten, Sink};
+use crate::errors::SyncError;
+use client::events::{voting_from_u64, voting_to_string};
+use client::state::{AppState, Epoch, OnChainEvent, Voting, Wallet};
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use web3::types::{Log, H160, U256};
+
+/// normalized schema, that is created if it is missing
+const SCHEMA: &str = r#"CREATE TABLE IF NOT EXISTS events (
+    network TEXT NOT NULL,
+    block_number BIGINT NOT NULL,
+    log_index BIGINT NOT NULL,
+    tx TEXT NOT NULL,
+    tm BIGINT NOT NULL,
+    type TEXT NOT NULL,
+    fields TEXT NOT NULL,
+    PRIMARY KEY (network, block_number, log_index)
+);
+CREATE TABLE IF NOT EXISTS wallets (
+    network TEXT NOT NULL,
+    address TEXT NOT NULL,
+    ens TEXT,
+    vested BOOLEAN NOT NULL,
+    supporter BOOLEAN NOT NULL,
+    deposited NUMERIC(38, 0) NOT NULL,
+    withdrawn NUMERIC(38, 0) NOT NULL,
+    staked NUMERIC(38, 0) NOT NULL,
+    shares NUMERIC(38, 0) NOT NULL,
+    voting_power NUMERIC(38, 0) NOT NULL,
+    rewards NUMERIC(38, 0) NOT NULL,
+    delegates TEXT,
+    votes BIGINT NOT NULL,
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL,
+    PRIMARY KEY (network, address)
+);
+CREATE TABLE IF NOT EXISTS votings (
+    network TEXT NOT NULL,
+    voting TEXT NOT NULL,
+    primary_agent BOOLEAN NOT NULL,
+    vote_id BIGINT NOT NULL,
+    tm BIGINT NOT NULL,
+    block_number BIGINT NOT NULL,
+    tx TEXT NOT NULL,
+    creator TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    voted_yes NUMERIC(38, 0) NOT NULL,
+    voted_no NUMERIC(38, 0) NOT NULL,
+    votes_total NUMERIC(38, 0) NOT NULL,
+    executed BOOLEAN NOT NULL,
+    PRIMARY KEY (network, voting)
+);
+CREATE TABLE IF NOT EXISTS votes (
+    network TEXT NOT NULL,
+    voting TEXT NOT NULL,
+    voter TEXT NOT NULL,
+    supports BOOLEAN NOT NULL,
+    stake NUMERIC(38, 0) NOT NULL,
+    PRIMARY KEY (network, voting, voter)
+);
+CREATE TABLE IF NOT EXISTS epochs (
+    network TEXT NOT NULL,
+    epoch BIGINT NOT NULL,
+    apr DOUBLE PRECISION NOT NULL,
+    minted NUMERIC(38, 0) NOT NULL,
+    total NUMERIC(38, 0) NOT NULL,
+    tm BIGINT NOT NULL,
+    block_number BIGINT NOT NULL,
+    tx TEXT NOT NULL,
+    PRIMARY KEY (network, epoch)
+);
+"#;
+
+/// quoted SQL string literal
+pub fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+pub fn addr(a: &H160) -> String {
+    quote(&format!("{:?}", a))
+}
+
+/// amounts are quoted, as they may not fit into integer literals
+pub fn num(x: &U256) -> String {
+    quote(&x.to_string())
+}
+
+/// Value of a column, written into the script as SQL literal
+/// or bound to a parameter of the statement
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    /// decimal text of the amount, as it may not fit into integer types
+    Amount(String),
+}
+
+impl SqlValue {
+    pub fn text(s: &str) -> Self {
+        Self::Text(s.to_owned())
+    }
+
+    pub fn address(a: &H160) -> Self {
+        Self::Text(format!("{:?}", a))
+    }
+
+    pub fn amount(x: &U256) -> Self {
+        Self::Amount(x.to_string())
+    }
+
+    pub fn literal(&self) -> String {
+        match self {
+            Self::Null => "NULL".to_owned(),
+            Self::Bool(x) => x.to_string(),
+            Self::Int(x) => x.to_string(),
+            Self::Float(x) => x.to_string(),
+            Self::Text(x) | Self::Amount(x) => quote(x),
+        }
+    }
+}
+
+fn literals(values: &[SqlValue]) -> String {
+    let list: Vec<String> = values.iter().map(SqlValue::literal).collect();
+    list.join(", ")
+}
+
+/// SQL script with events and the state they lead to.
+/// Events are appended, while wallets, votings, votes and epochs
+/// of the network are replaced when the export is finished
+pub struct SqlDump {
+    network: String,
+    app: AppState,
+    out: BufWriter<Box<dyn Write>>,
+    started: bool,
+}
+
+impl SqlDump {
+    /// script to stdout, or appended to `{dir}/{network}.sql`
+    pub fn new(dir: &str, network: &str, app: AppState) -> anyhow::Result<Self> {
+        let out: Box<dyn Write> = match dir.is_empty() {
+            true => Box::new(std::io::stdout()),
+            false => {
+                std::fs::create_dir_all(dir)?;
+                let path = format!("{}/{}.sql", dir, network);
+                tracing::info!("writing {}", path);
+                Box::new(OpenOptions::new().create(true).append(true).open(path)?)
+            }
+        };
+        Ok(Self {
+            network: network.to_owned(),
+            app,
+            out: BufWriter::new(out),
+            started: false,
+        })
+    }
+
+    fn start(&mut self) -> anyhow::Result<()> {
+        if !self.started {
+            writeln!(self.out, "BEGIN;")?;
+            write!(self.out, "{}", SCHEMA)?;
+            self.started = true;
+        }
+        Ok(())
+    }
+
+    fn state(&mut self) -> anyhow::Result<()> {
+        let net = quote(&self.network);
+        for table in &["wallets", "votings", "votes", "epochs"] {
+            writeln!(self.out, "DELETE FROM {} WHERE network = {};", table, net)?;
+        }
+        for w in self.app.wallets.values() {
+            let row = literals(&wallet_row(&self.network, w));
+            writeln!(self.out, "INSERT INTO wallets VALUES ({});", row)?;
+        }
+        for (id, v) in &self.app.votings {
+            let row = literals(&voting_row(&self.network, *id, v));
+            writeln!(self.out, "INSERT INTO votings VALUES ({});", row)?;
+            let voting = quote(&voting_id(*id));
+            let votes = v.yes.iter().map(|x| (x, true));
+            for ((voter, stake), supports) in votes.chain(v.no.iter().map(|x| (x, false))) {
+                writeln!(
+                    self.out,
+                    "INSERT INTO votes VALUES ({}, {}, {}, {}, {});",
+                    net,
+                    voting,
+                    addr(voter),
+                    supports,
+                    num(stake),
+                )?;
+            }
+        }
+        for ep in self.app.epochs.values() {
+            let row = literals(&epoch_row(&self.network, ep));
+            writeln!(self.out, "INSERT INTO epochs VALUES ({});", row)?;
+        }
+        Ok(())
+    }
+}
+
+fn voting_id(id: u64) -> String {
+    let (agent, vote_id) = voting_from_u64(id);
+    voting_to_string(&agent, vote_id)
+}
+
+/// values of the wallet, in the order of `wallets` columns
+pub fn wallet_row(network: &str, w: &Wallet) -> Vec<SqlValue> {
+    vec![
+        SqlValue::text(network),
+        SqlValue::address(&w.address),
+        match &w.ens {
+            Some(x) => SqlValue::text(x),
+            None => SqlValue::Null,
+        },
+        SqlValue::Bool(w.vested),
+        SqlValue::Bool(w.supporter),
+        SqlValue::amount(&w.deposited),
+        SqlValue::amount(&w.withdrawn),
+        SqlValue::amount(&w.staked),
+        SqlValue::amount(&w.shares),
+        SqlValue::amount(&w.voting_power),
+        SqlValue::amount(&w.rewards),
+        match &w.delegates {
+            Some(d) => SqlValue::address(&d.address),
+            None => SqlValue::Null,
+        },
+        SqlValue::Int(w.votes as i64),
+        SqlValue::Int(w.created_at as i64),
+        SqlValue::Int(w.updated_at as i64),
+    ]
+}
+
+/// values of the voting, in the order of `votings` columns
+pub fn voting_row(network: &str, id: u64, v: &Voting) -> Vec<SqlValue> {
+    vec![
+        SqlValue::text(network),
+        SqlValue::Text(voting_id(id)),
+        SqlValue::Bool(v.primary),
+        SqlValue::Int(v.vote_id as i64),
+        SqlValue::Int(v.tm as i64),
+        SqlValue::Int(v.block_number as i64),
+        SqlValue::Text(format!("{:?}", v.tx)),
+        SqlValue::address(&v.creator),
+        SqlValue::text(&v.title),
+        SqlValue::text(&v.description),
+        SqlValue::amount(&v.voted_yes),
+        SqlValue::amount(&v.voted_no),
+        SqlValue::amount(&v.votes_total),
+        SqlValue::Bool(v.executed),
+    ]
+}
+
+/// values of the epoch, in the order of `epochs` columns
+pub fn epoch_row(network: &str, ep: &Epoch) -> Vec<SqlValue> {
+    vec![
+        SqlValue::text(network),
+        SqlValue::Int(ep.index as i64),
+        SqlValue::Float(ep.apr),
+        SqlValue::amount(&ep.minted),
+        SqlValue::amount(&ep.total),
+        SqlValue::Int(ep.tm as i64),
+        SqlValue::Int(ep.block_number as i64),
+        SqlValue::Text(format!("{:?}", ep.tx)),
+    ]
+}
+
+impl Sink for SqlDump {
+    fn write(&mut self, e: &OnChainEvent) -> anyhow::Result<()> {
+        self.start()?;
+        let (kind, fields) = flatten(&e.entry);
+        writeln!(
+            self.out,
+            "INSERT INTO events VALUES ({}, {}, {}, {}, {}, {}, {}) ON CONFLICT DO NOTHING;",
+            quote(&self.network),
+            e.block_number,
+            e.log_index,
+            quote(&format!("{:?}", e.tx)),
+            e.tm,
+            quote(&kind),
+            quote(&fields_json(&fields)),
+        )?;
+        Ok(())
+    }
+
+    fn apply(&mut self, e: &OnChainEvent, l: &Log) -> Result<(), SyncError> {
+        self.app.update(e.clone(), l.clone())?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.start()?;
+        self.state()?;
+        writeln!(self.out, "COMMIT;")?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::{log, staked};
+
+    #[test]
+    pub fn it_quotes() {
+        assert_eq!(quote("it's"), "'it''s'");
+    }
+
+    #[test]
+    pub fn it_writes_script() {
+        let dir = std::env::temp_dir().join(format!("sqldump-{}", std::process::id()));
+        let dir = dir.display().to_string();
+        let mut sink = SqlDump::new(&dir, "mainnet", AppState::new(1)).unwrap();
+        let e = staked(1);
+        sink.write(&e).unwrap();
+        sink.apply(&e, &log()).unwrap();
+        sink.finish().unwrap();
+        let script = std::fs::read_to_string(format!("{}/mainnet.sql", dir)).unwrap();
+        assert!(script.starts_with("BEGIN;\nCREATE TABLE IF NOT EXISTS events"));
+        assert!(script.contains("'StakedV0'"));
+        assert!(script.contains("DELETE FROM wallets WHERE network = 'mainnet';"));
+        assert!(script.contains(
+            "INSERT INTO wallets VALUES ('mainnet', '0x0000000000000000000000000000000000000001'"
+        ));
+        assert!(script.trim_end().ends_with("COMMIT;"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
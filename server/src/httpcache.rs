@@ -0,0 +1,368 @@
+use crate::store::Snapshot;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+use warp::http::{header, Response, StatusCode};
+use warp::hyper::body::{Body, Bytes};
+use warp::Filter;
+
+/// bodies smaller than that are sent as they are, compression would not save a packet
+const MIN_COMPRESSED: usize = 860;
+/// responses kept for one snapshot, the rest are rendered on every request
+const MAX_ENTRIES: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Identity,
+    Gzip,
+    Brotli,
+}
+
+impl Encoding {
+    /// best encoding accepted by the client, brotli is preferred over gzip
+    pub fn negotiate(accept_encoding: Option<&str>) -> Self {
+        let mut gzip = false;
+        let mut brotli = false;
+        for part in accept_encoding.unwrap_or_default().split(',') {
+            let mut params = part.split(';');
+            let name = params.next().unwrap_or_default().trim().to_lowercase();
+            let rejected = params.any(|p| match p.trim().strip_prefix("q=") {
+                Some(q) => q.trim().parse::<f64>().map(|q| q <= 0.0).unwrap_or(false),
+                None => false,
+            });
+            if rejected {
+                continue;
+            }
+            match name.as_str() {
+                "br" => brotli = true,
+                "gzip" => gzip = true,
+                _ => {}
+            }
+        }
+        if brotli {
+            Self::Brotli
+        } else if gzip {
+            Self::Gzip
+        } else {
+            Self::Identity
+        }
+    }
+
+    /// value of the Content-Encoding header
+    pub fn header(&self) -> Option<&'static str> {
+        match self {
+            Self::Identity => None,
+            Self::Gzip => Some("gzip"),
+            Self::Brotli => Some("br"),
+        }
+    }
+}
+
+/// request headers that decide what the response should be
+#[derive(Debug, Clone, Default)]
+pub struct Conditions {
+    pub accept_encoding: Option<String>,
+    pub if_none_match: Option<String>,
+    pub if_modified_since: Option<String>,
+}
+
+impl Conditions {
+    /// whether the client already has the response of the snapshot with this validator
+    pub fn not_modified(&self, etag: &str, published_at: DateTime<Utc>) -> bool {
+        if let Some(tags) = &self.if_none_match {
+            // If-Modified-Since is ignored when If-None-Match is present
+            return tags.split(',').any(|tag| {
+                let tag = tag.trim();
+                let tag = tag.strip_prefix("W/").unwrap_or(tag).trim_matches('"');
+                // encoded variants share the validator of the snapshot
+                tag == "*" || tag.split('+').next() == Some(etag)
+            });
+        }
+        if let Some(since) = &self.if_modified_since {
+            if let Ok(since) = httpdate::parse_http_date(since) {
+                let since: DateTime<Utc> = since.into();
+                return published_at.timestamp() <= since.timestamp();
+            }
+        }
+        false
+    }
+}
+
+/// conditional and content negotiation headers of the request
+pub fn conditions() -> impl Filter<Extract = (Conditions,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("accept-encoding")
+        .and(warp::header::optional::<String>("if-none-match"))
+        .and(warp::header::optional::<String>("if-modified-since"))
+        .map(
+            |accept_encoding, if_none_match, if_modified_since| Conditions {
+                accept_encoding,
+                if_none_match,
+                if_modified_since,
+            },
+        )
+}
+
+/// rendered response body, compressed variants are built on the first request for them
+#[derive(Debug)]
+pub struct Entry {
+    content_type: &'static str,
+    body: Bytes,
+    gzip: OnceLock<Bytes>,
+    brotli: OnceLock<Bytes>,
+}
+
+impl Entry {
+    pub fn new(content_type: &'static str, body: Vec<u8>) -> Self {
+        Self {
+            content_type,
+            body: Bytes::from(body),
+            gzip: OnceLock::new(),
+            brotli: OnceLock::new(),
+        }
+    }
+
+    pub fn json<T: Serialize>(value: &T) -> Self {
+        let body = serde_json::to_vec(value).expect("value could not be converted to JSON");
+        Self::new("application/json", body)
+    }
+
+    pub fn html(body: String) -> Self {
+        Self::new("text/html; charset=utf-8", body.into_bytes())
+    }
+
+    /// body in the requested encoding, small bodies are never compressed
+    pub fn encoded(&self, encoding: Encoding) -> (Encoding, Bytes) {
+        if self.body.len() < MIN_COMPRESSED {
+            return (Encoding::Identity, self.body.clone());
+        }
+        let body = match encoding {
+            Encoding::Identity => self.body.clone(),
+            Encoding::Gzip => self.gzip.get_or_init(|| gzip(&self.body)).clone(),
+            Encoding::Brotli => self.brotli.get_or_init(|| brotli(&self.body)).clone(),
+        };
+        (encoding, body)
+    }
+}
+
+fn gzip(body: &[u8]) -> Bytes {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(body).expect("gzip failed");
+    Bytes::from(encoder.finish().expect("gzip failed"))
+}
+
+fn brotli(body: &[u8]) -> Bytes {
+    // quality 5 keeps the first request for the large state fast
+    let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+    encoder.write_all(body).expect("brotli failed");
+    Bytes::from(encoder.into_inner())
+}
+
+/// Responses rendered from the current snapshot.
+/// Everything is dropped as soon as a request comes with the newer snapshot
+#[derive(Debug, Default)]
+pub struct ResponseCache {
+    entries: Mutex<(u64, HashMap<String, Arc<Entry>>)>,
+}
+
+impl ResponseCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// cached response for the key, rendered if it is missing or belongs to the older snapshot
+    pub fn get<F>(&self, snapshot: &Snapshot, key: &str, render: F) -> Arc<Entry>
+    where
+        F: FnOnce() -> Entry,
+    {
+        let stale = {
+            let mut entries = self.entries.lock().unwrap();
+            if entries.0 < snapshot.version {
+                *entries = (snapshot.version, HashMap::new());
+            }
+            if let Some(entry) = entries.1.get(key).filter(|_| entries.0 == snapshot.version) {
+                crate::metrics::CACHE_HITS.inc();
+                return entry.clone();
+            }
+            entries.0 != snapshot.version
+        };
+        // requests that loaded the older snapshot are rendered without the cache
+        if stale {
+            return Arc::new(render());
+        }
+        crate::metrics::CACHE_MISSES.inc();
+        // the lock is not held while rendering, concurrent misses render the same response
+        let entry = Arc::new(render());
+        let mut entries = self.entries.lock().unwrap();
+        if entries.0 == snapshot.version && entries.1.len() < MAX_ENTRIES {
+            entries.1.insert(key.to_owned(), entry.clone());
+        }
+        entry
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().1.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// response with the validators of the snapshot, or 304 if the client has it already
+pub fn reply(snapshot: &Snapshot, conditions: &Conditions, entry: &Entry) -> warp::reply::Response {
+    let etag = snapshot.etag();
+    let modified = httpdate::fmt_http_date(SystemTime::from(snapshot.published_at));
+    let (encoding, body) =
+        entry.encoded(Encoding::negotiate(conditions.accept_encoding.as_deref()));
+    let tag = match encoding.header() {
+        Some(name) => format!("\"{}+{}\"", etag, name),
+        None => format!("\"{}\"", etag),
+    };
+    let builder = Response::builder()
+        .header(header::ETAG, tag)
+        .header(header::LAST_MODIFIED, modified)
+        .header(header::CACHE_CONTROL, "no-cache")
+        .header(header::VARY, "Accept-Encoding");
+    if conditions.not_modified(&etag, snapshot.published_at) {
+        return builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap();
+    }
+    with_body(builder, encoding, body, entry)
+}
+
+/// response that changes with time and not only with the snapshot,
+/// so it has no validators and is rendered on every request
+pub fn reply_fresh(conditions: &Conditions, entry: &Entry) -> warp::reply::Response {
+    let (encoding, body) =
+        entry.encoded(Encoding::negotiate(conditions.accept_encoding.as_deref()));
+    let builder = Response::builder()
+        .header(header::CACHE_CONTROL, "no-store")
+        .header(header::VARY, "Accept-Encoding");
+    with_body(builder, encoding, body, entry)
+}
+
+fn with_body(
+    builder: warp::http::response::Builder,
+    encoding: Encoding,
+    body: Bytes,
+    entry: &Entry,
+) -> warp::reply::Response {
+    let builder = match encoding.header() {
+        Some(name) => builder.header(header::CONTENT_ENCODING, name),
+        None => builder,
+    };
+    builder
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, entry.content_type)
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client::state::AppState;
+
+    fn snapshot(version: u64, last_block: u64) -> Snapshot {
+        let mut app = AppState::new(1);
+        app.last_block = last_block;
+        Snapshot {
+            version,
+            published_at: Utc::now(),
+            app: Arc::new(app),
+        }
+    }
+
+    #[test]
+    pub fn it_negotiates_encoding() {
+        assert_eq!(Encoding::negotiate(None), Encoding::Identity);
+        assert_eq!(Encoding::negotiate(Some("gzip, deflate")), Encoding::Gzip);
+        assert_eq!(
+            Encoding::negotiate(Some("gzip, deflate, br")),
+            Encoding::Brotli
+        );
+        assert_eq!(
+            Encoding::negotiate(Some("br;q=0, gzip;q=0.5")),
+            Encoding::Gzip
+        );
+        assert_eq!(Encoding::negotiate(Some("identity")), Encoding::Identity);
+    }
+
+    #[test]
+    pub fn it_replies_not_modified() {
+        let s = snapshot(1, 100);
+        let entry = Entry::html("<p>tracker</p>".repeat(100));
+        let res = reply(&s, &Conditions::default(), &entry);
+        assert_eq!(res.status(), StatusCode::OK);
+        let etag = res.headers()[header::ETAG].to_str().unwrap().to_owned();
+        let modified = res.headers()[header::LAST_MODIFIED]
+            .to_str()
+            .unwrap()
+            .to_owned();
+
+        let cond = Conditions {
+            if_none_match: Some(etag.clone()),
+            ..Default::default()
+        };
+        assert_eq!(reply(&s, &cond, &entry).status(), StatusCode::NOT_MODIFIED);
+        // validator of the encoded variant matches the same snapshot
+        let cond = Conditions {
+            accept_encoding: Some("gzip".to_owned()),
+            if_none_match: Some(format!("W/\"{}+gzip\"", s.etag())),
+            ..Default::default()
+        };
+        let res = reply(&s, &cond, &entry);
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        let cond = Conditions {
+            if_modified_since: Some(modified),
+            ..Default::default()
+        };
+        assert_eq!(reply(&s, &cond, &entry).status(), StatusCode::NOT_MODIFIED);
+
+        let newer = snapshot(2, 101);
+        let cond = Conditions {
+            accept_encoding: Some("br".to_owned()),
+            if_none_match: Some(etag),
+            ..Default::default()
+        };
+        let res = reply(&newer, &cond, &entry);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::CONTENT_ENCODING], "br");
+    }
+
+    #[test]
+    pub fn it_replies_fresh_without_validators() {
+        let s = snapshot(1, 100);
+        let entry = Entry::json(&"unstakes".repeat(200));
+        let cond = Conditions {
+            accept_encoding: Some("gzip".to_owned()),
+            if_none_match: Some(format!("\"{}\"", s.etag())),
+            if_modified_since: Some(httpdate::fmt_http_date(SystemTime::now())),
+        };
+        let res = reply_fresh(&cond, &entry);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get(header::ETAG).is_none());
+        assert!(res.headers().get(header::LAST_MODIFIED).is_none());
+        assert_eq!(res.headers()[header::CONTENT_ENCODING], "gzip");
+    }
+
+    #[test]
+    pub fn it_invalidates_on_new_snapshot() {
+        let cache = ResponseCache::new();
+        let (first, second) = (snapshot(1, 100), snapshot(2, 101));
+        let a = cache.get(&first, "api/state", || Entry::html("a".to_owned()));
+        let b = cache.get(&first, "api/state", || Entry::html("b".to_owned()));
+        assert!(Arc::ptr_eq(&a, &b));
+        cache.get(&first, "page/rewards", || Entry::html("r".to_owned()));
+        assert_eq!(cache.len(), 2);
+
+        let c = cache.get(&second, "api/state", || Entry::html("c".to_owned()));
+        assert_eq!(c.body, Bytes::from("c"));
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -51,6 +51,14 @@ pub fn replace(content: &str, start: &'static str, end: &'static str, replacemen
     content.to_string()
 }
 
+/// escapes the text to be placed inside of JavaScript template literal in the script tag
+pub fn template_literal(src: &str) -> String {
+    src.replace('\\', "\\\\")
+        .replace('`', "\\`")
+        .replace("${", "\\${")
+        .replace("</", "<\\/")
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -83,6 +91,14 @@ mod test {
         assert_eq!(res.as_str(), "this © can(`change`) ©r should(`{}`) a © lot");
     }
 
+    #[test]
+    fn it_escapes_template_literal() {
+        let res = template_literal(r#"{"a":"`${x}` \\n </script>"}"#);
+        assert_eq!(res.as_str(), r#"{"a":"\`\${x}\` \\\\n <\/script>"}"#);
+        let res = it("main(`{}`)", "main(`", "`)", &res);
+        assert!(!res.contains("</script>"));
+    }
+
     #[test]
     fn it_replaces_tag() {
         let res = replace(
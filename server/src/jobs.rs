@@ -0,0 +1,222 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Semaphore;
+use tokio::task::LocalSet;
+
+pub type JobResult = anyhow::Result<()>;
+// futures of web3 transport are not Send,
+// so jobs are not moved between threads and run on the local task set
+type JobFuture = Pin<Box<dyn Future<Output = JobResult>>>;
+type JobFn = Rc<dyn Fn() -> JobFuture>;
+
+/// Status of the background job, as it is reported in API and metrics
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JobStatus {
+    pub name: String,
+    /// seconds between runs
+    pub interval: u64,
+    /// seconds before the run is cancelled
+    pub timeout: u64,
+    /// whether the job is running now
+    pub running: bool,
+    /// number of finished runs
+    pub runs: u64,
+    /// number of runs that failed or timed out
+    pub failures: u64,
+    pub last_started: Option<DateTime<Utc>>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    /// duration of the last run in milliseconds
+    pub last_duration_ms: u64,
+}
+
+/// Named async job that is repeated with the given interval
+pub struct Job {
+    pub name: String,
+    pub interval: Duration,
+    /// max random delay that is added to every interval,
+    /// so jobs started together do not hit RPC at the same time
+    pub jitter: Duration,
+    pub timeout: Duration,
+    run: JobFn,
+}
+
+impl Job {
+    pub fn new<F, Fut>(name: &str, interval: Duration, f: F) -> Self
+    where
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = JobResult> + 'static,
+    {
+        Self {
+            name: name.to_owned(),
+            interval,
+            jitter: Duration::from_secs(0),
+            timeout: interval,
+            run: Rc::new(move || Box::pin(f())),
+        }
+    }
+
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+// random delay up to the given limit
+fn random_delay(max: Duration) -> Duration {
+    let max_ms = max.as_millis() as u64;
+    if max_ms == 0 {
+        return Duration::from_secs(0);
+    }
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or(0);
+    Duration::from_millis(nanos % max_ms)
+}
+
+/// Runs background jobs, allowing only limited number of them to run at the same time
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    limit: Arc<Semaphore>,
+    status: Arc<Mutex<BTreeMap<String, JobStatus>>>,
+}
+
+impl Scheduler {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            limit: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            status: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    /// statuses of all jobs, ordered by name
+    pub fn status(&self) -> Vec<JobStatus> {
+        self.status.lock().unwrap().values().cloned().collect()
+    }
+
+    fn update<F: FnOnce(&mut JobStatus)>(&self, name: &str, f: F) {
+        if let Some(status) = self.status.lock().unwrap().get_mut(name) {
+            f(status);
+        }
+    }
+
+    /// runs the job once, waiting for a free slot and recording the result
+    pub async fn run(&self, job: &Job) -> JobResult {
+        let _permit = self.limit.acquire().await?;
+        self.update(&job.name, |s| {
+            s.running = true;
+            s.last_started = Some(Utc::now());
+        });
+        let start = Instant::now();
+        let result = match tokio::time::timeout(job.timeout, (job.run)()).await {
+            Ok(x) => x,
+            Err(_) => Err(anyhow::Error::msg(format!(
+                "timed out after {:?}",
+                job.timeout
+            ))),
+        };
+        let elapsed = start.elapsed();
+        self.update(&job.name, |s| {
+            s.running = false;
+            s.runs += 1;
+            s.last_duration_ms = elapsed.as_millis() as u64;
+            match &result {
+                Ok(_) => s.last_success = Some(Utc::now()),
+                Err(e) => {
+                    s.failures += 1;
+                    s.last_error = Some(format!("{}", e));
+                    s.last_error_at = Some(Utc::now());
+                }
+            }
+        });
+        match &result {
+            Ok(_) => tracing::info!("job {} finished in {:?}", job.name, elapsed),
+            Err(e) => tracing::warn!("job {} failed in {:?}: {}", job.name, elapsed, e),
+        };
+        result
+    }
+
+    /// registers the job and keeps running it in background on the given task set
+    pub fn spawn(&self, local: &LocalSet, job: Job) -> tokio::task::JoinHandle<()> {
+        self.status.lock().unwrap().insert(
+            job.name.clone(),
+            JobStatus {
+                name: job.name.clone(),
+                interval: job.interval.as_secs(),
+                timeout: job.timeout.as_secs(),
+                ..Default::default()
+            },
+        );
+        let scheduler = self.clone();
+        local.spawn_local(async move {
+            tokio::time::sleep(random_delay(job.jitter)).await;
+            loop {
+                let _ = scheduler.run(&job).await;
+                tokio::time::sleep(job.interval + random_delay(job.jitter)).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn register(scheduler: &Scheduler, job: &Job) {
+        scheduler.status.lock().unwrap().insert(
+            job.name.clone(),
+            JobStatus {
+                name: job.name.clone(),
+                ..Default::default()
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn it_records_success_and_errors() {
+        let scheduler = Scheduler::new(1);
+        let ok = Job::new("ok", Duration::from_secs(60), || async { Ok(()) });
+        let failing = Job::new("failing", Duration::from_secs(60), || async {
+            Err(anyhow::Error::msg("rpc failure"))
+        });
+        register(&scheduler, &ok);
+        register(&scheduler, &failing);
+        assert!(scheduler.run(&ok).await.is_ok());
+        assert!(scheduler.run(&failing).await.is_err());
+
+        let status = scheduler.status();
+        assert_eq!(status[0].name, "failing");
+        assert_eq!(status[0].failures, 1);
+        assert_eq!(status[0].last_error, Some("rpc failure".to_owned()));
+        assert!(status[0].last_success.is_none());
+        assert_eq!(status[1].runs, 1);
+        assert!(status[1].last_success.is_some());
+        assert!(!status[1].running);
+    }
+
+    #[tokio::test]
+    async fn it_cancels_on_timeout() {
+        let scheduler = Scheduler::new(1);
+        let slow = Job::new("slow", Duration::from_secs(60), || async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok(())
+        })
+        .timeout(Duration::from_millis(10));
+        register(&scheduler, &slow);
+        assert!(scheduler.run(&slow).await.is_err());
+        assert_eq!(scheduler.status()[0].failures, 1);
+    }
+}
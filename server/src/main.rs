@@ -1,27 +1,45 @@
+pub mod apiv1;
 pub mod args;
 pub mod cache;
+pub mod commands;
+pub mod config;
 pub mod contracts;
 pub mod dumper;
 pub mod endpoints;
 pub mod ens;
+pub mod errors;
+pub mod export;
+pub mod httpcache;
 pub mod inject;
+pub mod jobs;
 pub mod metrics;
+pub mod mirror;
+pub mod networks;
 pub mod reader;
+pub mod store;
+pub mod sync;
 pub mod treasury;
 pub mod web3sync;
+pub mod webhooks;
 
-use args::DumpMode;
-use chrono::Utc;
-use client::state::{AppState, OnChainEvent};
+use args::{Args, Command, ConfigCommand};
+use client::state::{AppState, EnsRecords, OnChainEvent, QuarantinedEvent};
+use config::{Config, RefreshConfig};
+use errors::SyncError;
 use futures::{FutureExt, StreamExt};
-use std::collections::{BTreeMap, HashMap};
+use jobs::{Job, Scheduler};
+use networks::NetworkConfig;
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
 use tokio::sync::{mpsc, oneshot, RwLock};
 use tokio_stream::wrappers::UnboundedReceiverStream;
+use warp::filters::BoxedFilter;
 use warp::ws::{Message, WebSocket};
-use warp::Filter;
+use warp::{Filter, Reply};
 use web3::types::H160;
 
 static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
@@ -37,40 +55,81 @@ pub struct State {
     pub app: AppState,
     /// whether it is loading
     pub loading: bool,
+    /// snapshots of the application state for readers
+    pub store: Arc<store::Store>,
+    /// receivers of the new events
+    pub webhooks: webhooks::Webhooks,
+    /// database where events and the state are mirrored
+    pub mirror: mirror::MirrorHandle,
 }
 
 impl State {
-    pub fn new(subscribers: Subscribers, chain_id: u64) -> Self {
+    pub fn new(subscribers: Subscribers, chain_id: u64, store: Arc<store::Store>) -> Self {
         Self {
             subscribers,
             verbose: false,
             loading: true,
             app: AppState::new(chain_id),
+            store,
+            webhooks: webhooks::Webhooks::default(),
+            mirror: mirror::MirrorHandle::default(),
         }
     }
+
+    /// makes the current application state visible to readers
+    pub fn publish(&self) -> u64 {
+        let version = self.store.publish(self.app.clone());
+        self.mirror.state(self.store.app());
+        version
+    }
+}
+
+/// locks the server state, failing if the lock holder panicked
+fn lock(state: &Mutex<State>) -> anyhow::Result<MutexGuard<'_, State>> {
+    state
+        .lock()
+        .map_err(|_| anyhow::Error::msg("server state lock is poisoned"))
 }
 
 impl reader::EventHandler for State {
-    fn on(&mut self, e: OnChainEvent, log: web3::types::Log) -> () {
+    fn on(&mut self, e: OnChainEvent, log: web3::types::Log) -> Result<(), SyncError> {
+        let json_msg = serde_json::to_string(&e).unwrap_or_default();
         if self.verbose {
             // it becomes verbose in watching mode
-            tracing::info!("{}", serde_json::to_string(&e).unwrap());
+            tracing::info!("{}", json_msg);
         }
-        self.app.update(e.clone(), log);
-        if self.verbose {
+        // events are sent to webhooks once they are applied
+        let notified = if self.verbose { Some(e.clone()) } else { None };
+        // only events that were applied to the state are mirrored
+        let mirrored = e.clone();
+        self.app.update(e, log)?;
+        self.mirror.event(&mirrored);
+        if let Some(e) = notified {
+            // events are published one by one in watching mode,
+            // initial scan is published as a whole once it is finished
+            self.publish();
+            self.webhooks.notify(&e);
             futures::executor::block_on(async {
                 let list = self.subscribers.read().await;
                 // tracing::info!("sending to {:?} subscribers", list.len());
                 // broadcasting event to all subscribers
                 for (&subscriber_id, tx) in list.iter() {
-                    let json_msg = serde_json::to_string(&e).unwrap();
                     tracing::debug!("<sent to #{}> {}", subscriber_id, json_msg);
-                    if let Err(err) = tx.send(Ok(Message::text(json_msg))) {
+                    if let Err(err) = tx.send(Ok(Message::text(json_msg.clone()))) {
                         tracing::warn!("<disconnected #{}> {}", subscriber_id, err);
                     }
                 }
             });
         }
+        Ok(())
+    }
+
+    fn quarantine(&mut self, q: QuarantinedEvent) {
+        tracing::warn!("quarantined {} event: {}", q.kind, q.error);
+        self.app.quarantine.push(q);
+        if self.verbose {
+            self.publish();
+        }
     }
 }
 
@@ -124,372 +183,426 @@ async fn ws_disconnected(subscriber_id: usize, subscribers: &Subscribers) {
     tracing::info!("disconnected {}, {} online", subscriber_id, s.len());
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let args = match args::parse() {
-        Ok(x) => x,
-        Err(e) => return Err(anyhow::Error::msg(format!("Args parsing error {}", e))),
-    };
-    let addr_pool = H160::from_str(args.address_api3_pool.as_str()).expect("ADDR_API3_POOL");
-    let addr_token = H160::from_str(args.address_api3_token.as_str()).expect("ADDR_API3_TOKEN");
-    let addr_usdc_token =
-        H160::from_str(args.address_usdc_token.as_str()).expect("ADDR_USDC_TOKEN");
-    let addr_convenience =
-        H160::from_str(args.address_convenience.as_str()).expect("ADDR_API3_CONVENIENCE");
-    let addr_voting1 =
-        H160::from_str(args.address_voting1.as_str()).expect("ADDR_API3_VOTING_PRIMARY");
-    let addr_agent1 =
-        H160::from_str(args.address_agent1.as_str()).expect("ADDR_API3_AGENT_PRIMARY");
-    let addr_voting2 =
-        H160::from_str(args.address_voting2.as_str()).expect("ADDR_API3_VOTING_SECONDARY");
-    let addr_agent2 =
-        H160::from_str(args.address_agent2.as_str()).expect("ADDR_API3_AGENT_SECONDARY");
-
-    let mut treasury_tokens: BTreeMap<String, H160> = BTreeMap::new();
-    treasury_tokens.insert("USDC".into(), addr_usdc_token);
-    treasury_tokens.insert("API3".into(), addr_token);
-
-    if let Some(_) = args.rpc_endpoint.find(".ipc") {
-        return Err(anyhow::Error::msg("only HTTP(s) endpoint is allowed"));
-    }
-    let transport = web3::transports::Http::new(args.rpc_endpoint.as_str()).expect("HTTP endpoint");
-    let web3 = web3::Web3::new(transport);
-    let chain_id = web3.eth().chain_id().await?.as_u64();
-    let cache_dir = args.cache_dir.clone();
-
-    let mut addresses = vec![addr_pool, addr_convenience];
-    if let Some(address_api3_circulation) = args
-        .address_api3_circulation
-        .map(|x| H160::from_str(&x).expect("ADDR_API3_CIRCULATION"))
-    {
-        addresses.push(address_api3_circulation);
-    }
+/// DAO deployment that is synced and served
+pub struct Tenant {
+    pub name: String,
+    pub store: Arc<store::Store>,
+    pub subscribers: Subscribers,
+}
 
-    let mut scanner = reader::Scanner::new(
-        chain_id,
-        args.cache_dir.as_str(),
-        vec![addr_voting1, addr_agent1],
-        vec![addr_voting2, addr_agent2],
-        addresses,
-        args.genesis_block,
-        args.max_block,
-        args.rpc_batch_size,
-        &args.rpc_endpoint,
-    );
-
-    let socket_addr: std::net::SocketAddr = args.listen.parse().expect("invalid bind to listen");
-    let (tx, rx) = oneshot::channel();
-    // starting a "loading" only server
-    // and do not start if we are in dump-mode
-    let loading_server = match args.dump {
-        None => Some(tokio::spawn(async move {
-            let routes = endpoints::routes_loading();
-            let (_addr, server) = warp::serve(routes.with(warp::trace::request()))
-                .bind_with_graceful_shutdown(socket_addr, async {
-                    rx.await.ok();
-                });
-            server.await
-        })),
-        _ => None,
+/// syncs the state of the network and starts its watcher and background jobs
+async fn start(
+    net: &NetworkConfig,
+    config: &Config,
+    args: &Args,
+    scheduler: &Scheduler,
+    local: &tokio::task::LocalSet,
+    job_prefix: &str,
+) -> anyhow::Result<Tenant> {
+    let a = net.contracts.addresses()?;
+    let (web3, chain_id) = sync::connect(net).await?;
+    let cache_dir = sync::cache_dir(net)?;
+    let mut scanner = sync::scanner(net, &a, chain_id, &cache_dir, args.strict)?;
+
+    let treasury_tokens = net.treasury_tokens(&a)?;
+    let mut treasury_wallets = net.treasury_wallets(&a)?;
+    let info = config.info(net, &args.public_url);
+    let refresh = &config.refresh;
+    // ENS names are not cached if it is disabled
+    let ens_cache_dir = if config.cache.ens {
+        cache_dir.clone()
+    } else {
+        String::new()
     };
 
-    if let Some(mode) = &args.dump {
-        match mode {
-            DumpMode::Snapshot => {
-                let mut dumper = dumper::SnapshotBuilder::new(
-                    args.cache_dir.as_str(),
-                    chain_id,
-                    args.genesis_block,
-                );
-                scanner.scan(&web3, &mut dumper).await?;
-                dumper.done();
-            }
-            DumpMode::Unknown => {
-                let mut dumper = dumper::Unknown::new();
-                scanner.scan(&web3, &mut dumper).await?;
-                dumper.done();
-            }
-            DumpMode::Events => {
-                let mut dumper = dumper::Events::new();
-                scanner.scan(&web3, &mut dumper).await?;
-            }
-        };
-        std::process::exit(0);
-    }
-
-    let addr_circulation: Option<H160> = args
-        .address_circulation
-        .map(|x| H160::from_str(x.as_str()).expect("ADDR_API3_CIRCULATION"));
-
     // Keep track of all connected users, key is usize, value
     // is a websocket sender.
     let subscribers = Subscribers::default();
-    let server_state = State::new(subscribers.clone(), chain_id);
-    let state = Arc::new(Mutex::new(server_state));
-
-    let mut treasury_wallets: BTreeMap<String, H160> = BTreeMap::new();
-    treasury_wallets.insert("Primary Treasury".into(), addr_agent1);
-    treasury_wallets.insert("Secondary Treasury".into(), addr_agent2);
+    let mut initial = sync::new_state(net, chain_id);
+    initial.network = info.clone();
+    let store = Arc::new(store::Store::new(initial));
+    let mut server_state = State::new(subscribers.clone(), chain_id, store.clone());
+    server_state.app = sync::new_state(net, chain_id);
+    server_state.app.network = info;
+    server_state.webhooks = webhooks::Webhooks::new(&net.name, &config.webhooks);
+    if let Some(m) = &config.mirror {
+        // the mirror thread runs as long as the state
+        server_state.mirror = mirror::MirrorHandle::spawn(&m.url, &net.name)?.0;
+    }
 
-    // Turn our "state" into a new Filter...
-    let subscribers = warp::any().map(move || subscribers.clone());
+    // the initial sync is done before the state is shared, so no lock is held across RPC calls
     let _last_block = {
-        let rc = state.clone();
-        let last_block = scanner.scan(&web3, &mut *rc.lock().unwrap()).await?;
-        let mut s = rc.lock().unwrap();
+        let s = &mut server_state;
+        // vesting parameters of the pool are required to record rewards unlock time
+        s.app.pool_info = crate::contracts::Pool::new(&web3, a.api3_pool).read().await;
+        let last_block = scanner.scan(&web3, s).await?;
         tracing::info!(
-            "found: {} wallets, {} votings",
+            "{}: found {} wallets, {} votings",
+            net.name,
             s.app.wallets.len(),
             s.app.votings.len()
         );
-        s.app.pool_info = crate::contracts::Pool::new(&web3, addr_pool).read().await;
-        tracing::info!("pool info {:?}", s.app.pool_info);
-        if let Some(addr_supply) = addr_circulation {
-            s.app.circulation = crate::contracts::Supply::new(
-                &web3,
-                addr_supply,
-                addr_token,
-                addr_convenience,
-                addr_voting1,
-                addr_voting2,
-            )
-            .read()
-            .await;
-            tracing::info!("circulation info {:?}", s.app.circulation);
-            if let Some(ci) = &s.app.circulation {
-                treasury_wallets.insert("V1 Treasury".into(), ci.addr_v1_treasury);
-            }
-        }
-
-        s.app.treasuries =
-            crate::treasury::read_treasuries(&web3, &treasury_tokens, &treasury_wallets).await;
-        tracing::info!("treasuries {:?}", s.app.treasuries);
-
-        // re-read votings and extract static data for votes
-        let conv = crate::contracts::Convenience::new(&web3, addr_convenience);
-        let mut new_wallets: BTreeMap<H160, u64> = BTreeMap::new();
-        for (_, v) in &mut s.app.votings {
-            if let None = v.details {
-                let static_data = conv
-                    .get_voting_static_data(v.primary, v.creator, v.vote_id)
-                    .await;
-                println!("voting_static_data = {:?}", static_data);
-                if let Some(data) = static_data {
-                    v.votes_total = data.voting_power; // adjust with precise #
-                    let details = data.into_details();
-                    if let Some(action) = &details.action {
-                        if let Some(wallet) = action.wallet {
-                            new_wallets.insert(wallet.clone(), v.tm);
-                        }
+        sync::enrich(
+            &web3,
+            &a,
+            &mut s.app,
+            &treasury_tokens,
+            &mut treasury_wallets,
+        )
+        .await;
+        last_block
+    };
+    // one cache of ENS names is shared by the initial sync and the refreshing job
+    let ens =
+        Rc::new(crate::ens::ENS::new(&web3, ens_cache_dir.as_str()).with_ttl(config.cache.ens_ttl));
+    if !args.no_ens {
+        let addresses: Vec<H160> = server_state.app.wallets.keys().cloned().collect();
+        for addr in addresses {
+            match ens.lookup(&addr).await {
+                Ok(entry) => {
+                    if let Some(name) = &entry.name {
+                        tracing::info!("ENS for {:?} is {:?}", addr, name);
                     }
-                    v.details = Some(details);
+                    server_state.app.set_ens(&addr, entry.name, entry.records);
                 }
+                Err(e) => tracing::warn!("ENS for {:?} failed: {}", addr, e),
             }
         }
-        for (wallet, tm) in new_wallets {
-            s.app.grants.insert(wallet, tm);
-            // insert wallets that are missing
-            if let None = s.app.wallets_events.get(&wallet) {
-                s.app.wallets_events.insert(wallet.clone(), vec![]);
-                let mut w = client::state::Wallet::default();
-                w.delegated = BTreeMap::new();
-                w.address = wallet.clone();
-                w.created_at = tm;
-                s.app.wallets.insert(wallet.clone(), w);
-            }
-        }
-        last_block
-    };
-    if !args.no_ens {
-        let ens = crate::ens::ENS::new(&web3, args.cache_dir.as_str());
-        let rc = state.clone();
-        let mut s = rc.lock().unwrap();
-        for (addr, w) in &mut s.app.wallets {
-            if let Some(name) = ens.name(&addr).await {
-                tracing::info!("ENS for {:?} is {:?}", addr, name);
-                w.ens = Some(name);
-            };
+        if let Err(e) = ens.save() {
+            tracing::warn!("ENS cache was not saved: {}", e);
         }
         tracing::info!("done with ENS");
     }
-
-    loading_server.map(|server| {
-        tracing::info!("Killing temporary HTTP server");
-        let _ = tx.send(());
-        std::thread::sleep(std::time::Duration::from_secs(3)); // wait for server to shutdown
-        server.abort();
-    });
+    let state = Arc::new(Mutex::new(server_state));
+    let version = lock(&state)?.publish();
+    tracing::info!("{}: published state snapshot version {}", net.name, version);
 
     if args.watch {
-        let w3 = web3.clone();
-        let w3v = web3.clone();
-        let w3e = web3.clone();
         let rc = state.clone();
-        rc.lock().unwrap().verbose = true;
+        lock(&rc)?.verbose = true;
 
         let rc = state.clone();
         let rc2 = state.clone();
-        let addr = if args.watch_endpoint.len() > 0 {
-            args.watch_endpoint.clone()
-        } else {
-            args.rpc_endpoint.clone()
-        };
+        let addr = net.watch_endpoint();
 
+        let strict = args.strict;
         tokio::task::spawn_blocking(move || loop {
-            let lblock = {
-                let s = rc2.lock().unwrap();
-                s.app.last_block
+            let lblock = match lock(&rc2) {
+                Ok(s) => s.app.last_block,
+                Err(e) => {
+                    tracing::error!("watcher stopped: {}", e);
+                    return;
+                }
             };
             tracing::warn!("watcher starting at block {}", lblock);
             if let Err(e) = scanner.watch_http(&addr, lblock, rc.as_ref()) {
                 tracing::error!("watcher failure: {}", e);
+                if strict {
+                    return;
+                }
                 std::thread::sleep(std::time::Duration::from_secs(3));
             }
         });
-        let period = std::time::Duration::from_secs(20 * 60);
-        let ens_period = std::time::Duration::from_secs(15 * 60);
-        let rc = state.clone();
-        let w3t = web3.clone();
-
-        tokio::task::spawn_blocking(move || {
-            let mut interval = tokio::time::interval(period);
-            loop {
-                futures::executor::block_on(interval.tick());
-                tracing::info!("Reading Treasuries");
-                let out = futures::executor::block_on(crate::treasury::read_treasuries_box(
-                    &w3t,
-                    &treasury_tokens,
-                    &treasury_wallets,
-                ));
-                let mut s = rc.lock().unwrap();
-                s.app.treasuries = out.as_ref().clone();
-            }
-        });
-        let rc = state.clone();
-        tokio::task::spawn_blocking(move || {
-            let conv = crate::contracts::Convenience::new(&w3v, addr_convenience);
-            let mut interval = tokio::time::interval(period);
-            loop {
-                futures::executor::block_on(interval.tick());
-                // re-read votings and extract static data for votes
-                let mut s = rc.lock().unwrap();
-                tracing::info!("Re-reading Votings {}", s.app.votings.len());
-                for (_, v) in &mut s.app.votings {
-                    if let None = v.details {
-                        let static_data = futures::executor::block_on(
-                            conv.get_voting_static_data(v.primary, v.creator, v.vote_id),
-                        );
-                        println!("voting_static_data = {:?}", static_data);
-                        if let Some(data) = static_data {
-                            v.votes_total = data.voting_power; // adjust with precise #
-                            v.details = Some(data.into_details());
-                        }
+        let every = RefreshConfig::every;
+        let jitter = every(refresh.jitter);
+        let job_name = |name: &str| format!("{}{}", job_prefix, name);
+
+        scheduler.spawn(
+            local,
+            Job::new(&job_name("treasuries"), every(refresh.treasuries), {
+                let rc = state.clone();
+                let w3 = web3.clone();
+                move || {
+                    let rc = rc.clone();
+                    let w3 = w3.clone();
+                    let tokens = treasury_tokens.clone();
+                    let wallets = treasury_wallets.clone();
+                    async move {
+                        let out = crate::treasury::read_treasuries(&w3, &tokens, &wallets).await;
+                        let mut s = lock(&rc)?;
+                        s.app.treasuries = out;
+                        s.publish();
+                        Ok(())
                     }
                 }
+            })
+            .jitter(jitter)
+            .timeout(Duration::from_secs(5 * 60)),
+        );
+        scheduler.spawn(
+            local,
+            Job::new(&job_name("votings"), every(refresh.votings), {
+                let rc = state.clone();
+                let conv = Rc::new(crate::contracts::Convenience::new(&web3, a.convenience));
+                move || {
+                    let rc = rc.clone();
+                    let conv = conv.clone();
+                    async move {
+                        // re-read votings and extract static data for votes,
+                        // without holding the state while waiting for RPC
+                        let pending: Vec<(u64, bool, H160, u64)> = {
+                            let s = lock(&rc)?;
+                            s.app
+                                .votings
+                                .iter()
+                                .filter(|(_, v)| v.details.is_none())
+                                .map(|(vote_ref, v)| (*vote_ref, v.primary, v.creator, v.vote_id))
+                                .collect()
+                        };
+                        tracing::info!("Re-reading {} votings without details", pending.len());
+                        let mut found = vec![];
+                        for (vote_ref, primary, creator, vote_id) in pending {
+                            let static_data =
+                                conv.get_voting_static_data(primary, creator, vote_id).await;
+                            tracing::info!("voting_static_data = {:?}", static_data);
+                            if let Some(data) = static_data {
+                                found.push((vote_ref, data));
+                            }
+                        }
 
-                if let Some(the_last) = &mut s.app.the_last {
-                    the_last.votings = Some(Utc::now());
+                        let mut s = lock(&rc)?;
+                        for (vote_ref, data) in found {
+                            if let Some(v) = s.app.votings.get_mut(&vote_ref) {
+                                v.votes_total = data.voting_power; // adjust with precise #
+                                v.details = Some(data.into_details());
+                            }
+                        }
+                        s.publish();
+                        Ok(())
+                    }
                 }
-            }
-        });
+            })
+            .jitter(jitter)
+            .timeout(Duration::from_secs(10 * 60)),
+        );
 
         if !args.no_ens {
-            let rc = state.clone();
-            tokio::task::spawn_blocking(move || {
-                let ens = crate::ens::ENS::new(&w3e, &cache_dir);
-                let mut interval = tokio::time::interval(ens_period);
-                loop {
-                    futures::executor::block_on(interval.tick());
-                    let start = std::time::Instant::now();
-                    let wallets: Vec<H160> = {
-                        let s = rc.lock().unwrap();
-                        s.app
-                            .wallets
-                            .iter()
-                            .map(|(addr, wallet)| match &wallet.ens {
-                                Some(_) => None,
-                                None => Some(addr.clone()),
-                            })
-                            .flatten()
-                            .collect()
-                    };
-                    tracing::info!(
-                        "Reading ENS started {} wallets missing names",
-                        wallets.len()
-                    );
-                    for addr in wallets {
-                        futures::executor::block_on(async {
-                            if let Some(name) = ens.name(&addr).await {
-                                let mut s = rc.lock().unwrap();
-                                tracing::info!("New ENS for {:?} is {:?}", addr, name);
-                                if let Some(the_last) = &mut s.app.the_last {
-                                    the_last.votings = Some(Utc::now());
+            scheduler.spawn(
+                local,
+                Job::new(&job_name("ens"), every(refresh.ens), {
+                    let rc = state.clone();
+                    let ens = ens.clone();
+                    move || {
+                        let rc = rc.clone();
+                        let ens = ens.clone();
+                        async move {
+                            // only expired names are read from the chain again
+                            let wallets: Vec<(H160, Option<String>, EnsRecords)> = {
+                                let s = lock(&rc)?;
+                                s.app
+                                    .wallets
+                                    .iter()
+                                    .map(|(addr, w)| (*addr, w.ens.clone(), w.ens_records.clone()))
+                                    .collect()
+                            };
+                            tracing::info!("Reading ENS of {} wallets", wallets.len());
+                            let mut changed = vec![];
+                            for (addr, name, records) in wallets {
+                                match ens.lookup(&addr).await {
+                                    Ok(entry) if entry.name != name || entry.records != records => {
+                                        tracing::info!(
+                                            "ENS for {:?} is now {:?}",
+                                            addr,
+                                            entry.name
+                                        );
+                                        changed.push((addr, entry));
+                                    }
+                                    Ok(_) => {}
+                                    Err(e) => tracing::warn!("ENS for {:?} failed: {}", addr, e),
+                                }
+                            }
+                            ens.save()?;
+                            if !changed.is_empty() {
+                                let mut s = lock(&rc)?;
+                                for (addr, entry) in changed {
+                                    s.app.set_ens(&addr, entry.name, entry.records);
                                 }
-                                s.app.wallets.get_mut(&addr).unwrap().ens = Some(name)
+                                s.publish();
                             }
-                        });
+                            Ok(())
+                        }
                     }
-                    tracing::info!("Reading ENS finished {:?}", start.elapsed());
-                }
-            });
+                })
+                .jitter(jitter)
+                .timeout(Duration::from_secs(14 * 60)),
+            );
         }
 
-        // one more thread fto update ppol and circulation hourly
-        if let Some(addr_supply) = addr_circulation {
-            let rc = state.clone();
-            tokio::task::spawn_blocking(move || {
-                let mut interval = tokio::time::interval(period);
-                let contract_pool = crate::contracts::Pool::new(&w3, addr_pool.clone());
-                let contract_circulation = crate::contracts::Supply::new(
-                    &w3,
-                    addr_supply,
-                    addr_token,
-                    addr_convenience,
-                    addr_voting1,
-                    addr_voting2,
-                );
-                loop {
-                    futures::executor::block_on(async {
-                        interval.tick().await;
-                        if let Some(pool) = contract_pool.read().await {
-                            tracing::info!("pool info {:?}", pool);
-                            let mut s = rc.lock().unwrap();
-                            s.app.pool_info = Some(pool);
-                            if let Some(the_last) = &mut s.app.the_last {
-                                the_last.circulation = Some(Utc::now());
-                            }
-                        } else {
-                            tracing::info!("pool info - failed to update");
-                        }
-                        if let Some(circulation) = contract_circulation.read().await {
+        scheduler.spawn(
+            local,
+            Job::new(&job_name("pool"), every(refresh.pool), {
+                let rc = state.clone();
+                let contract_pool = Rc::new(crate::contracts::Pool::new(&web3, a.api3_pool));
+                move || {
+                    let rc = rc.clone();
+                    let contract_pool = contract_pool.clone();
+                    async move {
+                        let pool = match contract_pool.read().await {
+                            Some(x) => x,
+                            None => return Err(anyhow::Error::msg("pool info is not available")),
+                        };
+                        tracing::info!("pool info {:?}", pool);
+                        let mut s = lock(&rc)?;
+                        s.app.pool_info = Some(pool);
+                        s.publish();
+                        Ok(())
+                    }
+                }
+            })
+            .jitter(jitter)
+            .timeout(Duration::from_secs(2 * 60)),
+        );
+
+        if let Some(addr_supply) = a.circulation {
+            scheduler.spawn(
+                local,
+                Job::new(&job_name("circulation"), every(refresh.circulation), {
+                    let rc = state.clone();
+                    let contract_circulation = Rc::new(crate::contracts::Supply::new(
+                        &web3,
+                        addr_supply,
+                        a.api3_token,
+                        a.convenience,
+                        a.voting_primary,
+                        a.voting_secondary,
+                    ));
+                    move || {
+                        let rc = rc.clone();
+                        let contract_circulation = contract_circulation.clone();
+                        async move {
+                            let circulation = match contract_circulation.read().await {
+                                Some(x) => x,
+                                None => {
+                                    return Err(anyhow::Error::msg(
+                                        "circulation info is not available",
+                                    ))
+                                }
+                            };
                             tracing::info!("circulation info {:?}", circulation);
-                            let mut s = rc.lock().unwrap();
+                            let mut s = lock(&rc)?;
                             s.app.circulation = Some(circulation);
-                            if let Some(the_last) = &mut s.app.the_last {
-                                the_last.circulation = Some(Utc::now());
-                            }
-                        } else {
-                            tracing::info!("circulation info - failed to update");
+                            s.publish();
+                            Ok(())
                         }
-                    })
-                }
-            });
+                    }
+                })
+                .jitter(jitter)
+                .timeout(Duration::from_secs(2 * 60)),
+            );
         }
+    }
+    Ok(Tenant {
+        name: net.name.clone(),
+        store,
+        subscribers,
+    })
+}
+
+type Routes = BoxedFilter<(warp::reply::Response,)>;
+
+fn into_response<R: Reply>(reply: R) -> warp::reply::Response {
+    reply.into_response()
+}
 
-        let chat = warp::path("ws").and(warp::ws()).and(subscribers).map(
-            |ws: warp::ws::Ws, subscribers| {
+fn tenant_routes(t: &Tenant, static_dir: &str, scheduler: &Scheduler, watch: bool) -> Routes {
+    let routes = endpoints::routes(static_dir.to_owned(), t.store.clone(), scheduler.clone())
+        .map(into_response)
+        .boxed();
+    if !watch {
+        return routes;
+    }
+    let subscribers = t.subscribers.clone();
+    let subscribers = warp::any().map(move || subscribers.clone());
+    let chat =
+        warp::path("ws")
+            .and(warp::ws())
+            .and(subscribers)
+            .map(|ws: warp::ws::Ws, subscribers| {
                 ws.on_upgrade(move |socket| ws_connected(socket, subscribers))
-            },
-        );
-        let routes = endpoints::routes(args.static_dir.clone(), state).or(chat);
-        warp::serve(routes.with(warp::trace::request()))
-            .run(socket_addr)
-            .await;
+                    .into_response()
+            });
+    routes.or(chat).unify().boxed()
+}
+
+/// every network is served under its prefix,
+/// a single network is also served from the root as before
+fn routes(tenants: &[Tenant], config: &Config, args: &Args, scheduler: &Scheduler) -> Routes {
+    let static_dir = args.static_dir.as_str();
+    let mut routes = if tenants.len() == 1 {
+        tenant_routes(&tenants[0], static_dir, scheduler, args.watch)
     } else {
-        let routes = endpoints::routes(args.static_dir.clone(), state);
-        warp::serve(routes.with(warp::trace::request()))
-            .run(socket_addr)
-            .await;
+        let first = config.info(&config.networks[0], &args.public_url);
+        let home = first.network_url(&first.name);
+        let root = warp::path::end().map(move || {
+            let uri = warp::http::Uri::from_str(&home).unwrap_or_default();
+            warp::redirect::temporary(uri).into_response()
+        });
+        let liveness = warp::path!("_liveness").map(|| "# API3 DAO Tracker".into_response());
+        let files = warp::fs::dir(args.static_dir.clone()).map(into_response);
+        root.or(liveness).unify().or(files).unify().boxed()
+    };
+    for t in tenants {
+        let prefixed = warp::path(t.name.clone())
+            .and(tenant_routes(t, static_dir, scheduler, args.watch))
+            .boxed();
+        routes = prefixed.or(routes).unify().boxed();
+    }
+    routes
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = match args::parse() {
+        Ok(x) => x,
+        Err(e) => return Err(anyhow::Error::msg(format!("Args parsing error {}", e))),
+    };
+    if let Some(Command::Config(ConfigCommand::Check)) = &args.cmd {
+        // the config is checked before it is loaded, to report all problems
+        return commands::config_check(&args).await;
+    }
+    let config = match &args.config {
+        Some(path) => Config::load(path, &args.cache_dir)?,
+        None => Config::from_args(&args).validated()?,
+    };
+    match &args.cmd {
+        None | Some(Command::Serve) => {}
+        Some(cmd) => return commands::run(cmd, &config, &args).await,
     }
+
+    let socket_addr: std::net::SocketAddr = args.listen.parse()?;
+    let (tx, rx) = oneshot::channel();
+    // starting a "loading" only server
+    let loading_server = tokio::spawn(async move {
+        let routes = endpoints::routes_loading();
+        let (_addr, server) = warp::serve(routes.with(warp::trace::request()))
+            .bind_with_graceful_shutdown(socket_addr, async {
+                rx.await.ok();
+            });
+        server.await
+    });
+
+    let scheduler = Scheduler::new(args.max_jobs);
+    let local = tokio::task::LocalSet::new();
+    let mut tenants = vec![];
+    for net in &config.networks {
+        // jobs are named by network if there are several of them
+        let job_prefix = if config.networks.len() > 1 {
+            format!("{}/", net.name)
+        } else {
+            String::new()
+        };
+        tenants.push(start(net, &config, &args, &scheduler, &local, &job_prefix).await?);
+    }
+
+    tracing::info!("Killing temporary HTTP server");
+    let _ = tx.send(());
+    std::thread::sleep(std::time::Duration::from_secs(3)); // wait for server to shutdown
+    loading_server.abort();
+
+    let routes = routes(&tenants, &config, &args, &scheduler);
+    // background jobs are running as long as the server
+    local
+        .run_until(warp::serve(routes.with(warp::trace::request())).run(socket_addr))
+        .await;
     Ok(())
 }
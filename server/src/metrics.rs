@@ -1,8 +1,9 @@
+use crate::jobs::JobStatus;
 use client::nice;
 use client::state::AppState;
 use lazy_static::lazy_static;
-use prometheus::{opts, register_gauge, register_int_gauge};
-use prometheus::{Encoder, Gauge, IntGauge, Opts, Registry, TextEncoder};
+use prometheus::{opts, register_gauge, register_int_counter, register_int_gauge};
+use prometheus::{Encoder, Gauge, IntCounter, IntGauge, Opts, Registry, TextEncoder};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 lazy_static! {
@@ -68,29 +69,24 @@ lazy_static! {
         register_gauge!(opts!("dao_time_locked", "Time Locked",)).unwrap();
     pub static ref TOTAL_LOCKED: Gauge =
         register_gauge!(opts!("dao_total_locked", "Total Locked",)).unwrap();
-    pub static ref SINCE_LAST_VOTINGS: IntGauge = register_int_gauge!(opts!(
-        "dao_since_last_votings_read",
-        "Seconds since the last votings read",
+    pub static ref SNAPSHOT_VERSION: IntGauge = register_int_gauge!(opts!(
+        "sync_snapshot_version",
+        "Version of the published state snapshot",
     ))
     .unwrap();
-    pub static ref SINCE_LAST_ENS: IntGauge = register_int_gauge!(opts!(
-        "dao_since_last_ens_read",
-        "Seconds since the last ENS read",
+    pub static ref CACHE_HITS: IntCounter = register_int_counter!(opts!(
+        "http_cache_hits",
+        "Responses served from the render cache",
     ))
     .unwrap();
-    pub static ref SINCE_LAST_TREASURIES: IntGauge = register_int_gauge!(opts!(
-        "dao_since_last_treasuries_read",
-        "Seconds since the last treasuries read",
-    ))
-    .unwrap();
-    pub static ref SINCE_LAST_CIRCULATION: IntGauge = register_int_gauge!(opts!(
-        "dao_since_last_circulation_read",
-        "Seconds since the last circulation read",
+    pub static ref CACHE_MISSES: IntCounter = register_int_counter!(opts!(
+        "http_cache_misses",
+        "Responses rendered for the render cache",
     ))
     .unwrap();
 }
 
-pub fn handler(state: &AppState) -> String {
+pub fn handler(state: &AppState, jobs: &[JobStatus]) -> String {
     let encoder = TextEncoder::new();
     let sr = Registry::new();
     sr.register(Box::new(UP.clone())).unwrap();
@@ -103,6 +99,9 @@ pub fn handler(state: &AppState) -> String {
     sr.register(Box::new(EPOCH_INDEX.clone())).unwrap();
     sr.register(Box::new(APR.clone())).unwrap();
     sr.register(Box::new(LAST_BLOCK.clone())).unwrap();
+    sr.register(Box::new(SNAPSHOT_VERSION.clone())).unwrap();
+    sr.register(Box::new(CACHE_HITS.clone())).unwrap();
+    sr.register(Box::new(CACHE_MISSES.clone())).unwrap();
     // pool info
     sr.register(Box::new(GENESIS_APR.clone())).unwrap();
     sr.register(Box::new(MIN_APR.clone())).unwrap();
@@ -118,13 +117,6 @@ pub fn handler(state: &AppState) -> String {
     sr.register(Box::new(LOCKED_VESTINGS.clone())).unwrap();
     sr.register(Box::new(TIME_LOCKED.clone())).unwrap();
     sr.register(Box::new(TOTAL_LOCKED.clone())).unwrap();
-    // seconds, passed since various events
-    sr.register(Box::new(SINCE_LAST_VOTINGS.clone())).unwrap();
-    sr.register(Box::new(SINCE_LAST_ENS.clone())).unwrap();
-    sr.register(Box::new(SINCE_LAST_TREASURIES.clone()))
-        .unwrap();
-    sr.register(Box::new(SINCE_LAST_CIRCULATION.clone()))
-        .unwrap();
 
     NUM_ADDRESSES.set(state.wallets.len() as i64);
     NUM_VOTINGS.set(state.votings.len() as i64);
@@ -168,22 +160,36 @@ pub fn handler(state: &AppState) -> String {
             Some(sys_time) => secs_now - sys_time.timestamp(),
             None => -1,
         });
-        SINCE_LAST_VOTINGS.set(match the_last.votings {
-            Some(sys_time) => secs_now - sys_time.timestamp(),
-            None => -1,
-        });
-        SINCE_LAST_ENS.set(match the_last.ens {
-            Some(sys_time) => secs_now - sys_time.timestamp(),
-            None => -1,
-        });
-        SINCE_LAST_TREASURIES.set(match the_last.treasuries {
-            Some(sys_time) => secs_now - sys_time.timestamp(),
-            None => -1,
-        });
-        SINCE_LAST_CIRCULATION.set(match the_last.circulation {
+    }
+    for job in jobs {
+        let labels =
+            |name: &str, help: &str| Opts::new(name, help).const_label("job", job.name.as_str());
+        let running =
+            IntGauge::with_opts(labels("job_running", "Whether the job is running")).unwrap();
+        running.set(job.running as i64);
+        let runs = IntGauge::with_opts(labels("job_runs", "Number of finished job runs")).unwrap();
+        runs.set(job.runs as i64);
+        let failures =
+            IntGauge::with_opts(labels("job_failures", "Number of failed job runs")).unwrap();
+        failures.set(job.failures as i64);
+        let duration = IntGauge::with_opts(labels(
+            "job_last_duration_ms",
+            "Duration of the last job run in milliseconds",
+        ))
+        .unwrap();
+        duration.set(job.last_duration_ms as i64);
+        let since_success = IntGauge::with_opts(labels(
+            "job_since_last_success",
+            "Seconds since the last successful job run",
+        ))
+        .unwrap();
+        since_success.set(match job.last_success {
             Some(sys_time) => secs_now - sys_time.timestamp(),
             None => -1,
         });
+        for gauge in [running, runs, failures, duration, since_success] {
+            sr.register(Box::new(gauge)).unwrap();
+        }
     }
 
     UP.set(1);
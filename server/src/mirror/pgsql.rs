@@ -0,0 +1,101 @@
+use super::{Backend, Statement};
+use crate::export::sqldump::SqlValue;
+use postgres::types::ToSql;
+use postgres::{Client, NoTls};
+
+/// Postgres database, the primary target of the mirror
+pub struct Postgres {
+    client: Client,
+}
+
+impl Postgres {
+    pub fn connect(url: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: Client::connect(url, NoTls)?,
+        })
+    }
+}
+
+fn param(value: &SqlValue) -> Box<dyn ToSql + Sync> {
+    match value {
+        SqlValue::Null => Box::new(None::<String>),
+        SqlValue::Bool(x) => Box::new(*x),
+        SqlValue::Int(x) => Box::new(*x),
+        SqlValue::Float(x) => Box::new(*x),
+        SqlValue::Text(x) | SqlValue::Amount(x) => Box::new(x.clone()),
+    }
+}
+
+impl Backend for Postgres {
+    fn batch(&mut self, sql: &str) -> anyhow::Result<()> {
+        self.client.batch_execute(sql)?;
+        Ok(())
+    }
+
+    fn transaction(&mut self, statements: &[Statement]) -> anyhow::Result<()> {
+        let mut tx = self.client.transaction()?;
+        for st in statements {
+            let params: Vec<Box<dyn ToSql + Sync>> = st.params.iter().map(param).collect();
+            let refs: Vec<&(dyn ToSql + Sync)> = params.iter().map(|x| x.as_ref()).collect();
+            tx.execute(st.sql.as_str(), &refs)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn row(&mut self, sql: &str, params: &[SqlValue]) -> anyhow::Result<Option<Vec<i64>>> {
+        let params: Vec<Box<dyn ToSql + Sync>> = params.iter().map(param).collect();
+        let refs: Vec<&(dyn ToSql + Sync)> = params.iter().map(|x| x.as_ref()).collect();
+        let res = match self.client.query_opt(sql, &refs)? {
+            Some(row) => Some(
+                (0..row.len())
+                    .map(|i| row.try_get::<_, i64>(i))
+                    .collect::<Result<Vec<i64>, _>>()?,
+            ),
+            None => None,
+        };
+        Ok(res)
+    }
+
+    /// amounts are sent as text, that is converted into NUMERIC
+    fn placeholder(&self, index: usize, value: &SqlValue) -> String {
+        match value {
+            SqlValue::Amount(_) => format!("${}::TEXT::NUMERIC", index),
+            _ => format!("${}", index),
+        }
+    }
+
+    fn amount_type(&self) -> &'static str {
+        "NUMERIC(78, 0)"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::export::tests::staked;
+    use crate::mirror::Mirror;
+    use client::state::{AppState, Wallet};
+    use web3::types::{H160, U256};
+
+    #[test]
+    #[ignore]
+    pub fn it_mirrors_to_postgres() {
+        // DATABASE_URL=postgres://postgres@localhost/api3 cargo test -- --ignored
+        let url = std::env::var("DATABASE_URL").unwrap();
+        let mut m = Mirror::open(&url, "test").unwrap();
+        m.event(&staked(1)).unwrap();
+        m.flush().unwrap();
+        assert!(m.cursor().is_some());
+
+        let mut app = AppState::new(1);
+        let addr = H160::from_low_u64_be(1);
+        let w = Wallet {
+            address: addr,
+            ens: Some("o'neil.eth".into()),
+            staked: U256::exp10(30),
+            ..Default::default()
+        };
+        app.wallets.insert(addr, w);
+        m.state(&app).unwrap();
+    }
+}
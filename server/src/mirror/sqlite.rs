@@ -0,0 +1,77 @@
+use super::{Backend, Statement};
+use crate::export::sqldump::SqlValue;
+use rusqlite::types::{Null, ToSqlOutput};
+use rusqlite::{params_from_iter, Connection, OptionalExtension, ToSql};
+
+/// Embedded database in a file, used when Postgres is not available
+pub struct Sqlite {
+    conn: Connection,
+}
+
+impl Sqlite {
+    pub fn open(path: &str) -> anyhow::Result<Self> {
+        if path == ":memory:" {
+            return Self::memory();
+        }
+        Ok(Self {
+            conn: Connection::open(path)?,
+        })
+    }
+
+    pub fn memory() -> anyhow::Result<Self> {
+        Ok(Self {
+            conn: Connection::open_in_memory()?,
+        })
+    }
+}
+
+impl ToSql for SqlValue {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(match self {
+            Self::Null => ToSqlOutput::from(Null),
+            Self::Bool(x) => ToSqlOutput::from(*x),
+            Self::Int(x) => ToSqlOutput::from(*x),
+            Self::Float(x) => ToSqlOutput::from(*x),
+            Self::Text(x) | Self::Amount(x) => ToSqlOutput::from(x.as_str()),
+        })
+    }
+}
+
+impl Backend for Sqlite {
+    fn batch(&mut self, sql: &str) -> anyhow::Result<()> {
+        self.conn.execute_batch(sql)?;
+        Ok(())
+    }
+
+    fn transaction(&mut self, statements: &[Statement]) -> anyhow::Result<()> {
+        let tx = self.conn.transaction()?;
+        for st in statements {
+            tx.prepare_cached(&st.sql)?
+                .execute(params_from_iter(st.params.iter()))?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn row(&mut self, sql: &str, params: &[SqlValue]) -> anyhow::Result<Option<Vec<i64>>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let columns = stmt.column_count();
+        let res = stmt
+            .query_row(params_from_iter(params.iter()), |row| {
+                (0..columns)
+                    .map(|i| row.get::<_, i64>(i))
+                    .collect::<Result<Vec<i64>, _>>()
+            })
+            .optional()?;
+        Ok(res)
+    }
+
+    fn placeholder(&self, index: usize, _: &SqlValue) -> String {
+        format!("?{}", index)
+    }
+
+    /// amounts are kept as decimal texts, as numbers lose precision
+    fn amount_type(&self) -> &'static str {
+        "TEXT"
+    }
+}
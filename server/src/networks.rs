@@ -0,0 +1,263 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use web3::types::H160;
+
+/// Contracts of the DAO deployment, addresses could be without 0x prefix
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ContractsConfig {
+    pub api3_token: String,
+    pub api3_pool: String,
+    pub usdc_token: String,
+    pub convenience: String,
+    pub voting_primary: String,
+    pub agent_primary: String,
+    pub voting_secondary: String,
+    pub agent_secondary: String,
+    /// API3 contract with supply (optional)
+    pub circulation: Option<String>,
+}
+
+/// Parsed addresses of the DAO contracts
+#[derive(Debug, Clone)]
+pub struct Addresses {
+    pub api3_token: H160,
+    pub api3_pool: H160,
+    pub usdc_token: H160,
+    pub convenience: H160,
+    pub voting_primary: H160,
+    pub agent_primary: H160,
+    pub voting_secondary: H160,
+    pub agent_secondary: H160,
+    pub circulation: Option<H160>,
+}
+
+pub fn address(name: &str, value: &str) -> anyhow::Result<H160> {
+    H160::from_str(value).map_err(|e| anyhow::Error::msg(format!("{}: {}", name, e)))
+}
+
+impl ContractsConfig {
+    /// all configured contracts with their names in config
+    pub fn named(&self) -> Vec<(&'static str, &str)> {
+        let mut res = vec![
+            ("api3_token", self.api3_token.as_str()),
+            ("api3_pool", self.api3_pool.as_str()),
+            ("usdc_token", self.usdc_token.as_str()),
+            ("convenience", self.convenience.as_str()),
+            ("voting_primary", self.voting_primary.as_str()),
+            ("agent_primary", self.agent_primary.as_str()),
+            ("voting_secondary", self.voting_secondary.as_str()),
+            ("agent_secondary", self.agent_secondary.as_str()),
+        ];
+        if let Some(x) = &self.circulation {
+            res.push(("circulation", x.as_str()));
+        }
+        res
+    }
+
+    pub fn addresses(&self) -> anyhow::Result<Addresses> {
+        Ok(Addresses {
+            api3_token: address("api3_token", &self.api3_token)?,
+            api3_pool: address("api3_pool", &self.api3_pool)?,
+            usdc_token: address("usdc_token", &self.usdc_token)?,
+            convenience: address("convenience", &self.convenience)?,
+            voting_primary: address("voting_primary", &self.voting_primary)?,
+            agent_primary: address("agent_primary", &self.agent_primary)?,
+            voting_secondary: address("voting_secondary", &self.voting_secondary)?,
+            agent_secondary: address("agent_secondary", &self.agent_secondary)?,
+            circulation: match &self.circulation {
+                Some(x) => Some(address("circulation", x)?),
+                None => None,
+            },
+        })
+    }
+}
+
+/// ERC20 token, which balance is shown for treasuries
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenConfig {
+    pub symbol: String,
+    pub address: String,
+    /// decimals of the token, if it is not one of the known tokens
+    pub decimals: Option<usize>,
+}
+
+/// wallet that is shown as DAO treasury
+#[derive(Debug, Clone, Deserialize)]
+pub struct TreasuryConfig {
+    pub name: String,
+    pub address: String,
+}
+
+fn default_batch_size() -> u64 {
+    500
+}
+
+/// DAO deployment that is tracked by the server
+#[derive(Debug, Clone, Deserialize)]
+pub struct NetworkConfig {
+    /// name of the network, used as URL prefix and cache namespace
+    pub name: String,
+    /// Ethereum JSON+RPC HTTP address
+    pub rpc_endpoint: String,
+    /// another endpoint that would be used to watch events
+    #[serde(default)]
+    pub watch_endpoint: String,
+    #[serde(default = "default_batch_size")]
+    pub rpc_batch_size: u64,
+    /// block explorer, used for links to transactions and addresses
+    pub explorer_url: Option<String>,
+    #[serde(default)]
+    pub testnet: bool,
+    /// expected chain ID of the RPC endpoint
+    pub chain_id: Option<u64>,
+    /// number of the first block to start watching
+    pub genesis_block: u64,
+    /// max block to stop contract events listening
+    pub max_block: Option<u64>,
+    /// cache folder, defaults to the network subfolder of the cache
+    pub cache_dir: Option<String>,
+    pub contracts: ContractsConfig,
+    /// tokens of treasuries, USDC and API3 tokens of the contracts by default
+    #[serde(default, rename = "token")]
+    pub tokens: Vec<TokenConfig>,
+    /// treasuries, primary and secondary agents by default
+    #[serde(default, rename = "treasury")]
+    pub treasuries: Vec<TreasuryConfig>,
+}
+
+impl NetworkConfig {
+    pub fn watch_endpoint(&self) -> String {
+        if !self.watch_endpoint.is_empty() {
+            self.watch_endpoint.clone()
+        } else {
+            self.rpc_endpoint.clone()
+        }
+    }
+
+    /// tokens which balances are read for treasuries
+    pub fn treasury_tokens(&self, a: &Addresses) -> anyhow::Result<BTreeMap<String, H160>> {
+        let mut res = BTreeMap::new();
+        if self.tokens.is_empty() {
+            res.insert("USDC".to_owned(), a.usdc_token);
+            res.insert("API3".to_owned(), a.api3_token);
+        }
+        for t in &self.tokens {
+            res.insert(t.symbol.clone(), address(&t.symbol, &t.address)?);
+        }
+        Ok(res)
+    }
+
+    /// wallets that are shown as treasuries
+    pub fn treasury_wallets(&self, a: &Addresses) -> anyhow::Result<BTreeMap<String, H160>> {
+        let mut res = BTreeMap::new();
+        if self.treasuries.is_empty() {
+            res.insert("Primary Treasury".to_owned(), a.agent_primary);
+            res.insert("Secondary Treasury".to_owned(), a.agent_secondary);
+        }
+        for t in &self.treasuries {
+            res.insert(t.name.clone(), address(&t.name, &t.address)?);
+        }
+        Ok(res)
+    }
+
+    /// problems of the network that could be found without connecting to it
+    pub fn problems(&self) -> Vec<String> {
+        let mut res = vec![];
+        let mut problem = |msg: String| res.push(format!("{}: {}", self.name, msg));
+        if !self.rpc_endpoint.starts_with("http://") && !self.rpc_endpoint.starts_with("https://") {
+            problem(format!(
+                "rpc_endpoint {:?} is not HTTP(s)",
+                self.rpc_endpoint
+            ));
+        }
+        for (name, value) in self.contracts.named() {
+            if let Err(e) = address(&format!("contracts.{}", name), value) {
+                problem(e.to_string());
+            }
+        }
+        for t in &self.tokens {
+            if let Err(e) = address(&format!("token {}", t.symbol), &t.address) {
+                problem(e.to_string());
+            }
+        }
+        for t in &self.treasuries {
+            if let Err(e) = address(&format!("treasury {}", t.name), &t.address) {
+                problem(e.to_string());
+            }
+        }
+        if let Some(max_block) = self.max_block {
+            if max_block < self.genesis_block {
+                problem(format!(
+                    "max_block {} is before genesis_block {}",
+                    max_block, self.genesis_block
+                ));
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contracts() -> ContractsConfig {
+        ContractsConfig {
+            api3_token: "0b38210ea11411557c13457D4dA7dC6ea731B88a".into(),
+            api3_pool: "6dd655f10d4b9e242ae186d9050b68f725c76d76".into(),
+            usdc_token: "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".into(),
+            convenience: "95087266018b9637aff3d76d4e0cad7e52c19636".into(),
+            voting_primary: "db6c812e439ce5c740570578681ea7aadba5170b".into(),
+            agent_primary: "d9f80bdb37e6bad114d747e60ce6d2aaf26704ae".into(),
+            voting_secondary: "1c8058e72e4902b3431ef057e8d9a58a73f26372".into(),
+            agent_secondary: "556ecbb0311d350491ba0ec7e019c354d7723ce0".into(),
+            circulation: None,
+        }
+    }
+
+    fn network() -> NetworkConfig {
+        NetworkConfig {
+            name: "mainnet".into(),
+            rpc_endpoint: "http://localhost:8545".into(),
+            watch_endpoint: "".into(),
+            rpc_batch_size: 500,
+            explorer_url: None,
+            testnet: false,
+            chain_id: Some(1),
+            genesis_block: 12786500,
+            max_block: None,
+            cache_dir: None,
+            contracts: contracts(),
+            tokens: vec![],
+            treasuries: vec![],
+        }
+    }
+
+    #[test]
+    pub fn it_has_default_treasuries() {
+        let net = network();
+        let a = net.contracts.addresses().unwrap();
+        let tokens = net.treasury_tokens(&a).unwrap();
+        assert_eq!(tokens.keys().collect::<Vec<_>>(), vec!["API3", "USDC"]);
+        let wallets = net.treasury_wallets(&a).unwrap();
+        assert_eq!(wallets["Primary Treasury"], a.agent_primary);
+        assert!(net.problems().is_empty());
+    }
+
+    #[test]
+    pub fn it_reports_all_problems() {
+        let mut net = network();
+        net.rpc_endpoint = "/tmp/geth.ipc".into();
+        net.contracts.api3_pool = "".into();
+        net.contracts.circulation = Some("0xnothex".into());
+        net.treasuries.push(TreasuryConfig {
+            name: "Grants".into(),
+            address: "123".into(),
+        });
+        net.max_block = Some(1);
+        let problems = net.problems();
+        assert_eq!(problems.len(), 5, "{:?}", problems);
+        assert!(problems[1].starts_with("mainnet: contracts.api3_pool: "));
+    }
+}
@@ -1,19 +1,53 @@
 use crate::cache::blockstime;
 use crate::cache::logsbatch::{self, BlockBatch};
 use crate::cache::prices;
+use crate::errors::SyncError;
 use crate::web3sync::EthClient;
 use chrono::NaiveDateTime;
+use client::abi::Abi;
 use client::events::{Api3, VotingAgent};
 use client::fees::TxFee;
-use client::state::OnChainEvent;
+use client::state::{OnChainEvent, QuarantinedEvent};
 use std::collections::BTreeMap;
 use std::sync::Mutex;
 use web3::api::Eth;
-use web3::types::{BlockId, FilterBuilder, Log, H160, H256};
+use web3::types::{BlockId, FilterBuilder, Log, H160, H256, U256};
 use web3::{Transport, Web3};
 
+/// attempts to read the block time before the event is applied with a fallback time
+const BLOCK_TIME_ATTEMPTS: u32 = 3;
+
 pub trait EventHandler {
-    fn on(&mut self, entry: OnChainEvent, l: Log) -> ();
+    fn on(&mut self, entry: OnChainEvent, l: Log) -> Result<(), SyncError>;
+
+    /// called for the log that was skipped because of the failure
+    fn quarantine(&mut self, q: QuarantinedEvent) {
+        tracing::warn!("quarantined {:?}", q);
+    }
+}
+
+// block hash, transaction hash, block number and log index,
+// that are missing for pending logs
+fn log_position(l: &Log) -> Result<(H256, H256, u64, u64), SyncError> {
+    let block_hash = l.block_hash.ok_or(SyncError::MissingField("block hash"))?;
+    let tx = l
+        .transaction_hash
+        .ok_or(SyncError::MissingField("transaction hash"))?;
+    let block_number = l
+        .block_number
+        .ok_or(SyncError::MissingField("block number"))?;
+    let log_index = l.log_index.ok_or(SyncError::MissingField("log index"))?;
+    Ok((block_hash, tx, block_number.as_u64(), log_index.as_u64()))
+}
+
+// fees of the transaction that could not be read
+fn unknown_fees() -> TxFee {
+    TxFee {
+        gas_price: U256::from(0),
+        gas: U256::from(0),
+        gas_used: None,
+        usd: None,
+    }
 }
 
 pub async fn get_batches<T: Transport>(
@@ -21,14 +55,10 @@ pub async fn get_batches<T: Transport>(
     genesis: u64,
     max: Option<u64>,
     batch_size: u64,
-) -> Vec<BlockBatch> {
+) -> Result<Vec<BlockBatch>, SyncError> {
     let max_block: u64 = match max {
         Some(x) => x,
-        None => eth
-            .block_number()
-            .await
-            .expect("max block height failure")
-            .as_u64(),
+        None => eth.block_number().await.map_err(SyncError::rpc)?.as_u64(),
     };
     let mut from = genesis;
     let mut res = vec![];
@@ -41,7 +71,7 @@ pub async fn get_batches<T: Transport>(
         res.push(BlockBatch { from, to });
         from = from + batch_size
     }
-    res
+    Ok(res)
 }
 
 #[derive(Debug, Clone)]
@@ -57,6 +87,14 @@ pub struct Scanner {
     batch_size: u64,
     blocks_time: BTreeMap<H256, u64>,
     fees: BTreeMap<H256, TxFee>,
+    /// whether to stop on the first failure instead of skipping the event
+    strict: bool,
+    /// definitions of the events of the watched contracts
+    abi: Abi,
+    /// whether to restore events from the latest snapshot instead of scanning
+    snapshots: bool,
+    /// time of the previous event, used when the block time cannot be read
+    last_tm: u64,
 }
 
 impl Scanner {
@@ -70,7 +108,8 @@ impl Scanner {
         max_block: Option<u64>,
         batch_size: u64,
         rpc_endpoint: &str,
-    ) -> Self {
+        strict: bool,
+    ) -> anyhow::Result<Self> {
         let mut addr_watched: Vec<H160> = addr.clone();
         addr_primary
             .iter()
@@ -79,7 +118,7 @@ impl Scanner {
             .iter()
             .for_each(|x| addr_watched.push(x.clone()));
 
-        Self {
+        Ok(Self {
             chain_id,
             cache_dir: cache_dir.to_owned(),
             rpc_endpoint: rpc_endpoint.to_string(),
@@ -91,8 +130,24 @@ impl Scanner {
             batch_size,
             blocks_time: blockstime::load(&cache_dir, chain_id),
             fees: prices::load(&cache_dir, chain_id),
-        }
+            strict,
+            abi: crate::contracts::events_abi()?,
+            snapshots: true,
+            last_tm: 0,
+        })
     }
+
+    /// disables restoring from snapshots, so all events are read from batches
+    pub fn snapshots(mut self, enabled: bool) -> Self {
+        self.snapshots = enabled;
+        self
+    }
+
+    /// checksum of the watched addresses, that is a part of cached batches names
+    pub fn cache_checksum(&self) -> u32 {
+        logsbatch::checksum(&self.addr_watched)
+    }
+
     pub fn agent(&self, address: H160) -> Option<VotingAgent> {
         let mut v: Option<VotingAgent> = None;
         if let Some(_) = self.addr_primary.iter().position(|&r| r == address) {
@@ -104,6 +159,103 @@ impl Scanner {
         v
     }
 
+    // skips the failed log, or stops syncing in strict mode
+    fn reject(
+        &self,
+        handler: &mut impl EventHandler,
+        l: &Log,
+        entry: Option<Api3>,
+        err: SyncError,
+    ) -> Result<(), SyncError> {
+        if self.strict {
+            return Err(err);
+        }
+        tracing::warn!("skipping log {:?}: {}", l.transaction_hash, err);
+        handler.quarantine(err.quarantine(l, entry));
+        Ok(())
+    }
+
+    // cache failures do not affect events, they only fail in strict mode
+    fn cache_failure(&self, err: anyhow::Error) -> Result<(), SyncError> {
+        let err = SyncError::cache(err);
+        if self.strict {
+            return Err(err);
+        }
+        tracing::warn!("{}", err);
+        Ok(())
+    }
+
+    // RPC failures of the event that was decoded do not skip it: the event is applied
+    // with the fallback time, or with unknown fees, unless in strict mode
+    fn rpc_failure<X>(&self, res: Result<X, SyncError>, fallback: X) -> Result<X, SyncError> {
+        match res {
+            Ok(x) => Ok(x),
+            Err(err) if self.strict => Err(err),
+            Err(err) => {
+                tracing::warn!("{}, applying event with defaults", err);
+                Ok(fallback)
+            }
+        }
+    }
+
+    // block time, that is retried on RPC failures
+    async fn block_time_retry<T>(
+        &mut self,
+        web3: &Web3<T>,
+        block_hash: H256,
+    ) -> Result<u64, SyncError>
+    where
+        T: Transport,
+    {
+        let mut attempt = 1;
+        loop {
+            match self.block_time(web3, block_hash).await {
+                Err(err) if attempt < BLOCK_TIME_ATTEMPTS => {
+                    tracing::warn!("block {:?} time, attempt {}: {}", block_hash, attempt, err);
+                    tokio::time::sleep(std::time::Duration::from_secs(attempt as u64)).await;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    async fn block_time<T>(&mut self, web3: &Web3<T>, block_hash: H256) -> Result<u64, SyncError>
+    where
+        T: Transport,
+    {
+        if let Some(x) = self.blocks_time.get(&block_hash) {
+            return Ok(*x);
+        }
+        let ts = match web3
+            .eth()
+            .block(BlockId::Hash(block_hash))
+            .await
+            .map_err(SyncError::rpc)?
+        {
+            Some(block) => block.timestamp.as_u64(),
+            None => return Err(SyncError::Rpc(format!("block {:?} not found", block_hash))),
+        };
+        self.blocks_time.insert(block_hash, ts);
+        Ok(ts)
+    }
+
+    fn tx_fees(&mut self, w3client: &EthClient, txkey: H256, ts: u64) -> Result<TxFee, SyncError> {
+        let dt = NaiveDateTime::from_timestamp(ts as i64, 0);
+        let fees = match self.fees.get(&txkey) {
+            Some(x) => x.from(dt),
+            None => {
+                let txfee = w3client.fees(txkey, dt).map_err(SyncError::rpc)?;
+                if let Err(err) = prices::save(&self.cache_dir, self.chain_id, &self.fees) {
+                    self.cache_failure(err)?;
+                }
+                txfee
+            }
+        };
+        self.fees.insert(txkey, fees.clone());
+        Ok(fees)
+    }
+
     async fn handle_logs<T>(
         &mut self,
         web3: &Web3<T>,
@@ -111,7 +263,7 @@ impl Scanner {
         handler: &mut impl EventHandler,
         w3client: &EthClient,
         logs: &Vec<Log>,
-    ) -> anyhow::Result<u64>
+    ) -> Result<u64, SyncError>
     where
         T: Transport,
     {
@@ -120,62 +272,45 @@ impl Scanner {
         let mut prices_dur = std::time::Duration::from_nanos(0);
         let mut handler_dur = std::time::Duration::from_nanos(0);
         for l in logs {
-            if let Ok(entry) = Api3::from_log(self.agent(l.address), &l) {
-                let blockstart = std::time::Instant::now();
-                let tmkey: H256 = l.block_hash.unwrap();
-                let ts: u64 = if self.blocks_time.contains_key(&tmkey) {
-                    match self.blocks_time.get(&tmkey) {
-                        Some(x) => x.clone(),
-                        None => web3
-                            .eth()
-                            .block(BlockId::Hash(l.block_hash.unwrap()))
-                            .await
-                            .expect("block failure")
-                            .expect("block timestamp failure")
-                            .timestamp
-                            .as_u64(),
-                    }
-                } else {
-                    web3.eth()
-                        .block(BlockId::Hash(l.block_hash.unwrap()))
-                        .await
-                        .expect("block failure")
-                        .expect("block timestamp failure")
-                        .timestamp
-                        .as_u64()
-                };
-                self.blocks_time.insert(tmkey, ts);
-                blocktime_dur += blockstart.elapsed();
+            let entry = match Api3::from_log(&self.abi, self.agent(l.address), &l) {
+                Ok(x) => x,
+                Err(err) => {
+                    self.reject(handler, l, None, err.into())?;
+                    continue;
+                }
+            };
+            let (tmkey, txkey, block_number, log_index) = match log_position(l) {
+                Ok(x) => x,
+                Err(err) => {
+                    self.reject(handler, l, Some(entry), err)?;
+                    continue;
+                }
+            };
 
-                let txkey: H256 = l.transaction_hash.unwrap();
-                let pricesstart = std::time::Instant::now();
-                let dt = NaiveDateTime::from_timestamp(ts as i64, 0);
+            let blockstart = std::time::Instant::now();
+            let res = self.block_time_retry(web3, tmkey).await;
+            let ts = self.rpc_failure(res, self.last_tm)?;
+            self.last_tm = ts;
+            blocktime_dur += blockstart.elapsed();
 
-                let fees = match self.fees.get(&txkey) {
-                    Some(x) => x.from(dt),
-                    None => {
-                        let txfee = w3client.fees(txkey, dt).unwrap();
-                        prices::save(&self.cache_dir, self.chain_id, &self.fees)?;
-                        txfee
-                    }
-                };
-                self.fees.insert(txkey, fees.clone());
-                prices_dur += pricesstart.elapsed();
+            let pricesstart = std::time::Instant::now();
+            let res = self.tx_fees(w3client, txkey, ts);
+            let fees = self.rpc_failure(res, unknown_fees())?;
+            prices_dur += pricesstart.elapsed();
 
-                let handlerstart = std::time::Instant::now();
-                handler.on(
-                    OnChainEvent {
-                        block_number: l.block_number.unwrap().as_u64(),
-                        tx: txkey,
-                        log_index: l.log_index.unwrap().as_u64(),
-                        entry,
-                        tm: ts,
-                        fees,
-                    },
-                    l.clone(),
-                );
-                handler_dur += handlerstart.elapsed();
+            let handlerstart = std::time::Instant::now();
+            let event = OnChainEvent {
+                block_number,
+                tx: txkey,
+                log_index,
+                entry: entry.clone(),
+                tm: ts,
+                fees,
+            };
+            if let Err(err) = handler.on(event, l.clone()) {
+                self.reject(handler, l, Some(entry), err)?;
             }
+            handler_dur += handlerstart.elapsed();
         }
 
         tracing::info!(
@@ -204,7 +339,11 @@ impl Scanner {
         let w3client = EthClient::new(&self.rpc_endpoint);
         let mut last_block = self.genesis_block;
 
-        if let Some(archive) = crate::cache::snapshot::load(&cache_dir, chain_id) {
+        let archive = match self.snapshots {
+            true => crate::cache::snapshot::load(&cache_dir, chain_id),
+            false => None,
+        };
+        if let Some(archive) = archive {
             crate::metrics::BLOCK_START_GAUGE.set(archive.start_block as i64);
             crate::metrics::BLOCK_END_GAUGE.set(archive.end_block as i64);
             let start = std::time::Instant::now();
@@ -212,13 +351,11 @@ impl Scanner {
                 "snapshot {}..{}/{}",
                 archive.start_block, archive.end_block, chain_id
             );
-            if let Err(e) = self
-                .handle_logs(web3, &method, handler, &w3client, &archive.logs)
-                .await
-            {
-                tracing::error!("error {}", e);
+            self.handle_logs(web3, &method, handler, &w3client, &archive.logs)
+                .await?;
+            if let Err(err) = blockstime::save(&self.cache_dir, chain_id, &self.blocks_time) {
+                self.cache_failure(err)?;
             }
-            blockstime::save(&self.cache_dir, chain_id, &self.blocks_time)?;
             tracing::info!("{} restored in {:?}", method, start.elapsed());
             crate::metrics::BLOCK_START_GAUGE.set(0);
             crate::metrics::BLOCK_END_GAUGE.set(0);
@@ -231,15 +368,27 @@ impl Scanner {
             self.max_block,
             self.batch_size,
         )
-        .await
+        .await?
         {
             crate::metrics::BLOCK_START_GAUGE.set(b.from as i64);
             crate::metrics::BLOCK_END_GAUGE.set(b.to as i64);
             let start = std::time::Instant::now();
             let mut method = "".to_owned();
             let _ = method; // dummy warning workaround
-            let logs: Vec<Log> = if logsbatch::exists(&cache_dir, chain_id, checksum, &b) {
-                let logs = logsbatch::load(&cache_dir, chain_id, checksum, &b).await?;
+            let cached: Option<Vec<Log>> = if logsbatch::exists(&cache_dir, chain_id, checksum, &b)
+            {
+                // broken cache file is replaced with logs from RPC
+                match logsbatch::load(&cache_dir, chain_id, checksum, &b).await {
+                    Ok(logs) => Some(logs),
+                    Err(err) => {
+                        self.cache_failure(err)?;
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            let logs: Vec<Log> = if let Some(logs) = cached {
                 method = format!(
                     "cached {}..{}/{} in {:?}",
                     b.from,
@@ -254,8 +403,10 @@ impl Scanner {
                     .to_block(b.to.into())
                     .address(self.addr_watched.clone())
                     .build();
-                let logs: Vec<Log> = web3.eth().logs(filter).await?;
-                logsbatch::save(&cache_dir, chain_id, checksum, &b, &logs).await?;
+                let logs: Vec<Log> = web3.eth().logs(filter).await.map_err(SyncError::rpc)?;
+                if let Err(err) = logsbatch::save(&cache_dir, chain_id, checksum, &b, &logs).await {
+                    self.cache_failure(err)?;
+                }
                 method = format!(
                     "scanned {}..{}/{} in {:?}",
                     b.from,
@@ -267,13 +418,11 @@ impl Scanner {
                 logs
             };
 
-            if let Err(e) = self
-                .handle_logs(web3, &method, handler, &w3client, &logs)
-                .await
-            {
-                tracing::error!("error {}", e);
+            self.handle_logs(web3, &method, handler, &w3client, &logs)
+                .await?;
+            if let Err(err) = blockstime::save(&self.cache_dir, chain_id, &self.blocks_time) {
+                self.cache_failure(err)?;
             }
-            blockstime::save(&self.cache_dir, chain_id, &self.blocks_time)?;
         }
         crate::metrics::BLOCK_START_GAUGE.set(0);
         crate::metrics::BLOCK_END_GAUGE.set(0);
@@ -297,7 +446,7 @@ impl Scanner {
             .from_block(from_block.into())
             .address(self.addr_watched.clone())
             .build();
-        let filter_id = w3client.new_filter(&filter).expect("new_filter error");
+        let filter_id = w3client.new_filter(&filter)?;
         crate::metrics::WATCHING.set(1);
         // let mut interval = tokio::time::interval(std::time::Duration::from_secs(20));
         loop {
@@ -307,49 +456,25 @@ impl Scanner {
             match w3client.filter_changes(filter_id) {
                 Ok(logs) => {
                     for l in logs {
-                        if let Ok(entry) = Api3::from_log(self.agent(l.address), &l) {
-                            let bhash: H256 = l.block_hash.expect("block hash");
-                            let block_number = l.block_number.expect("block number").as_u64();
-                            let tx = l.transaction_hash.expect("tx hash");
-                            let log_index = l.log_index.expect("log_index").as_u64();
-                            let tm = match self.blocks_time.get(&bhash) {
-                                Some(x) => *x,
-                                None => {
-                                    let tm = w3client
-                                        .block(bhash)
-                                        .expect("block timestamp failure")
-                                        .timestamp
-                                        .as_u64();
-                                    self.blocks_time.insert(bhash, tm);
-                                    blockstime::save(
-                                        &self.cache_dir,
-                                        self.chain_id,
-                                        &self.blocks_time,
-                                    )?;
-                                    tm
+                        let entry = Api3::from_log(&self.abi, self.agent(l.address), &l);
+                        // RPC calls are made before the handler is locked
+                        let result = match &entry {
+                            Ok(x) => self.watch_event(&w3client, &l, x.clone()),
+                            Err(err) => Err(err.clone().into()),
+                        };
+                        let mut handler = match handler_mux.lock() {
+                            Ok(x) => x,
+                            Err(_) => {
+                                return Err(anyhow::Error::msg("event handler mutex is poisoned"))
+                            }
+                        };
+                        match result {
+                            Ok(event) => {
+                                if let Err(err) = handler.on(event, l.clone()) {
+                                    self.reject(&mut *handler, &l, entry.ok(), err)?;
                                 }
-                            };
-                            let dt = NaiveDateTime::from_timestamp(tm as i64, 0);
-                            let fees = match self.fees.get(&bhash) {
-                                Some(x) => (*x).clone(),
-                                None => {
-                                    let txfee = w3client.fees(tx, dt).unwrap();
-                                    self.fees.insert(bhash, txfee.clone());
-                                    prices::save(&self.cache_dir, self.chain_id, &self.fees)?;
-                                    txfee
-                                }
-                            };
-                            handler_mux.lock().expect("unlock event handler mutex").on(
-                                OnChainEvent {
-                                    block_number,
-                                    tx,
-                                    log_index,
-                                    entry,
-                                    tm,
-                                    fees,
-                                },
-                                l,
-                            );
+                            }
+                            Err(err) => self.reject(&mut *handler, &l, entry.ok(), err)?,
                         }
                     }
                 }
@@ -360,4 +485,43 @@ impl Scanner {
             };
         }
     }
+
+    fn watch_event(
+        &mut self,
+        w3client: &EthClient,
+        l: &Log,
+        entry: Api3,
+    ) -> Result<OnChainEvent, SyncError> {
+        let (bhash, tx, block_number, log_index) = log_position(l)?;
+        let tm = match self.blocks_time.get(&bhash) {
+            Some(x) => *x,
+            None => match w3client.block(bhash) {
+                Ok(block) => {
+                    let tm = block.timestamp.as_u64();
+                    self.blocks_time.insert(bhash, tm);
+                    if let Err(err) =
+                        blockstime::save(&self.cache_dir, self.chain_id, &self.blocks_time)
+                    {
+                        self.cache_failure(err)?;
+                    }
+                    tm
+                }
+                // the block was just mined
+                Err(err) => {
+                    let now = chrono::Utc::now().timestamp() as u64;
+                    self.rpc_failure(Err(SyncError::rpc(err)), now)?
+                }
+            },
+        };
+        let res = self.tx_fees(w3client, tx, tm);
+        let fees = self.rpc_failure(res, unknown_fees())?;
+        Ok(OnChainEvent {
+            block_number,
+            tx,
+            log_index,
+            entry,
+            tm,
+            fees,
+        })
+    }
 }
@@ -0,0 +1,219 @@
+use arc_swap::ArcSwap;
+use chrono::{DateTime, Utc};
+use client::state::AppState;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Immutable version of the application state.
+/// Readers keep it as long as the request lasts, writers never change it
+#[derive(Debug)]
+pub struct Snapshot {
+    /// incremented on every publication
+    pub version: u64,
+    /// time of publication
+    pub published_at: DateTime<Utc>,
+    /// application state, shared between all requests of this version
+    pub app: Arc<AppState>,
+}
+
+impl Snapshot {
+    /// validator of the responses built from this snapshot,
+    /// changes with the synced block and with every refresh of the state
+    pub fn etag(&self) -> String {
+        format!(
+            "{}-{}",
+            self.app.last_block,
+            self.published_at.timestamp_millis()
+        )
+    }
+}
+
+/// Store of the published snapshots.
+/// Loading the current snapshot is lock-free, so readers never block writers,
+/// and writers publish a new copy of the state instead of changing it in place
+#[derive(Debug)]
+pub struct Store {
+    current: ArcSwap<Snapshot>,
+    version: AtomicU64,
+}
+
+impl Store {
+    pub fn new(app: AppState) -> Self {
+        Self {
+            current: ArcSwap::from_pointee(Snapshot {
+                version: 0,
+                published_at: Utc::now(),
+                app: Arc::new(app),
+            }),
+            version: AtomicU64::new(0),
+        }
+    }
+
+    /// current snapshot of the state
+    pub fn load(&self) -> Arc<Snapshot> {
+        self.current.load_full()
+    }
+
+    /// current application state
+    pub fn app(&self) -> Arc<AppState> {
+        self.current.load().app.clone()
+    }
+
+    /// replaces current snapshot with the given state, returns its version
+    pub fn publish(&self, app: AppState) -> u64 {
+        let version = self.version.fetch_add(1, Ordering::SeqCst) + 1;
+        self.current.store(Arc::new(Snapshot {
+            version,
+            published_at: Utc::now(),
+            app: Arc::new(app),
+        }));
+        crate::metrics::SNAPSHOT_VERSION.set(version as i64);
+        version
+    }
+
+    pub fn version(&self) -> u64 {
+        self.current.load().version
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client::state::Wallet;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+    use web3::types::H160;
+
+    fn sample_state(wallets: u64) -> AppState {
+        let mut app = AppState::new(1);
+        for n in 0..wallets {
+            let w = Wallet {
+                address: H160::from_low_u64_be(n + 1),
+                ..Default::default()
+            };
+            app.wallets.insert(w.address, w);
+        }
+        app
+    }
+
+    #[test]
+    pub fn it_publishes_versions() {
+        let store = Store::new(sample_state(1));
+        let before = store.load();
+        assert_eq!(store.publish(sample_state(2)), 1);
+        assert_eq!(store.publish(sample_state(3)), 2);
+        assert_eq!(store.version(), 2);
+        assert_eq!(store.app().wallets.len(), 3);
+        // previously loaded snapshot is not affected by publishing
+        assert_eq!(before.version, 0);
+        assert_eq!(before.app.wallets.len(), 1);
+    }
+
+    fn percentile(sorted: &[Duration], p: usize) -> Duration {
+        sorted[(sorted.len() - 1) * p / 100]
+    }
+
+    // Runs `readers` threads doing `requests` each while one writer keeps updating the state,
+    // returns sorted latencies of all requests
+    fn measure<R, W>(readers: usize, requests: usize, read: R, write: W) -> Vec<Duration>
+    where
+        R: Fn() -> usize + Send + Sync + 'static,
+        W: Fn(u64) + Send + Sync + 'static,
+    {
+        let read = Arc::new(read);
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let writer = {
+            let done = done.clone();
+            std::thread::spawn(move || {
+                let mut n = 0;
+                while !done.load(Ordering::Relaxed) {
+                    n += 1;
+                    write(n);
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            })
+        };
+        let handles: Vec<_> = (0..readers)
+            .map(|_| {
+                let read = read.clone();
+                std::thread::spawn(move || {
+                    let mut out = Vec::with_capacity(requests);
+                    for _ in 0..requests {
+                        let start = Instant::now();
+                        assert!(read() > 0);
+                        out.push(start.elapsed());
+                    }
+                    out
+                })
+            })
+            .collect();
+        let mut latencies: Vec<Duration> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+        done.store(true, Ordering::Relaxed);
+        writer.join().unwrap();
+        latencies.sort();
+        latencies
+    }
+
+    fn report(name: &str, latencies: &[Duration]) {
+        println!(
+            "{:>10}: p50 {:?}, p95 {:?}, p99 {:?}, max {:?}",
+            name,
+            percentile(latencies, 50),
+            percentile(latencies, 95),
+            percentile(latencies, 99),
+            latencies[latencies.len() - 1],
+        );
+    }
+
+    // Request latency under load, compared with the state behind a mutex
+    // that is cloned on every request. Run with
+    // cargo test --release store -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    pub fn bench_request_latency() {
+        let (wallets, readers, requests) = (5000, 8, 500);
+
+        let locked = Arc::new(Mutex::new(sample_state(wallets)));
+        let latencies = measure(
+            readers,
+            requests,
+            {
+                let locked = locked.clone();
+                move || {
+                    let app = locked.lock().unwrap().clone();
+                    app.wallets.len()
+                }
+            },
+            {
+                let locked = locked.clone();
+                move |n| {
+                    locked.lock().unwrap().last_block = n;
+                }
+            },
+        );
+        report("mutex", &latencies);
+
+        let store = Arc::new(Store::new(sample_state(wallets)));
+        let writer_state = Arc::new(Mutex::new(sample_state(wallets)));
+        let latencies = measure(
+            readers,
+            requests,
+            {
+                let store = store.clone();
+                move || store.app().wallets.len()
+            },
+            {
+                let store = store.clone();
+                move |n| {
+                    let mut app = writer_state.lock().unwrap();
+                    app.last_block = n;
+                    store.publish(app.clone());
+                }
+            },
+        );
+        report("snapshot", &latencies);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::errors::SyncError;
+use crate::networks::{Addresses, NetworkConfig};
+use crate::reader;
+use client::state::{AppState, OnChainEvent, QuarantinedEvent};
+use std::collections::BTreeMap;
+use web3::types::H160;
+
+pub type Web3Http = web3::Web3<web3::transports::Http>;
+
+/// connects to the network and returns its chain ID
+pub async fn connect(net: &NetworkConfig) -> anyhow::Result<(Web3Http, u64)> {
+    if let Some(_) = net.rpc_endpoint.find(".ipc") {
+        return Err(anyhow::Error::msg("only HTTP(s) endpoint is allowed"));
+    }
+    let transport = web3::transports::Http::new(net.rpc_endpoint.as_str())?;
+    let web3 = web3::Web3::new(transport);
+    let chain_id = web3.eth().chain_id().await?.as_u64();
+    Ok((web3, chain_id))
+}
+
+pub fn scanner(
+    net: &NetworkConfig,
+    a: &Addresses,
+    chain_id: u64,
+    cache_dir: &str,
+    strict: bool,
+) -> anyhow::Result<reader::Scanner> {
+    let mut addresses = vec![a.api3_pool, a.convenience];
+    if let Some(x) = a.circulation {
+        addresses.push(x);
+    }
+    reader::Scanner::new(
+        chain_id,
+        cache_dir,
+        vec![a.voting_primary, a.agent_primary],
+        vec![a.voting_secondary, a.agent_secondary],
+        addresses,
+        net.genesis_block,
+        net.max_block,
+        net.rpc_batch_size,
+        &net.rpc_endpoint,
+        strict,
+    )
+}
+
+pub fn cache_dir(net: &NetworkConfig) -> anyhow::Result<String> {
+    let dir = net.cache_dir.clone().unwrap_or_default();
+    if !dir.is_empty() {
+        std::fs::create_dir_all(&dir)?;
+    }
+    Ok(dir)
+}
+
+/// empty application state of the network
+pub fn new_state(net: &NetworkConfig, chain_id: u64) -> AppState {
+    let mut app = AppState::new(chain_id);
+    for t in &net.tokens {
+        if let Some(decimals) = t.decimals {
+            app.decimals.insert(t.symbol.clone(), decimals);
+        }
+    }
+    app
+}
+
+/// events are applied to the state without serving it
+impl reader::EventHandler for AppState {
+    fn on(&mut self, e: OnChainEvent, log: web3::types::Log) -> Result<(), SyncError> {
+        self.update(e, log)?;
+        Ok(())
+    }
+
+    fn quarantine(&mut self, q: QuarantinedEvent) {
+        tracing::warn!("quarantined {} event: {}", q.kind, q.error);
+        self.quarantine.push(q);
+    }
+}
+
+/// reads pool, circulation and treasuries, and details of the votings
+/// once all events are applied
+pub async fn enrich(
+    web3: &Web3Http,
+    a: &Addresses,
+    app: &mut AppState,
+    treasury_tokens: &BTreeMap<String, H160>,
+    treasury_wallets: &mut BTreeMap<String, H160>,
+) {
+    app.pool_info = crate::contracts::Pool::new(web3, a.api3_pool).read().await;
+    tracing::info!("pool info {:?}", app.pool_info);
+    if let Some(addr_supply) = a.circulation {
+        app.circulation = crate::contracts::Supply::new(
+            web3,
+            addr_supply,
+            a.api3_token,
+            a.convenience,
+            a.voting_primary,
+            a.voting_secondary,
+        )
+        .read()
+        .await;
+        tracing::info!("circulation info {:?}", app.circulation);
+        if let Some(ci) = &app.circulation {
+            treasury_wallets.insert("V1 Treasury".into(), ci.addr_v1_treasury);
+        }
+    }
+
+    app.treasuries =
+        crate::treasury::read_treasuries(web3, treasury_tokens, treasury_wallets).await;
+    tracing::info!("treasuries {:?}", app.treasuries);
+
+    // re-read votings and extract static data for votes
+    let conv = crate::contracts::Convenience::new(web3, a.convenience);
+    let mut new_wallets: BTreeMap<H160, u64> = BTreeMap::new();
+    for (_, v) in &mut app.votings {
+        if let None = v.details {
+            let static_data = conv
+                .get_voting_static_data(v.primary, v.creator, v.vote_id)
+                .await;
+            tracing::debug!("voting_static_data = {:?}", static_data);
+            if let Some(data) = static_data {
+                v.votes_total = data.voting_power; // adjust with precise #
+                let details = data.into_details();
+                if let Some(action) = &details.action {
+                    if let Some(wallet) = action.wallet {
+                        new_wallets.insert(wallet.clone(), v.tm);
+                    }
+                }
+                v.details = Some(details);
+            }
+        }
+    }
+    for (wallet, tm) in new_wallets {
+        app.grants.insert(wallet, tm);
+        // insert wallets that are missing
+        if let None = app.wallets_events.get(&wallet) {
+            app.wallets_events.insert(wallet.clone(), vec![]);
+            let mut w = client::state::Wallet::default();
+            w.delegated = BTreeMap::new();
+            w.address = wallet.clone();
+            w.created_at = tm;
+            app.search.wallet(&w);
+            app.wallets.insert(wallet.clone(), w);
+        }
+    }
+}
+
+/// network, which events were applied to the state
+pub struct Synced {
+    pub web3: Web3Http,
+    pub addresses: Addresses,
+    pub cache_dir: String,
+    pub app: AppState,
+}
+
+impl Synced {
+    /// applies events of the network to the new state, without the data of the contracts
+    pub async fn scan(
+        net: &NetworkConfig,
+        strict: bool,
+        snapshots: bool,
+    ) -> anyhow::Result<Synced> {
+        let a = net.contracts.addresses()?;
+        let (web3, chain_id) = connect(net).await?;
+        let cache_dir = cache_dir(net)?;
+        let mut scanner = scanner(net, &a, chain_id, &cache_dir, strict)?.snapshots(snapshots);
+        let mut app = new_state(net, chain_id);
+        // vesting parameters of the pool are required to record rewards unlock time
+        app.pool_info = crate::contracts::Pool::new(&web3, a.api3_pool).read().await;
+        scanner.scan(&web3, &mut app).await?;
+        tracing::info!(
+            "{}: found {} wallets, {} votings",
+            net.name,
+            app.wallets.len(),
+            app.votings.len()
+        );
+        Ok(Self {
+            web3,
+            addresses: a,
+            cache_dir,
+            app,
+        })
+    }
+
+    /// applies events of the network and reads the data of the contracts
+    pub async fn sync(
+        net: &NetworkConfig,
+        strict: bool,
+        snapshots: bool,
+    ) -> anyhow::Result<Synced> {
+        let mut res = Self::scan(net, strict, snapshots).await?;
+        let tokens = net.treasury_tokens(&res.addresses)?;
+        let mut wallets = net.treasury_wallets(&res.addresses)?;
+        enrich(
+            &res.web3,
+            &res.addresses,
+            &mut res.app,
+            &tokens,
+            &mut wallets,
+        )
+        .await;
+        Ok(res)
+    }
+}
@@ -57,14 +57,13 @@ where
     T: DeserializeOwned,
 {
     for v in response {
-        let id = v.as_object().unwrap().get("id").unwrap().as_str();
+        let id = v.get("id").and_then(|x| x.as_str());
         if let Some(id_val) = id {
             if id_val == id_match {
-                let s = serde_json::to_string(&v).unwrap();
-                if let Ok(err) = serde_json::from_str::<RpcErrorResponse>(&s) {
+                if let Ok(err) = serde_json::from_value::<RpcErrorResponse>(v.clone()) {
                     return Err(anyhow::Error::msg(err.error.message));
                 }
-                let out: RpcSingleResponse<T> = serde_json::from_str(&s).unwrap();
+                let out: RpcSingleResponse<T> = serde_json::from_value(v.clone())?;
                 return Ok(out.result);
             }
         }
@@ -94,7 +93,7 @@ impl EthClient {
             .set("Content-Type", "application/json");
         tracing::debug!("JSONRPC request={}", payload);
         let response: String = match rq.send_string(&payload) {
-            Ok(x) => x.into_string().unwrap(),
+            Ok(x) => x.into_string()?,
             Err(e) => return Err(anyhow::Error::new(e)),
         };
         if let Ok(err) = serde_json::from_str::<RpcErrorResponse>(&response) {
@@ -102,7 +101,7 @@ impl EthClient {
         }
 
         tracing::debug!("JSONRPC response={}", response);
-        Ok(serde_json::from_str::<T>(&response).unwrap())
+        Ok(serde_json::from_str::<T>(&response)?)
     }
 
     pub fn execute<T>(&self, method: &str, params: Params) -> anyhow::Result<T>
@@ -114,13 +113,12 @@ impl EthClient {
             id: "1".to_owned(),
             method: method.to_string(),
             params: params.clone(),
-        })
-        .unwrap();
+        })?;
         self.execute_str(&payload)
     }
 
     pub fn new_filter(&self, filter: &Filter) -> anyhow::Result<U256> {
-        let filter_str = serde_json::to_string(filter).expect("filter serialize failure");
+        let filter_str = serde_json::to_string(filter)?;
         let payload = format!(
             "{{\"jsonrpc\":\"2.0\",\"method\":\"eth_newFilter\",\"params\":[{}],\"id\":\"1\"}}",
             filter_str
@@ -180,10 +178,10 @@ impl EthClient {
             params: Params::Array(vec![txh.clone()]),
         };
         let batch: RpcBatchRequest = vec![rq1, rq2];
-        let payload = serde_json::to_string(&batch).unwrap();
+        let payload = serde_json::to_string(&batch)?;
         let response: RpcBatchResponse = self.execute_str(&payload)?;
-        let tx: Transaction = batch_fragment(&response, "hash").unwrap();
-        let receipt: Receipt = batch_fragment(&response, "receipt").unwrap();
+        let tx: Transaction = batch_fragment(&response, "hash")?;
+        let receipt: Receipt = batch_fragment(&response, "receipt")?;
         Ok(TxFee::new(&tx, &receipt, dt))
     }
 }
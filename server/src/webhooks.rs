@@ -0,0 +1,130 @@
+use client::state::OnChainEvent;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// HTTP endpoint that receives new events as JSON in watching mode
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// types of events to send, all events if empty
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// networks which events are sent, all networks if empty
+    #[serde(default)]
+    pub networks: Vec<String>,
+}
+
+impl WebhookConfig {
+    pub fn matches(&self, network: &str, kind: &str) -> bool {
+        (self.networks.is_empty() || self.networks.iter().any(|x| x == network))
+            && (self.events.is_empty() || self.events.iter().any(|x| x == kind))
+    }
+
+    pub fn problems(&self, networks: &[String]) -> Vec<String> {
+        let mut res = vec![];
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            res.push(format!("webhook {:?} is not HTTP(s)", self.url));
+        }
+        for net in &self.networks {
+            if !networks.contains(net) {
+                res.push(format!("webhook {}: unknown network {}", self.url, net));
+            }
+        }
+        res
+    }
+}
+
+/// type of the event, as it is serialized
+pub fn kind(e: &OnChainEvent) -> String {
+    match serde_json::to_value(&e.entry) {
+        Ok(v) => v["type"].as_str().unwrap_or_default().to_owned(),
+        Err(_) => String::new(),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    network: &'a str,
+    event: &'a OnChainEvent,
+}
+
+/// webhooks of the network
+#[derive(Debug, Clone, Default)]
+pub struct Webhooks {
+    pub network: String,
+    pub hooks: Vec<WebhookConfig>,
+}
+
+impl Webhooks {
+    pub fn new(network: &str, hooks: &[WebhookConfig]) -> Self {
+        Self {
+            network: network.to_owned(),
+            hooks: hooks
+                .iter()
+                .filter(|h| h.networks.is_empty() || h.networks.iter().any(|x| x == network))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// posts the event to the matching webhooks without waiting for them
+    pub fn notify(&self, e: &OnChainEvent) {
+        let kind = kind(e);
+        let urls: Vec<String> = self
+            .hooks
+            .iter()
+            .filter(|h| h.matches(&self.network, &kind))
+            .map(|h| h.url.clone())
+            .collect();
+        if urls.is_empty() {
+            return;
+        }
+        let body = match serde_json::to_string(&Payload {
+            network: &self.network,
+            event: e,
+        }) {
+            Ok(x) => x,
+            Err(err) => {
+                tracing::warn!("webhook payload error: {}", err);
+                return;
+            }
+        };
+        std::thread::spawn(move || {
+            let agent = ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(10))
+                .build();
+            for url in urls {
+                let rq = agent.post(&url).set("Content-Type", "application/json");
+                if let Err(err) = rq.send_string(&body) {
+                    tracing::warn!("webhook {} failure: {}", url, err);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn it_filters_webhooks() {
+        let hooks: Vec<WebhookConfig> = vec![
+            WebhookConfig {
+                url: "https://example.com/all".into(),
+                events: vec![],
+                networks: vec![],
+            },
+            WebhookConfig {
+                url: "https://example.com/votes".into(),
+                events: vec!["StartVote".into()],
+                networks: vec!["rinkeby".into()],
+            },
+        ];
+        let mainnet = Webhooks::new("mainnet", &hooks);
+        assert_eq!(mainnet.hooks.len(), 1);
+        assert!(hooks[1].matches("rinkeby", "StartVote"));
+        assert!(!hooks[1].matches("rinkeby", "CastVote"));
+        assert_eq!(hooks[1].problems(&["mainnet".into()]).len(), 1);
+    }
+}